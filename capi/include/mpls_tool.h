@@ -0,0 +1,154 @@
+/* Generated by cbindgen from src/lib.rs; do not edit. */
+
+#ifndef MPLS_TOOL_H
+#define MPLS_TOOL_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum MplsStreamKind {
+  MPLS_STREAM_KIND_VIDEO,
+  MPLS_STREAM_KIND_AUDIO,
+  MPLS_STREAM_KIND_GRAPHICS,
+  MPLS_STREAM_KIND_TEXT,
+} MplsStreamKind;
+
+// A parsed playlist. Free it with `mpls_free`.
+typedef struct MplsPlaylist MplsPlaylist;
+
+// A play item. Strings belong to the playlist.
+typedef struct MplsItem {
+  // Stream file of the clip, like `00001.M2TS`.
+  const char *clip;
+  // Seconds on the clip's timeline.
+  double time_in;
+  double time_out;
+  // Seconds from the start of the playlist.
+  double relative_time_in;
+  double length;
+  // Number of angles, 1 for a single angle item.
+  size_t angle_count;
+} MplsItem;
+
+// A stream of the playlist. Strings belong to the playlist.
+typedef struct MplsStream {
+  uint16_t pid;
+  enum MplsStreamKind kind;
+  const char *codec;
+  // ISO 639-2 code, `NULL` for streams without a language.
+  const char *language;
+  const char *description;
+} MplsStream;
+
+typedef struct MplsChapter {
+  // Seconds from the start of the playlist.
+  double time_in;
+  double length;
+} MplsChapter;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Parses a playlist held in memory. Returns `NULL` on failure, see
+// `mpls_last_error`.
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes.
+struct MplsPlaylist *mpls_parse_buffer(const uint8_t *data, size_t len);
+
+// Parses a playlist file. Returns `NULL` on failure, see `mpls_last_error`.
+//
+// # Safety
+//
+// `path` must be a NUL terminated string.
+struct MplsPlaylist *mpls_parse_file(const char *path);
+
+// Frees a playlist. `NULL` is ignored.
+void mpls_free(struct MplsPlaylist *playlist);
+
+// Message of the last failure on the calling thread, `NULL` if none. Valid
+// until the next parse on this thread.
+const char *mpls_last_error(void);
+
+// Byte offset of the last parse failure on the calling thread, -1 when it
+// isn't about a position in the data.
+int64_t mpls_last_error_offset(void);
+
+// File name of the playlist, empty when parsed from a buffer. `NULL` when
+// `playlist` is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed.
+const char *mpls_name(const struct MplsPlaylist *playlist);
+
+// Length in seconds, 0 when `playlist` is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed.
+double mpls_duration(const struct MplsPlaylist *playlist);
+
+// Number of angles, 0 when `playlist` is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed.
+size_t mpls_angle_count(const struct MplsPlaylist *playlist);
+
+// Number of play items, 0 when `playlist` is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed.
+size_t mpls_item_count(const struct MplsPlaylist *playlist);
+
+// Fills `out` with play item `index`. Returns false when out of range or
+// either pointer is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed, and `out`
+// `NULL` or writable.
+bool mpls_item(const struct MplsPlaylist *playlist, size_t index, struct MplsItem *out);
+
+// Number of streams, 0 when `playlist` is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed.
+size_t mpls_stream_count(const struct MplsPlaylist *playlist);
+
+// Fills `out` with stream `index`. Returns false when out of range or
+// either pointer is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed, and `out`
+// `NULL` or writable.
+bool mpls_stream(const struct MplsPlaylist *playlist, size_t index, struct MplsStream *out);
+
+// Number of chapters, 0 when `playlist` is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed.
+size_t mpls_chapter_count(const struct MplsPlaylist *playlist);
+
+// Fills `out` with chapter `index`. Returns false when out of range or
+// either pointer is `NULL`.
+//
+// # Safety
+//
+// `playlist` must be `NULL` or a playlist that hasn't been freed, and `out`
+// `NULL` or writable.
+bool mpls_chapter(const struct MplsPlaylist *playlist, size_t index, struct MplsChapter *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MPLS_TOOL_H */
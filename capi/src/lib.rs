@@ -0,0 +1,492 @@
+//! C API over [`PlaylistModel`]: an opaque playlist handle with accessors
+//! for its play items, streams and chapters. `include/mpls_tool.h` is
+//! generated from this file with `cbindgen`.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    ptr,
+};
+
+use mpls_tool::{
+    model::{PlaylistModel, StreamModel},
+    parser::Parser,
+};
+
+/// A parsed playlist. Free it with `mpls_free`.
+pub struct MplsPlaylist {
+    model: PlaylistModel,
+    name: CString,
+    clips: Vec<CString>,
+    streams: Vec<StreamStrings>,
+}
+
+/// Strings of a stream, kept alive for the pointers handed out.
+struct StreamStrings {
+    codec: CString,
+    language: Option<CString>,
+    description: CString,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MplsStreamKind {
+    Video,
+    Audio,
+    Graphics,
+    Text,
+}
+
+/// A play item. Strings belong to the playlist.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MplsItem {
+    /// Stream file of the clip, like `00001.M2TS`.
+    pub clip: *const c_char,
+    /// Seconds on the clip's timeline.
+    pub time_in: f64,
+    pub time_out: f64,
+    /// Seconds from the start of the playlist.
+    pub relative_time_in: f64,
+    pub length: f64,
+    /// Number of angles, 1 for a single angle item.
+    pub angle_count: usize,
+}
+
+/// A stream of the playlist. Strings belong to the playlist.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MplsStream {
+    pub pid: u16,
+    pub kind: MplsStreamKind,
+    pub codec: *const c_char,
+    /// ISO 639-2 code, `NULL` for streams without a language.
+    pub language: *const c_char,
+    pub description: *const c_char,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MplsChapter {
+    /// Seconds from the start of the playlist.
+    pub time_in: f64,
+    pub length: f64,
+}
+
+thread_local! {
+    /// Message and byte offset of the last failure on this thread.
+    static LAST_ERROR: RefCell<Option<(CString, i64)>> = const { RefCell::new(None) };
+}
+
+/// Parses a playlist held in memory. Returns `NULL` on failure, see
+/// `mpls_last_error`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_parse_buffer(data: *const u8, len: usize) -> *mut MplsPlaylist {
+    if data.is_null() {
+        set_error("data is NULL", None);
+        return ptr::null_mut();
+    }
+    let data = std::slice::from_raw_parts(data, len);
+    parse(data, String::new())
+}
+
+/// Parses a playlist file. Returns `NULL` on failure, see `mpls_last_error`.
+///
+/// # Safety
+///
+/// `path` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_parse_file(path: *const c_char) -> *mut MplsPlaylist {
+    if path.is_null() {
+        set_error("path is NULL", None);
+        return ptr::null_mut();
+    }
+    let path = path_from_c(CStr::from_ptr(path));
+    let data = match std::fs::read(&path) {
+        Ok(data) => data,
+        Err(e) => {
+            set_error(&format!("{}: {}", path.display(), e), None);
+            return ptr::null_mut();
+        }
+    };
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    parse(&data, name)
+}
+
+/// Frees a playlist. `NULL` is ignored.
+#[no_mangle]
+pub extern "C" fn mpls_free(playlist: Option<Box<MplsPlaylist>>) {
+    drop(playlist);
+}
+
+/// Message of the last failure on the calling thread, `NULL` if none. Valid
+/// until the next parse on this thread.
+#[no_mangle]
+pub extern "C" fn mpls_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |(message, _)| message.as_ptr())
+    })
+}
+
+/// Byte offset of the last parse failure on the calling thread, -1 when it
+/// isn't about a position in the data.
+#[no_mangle]
+pub extern "C" fn mpls_last_error_offset() -> i64 {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(-1, |(_, offset)| *offset))
+}
+
+/// File name of the playlist, empty when parsed from a buffer. `NULL` when
+/// `playlist` is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_name(playlist: *const MplsPlaylist) -> *const c_char {
+    playlist
+        .as_ref()
+        .map_or(ptr::null(), |playlist| playlist.name.as_ptr())
+}
+
+/// Length in seconds, 0 when `playlist` is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_duration(playlist: *const MplsPlaylist) -> f64 {
+    playlist
+        .as_ref()
+        .map_or(0.0, |playlist| playlist.model.length)
+}
+
+/// Number of angles, 0 when `playlist` is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_angle_count(playlist: *const MplsPlaylist) -> usize {
+    playlist
+        .as_ref()
+        .map_or(0, |playlist| playlist.model.angle_count)
+}
+
+/// Number of play items, 0 when `playlist` is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_item_count(playlist: *const MplsPlaylist) -> usize {
+    playlist
+        .as_ref()
+        .map_or(0, |playlist| playlist.model.play_items.len())
+}
+
+/// Fills `out` with play item `index`. Returns false when out of range or
+/// either pointer is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed, and `out`
+/// `NULL` or writable.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_item(
+    playlist: *const MplsPlaylist,
+    index: usize,
+    out: *mut MplsItem,
+) -> bool {
+    let (Some(playlist), Some(out)) = (playlist.as_ref(), out.as_mut()) else {
+        return false;
+    };
+    let Some(item) = playlist.model.play_items.get(index) else {
+        return false;
+    };
+    *out = MplsItem {
+        clip: playlist.clips[index].as_ptr(),
+        time_in: item.time_in,
+        time_out: item.time_out,
+        relative_time_in: item.relative_time_in,
+        length: item.length,
+        angle_count: item.angles.len() + 1,
+    };
+    true
+}
+
+/// Number of streams, 0 when `playlist` is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_stream_count(playlist: *const MplsPlaylist) -> usize {
+    playlist
+        .as_ref()
+        .map_or(0, |playlist| playlist.model.streams.len())
+}
+
+/// Fills `out` with stream `index`. Returns false when out of range or
+/// either pointer is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed, and `out`
+/// `NULL` or writable.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_stream(
+    playlist: *const MplsPlaylist,
+    index: usize,
+    out: *mut MplsStream,
+) -> bool {
+    let (Some(playlist), Some(out)) = (playlist.as_ref(), out.as_mut()) else {
+        return false;
+    };
+    let Some(stream) = playlist.model.streams.get(index) else {
+        return false;
+    };
+    let strings = &playlist.streams[index];
+    *out = MplsStream {
+        pid: stream.pid,
+        kind: stream_kind(stream),
+        codec: strings.codec.as_ptr(),
+        language: strings
+            .language
+            .as_ref()
+            .map_or(ptr::null(), |language| language.as_ptr()),
+        description: strings.description.as_ptr(),
+    };
+    true
+}
+
+/// Number of chapters, 0 when `playlist` is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_chapter_count(playlist: *const MplsPlaylist) -> usize {
+    playlist
+        .as_ref()
+        .map_or(0, |playlist| playlist.model.chapters.len())
+}
+
+/// Fills `out` with chapter `index`. Returns false when out of range or
+/// either pointer is `NULL`.
+///
+/// # Safety
+///
+/// `playlist` must be `NULL` or a playlist that hasn't been freed, and `out`
+/// `NULL` or writable.
+#[no_mangle]
+pub unsafe extern "C" fn mpls_chapter(
+    playlist: *const MplsPlaylist,
+    index: usize,
+    out: *mut MplsChapter,
+) -> bool {
+    let (Some(playlist), Some(out)) = (playlist.as_ref(), out.as_mut()) else {
+        return false;
+    };
+    let Some(chapter) = playlist.model.chapters.get(index) else {
+        return false;
+    };
+    *out = MplsChapter {
+        time_in: chapter.time_in,
+        length: chapter.length,
+    };
+    true
+}
+
+fn parse(data: &[u8], name: String) -> *mut MplsPlaylist {
+    // Unwinding into C is undefined, so a bug in the parser becomes an error.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        Parser::from_bytes(data).map(|parser| PlaylistModel::new(&name, &parser))
+    }));
+    match result {
+        Ok(Ok(model)) => {
+            clear_error();
+            Box::into_raw(Box::new(MplsPlaylist::new(model)))
+        }
+        Ok(Err(e)) => {
+            set_error(&e.to_string(), Some(e.offset));
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error("internal error while parsing", None);
+            ptr::null_mut()
+        }
+    }
+}
+
+impl MplsPlaylist {
+    fn new(model: PlaylistModel) -> Self {
+        MplsPlaylist {
+            name: c_string(&model.name),
+            clips: model.play_items.iter().map(|i| c_string(&i.clip)).collect(),
+            streams: model
+                .streams
+                .iter()
+                .map(|s| StreamStrings {
+                    codec: c_string(s.codec),
+                    language: s.language.as_deref().map(c_string),
+                    description: c_string(&s.description),
+                })
+                .collect(),
+            model,
+        }
+    }
+}
+
+fn stream_kind(stream: &StreamModel) -> MplsStreamKind {
+    match stream.kind {
+        "video" => MplsStreamKind::Video,
+        "audio" => MplsStreamKind::Audio,
+        "graphics" => MplsStreamKind::Graphics,
+        _ => MplsStreamKind::Text,
+    }
+}
+
+fn c_string(text: &str) -> CString {
+    CString::new(text.replace('\0', "")).unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn path_from_c(path: &CStr) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(path.to_bytes()))
+}
+
+#[cfg(not(unix))]
+fn path_from_c(path: &CStr) -> PathBuf {
+    PathBuf::from(path.to_string_lossy().into_owned())
+}
+
+fn set_error(message: &str, offset: Option<usize>) {
+    let offset = offset.map_or(-1, |offset| offset as i64);
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some((c_string(message), offset)));
+}
+
+fn clear_error() {
+    LAST_ERROR.with(|error| *error.borrow_mut() = None);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    use super::*;
+
+    const MPLS_00800: &[u8] = include_bytes!("../../00800.mpls");
+
+    fn text(text: *const c_char) -> &'static str {
+        assert!(!text.is_null());
+        unsafe { CStr::from_ptr(text) }.to_str().unwrap()
+    }
+
+    #[test]
+    fn reads_playlists() {
+        unsafe {
+            let playlist = mpls_parse_buffer(MPLS_00800.as_ptr(), MPLS_00800.len());
+            assert!(!playlist.is_null());
+            assert!(mpls_last_error().is_null());
+            assert_eq!(text(mpls_name(playlist)), "");
+            assert!(mpls_duration(playlist) > 0.0);
+            assert_eq!(mpls_angle_count(playlist), 1);
+
+            assert_eq!(mpls_item_count(playlist), 1);
+            let mut item = MaybeUninit::uninit();
+            assert!(mpls_item(playlist, 0, item.as_mut_ptr()));
+            let mut item = item.assume_init();
+            assert_eq!(text(item.clip), "00037.M2TS");
+            assert_eq!(item.time_in, 4198.375);
+            assert_eq!(item.angle_count, 1);
+
+            assert_eq!(mpls_stream_count(playlist), 30);
+            let mut stream = MaybeUninit::uninit();
+            assert!(mpls_stream(playlist, 1, stream.as_mut_ptr()));
+            let stream = stream.assume_init();
+            assert_eq!(stream.kind, MplsStreamKind::Audio);
+            assert_eq!(text(stream.language), "eng");
+
+            let count = mpls_chapter_count(playlist);
+            assert!(count > 0);
+            let mut chapter = MaybeUninit::uninit();
+            assert!(mpls_chapter(playlist, 0, chapter.as_mut_ptr()));
+            assert_eq!(chapter.assume_init().time_in, 0.0);
+            assert!(!mpls_chapter(playlist, count, chapter.as_mut_ptr()));
+            assert!(!mpls_item(playlist, 1, &mut item));
+
+            mpls_free(Some(Box::from_raw(playlist)));
+        }
+    }
+
+    #[test]
+    fn reads_playlist_files() {
+        let path = CString::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../00800.mpls")).unwrap();
+        unsafe {
+            let playlist = mpls_parse_file(path.as_ptr());
+            assert!(!playlist.is_null());
+            assert_eq!(text(mpls_name(playlist)), "00800.mpls");
+            mpls_free(Some(Box::from_raw(playlist)));
+
+            let missing = CString::new("/nonexistent/00800.mpls").unwrap();
+            assert!(mpls_parse_file(missing.as_ptr()).is_null());
+            assert!(text(mpls_last_error()).starts_with("/nonexistent/00800.mpls: "));
+            assert_eq!(mpls_last_error_offset(), -1);
+        }
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        let truncated = &MPLS_00800[..MPLS_00800.len() / 2];
+        unsafe {
+            assert!(mpls_parse_buffer(truncated.as_ptr(), truncated.len()).is_null());
+            assert!(!text(mpls_last_error()).is_empty());
+            assert!(mpls_last_error_offset() > 0);
+
+            assert!(mpls_parse_buffer(ptr::null(), 10).is_null());
+            assert_eq!(text(mpls_last_error()), "data is NULL");
+            assert!(mpls_parse_file(ptr::null()).is_null());
+            assert_eq!(text(mpls_last_error()), "path is NULL");
+        }
+    }
+
+    #[test]
+    fn accepts_null_pointers() {
+        unsafe {
+            let null = ptr::null();
+            assert!(mpls_name(null).is_null());
+            assert_eq!(mpls_duration(null), 0.0);
+            assert_eq!(mpls_angle_count(null), 0);
+            assert_eq!(mpls_item_count(null), 0);
+            assert_eq!(mpls_stream_count(null), 0);
+            assert_eq!(mpls_chapter_count(null), 0);
+            mpls_free(None);
+
+            let mut item = MaybeUninit::uninit();
+            assert!(!mpls_item(null, 0, item.as_mut_ptr()));
+            let mut stream = MaybeUninit::uninit();
+            assert!(!mpls_stream(null, 0, stream.as_mut_ptr()));
+            let mut chapter = MaybeUninit::uninit();
+            assert!(!mpls_chapter(null, 0, chapter.as_mut_ptr()));
+
+            let playlist = mpls_parse_buffer(MPLS_00800.as_ptr(), MPLS_00800.len());
+            assert!(!mpls_item(playlist, 0, ptr::null_mut()));
+            assert!(!mpls_stream(playlist, 0, ptr::null_mut()));
+            assert!(!mpls_chapter(playlist, 0, ptr::null_mut()));
+            mpls_free(Some(Box::from_raw(playlist)));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+from os import PathLike
+from typing import List, Literal, Optional, TypedDict, Union
+
+class PlaylistFlags(TypedDict):
+    random_access: bool
+    audio_mix_app: bool
+    lossless_may_bypass_mixer: bool
+    mvc_base_view_r: bool
+    sdr_conversion_notification: bool
+
+class PlayItem(TypedDict):
+    clip: str
+    time_in: float
+    time_out: float
+    relative_time_in: float
+    length: float
+    angles: List[str]
+    pids: List[int]
+
+class Stream(TypedDict):
+    pid: int
+    kind: Literal["video", "audio", "graphics", "text"]
+    codec: str
+    language: Optional[str]
+    language_tag: Optional[str]
+    language_name: Optional[str]
+    description: str
+    bit_rate: Optional[int]
+
+class Chapter(TypedDict):
+    number: int
+    time_in: float
+    length: float
+
+class Playlist(TypedDict):
+    name: str
+    version: str
+    playback_type: int
+    playback_count: int
+    length: float
+    angle_count: int
+    flags: PlaylistFlags
+    play_items: List[PlayItem]
+    streams: List[Stream]
+    chapters: List[Chapter]
+
+class Disc(TypedDict):
+    name: str
+    label: str
+    title: Optional[str]
+    size: int
+    protection: str
+    extras: List[str]
+    main_feature: Optional[str]
+    playlists: List[Playlist]
+
+class MplsError(ValueError):
+    """`args` are the message and the byte offset where parsing failed."""
+
+def parse_mpls(
+    source: Union[bytes, str, PathLike[str]], name: Optional[str] = None
+) -> Playlist: ...
+def scan_disc(
+    path: Union[str, PathLike[str]], streams: bool = False, probe: bool = False
+) -> Disc: ...
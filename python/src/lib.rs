@@ -0,0 +1,148 @@
+use std::{
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+use mpls_tool::{
+    disc::Disc,
+    model::{DiscModel, PlaylistModel},
+    parser::Parser,
+};
+use pyo3::{create_exception, exceptions::PyValueError, prelude::*};
+use serde::Serialize;
+
+create_exception!(
+    mpls_tool,
+    MplsError,
+    PyValueError,
+    "Data that isn't a valid playlist. `args` are the message and the byte \
+     offset where parsing failed."
+);
+
+/// A playlist given as its contents or as a path.
+#[derive(FromPyObject)]
+enum Source {
+    Bytes(Vec<u8>),
+    Path(PathBuf),
+}
+
+/// Parses an `.mpls` file into a dict with its play items, streams and
+/// chapters.
+#[pyfunction]
+#[pyo3(signature = (source, name = None))]
+fn parse_mpls(py: Python<'_>, source: Source, name: Option<String>) -> PyResult<PyObject> {
+    let (data, default_name) = match source {
+        Source::Bytes(data) => (data, String::new()),
+        Source::Path(path) => {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let data = std::fs::read(&path).map_err(|e| with_path(&path, e))?;
+            (data, name)
+        }
+    };
+    let parser =
+        Parser::from_bytes(&data).map_err(|e| MplsError::new_err((e.to_string(), e.offset)))?;
+    to_py(
+        py,
+        &PlaylistModel::new(name.as_deref().unwrap_or(&default_name), &parser),
+    )
+}
+
+/// Opens a disc folder or image and returns a dict with its playlists and
+/// main feature. `streams` and `probe` read the stream files for bit rates
+/// and codec details, which takes a while on a full disc.
+#[pyfunction]
+#[pyo3(signature = (path, streams = false, probe = false))]
+fn scan_disc(py: Python<'_>, path: PathBuf, streams: bool, probe: bool) -> PyResult<PyObject> {
+    let model = py.allow_threads(|| {
+        let mut disc = Disc::open(&path).map_err(|e| with_path(&path, e))?;
+        if streams {
+            disc.scan_stream_files()?;
+        }
+        if probe {
+            disc.probe_streams()?;
+        }
+        Ok::<_, Error>(DiscModel::new(&disc))
+    })?;
+    to_py(py, &model)
+}
+
+/// Prefixes an error message with the file it is about, keeping its kind
+/// for the `OSError` subclass.
+fn with_path(path: &Path, error: Error) -> Error {
+    Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
+
+/// Converts a model to plain dicts and lists through JSON, which keeps the
+/// field order of the model.
+fn to_py(py: Python<'_>, value: &impl Serialize) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+#[pymodule]
+#[pyo3(name = "mpls_tool")]
+fn mpls_tool_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse_mpls, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_disc, m)?)?;
+    m.add("MplsError", m.py().get_type::<MplsError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::{exceptions::PyFileNotFoundError, types::PyDict};
+
+    use super::*;
+
+    const MPLS_00800: &[u8] = include_bytes!("../../00800.mpls");
+
+    fn with_module<F: FnOnce(&Bound<'_, PyModule>)>(f: F) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "mpls_tool").unwrap();
+            mpls_tool_module(&module).unwrap();
+            f(&module);
+        });
+    }
+
+    #[test]
+    fn parses_bytes_and_paths() {
+        with_module(|m| {
+            let playlist = m
+                .call_method1("parse_mpls", (MPLS_00800, "00800.MPLS"))
+                .unwrap();
+            let playlist = playlist.downcast::<PyDict>().unwrap();
+            let get = |key| playlist.get_item(key).unwrap().unwrap();
+            assert_eq!(get("name").extract::<String>().unwrap(), "00800.MPLS");
+            assert_eq!(get("version").extract::<String>().unwrap(), "MPLS0300");
+            assert_eq!(get("streams").len().unwrap(), 30);
+
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../00800.mpls");
+            let playlist = m.call_method1("parse_mpls", (path,)).unwrap();
+            let name = playlist.get_item("name").unwrap();
+            assert_eq!(name.extract::<String>().unwrap(), "00800.mpls");
+        });
+    }
+
+    #[test]
+    fn raises_mpls_errors() {
+        with_module(|m| {
+            let py = m.py();
+            let truncated = &MPLS_00800[..MPLS_00800.len() / 2];
+            let error = m.call_method1("parse_mpls", (truncated,)).unwrap_err();
+            assert!(error.is_instance_of::<MplsError>(py));
+            assert!(error.is_instance_of::<PyValueError>(py));
+            let (_, offset): (String, usize) =
+                error.value(py).getattr("args").unwrap().extract().unwrap();
+            assert!(offset > 0);
+
+            let error = m
+                .call_method1("scan_disc", ("/nonexistent/disc",))
+                .unwrap_err();
+            assert!(error.is_instance_of::<PyFileNotFoundError>(py));
+        });
+    }
+}
@@ -1,22 +1,898 @@
+mod output;
+
 use std::{
-    env::args,
     fs::File,
-    io::{BufReader, Result},
+    io::{BufWriter, Error, ErrorKind, IsTerminal, Result, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Args, Parser as _, Subcommand, ValueEnum};
+use mpls_tool::{
+    chapters::{self, ChapterFormat},
+    demux::DemuxOptions,
+    disc::{self, Disc},
+    explain::Explanation,
+    lint::{LintReport, Severity},
+    log::{self, ConsoleSink},
+    model::{self, CatalogRow, DiscModel, PlaylistModel, StreamModel},
+    parser::Parser,
+    report::{self, format_time, ReportMode},
+    trace,
+    ts::{language::Language, stream::Stream},
 };
+use rayon::prelude::*;
+use serde_json::json;
+use termcolor::Color;
+
+use output::{ColorWhen, Output};
+
+/// The playlists differ for `diff`, `lint` or `verify` found errors, or some
+/// discs failed for `catalog`.
+const EXIT_DIFFERENT: u8 = 1;
+/// The input is missing or can't be read.
+const EXIT_UNREADABLE: u8 = 3;
+/// The input isn't a valid playlist or disc, or the disc has no main feature
+/// playlist.
+const EXIT_INVALID: u8 = 4;
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    version,
+    about = "Inspect Blu-ray playlists and discs",
+    after_help = "Exit status: 0 on success, 1 when `diff` finds differences, `lint` or \
+                  `verify` find errors or `catalog` can't read some discs, 2 on usage \
+                  errors, 3 when an input can't be read and 4 when it isn't a valid \
+                  playlist or disc, or a disc has no main feature playlist."
+)]
+struct Cli {
+    /// When to color the output.
+    #[arg(long, value_enum, default_value_t = ColorWhen::Auto, global = true)]
+    color: ColorWhen,
+    /// Print every field on stderr as it is parsed.
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Summary of a playlist, or of a disc and its main feature.
+    Info {
+        #[command(flatten)]
+        target: Target,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Streams of a playlist.
+    Streams {
+        #[command(flatten)]
+        target: Target,
+        #[arg(long, value_enum, default_value_t = TableFormat::Text)]
+        format: TableFormat,
+    },
+    /// Chapters of a playlist, as a table or for muxing tools.
+    Chapters {
+        #[command(flatten)]
+        target: Target,
+        #[arg(long, value_enum, default_value_t = ChaptersFormat::Text)]
+        format: ChaptersFormat,
+    },
+    /// The whole playlist, or every playlist of a disc, as JSON.
+    Dump {
+        #[command(flatten)]
+        target: Target,
+    },
+    /// Every byte range of a playlist file with the field it belongs to.
+    Explain {
+        file: PathBuf,
+        /// Only show unknown, reserved and unparsed bytes.
+        #[arg(long)]
+        unknown: bool,
+    },
+    /// Check a playlist file, or every playlist of a disc, for authoring
+    /// errors.
+    Lint {
+        /// A playlist file, or a disc folder or image.
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Check that a disc's playlists, clip information and stream files
+    /// agree, and find missing or damaged files.
+    Verify {
+        /// The disc root, its `BDMV` folder or an image.
+        disc: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Rank the playlists of a disc and group duplicates.
+    Scan {
+        /// The disc root, its `BDMV` folder or an image.
+        disc: PathBuf,
+        /// Scan the stream files for sizes and bit rates.
+        #[arg(long)]
+        streams: bool,
+        /// Probe the codecs of the elementary streams.
+        #[arg(long)]
+        probe: bool,
+        /// Print a BDInfo report of the main feature instead.
+        #[arg(long, value_enum)]
+        report: Option<ReportKind>,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// A row per playlist of every disc under the given paths.
+    Catalog {
+        /// Disc folders and images, or folders holding them.
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        #[arg(long, value_enum, default_value_t = CatalogFormat::Jsonl)]
+        format: CatalogFormat,
+        /// Write to a file instead of standard output.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Discs to read at once, the number of CPUs by default.
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+    /// Compare two playlist files.
+    Diff {
+        a: PathBuf,
+        b: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Extract one stream of a disc playlist, presentation graphics as .sup and
+    /// text subtitles as SRT or ASS.
+    Demux {
+        disc: PathBuf,
+        playlist: String,
+        /// A PID, or the ISO 639 language of a presentation graphics or text
+        /// subtitle stream.
+        stream: String,
+        /// Text subtitles are written as ASS for a `.ass` file, SRT otherwise.
+        output: PathBuf,
+        /// Keep only forced subtitles.
+        #[arg(long)]
+        forced: bool,
+        #[arg(long, default_value_t = 0)]
+        angle: usize,
+    },
+}
+
+/// A playlist file, or a playlist of a disc.
+#[derive(Debug, Args)]
+struct Target {
+    /// A playlist file, or a disc folder or image.
+    path: PathBuf,
+    /// Playlist of the disc, the main feature by default.
+    #[arg(short, long)]
+    playlist: Option<String>,
+    /// Scan the disc's stream files for sizes and bit rates.
+    #[arg(long)]
+    scan: bool,
+    /// Probe the codecs of the disc's elementary streams.
+    #[arg(long)]
+    probe: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TableFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ChaptersFormat {
+    Text,
+    Json,
+    Csv,
+    /// `CHAPTER01=` lines.
+    Ogm,
+    /// Matroska chapter XML.
+    Xml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CatalogFormat {
+    /// A JSON object per line.
+    Jsonl,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ReportKind {
+    Full,
+    Quick,
+}
 
-use mpls_tool::parser::Parser;
-fn main() -> Result<()> {
-    let mut args = args();
-    let path = args.nth(1).expect("Path not provided as first argument");
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    log::set_color(cli.color.enabled(std::io::stderr().is_terminal()));
+    if cli.verbose {
+        trace::set_sink(ConsoleSink);
+    }
+    let out = &mut Output::new(cli.color);
+    match run(cli.command, out) {
+        Ok(code) => code,
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+fn exit_code(error: &Error) -> u8 {
+    match error.kind() {
+        ErrorKind::InvalidData | ErrorKind::UnexpectedEof => EXIT_INVALID,
+        _ => EXIT_UNREADABLE,
+    }
+}
+
+/// A disc whose playlists are all too short or likely decoys.
+fn no_main_feature() -> Error {
+    Error::new(ErrorKind::InvalidData, "no main feature playlist")
+}
+
+fn run(command: Command, out: &mut Output) -> Result<ExitCode> {
+    match command {
+        Command::Info { target, format } => info(&Source::open(&target)?, format, out)?,
+        Command::Streams { target, format } => streams(&Source::open(&target)?, format, out)?,
+        Command::Chapters { target, format } => chapters(&Source::open(&target)?, format, out)?,
+        Command::Dump { target } => {
+            let source = Source::open(&target)?;
+            match &source {
+                Source::Disc { disc, .. } if target.playlist.is_none() => {
+                    out.json(&DiscModel::new(disc))?
+                }
+                _ => out.json(&PlaylistModel::new(source.name(), source.parser()))?,
+            }
+        }
+        Command::Explain { file, unknown } => explain(&file, unknown, out)?,
+        Command::Lint { path, format } => return lint(&path, format, out),
+        Command::Verify { disc, format } => return verify(&disc, format, out),
+        Command::Scan {
+            disc,
+            streams,
+            probe,
+            report,
+            format,
+        } => scan(&disc, streams, probe, report, format, out)?,
+        Command::Catalog {
+            paths,
+            format,
+            output,
+            jobs,
+        } => return catalog(&paths, format, output.as_deref(), jobs, out),
+        Command::Diff { a, b, format } => return diff(&a, &b, format, out),
+        Command::Demux {
+            disc,
+            playlist,
+            stream,
+            output,
+            forced,
+            angle,
+        } => demux(&disc, &playlist, &stream, &output, forced, angle, out)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A parsed playlist, on its own or as part of a disc.
+enum Source {
+    File { name: String, parser: Parser },
+    Disc { disc: Disc, name: String },
+}
+
+impl Source {
+    fn open(target: &Target) -> Result<Self> {
+        if !is_disc(&target.path) {
+            let name = target
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let parser = parse_file(&target.path)?;
+            return Ok(Source::File { name, parser });
+        }
+
+        let mut disc = open_disc(&target.path)?;
+        if target.scan {
+            disc.scan_stream_files()?;
+        }
+        if target.probe {
+            disc.probe_streams()?;
+        }
+        let name = match target.playlist.as_deref() {
+            Some(name) => find_playlist(&disc, name)?,
+            None => disc
+                .rank_playlists()
+                .into_iter()
+                .find(|rank| rank.is_main_feature())
+                .map(|rank| rank.name)
+                .ok_or_else(no_main_feature)?,
+        };
+        Ok(Source::Disc { disc, name })
+    }
 
-    let f = File::open(path)?;
-    let reader = &mut BufReader::new(f);
-    let mut parser = Parser::default();
-    parser.read_header(reader)?;
-    parser.read_playlist_header(reader)?;
-    parser.read_play_item_entries(reader)?;
-    parser.read_chapters_header(reader)?;
-    parser.read_chapters(reader)?;
+    fn name(&self) -> &str {
+        match self {
+            Source::File { name, .. } | Source::Disc { name, .. } => name,
+        }
+    }
+
+    fn parser(&self) -> &Parser {
+        match self {
+            Source::File { parser, .. } => parser,
+            Source::Disc { disc, name } => match disc.playlist(name) {
+                Some(playlist) => &playlist.parser,
+                None => unreachable!("playlist names come from the disc"),
+            },
+        }
+    }
+}
+
+/// A disc folder or image, as opposed to a single playlist file.
+fn is_disc(path: &Path) -> bool {
+    path.is_dir()
+        || path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("iso") || ext.eq_ignore_ascii_case("img"))
+}
+
+fn parse_file(path: &Path) -> Result<Parser> {
+    let data = std::fs::read(path).map_err(|e| with_path(path, e))?;
+    Parser::from_bytes(&data).map_err(|e| with_path(path, e.into()))
+}
+
+fn open_disc(path: &Path) -> Result<Disc> {
+    Disc::open(path).map_err(|e| with_path(path, e))
+}
+
+/// Prefixes an error message with the file it is about.
+fn with_path(path: &Path, error: Error) -> Error {
+    Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
 
+/// The disc's name for a playlist given with or without `.mpls`.
+fn find_playlist(disc: &Disc, name: &str) -> Result<String> {
+    disc.playlist(name)
+        .or_else(|| disc.playlist(&format!("{}.mpls", name)))
+        .map(|playlist| playlist.name.clone())
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no playlist {}", name)))
+}
+
+fn info(source: &Source, format: Format, out: &mut Output) -> Result<()> {
+    let parser = source.parser();
+    let streams = parser.streams();
+    let count = |kind: fn(&Stream) -> bool| streams.iter().filter(|s| kind(s)).count();
+    let video = count(|s| matches!(s, Stream::VideoStream(_)));
+    let audio = count(|s| matches!(s, Stream::AudioStream(_)));
+    let subtitles = count(|s| matches!(s, Stream::GraphicsStream(_) | Stream::TextStream(_)));
+    let chapters = parser.chapter_times().len();
+
+    if format == Format::Json {
+        let mut info = json!({
+            "playlist": source.name(),
+            "version": parser.mpls_header().magic_string(),
+            "length": parser.total_length(),
+            "play_items": parser.clips().len(),
+            "angles": parser.angle_count(),
+            "video_streams": video,
+            "audio_streams": audio,
+            "subtitle_streams": subtitles,
+            "chapters": chapters,
+        });
+        if let Source::Disc { disc, .. } = source {
+            info["disc"] = json!({
+                "label": disc.label(),
+                "title": disc.title(),
+                "size": disc.size(),
+                "protection": disc.protection(),
+            });
+        }
+        return out.json(&info);
+    }
+
+    if let Source::Disc { disc, .. } = source {
+        out.key_val("Disc label", disc.label())?;
+        if let Some(title) = disc.title() {
+            out.key_val("Disc title", title)?;
+        }
+        out.key_val("Disc size", format!("{} bytes", disc.size()))?;
+        out.key_val("Protection", disc.protection())?;
+    }
+    out.key_val("Playlist", source.name())?;
+    out.key_val("Version", parser.mpls_header().magic_string())?;
+    out.key_val("Length", format_time(parser.total_length()))?;
+    out.key_val("Play items", parser.clips().len())?;
+    out.key_val("Angles", parser.angle_count())?;
+    out.key_val("Video", video)?;
+    out.key_val("Audio", audio)?;
+    out.key_val("Subtitles", subtitles)?;
+    out.key_val("Chapters", chapters)
+}
+
+fn streams(source: &Source, format: TableFormat, out: &mut Output) -> Result<()> {
+    let streams: Vec<StreamModel> = source
+        .parser()
+        .streams()
+        .into_iter()
+        .map(StreamModel::new)
+        .collect();
+    match format {
+        TableFormat::Json => out.json(&streams),
+        TableFormat::Csv => {
+            out.text("pid,kind,codec,language,description,bit_rate\n")?;
+            for s in streams {
+                out.text(format_args!(
+                    "{},{},{},{},{},{}\n",
+                    s.pid,
+                    s.kind,
+                    csv_field(s.codec),
+                    s.language.unwrap_or_default(),
+                    csv_field(&s.description),
+                    s.bit_rate.map(|rate| rate.to_string()).unwrap_or_default()
+                ))?;
+            }
+            Ok(())
+        }
+        TableFormat::Text => {
+            out.colored(
+                Some(Color::Cyan),
+                &format!(
+                    "{:<8}{:<10}{:<28}{:<10}{}",
+                    "PID", "Kind", "Codec", "Language", "Description"
+                ),
+            )?;
+            for s in streams {
+                out.text(format_args!(
+                    "{:<8}{:<10}{:<28}{:<10}{}\n",
+                    format!("{:#06X}", s.pid),
+                    s.kind,
+                    s.codec,
+                    s.language.unwrap_or_default(),
+                    s.description
+                ))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Quotes a CSV field when it holds a separator or quote.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+fn catalog(
+    paths: &[PathBuf],
+    format: CatalogFormat,
+    output: Option<&Path>,
+    jobs: Option<usize>,
+    out: &mut Output,
+) -> Result<ExitCode> {
+    let discs = paths
+        .iter()
+        .flat_map(|path| disc::find_discs(path))
+        .collect::<Vec<_>>();
+    if discs.is_empty() {
+        return Err(Error::new(ErrorKind::NotFound, "no discs found"));
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()
+        .map_err(Error::other)?;
+    let results = pool.install(|| {
+        discs
+            .par_iter()
+            .map(|path| {
+                let disc = open_disc(path)?;
+                Ok(CatalogRow::for_disc(&disc, &path.display().to_string()))
+            })
+            .collect::<Vec<Result<_>>>()
+    });
+
+    let mut text = String::new();
+    if format == CatalogFormat::Csv {
+        text.push_str(
+            "disc,disc_id,disc_label,playlist,duration,item_count,video_format,\
+             audio_languages,subtitle_languages,chapter_count,flags\n",
+        );
+    }
+    let mut failed = 0;
+    for result in results {
+        let rows = match result {
+            Ok(rows) => rows,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                failed += 1;
+                continue;
+            }
+        };
+        for row in rows {
+            match format {
+                CatalogFormat::Jsonl => {
+                    text.push_str(&serde_json::to_string(&row)?);
+                    text.push('\n');
+                }
+                CatalogFormat::Csv => {
+                    let fields = [
+                        row.disc,
+                        row.disc_id.unwrap_or_default(),
+                        row.disc_label,
+                        row.playlist,
+                        format!("{:.3}", row.duration),
+                        row.item_count.to_string(),
+                        row.video_format.unwrap_or_default(),
+                        row.audio_languages.join(";"),
+                        row.subtitle_languages.join(";"),
+                        row.chapter_count.to_string(),
+                        row.flags.join(";"),
+                    ];
+                    let fields = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+                    text.push_str(&fields.join(","));
+                    text.push('\n');
+                }
+            }
+        }
+    }
+
+    match output {
+        Some(path) => {
+            let mut file = File::create(path).map_err(|e| with_path(path, e))?;
+            file.write_all(text.as_bytes())?;
+        }
+        None => out.text(text)?,
+    }
+    Ok(if failed > 0 {
+        ExitCode::from(EXIT_DIFFERENT)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn chapters(source: &Source, format: ChaptersFormat, out: &mut Output) -> Result<()> {
+    let list = model::chapters(source.parser());
+    match format {
+        ChaptersFormat::Json => out.json(&list),
+        ChaptersFormat::Csv => out.text(chapters::export(&list, ChapterFormat::Csv)),
+        ChaptersFormat::Ogm => out.text(chapters::export(&list, ChapterFormat::Ogm)),
+        ChaptersFormat::Xml => out.text(chapters::export(&list, ChapterFormat::Matroska)),
+        ChaptersFormat::Text => {
+            out.colored(
+                Some(Color::Cyan),
+                &format!("{:<8}{:<16}{}", "Number", "Time In", "Length"),
+            )?;
+            for chapter in list {
+                out.text(format_args!(
+                    "{:<8}{:<16}{}\n",
+                    chapter.number,
+                    format_time(chapter.time_in),
+                    format_time(chapter.length)
+                ))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Prints the annotated bytes, unknown ones highlighted by their marker.
+fn explain(path: &Path, unknown_only: bool, out: &mut Output) -> Result<()> {
+    let data = std::fs::read(path).map_err(|e| with_path(path, e))?;
+    let explanation = Explanation::new(&data);
+    for line in explanation.to_string().lines() {
+        let color = match line.chars().nth(9) {
+            Some('?') => Some(Color::Yellow),
+            Some('-') => Some(Color::Blue),
+            Some('!') => Some(Color::Red),
+            _ => None,
+        };
+        if unknown_only && color.is_none() {
+            continue;
+        }
+        out.colored(color, line)?;
+    }
+    match explanation.error {
+        Some(error) => Err(Error::new(ErrorKind::InvalidData, error)),
+        None => Ok(()),
+    }
+}
+
+fn lint(path: &Path, format: Format, out: &mut Output) -> Result<ExitCode> {
+    let reports = if is_disc(path) {
+        open_disc(path)?.lint()?
+    } else {
+        let data = std::fs::read(path).map_err(|e| with_path(path, e))?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        vec![(name, mpls_tool::lint::lint(&data))]
+    };
+
+    let clean = format!("{} playlists, no problems found", reports.len());
+    print_findings(&reports, "playlist", &clean, format, out)
+}
+
+fn verify(path: &Path, format: Format, out: &mut Output) -> Result<ExitCode> {
+    let reports = open_disc(path)?.verify()?;
+    print_findings(&reports, "file", "no problems found", format, out)
+}
+
+/// Findings grouped by file, with `key` naming the file in JSON and
+/// `clean` printed when there are none. Fails the exit status on errors.
+fn print_findings(
+    reports: &[(String, LintReport)],
+    key: &str,
+    clean: &str,
+    format: Format,
+    out: &mut Output,
+) -> Result<ExitCode> {
+    match format {
+        Format::Json => {
+            let reports = reports
+                .iter()
+                .map(|(name, report)| json!({ key: name, "findings": report.findings }))
+                .collect::<Vec<_>>();
+            out.json(&reports)?;
+        }
+        Format::Text => {
+            for (name, report) in reports.iter().filter(|(_, r)| !r.is_empty()) {
+                out.colored(Some(Color::Cyan), name)?;
+                for finding in report.findings.iter() {
+                    let color = match finding.severity {
+                        Severity::Error => Some(Color::Red),
+                        Severity::Warning => Some(Color::Yellow),
+                        Severity::Info => None,
+                    };
+                    out.colored(color, &format!("  {}", finding))?;
+                }
+            }
+            if reports.iter().all(|(_, r)| r.is_empty()) {
+                out.text(format_args!("{}\n", clean))?;
+            }
+        }
+    }
+    let failed = reports
+        .iter()
+        .any(|(_, report)| report.severity() == Some(Severity::Error));
+    Ok(if failed {
+        ExitCode::from(EXIT_DIFFERENT)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn scan(
+    path: &Path,
+    streams: bool,
+    probe: bool,
+    report: Option<ReportKind>,
+    format: Format,
+    out: &mut Output,
+) -> Result<()> {
+    let mut disc = open_disc(path)?;
+    if streams {
+        disc.scan_stream_files()?;
+    }
+    if probe {
+        disc.probe_streams()?;
+    }
+    if let Some(kind) = report {
+        let mode = match kind {
+            ReportKind::Full => ReportMode::Full,
+            ReportKind::Quick => ReportMode::QuickSummary,
+        };
+        let report = report::generate_main_feature(&disc, mode).ok_or_else(no_main_feature)?;
+        return out.text(report);
+    }
+
+    let ranks = disc.rank_playlists();
+    let groups = disc.duplicate_groups();
+    if format == Format::Json {
+        let ranks: Vec<_> = ranks
+            .iter()
+            .map(|rank| {
+                json!({
+                    "name": rank.name,
+                    "score": rank.score,
+                    "length": rank.length,
+                    "main_feature": rank.is_main_feature(),
+                    "flags": rank.flags.iter().map(|f| format!("{:?}", f)).collect::<Vec<_>>(),
+                    "reasons": rank.reasons,
+                })
+            })
+            .collect();
+        let groups: Vec<_> = groups
+            .iter()
+            .map(|group| {
+                json!({
+                    "representative": group.representative,
+                    "members": group.members.iter().map(|(name, equivalence)| json!({
+                        "name": name,
+                        "equivalence": equivalence.as_str(),
+                    })).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        return out.json(&json!({ "playlists": ranks, "duplicates": groups }));
+    }
+
+    for rank in ranks {
+        let color = if rank.is_main_feature() {
+            Color::Green
+        } else if rank.is_decoy() {
+            Color::Red
+        } else {
+            Color::Yellow
+        };
+        out.colored(
+            Some(color),
+            &format!(
+                "{:<16}{:>8.2}  {}",
+                rank.name,
+                rank.score,
+                format_time(rank.length)
+            ),
+        )?;
+        for reason in rank.reasons.iter() {
+            out.text(format_args!("{:<18}{}\n", "", reason))?;
+        }
+    }
+    for group in groups {
+        out.colored(
+            Some(Color::Magenta),
+            &format!(
+                "{:<16}{} equivalent playlist(s)",
+                group.representative,
+                group.members.len()
+            ),
+        )?;
+        for (name, equivalence) in group.members.iter() {
+            out.text(format_args!("{:<18}{} {}\n", "", name, equivalence))?;
+        }
+    }
     Ok(())
 }
+
+fn diff(a: &Path, b: &Path, format: Format, out: &mut Output) -> Result<ExitCode> {
+    let diff = mpls_tool::diff::diff(&parse_file(a)?, &parse_file(b)?);
+    match format {
+        Format::Json => out.text(format_args!("{}\n", diff.to_json()))?,
+        Format::Text => out.text(&diff)?,
+    }
+    Ok(if diff.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_DIFFERENT)
+    })
+}
+
+fn demux(
+    path: &Path,
+    playlist: &str,
+    stream: &str,
+    output: &Path,
+    forced_only: bool,
+    angle: usize,
+    out: &mut Output,
+) -> Result<()> {
+    let disc = open_disc(path)?;
+    let playlist = find_playlist(&disc, playlist)?;
+    let parser = &disc.playlist(&playlist).map(|p| &p.parser);
+    let pid = match stream.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => stream.parse().ok(),
+    }
+    .or_else(|| {
+        // `fr`, `fre` and `fra` all pick a French stream.
+        let language = Language::parse(stream.as_bytes())?;
+        parser
+            .iter()
+            .flat_map(|parser| parser.streams())
+            .find(|s| {
+                matches!(s, Stream::GraphicsStream(_) | Stream::TextStream(_))
+                    && s.language().is_some_and(|l| l.same_as(&language))
+            })
+            .map(|s| s.pid())
+    })
+    .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no stream {}", stream)))?;
+
+    let is_text = parser
+        .and_then(|parser| parser.playlist_streams().get(&pid))
+        .is_some_and(|s| matches!(s, Stream::TextStream(_)));
+    if is_text {
+        let mut subtitle = disc.text_subtitles(&playlist, pid)?;
+        if forced_only {
+            subtitle.dialogs.retain_mut(|dialog| {
+                dialog.regions.retain(|region| region.forced);
+                !dialog.regions.is_empty()
+            });
+        }
+        let is_ass = output
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ass"));
+        let text = if is_ass {
+            subtitle.to_ass()
+        } else {
+            subtitle.to_srt()
+        };
+        std::fs::write(output, text)?;
+        return out.key_val(
+            &output.display().to_string(),
+            format_args!(
+                "{} dialogs, {}",
+                subtitle.dialogs.len(),
+                subtitle.character_code
+            ),
+        );
+    }
+
+    let file = &mut BufWriter::new(File::create(output)?);
+    let stats = disc.demux(&playlist, angle, pid, DemuxOptions { forced_only }, file)?;
+    out.key_val(
+        &output.display().to_string(),
+        format_args!(
+            "{} PES packets, {} bytes, {} display sets ({} forced)",
+            stats.pes_count, stats.bytes_written, stats.display_sets, stats.forced_display_sets
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn cli_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_chapter_formats() {
+        let cli = Cli::try_parse_from(["mpls", "chapters", "a.mpls", "--format", "xml"]).unwrap();
+        let Command::Chapters { target, format } = cli.command else {
+            panic!("expected the chapters command");
+        };
+        assert_eq!(target.path, Path::new("a.mpls"));
+        assert_eq!(format, ChaptersFormat::Xml);
+        assert!(Cli::try_parse_from(["mpls", "chapters", "a.mpls", "--format", "srt"]).is_err());
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(exit_code(&no_main_feature()), EXIT_INVALID);
+        assert_eq!(
+            exit_code(&Error::from(ErrorKind::UnexpectedEof)),
+            EXIT_INVALID
+        );
+        assert_eq!(
+            exit_code(&Error::from(ErrorKind::NotFound)),
+            EXIT_UNREADABLE
+        );
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("00800.MPLS"), "00800.MPLS");
+        assert_eq!(csv_field("eng,fra"), "\"eng,fra\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
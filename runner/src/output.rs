@@ -0,0 +1,71 @@
+use std::{
+    fmt::Display,
+    io::{IsTerminal, Result, Write},
+};
+
+use clap::ValueEnum;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorWhen {
+    /// Color when writing to a terminal.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorWhen {
+    /// Whether to color a stream, `terminal` telling if it is one.
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorWhen::Auto => terminal && std::env::var_os("NO_COLOR").is_none(),
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+        }
+    }
+}
+
+/// Standard output, colored or not.
+pub struct Output {
+    stdout: StandardStream,
+}
+
+impl Output {
+    pub fn new(color: ColorWhen) -> Self {
+        let choice = if color.enabled(std::io::stdout().is_terminal()) {
+            ColorChoice::Always
+        } else {
+            ColorChoice::Never
+        };
+        Output {
+            stdout: StandardStream::stdout(choice),
+        }
+    }
+
+    /// A line of text in `color`.
+    pub fn colored(&mut self, color: Option<Color>, text: &str) -> Result<()> {
+        self.stdout
+            .set_color(ColorSpec::new().set_fg(color).set_bold(color.is_some()))?;
+        write!(self.stdout, "{}", text)?;
+        self.stdout.reset()?;
+        writeln!(self.stdout)
+    }
+
+    /// `key` left aligned in a column, then `value`.
+    pub fn key_val(&mut self, key: &str, value: impl Display) -> Result<()> {
+        self.stdout
+            .set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+        write!(self.stdout, "{:<16}", key)?;
+        self.stdout.reset()?;
+        writeln!(self.stdout, "{}", value)
+    }
+
+    pub fn text(&mut self, text: impl Display) -> Result<()> {
+        write!(self.stdout, "{}", text)
+    }
+
+    pub fn json(&mut self, value: &impl serde::Serialize) -> Result<()> {
+        serde_json::to_writer_pretty(&mut self.stdout, value)?;
+        writeln!(self.stdout)
+    }
+}
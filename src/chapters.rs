@@ -0,0 +1,150 @@
+//! Chapter lists in the formats muxing tools import.
+
+use std::fmt::Write;
+
+use crate::model::ChapterModel;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterFormat {
+    /// `CHAPTER01=00:00:00.000` and `CHAPTER01NAME=` pairs, as read by
+    /// mkvmerge and most other muxers.
+    Ogm,
+    /// Matroska chapter XML.
+    Matroska,
+    /// `number,time_in,length` with a header row, times in seconds.
+    Csv,
+}
+
+pub fn export(chapters: &[ChapterModel], format: ChapterFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ChapterFormat::Ogm => {
+            for chapter in chapters {
+                writeln!(
+                    out,
+                    "CHAPTER{:02}={}",
+                    chapter.number,
+                    timestamp(chapter.time_in, 3)
+                )
+                .ok();
+                writeln!(
+                    out,
+                    "CHAPTER{:02}NAME=Chapter {:02}",
+                    chapter.number, chapter.number
+                )
+                .ok();
+            }
+        }
+        ChapterFormat::Matroska => {
+            writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).ok();
+            writeln!(out, r#"<!DOCTYPE Chapters SYSTEM "matroskachapters.dtd">"#).ok();
+            writeln!(out, "<Chapters>").ok();
+            writeln!(out, "  <EditionEntry>").ok();
+            for chapter in chapters {
+                writeln!(out, "    <ChapterAtom>").ok();
+                writeln!(
+                    out,
+                    "      <ChapterTimeStart>{}</ChapterTimeStart>",
+                    timestamp(chapter.time_in, 9)
+                )
+                .ok();
+                writeln!(out, "      <ChapterDisplay>").ok();
+                writeln!(
+                    out,
+                    "        <ChapterString>Chapter {:02}</ChapterString>",
+                    chapter.number
+                )
+                .ok();
+                writeln!(out, "        <ChapterLanguage>und</ChapterLanguage>").ok();
+                writeln!(out, "      </ChapterDisplay>").ok();
+                writeln!(out, "    </ChapterAtom>").ok();
+            }
+            writeln!(out, "  </EditionEntry>").ok();
+            writeln!(out, "</Chapters>").ok();
+        }
+        ChapterFormat::Csv => {
+            writeln!(out, "number,time_in,length").ok();
+            for chapter in chapters {
+                writeln!(
+                    out,
+                    "{},{:.3},{:.3}",
+                    chapter.number, chapter.time_in, chapter.length
+                )
+                .ok();
+            }
+        }
+    }
+    out
+}
+
+/// `hh:mm:ss` with `digits` fractional digits.
+fn timestamp(seconds: f64, digits: u32) -> String {
+    let scale = 10u64.pow(digits);
+    let units = (seconds.max(0.0) * scale as f64).round() as u64;
+    let whole = units / scale;
+    format!(
+        "{:02}:{:02}:{:02}.{:0width$}",
+        whole / 3600,
+        whole / 60 % 60,
+        whole % 60,
+        units % scale,
+        width = digits as usize
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters() -> Vec<ChapterModel> {
+        vec![
+            ChapterModel {
+                number: 1,
+                time_in: 0.0,
+                length: 61.5,
+            },
+            ChapterModel {
+                number: 2,
+                time_in: 61.5,
+                length: 3600.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn exports_ogm() {
+        assert_eq!(
+            export(&chapters(), ChapterFormat::Ogm),
+            "CHAPTER01=00:00:00.000\n\
+             CHAPTER01NAME=Chapter 01\n\
+             CHAPTER02=00:01:01.500\n\
+             CHAPTER02NAME=Chapter 02\n"
+        );
+    }
+
+    #[test]
+    fn exports_matroska() {
+        let xml = export(&chapters(), ChapterFormat::Matroska);
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert_eq!(xml.matches("<ChapterAtom>").count(), 2);
+        assert!(xml.contains("<ChapterTimeStart>00:01:01.500000000</ChapterTimeStart>"));
+        assert!(xml.contains("<ChapterString>Chapter 02</ChapterString>"));
+        assert!(xml.ends_with("  </EditionEntry>\n</Chapters>\n"));
+    }
+
+    #[test]
+    fn exports_csv() {
+        assert_eq!(
+            export(&chapters(), ChapterFormat::Csv),
+            "number,time_in,length\n1,0.000,61.500\n2,61.500,3600.000\n"
+        );
+        assert_eq!(export(&[], ChapterFormat::Csv), "number,time_in,length\n");
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        assert_eq!(timestamp(3599.9996, 3), "01:00:00.000");
+        assert_eq!(timestamp(86_400.25, 2), "24:00:00.25");
+        assert_eq!(timestamp(-1.0, 3), "00:00:00.000");
+    }
+}
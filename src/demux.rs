@@ -0,0 +1,666 @@
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Write};
+
+use crate::{
+    disc::Disc,
+    playlist_reader::{PlaylistReader, Segment},
+    ts::{
+        packet::{clock_delta, PesHeader, PTS_CLOCK, PTS_MODULUS},
+        stream_file,
+        stream_type::StreamType,
+    },
+};
+
+const PGS_MAGIC: &[u8; 2] = b"PG";
+const PGS_PALETTE_DEFINITION: u8 = 0x14;
+const PGS_OBJECT_DEFINITION: u8 = 0x15;
+const PGS_PRESENTATION_COMPOSITION: u8 = 0x16;
+const PGS_WINDOW_DEFINITION: u8 = 0x17;
+const PGS_END_OF_DISPLAY_SET: u8 = 0x80;
+const PGS_EPOCH_START: u8 = 0x80;
+const PGS_FIRST_IN_SEQUENCE: u8 = 0x80;
+const PGS_OBJECT_CROPPED: u8 = 0x80;
+const PGS_OBJECT_FORCED: u8 = 0x40;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DemuxOptions {
+    /// Presentation graphics only: keep display sets that show forced
+    /// objects, and the ones clearing them.
+    pub forced_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DemuxStats {
+    pub pes_count: u64,
+    pub bytes_written: u64,
+    /// Presentation graphics only.
+    pub display_sets: u64,
+    pub forced_display_sets: u64,
+}
+
+/// Writes one PID of every play item to `out`. Presentation graphics are
+/// written as a `.sup` stream with timestamps rebased to playlist time, any
+/// other stream type as its raw elementary stream.
+pub fn demux<W: Write>(
+    reader: &PlaylistReader,
+    pid: u16,
+    stream_type: StreamType,
+    options: DemuxOptions,
+    out: &mut W,
+) -> Result<DemuxStats> {
+    let mut sink: Box<dyn PesSink + '_> = if stream_type == StreamType::PRESENTATION_GRAPHICS {
+        Box::new(SupWriter::new(out, options.forced_only))
+    } else {
+        Box::new(RawWriter { out })
+    };
+
+    let mut stats = DemuxStats::default();
+    for segment in reader.segments() {
+        let data = reader.open_segment(segment)?;
+        demux_segment(data, segment, pid, sink.as_mut(), &mut stats)?;
+    }
+    sink.finish(&mut stats)?;
+    Ok(stats)
+}
+
+/// Extracts one PID of the named playlist of `disc` to `out`, see
+/// [`demux`].
+pub fn demux_playlist<W: Write>(
+    disc: &Disc,
+    name: &str,
+    angle: usize,
+    pid: u16,
+    options: DemuxOptions,
+    out: &mut W,
+) -> Result<DemuxStats> {
+    let reader = disc.playlist_reader(name, angle)?;
+    let stream_type = disc
+        .playlist(name)
+        .and_then(|p| p.parser.playlist_streams().get(&pid))
+        .map(|s| s.stream_type())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("{} has no stream with PID {:#06x}", name, pid),
+            )
+        })?;
+    demux(&reader, pid, stream_type, options, out)
+}
+
+fn demux_segment<R: Read>(
+    data: R,
+    segment: &Segment,
+    pid: u16,
+    sink: &mut dyn PesSink,
+    stats: &mut DemuxStats,
+) -> Result<()> {
+    let mut reader = BufReader::new(data);
+
+    let timing = Timing {
+        in_pts: (segment.time_in * PTS_CLOCK).round() as u64,
+        out_pts: (segment.time_out * PTS_CLOCK).round() as u64,
+        playlist_pts: (segment.playlist_time * PTS_CLOCK).round() as u64,
+    };
+    for_each_pes(&mut reader, pid, |data| {
+        write_pes(data, &timing, sink, stats)
+    })
+}
+
+/// Calls `f` with each whole PES packet of `pid`, header included, until
+/// it fails.
+pub(crate) fn for_each_pes<R: Read>(
+    reader: &mut R,
+    pid: u16,
+    mut f: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut pes: Option<Vec<u8>> = None;
+    let mut result = Ok(());
+    stream_file::for_each_packet(reader, |packet| {
+        if packet.pid != pid {
+            return true;
+        }
+        if packet.payload_unit_start {
+            if let Some(data) = pes.take() {
+                result = f(&data);
+            }
+            pes = Some(packet.payload.to_vec());
+        } else if let Some(data) = pes.as_mut() {
+            data.extend_from_slice(packet.payload);
+        }
+        result.is_ok()
+    })?;
+    result?;
+    match pes {
+        Some(data) => f(&data),
+        None => Ok(()),
+    }
+}
+
+struct Timing {
+    in_pts: u64,
+    out_pts: u64,
+    playlist_pts: u64,
+}
+
+impl Timing {
+    /// Playlist time of a clip timestamp. Timestamps before the in point
+    /// (segments start at the entry point before it) are clamped to the
+    /// start of the play item, `None` after the out point.
+    fn rebase(&self, pts: u64) -> Option<u64> {
+        if clock_delta(pts, self.in_pts, PTS_MODULUS) < PTS_MODULUS / 2 {
+            return Some(self.playlist_pts);
+        }
+        let offset = clock_delta(self.in_pts, pts, PTS_MODULUS);
+        if offset > clock_delta(self.in_pts, self.out_pts, PTS_MODULUS) {
+            return None;
+        }
+        Some((self.playlist_pts + offset) % PTS_MODULUS)
+    }
+}
+
+fn write_pes(
+    data: &[u8],
+    timing: &Timing,
+    sink: &mut dyn PesSink,
+    stats: &mut DemuxStats,
+) -> Result<()> {
+    let Some(header) = PesHeader::parse(data) else {
+        return Ok(());
+    };
+    let Some(payload) = data.get(header.header_len..) else {
+        return Ok(());
+    };
+    stats.pes_count += 1;
+    let pts = header.pts.and_then(|pts| timing.rebase(pts));
+    let dts = header.dts.and_then(|dts| timing.rebase(dts));
+    sink.write_pes(payload, pts, dts, stats)
+}
+
+trait PesSink {
+    /// Timestamps are in playlist time, `None` when missing or after the
+    /// play item.
+    fn write_pes(
+        &mut self,
+        payload: &[u8],
+        pts: Option<u64>,
+        dts: Option<u64>,
+        stats: &mut DemuxStats,
+    ) -> Result<()>;
+
+    fn finish(&mut self, _stats: &mut DemuxStats) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct RawWriter<'a, W: Write> {
+    out: &'a mut W,
+}
+
+impl<W: Write> PesSink for RawWriter<'_, W> {
+    fn write_pes(
+        &mut self,
+        payload: &[u8],
+        _pts: Option<u64>,
+        _dts: Option<u64>,
+        stats: &mut DemuxStats,
+    ) -> Result<()> {
+        self.out.write_all(payload)?;
+        stats.bytes_written += payload.len() as u64;
+        Ok(())
+    }
+
+    fn finish(&mut self, _stats: &mut DemuxStats) -> Result<()> {
+        self.out.flush()
+    }
+}
+
+/// A PGS segment with the timestamps of the PES it came in.
+#[derive(Clone)]
+struct PgsSegment {
+    pts: u64,
+    dts: u64,
+    kind: u8,
+    data: Vec<u8>,
+}
+
+impl PgsSegment {
+    /// What a window, palette or object definition defines, so a later
+    /// definition of the same thing replaces it.
+    fn definition_id(&self) -> Option<(u8, u16)> {
+        match self.kind {
+            PGS_WINDOW_DEFINITION => Some((self.kind, 0)),
+            PGS_PALETTE_DEFINITION => Some((self.kind, u16::from(*self.data.first()?))),
+            PGS_OBJECT_DEFINITION => Some((
+                self.kind,
+                u16::from_be_bytes([*self.data.first()?, *self.data.get(1)?]),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Collects PGS segments into display sets (presentation composition to
+/// end of display set) and writes them in `.sup` framing.
+struct SupWriter<'a, W: Write> {
+    out: &'a mut W,
+    forced_only: bool,
+    display_set: Vec<PgsSegment>,
+    /// Whether the last display set written put objects on screen.
+    showing: bool,
+    /// Definitions of the current epoch, in the order they were given.
+    definitions: Vec<PgsSegment>,
+    /// Whether a display set of the current epoch has been dropped since
+    /// the last one written, which may have carried definitions.
+    dropped: bool,
+}
+
+impl<'a, W: Write> SupWriter<'a, W> {
+    fn new(out: &'a mut W, forced_only: bool) -> Self {
+        SupWriter {
+            out,
+            forced_only,
+            display_set: Vec::new(),
+            showing: false,
+            definitions: Vec::new(),
+            dropped: false,
+        }
+    }
+
+    /// Adds the definitions of a display set to those of the epoch and
+    /// returns the earlier ones it does not redefine.
+    fn update_definitions(
+        &mut self,
+        segments: &[PgsSegment],
+        epoch_start: bool,
+    ) -> Vec<PgsSegment> {
+        if epoch_start {
+            self.definitions.clear();
+            self.dropped = false;
+        }
+        let ids = segments
+            .iter()
+            .filter_map(PgsSegment::definition_id)
+            .collect::<Vec<_>>();
+        let earlier = self
+            .definitions
+            .iter()
+            .filter(|d| d.definition_id().is_some_and(|id| !ids.contains(&id)))
+            .cloned()
+            .collect();
+        for segment in segments {
+            let Some(id) = segment.definition_id() else {
+                continue;
+            };
+            // Objects too large for one segment continue in the next ones.
+            let continued = segment.kind == PGS_OBJECT_DEFINITION
+                && segment
+                    .data
+                    .get(3)
+                    .is_some_and(|f| f & PGS_FIRST_IN_SEQUENCE == 0);
+            if !continued {
+                self.definitions.retain(|d| d.definition_id() != Some(id));
+            }
+            self.definitions.push(segment.clone());
+        }
+        earlier
+    }
+
+    fn write_display_set(&mut self, stats: &mut DemuxStats) -> Result<()> {
+        let mut segments = std::mem::take(&mut self.display_set);
+        let Some(composition) = segments
+            .iter_mut()
+            .find(|s| s.kind == PGS_PRESENTATION_COMPOSITION)
+        else {
+            return Ok(());
+        };
+        let objects = composition_objects(&composition.data).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidData, "truncated presentation composition")
+        })?;
+        let forced = objects.iter().filter(|o| o.forced).count();
+        stats.display_sets += 1;
+        if forced > 0 {
+            stats.forced_display_sets += 1;
+        }
+        let epoch_start = composition
+            .data
+            .get(7)
+            .is_some_and(|state| state & PGS_EPOCH_START != 0);
+
+        if self.forced_only {
+            let earlier = self.update_definitions(&segments, epoch_start);
+            let keep = if objects.is_empty() {
+                // Only clear the screen when it shows a forced subtitle.
+                self.showing
+            } else {
+                forced > 0
+            };
+            if !keep {
+                self.dropped = true;
+                return Ok(());
+            }
+            let Some(composition) = segments
+                .iter_mut()
+                .find(|s| s.kind == PGS_PRESENTATION_COMPOSITION)
+            else {
+                return Ok(());
+            };
+            if forced < objects.len() {
+                composition.data = keep_forced_objects(&composition.data, &objects);
+            }
+            // Sets dropped earlier in the epoch may have defined what this
+            // one shows, so it starts a new epoch carrying those definitions.
+            if self.dropped {
+                composition.data[7] = PGS_EPOCH_START;
+                let (pts, dts) = (composition.pts, composition.dts);
+                let position = segments
+                    .iter()
+                    .position(|s| s.kind == PGS_PRESENTATION_COMPOSITION)
+                    .map_or(0, |i| i + 1);
+                segments.splice(
+                    position..position,
+                    earlier.into_iter().map(|d| PgsSegment { pts, dts, ..d }),
+                );
+                self.dropped = false;
+            }
+        }
+        self.showing = !objects.is_empty();
+
+        for segment in segments {
+            self.out.write_all(PGS_MAGIC)?;
+            self.out.write_all(&(segment.pts as u32).to_be_bytes())?;
+            self.out.write_all(&(segment.dts as u32).to_be_bytes())?;
+            self.out.write_all(&[segment.kind])?;
+            self.out
+                .write_all(&(segment.data.len() as u16).to_be_bytes())?;
+            self.out.write_all(&segment.data)?;
+            stats.bytes_written += 13 + segment.data.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> PesSink for SupWriter<'_, W> {
+    fn write_pes(
+        &mut self,
+        payload: &[u8],
+        pts: Option<u64>,
+        dts: Option<u64>,
+        stats: &mut DemuxStats,
+    ) -> Result<()> {
+        let Some(pts) = pts else {
+            return Ok(());
+        };
+        let dts = dts.unwrap_or(0);
+
+        let mut pos = 0;
+        while pos + 3 <= payload.len() {
+            let kind = payload[pos];
+            let size = u16::from_be_bytes([payload[pos + 1], payload[pos + 2]]) as usize;
+            let Some(data) = payload.get(pos + 3..pos + 3 + size) else {
+                break;
+            };
+            pos += 3 + size;
+
+            if kind == PGS_PRESENTATION_COMPOSITION {
+                self.display_set.clear();
+            }
+            self.display_set.push(PgsSegment {
+                pts,
+                dts,
+                kind,
+                data: data.to_vec(),
+            });
+            if kind == PGS_END_OF_DISPLAY_SET {
+                self.write_display_set(stats)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _stats: &mut DemuxStats) -> Result<()> {
+        self.out.flush()
+    }
+}
+
+struct CompositionObject {
+    /// Byte range of the object in the composition segment.
+    start: usize,
+    end: usize,
+    forced: bool,
+}
+
+/// Composition objects of a presentation composition segment.
+fn composition_objects(data: &[u8]) -> Option<Vec<CompositionObject>> {
+    let count = *data.get(10)? as usize;
+    let mut objects = Vec::with_capacity(count);
+    let mut pos = 11;
+    for _ in 0..count {
+        let flags = *data.get(pos + 3)?;
+        let len = if flags & PGS_OBJECT_CROPPED != 0 {
+            16
+        } else {
+            8
+        };
+        if pos + len > data.len() {
+            return None;
+        }
+        objects.push(CompositionObject {
+            start: pos,
+            end: pos + len,
+            forced: flags & PGS_OBJECT_FORCED != 0,
+        });
+        pos += len;
+    }
+    Some(objects)
+}
+
+fn keep_forced_objects(data: &[u8], objects: &[CompositionObject]) -> Vec<u8> {
+    let mut out = data[..11].to_vec();
+    out[10] = objects.iter().filter(|o| o.forced).count() as u8;
+    for object in objects.iter().filter(|o| o.forced) {
+        out.extend_from_slice(&data[object.start..object.end]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{pes, pes_packets};
+
+    const IN_PTS: u64 = 10 * 90_000;
+    const OUT_PTS: u64 = 20 * 90_000;
+    const PLAYLIST_PTS: u64 = 100 * 90_000;
+
+    fn timing() -> Timing {
+        Timing {
+            in_pts: IN_PTS,
+            out_pts: OUT_PTS,
+            playlist_pts: PLAYLIST_PTS,
+        }
+    }
+
+    fn pgs_segment(kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![kind];
+        segment.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    /// A display set showing `objects` (id, forced), with a window, palette
+    /// and object definition when it starts an epoch.
+    fn display_set(state: u8, objects: &[(u16, bool)]) -> Vec<u8> {
+        let mut composition = vec![0x07, 0x80, 0x04, 0x38, 0x10, 0, 1, state, 0, 0];
+        composition.push(objects.len() as u8);
+        for (id, forced) in objects {
+            composition.extend_from_slice(&id.to_be_bytes());
+            composition.push(0);
+            composition.push(if *forced { PGS_OBJECT_FORCED } else { 0 });
+            composition.extend_from_slice(&[0, 0x10, 0, 0x20]);
+        }
+        let mut set = pgs_segment(PGS_PRESENTATION_COMPOSITION, &composition);
+        if state == PGS_EPOCH_START {
+            set.extend(pgs_segment(
+                PGS_WINDOW_DEFINITION,
+                &[1, 0, 0, 0x10, 0, 0x20, 0, 0x40, 0, 0x10],
+            ));
+            set.extend(pgs_segment(
+                PGS_PALETTE_DEFINITION,
+                &[0, 0, 1, 235, 128, 128, 255],
+            ));
+            for (id, _) in objects {
+                let mut object = id.to_be_bytes().to_vec();
+                object.extend_from_slice(&[0, 0xC0, 0, 0, 8, 0, 1, 0, 1, 1, 0, 0]);
+                set.extend(pgs_segment(PGS_OBJECT_DEFINITION, &object));
+            }
+        }
+        set.extend(pgs_segment(PGS_END_OF_DISPLAY_SET, &[]));
+        set
+    }
+
+    /// (pts, kind, data) of each segment of a `.sup` stream.
+    fn sup_segments(sup: &[u8]) -> Vec<(u32, u8, Vec<u8>)> {
+        let mut segments = Vec::new();
+        let mut pos = 0;
+        while pos < sup.len() {
+            assert_eq!(&sup[pos..pos + 2], PGS_MAGIC);
+            let pts = u32::from_be_bytes(sup[pos + 2..pos + 6].try_into().unwrap());
+            let size = u16::from_be_bytes([sup[pos + 11], sup[pos + 12]]) as usize;
+            segments.push((pts, sup[pos + 10], sup[pos + 13..pos + 13 + size].to_vec()));
+            pos += 13 + size;
+        }
+        segments
+    }
+
+    #[test]
+    fn rebases_to_playlist_time() {
+        let timing = timing();
+        assert_eq!(timing.rebase(IN_PTS), Some(PLAYLIST_PTS));
+        assert_eq!(timing.rebase(IN_PTS + 4500), Some(PLAYLIST_PTS + 4500));
+        assert_eq!(
+            timing.rebase(OUT_PTS),
+            Some(PLAYLIST_PTS + OUT_PTS - IN_PTS)
+        );
+        assert_eq!(timing.rebase(IN_PTS - 90_000), Some(PLAYLIST_PTS));
+        assert_eq!(timing.rebase(OUT_PTS + 1), None);
+
+        // Across the 33 bit wrap.
+        let wrapped = Timing {
+            in_pts: PTS_MODULUS - 90_000,
+            out_pts: 90_000,
+            playlist_pts: 0,
+        };
+        assert_eq!(wrapped.rebase(45_000), Some(135_000));
+        assert_eq!(wrapped.rebase(PTS_MODULUS - 180_000), Some(0));
+        assert_eq!(wrapped.rebase(180_000), None);
+    }
+
+    #[test]
+    fn clamps_early_display_sets_to_item_start() {
+        let mut stream = Vec::new();
+        for pts in [IN_PTS - 90_000, IN_PTS + 90_000, OUT_PTS + 90_000] {
+            let set = display_set(PGS_EPOCH_START, &[(0, false)]);
+            stream.extend(pes_packets(0x1200, &pes(0xBD, Some(pts), &set)));
+        }
+        let segment = Segment {
+            path: "BDMV/STREAM/00001.M2TS".to_owned(),
+            start: 0,
+            end: stream.len() as u64,
+            offset: 0,
+            time_in: 10.0,
+            time_out: 20.0,
+            playlist_time: 100.0,
+        };
+
+        let mut sup = Vec::new();
+        let mut stats = DemuxStats::default();
+        let mut writer = SupWriter::new(&mut sup, false);
+        demux_segment(&stream[..], &segment, 0x1200, &mut writer, &mut stats).unwrap();
+        writer.finish(&mut stats).unwrap();
+
+        assert_eq!((stats.pes_count, stats.display_sets), (3, 2));
+        let starts = sup_segments(&sup)
+            .into_iter()
+            .filter(|(_, kind, _)| *kind == PGS_PRESENTATION_COMPOSITION)
+            .map(|(pts, _, _)| pts as u64)
+            .collect::<Vec<_>>();
+        assert_eq!(starts, [PLAYLIST_PTS, PLAYLIST_PTS + 90_000]);
+    }
+
+    #[test]
+    fn forced_set_carries_definitions_of_dropped_epoch_start() {
+        let mut sup = Vec::new();
+        let mut stats = DemuxStats::default();
+        let mut writer = SupWriter::new(&mut sup, true);
+        let sets = [
+            display_set(PGS_EPOCH_START, &[(1, false)]),
+            display_set(0, &[(1, true)]),
+            display_set(0, &[]),
+            display_set(0, &[(1, false)]),
+            display_set(PGS_EPOCH_START, &[(2, true)]),
+        ];
+        for (i, set) in sets.iter().enumerate() {
+            writer
+                .write_pes(set, Some(i as u64 * 90_000), None, &mut stats)
+                .unwrap();
+        }
+        writer.finish(&mut stats).unwrap();
+        assert_eq!((stats.display_sets, stats.forced_display_sets), (5, 2));
+
+        let segments = sup_segments(&sup);
+        let kinds = segments
+            .iter()
+            .map(|(_, kind, _)| *kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                // The forced set, now an epoch start with the definitions
+                // of the dropped one.
+                PGS_PRESENTATION_COMPOSITION,
+                PGS_WINDOW_DEFINITION,
+                PGS_PALETTE_DEFINITION,
+                PGS_OBJECT_DEFINITION,
+                PGS_END_OF_DISPLAY_SET,
+                // Clearing it.
+                PGS_PRESENTATION_COMPOSITION,
+                PGS_END_OF_DISPLAY_SET,
+                // A new epoch brings its own definitions.
+                PGS_PRESENTATION_COMPOSITION,
+                PGS_WINDOW_DEFINITION,
+                PGS_PALETTE_DEFINITION,
+                PGS_OBJECT_DEFINITION,
+                PGS_END_OF_DISPLAY_SET,
+            ]
+        );
+        assert_eq!(segments[0].2[7], PGS_EPOCH_START);
+        assert!(segments[1..5].iter().all(|(pts, _, _)| *pts == 90_000));
+        assert_eq!(segments[5].2[7], 0);
+    }
+
+    #[test]
+    fn keeps_only_forced_objects() {
+        let mut sup = Vec::new();
+        let mut stats = DemuxStats::default();
+        let mut writer = SupWriter::new(&mut sup, true);
+        let set = display_set(PGS_EPOCH_START, &[(1, false), (2, true)]);
+        writer.write_pes(&set, Some(0), None, &mut stats).unwrap();
+
+        let composition = &sup_segments(&sup)[0].2;
+        assert_eq!(composition[10], 1);
+        assert_eq!(&composition[11..13], [0, 2]);
+        assert_eq!(composition.len(), 11 + 8);
+    }
+
+    #[test]
+    fn rejects_truncated_composition() {
+        let mut sup = Vec::new();
+        let mut stats = DemuxStats::default();
+        let mut writer = SupWriter::new(&mut sup, false);
+        let mut set = pgs_segment(PGS_PRESENTATION_COMPOSITION, &[0; 10]);
+        set.extend(pgs_segment(PGS_END_OF_DISPLAY_SET, &[]));
+        let error = writer
+            .write_pes(&set, Some(0), None, &mut stats)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
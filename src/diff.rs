@@ -0,0 +1,545 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{
+    parser::Parser,
+    ts::{plist::ChapterSection, stream::Stream, stream_clip::StreamClip, stream_type::StreamType},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub a: String,
+    pub b: String,
+}
+
+impl FieldChange {
+    fn compare<T: PartialEq + Display>(changes: &mut Vec<FieldChange>, field: &str, a: T, b: T) {
+        if a != b {
+            changes.push(FieldChange {
+                field: field.to_owned(),
+                a: a.to_string(),
+                b: b.to_string(),
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum StreamChange {
+    Added { pid: u16, stream: String },
+    Removed { pid: u16, stream: String },
+    Changed { pid: u16, fields: Vec<FieldChange> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ItemChange {
+    Added {
+        index: usize,
+        clip: String,
+    },
+    Removed {
+        index: usize,
+        clip: String,
+    },
+    Moved {
+        from: usize,
+        to: usize,
+        clip: String,
+    },
+    Changed {
+        index_a: usize,
+        index_b: usize,
+        clip: String,
+        fields: Vec<FieldChange>,
+        streams: Vec<StreamChange>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ChapterChange {
+    Added {
+        index: usize,
+        play_item_id: u16,
+        timestamp: f64,
+    },
+    Removed {
+        index: usize,
+        play_item_id: u16,
+        timestamp: f64,
+    },
+    Changed {
+        index_a: usize,
+        index_b: usize,
+        fields: Vec<FieldChange>,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlaylistDiff {
+    pub header: Vec<FieldChange>,
+    pub items: Vec<ItemChange>,
+    pub chapters: Vec<ChapterChange>,
+}
+
+impl PlaylistDiff {
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.items.is_empty() && self.chapters.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Compares two parsed playlists. Play items are aligned on their clip names
+/// so inserted or dropped items do not show up as changes to every item that
+/// follows them.
+pub fn diff(a: &Parser, b: &Parser) -> PlaylistDiff {
+    PlaylistDiff {
+        header: diff_header(a, b),
+        items: diff_items(a.clips(), b.clips()),
+        chapters: diff_chapters(a.chapters(), b.chapters()),
+    }
+}
+
+fn diff_header(a: &Parser, b: &Parser) -> Vec<FieldChange> {
+    let (a, b) = (a.mpls_header(), b.mpls_header());
+    let mut changes = Vec::new();
+    FieldChange::compare(&mut changes, "magic", a.magic_string(), b.magic_string());
+    FieldChange::compare(
+        &mut changes,
+        "playback_type",
+        a.playback_type(),
+        b.playback_type(),
+    );
+    FieldChange::compare(
+        &mut changes,
+        "playback_count",
+        a.playback_count(),
+        b.playback_count(),
+    );
+    FieldChange::compare(
+        &mut changes,
+        "operation_mask",
+        hex(&a.operation_mask()),
+        hex(&b.operation_mask()),
+    );
+    FieldChange::compare(
+        &mut changes,
+        "misc_flags",
+        format!("{:02X}", a.misc_flags),
+        format!("{:02X}", b.misc_flags),
+    );
+    changes
+}
+
+fn diff_items(a: &[StreamClip], b: &[StreamClip]) -> Vec<ItemChange> {
+    let pairs = align(a, b, |clip| clip.name.as_str());
+    let mut changes = Vec::new();
+
+    let removed = (0..a.len())
+        .filter(|i| !pairs.iter().any(|(ia, _)| ia == i))
+        .collect::<Vec<_>>();
+    let mut added = (0..b.len())
+        .filter(|i| !pairs.iter().any(|(_, ib)| ib == i))
+        .collect::<Vec<_>>();
+
+    let mut matched = pairs;
+    for ia in removed {
+        if let Some(pos) = added.iter().position(|&ib| b[ib].name == a[ia].name) {
+            let ib = added.remove(pos);
+            changes.push(ItemChange::Moved {
+                from: ia,
+                to: ib,
+                clip: a[ia].name.to_owned(),
+            });
+            matched.push((ia, ib));
+        } else {
+            changes.push(ItemChange::Removed {
+                index: ia,
+                clip: a[ia].name.to_owned(),
+            });
+        }
+    }
+    for ib in added {
+        changes.push(ItemChange::Added {
+            index: ib,
+            clip: b[ib].name.to_owned(),
+        });
+    }
+
+    matched.sort_unstable();
+    for (ia, ib) in matched {
+        let (ca, cb) = (&a[ia], &b[ib]);
+        let mut fields = Vec::new();
+        FieldChange::compare(&mut fields, "time_in", ca.time_in, cb.time_in);
+        FieldChange::compare(&mut fields, "time_out", ca.time_out, cb.time_out);
+        let streams = diff_streams(&ca.streams, &cb.streams);
+        if !fields.is_empty() || !streams.is_empty() {
+            changes.push(ItemChange::Changed {
+                index_a: ia,
+                index_b: ib,
+                clip: ca.name.to_owned(),
+                fields,
+                streams,
+            });
+        }
+    }
+
+    changes
+}
+
+/// Longest common subsequence of the items' keys, returned as index pairs.
+fn align<'a, T, K: PartialEq>(
+    a: &'a [T],
+    b: &'a [T],
+    key: impl Fn(&'a T) -> K,
+) -> Vec<(usize, usize)> {
+    let (a, b) = (
+        a.iter().map(&key).collect::<Vec<_>>(),
+        b.iter().map(&key).collect::<Vec<_>>(),
+    );
+    let mut table = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = if a[i] == b[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Streams are matched on PID and kind, so a PID reused for another kind of
+/// stream shows up as a removal and an addition.
+fn diff_streams(a: &[Stream], b: &[Stream]) -> Vec<StreamChange> {
+    let same = |sa: &Stream, sb: &Stream| sa.pid() == sb.pid() && kind(sa) == kind(sb);
+    let mut changes = Vec::new();
+    for sa in a.iter() {
+        match b.iter().find(|sb| same(sa, sb)) {
+            Some(sb) => {
+                let mut fields = Vec::new();
+                FieldChange::compare(&mut fields, "position", position(a, sa), position(b, sb));
+                for ((name, va), (_, vb)) in stream_fields(sa).iter().zip(stream_fields(sb).iter())
+                {
+                    FieldChange::compare(&mut fields, name, va, vb);
+                }
+                if !fields.is_empty() {
+                    changes.push(StreamChange::Changed {
+                        pid: sa.pid(),
+                        fields,
+                    });
+                }
+            }
+            None => changes.push(StreamChange::Removed {
+                pid: sa.pid(),
+                stream: describe(sa),
+            }),
+        }
+    }
+    for sb in b.iter() {
+        if !a.iter().any(|sa| same(sa, sb)) {
+            changes.push(StreamChange::Added {
+                pid: sb.pid(),
+                stream: describe(sb),
+            });
+        }
+    }
+    changes
+}
+
+/// The STN table category a stream is listed under.
+fn kind(stream: &Stream) -> &'static str {
+    match stream {
+        Stream::VideoStream(_) => "video",
+        Stream::AudioStream(_) => "audio",
+        Stream::GraphicsStream(s) if s.stream_type == StreamType::INTERACTIVE_GRAPHICS => "ig",
+        Stream::GraphicsStream(_) => "pg",
+        Stream::TextStream(_) => "text",
+    }
+}
+
+/// Stream number of `stream` within its category, counting from 1 like
+/// player stream selection does.
+fn position(streams: &[Stream], stream: &Stream) -> usize {
+    streams
+        .iter()
+        .filter(|s| kind(s) == kind(stream))
+        .position(|s| s.pid() == stream.pid())
+        .map_or(0, |i| i + 1)
+}
+
+fn stream_fields(stream: &Stream) -> Vec<(&'static str, String)> {
+    let mut fields = vec![("codec", stream.stream_type().codec_name().to_owned())];
+    match stream {
+        Stream::VideoStream(s) => {
+            fields.push(("video_format", s.video_format.to_string()));
+            fields.push(("frame_rate", s.frame_rate.to_string()));
+            fields.push(("aspect_ratio", s.aspect_ratio.to_string()));
+        }
+        Stream::AudioStream(s) => {
+            fields.push(("language", s.language.to_string()));
+            fields.push(("channel_layout", s.channel_layout.to_string()));
+            fields.push(("sample_rate", s.sample_rate.to_string()));
+        }
+        Stream::GraphicsStream(s) => fields.push(("language", s.language.to_string())),
+        Stream::TextStream(s) => {
+            fields.push(("language", s.language.to_string()));
+            fields.push(("character_code", s.character_code.to_string()));
+        }
+    }
+    fields
+}
+
+fn describe(stream: &Stream) -> String {
+    stream_fields(stream)
+        .into_iter()
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// Marks are aligned on their play item and time, so an inserted or dropped
+/// mark does not show up as a change to every mark that follows it.
+fn diff_chapters(a: &[ChapterSection], b: &[ChapterSection]) -> Vec<ChapterChange> {
+    let pairs = align(a, b, |mark| (mark.play_item_id, mark.timestamp()));
+    let mut changes = Vec::new();
+    for (index, mark) in a.iter().enumerate() {
+        if !pairs.iter().any(|(ia, _)| *ia == index) {
+            changes.push(ChapterChange::Removed {
+                index,
+                play_item_id: mark.play_item_id,
+                timestamp: mark.timestamp(),
+            });
+        }
+    }
+    for (index, mark) in b.iter().enumerate() {
+        if !pairs.iter().any(|(_, ib)| *ib == index) {
+            changes.push(ChapterChange::Added {
+                index,
+                play_item_id: mark.play_item_id,
+                timestamp: mark.timestamp(),
+            });
+        }
+    }
+    for (ia, ib) in pairs {
+        let (ca, cb) = (&a[ia], &b[ib]);
+        let mut fields = Vec::new();
+        FieldChange::compare(&mut fields, "mark_type", ca.mark_type, cb.mark_type);
+        FieldChange::compare(&mut fields, "duration", ca.duration(), cb.duration());
+        if !fields.is_empty() {
+            changes.push(ChapterChange::Changed {
+                index_a: ia,
+                index_b: ib,
+                fields,
+            });
+        }
+    }
+    changes
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.a, self.b)
+    }
+}
+
+impl Display for PlaylistDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "playlists are identical");
+        }
+        if !self.header.is_empty() {
+            writeln!(f, "Header")?;
+            for change in self.header.iter() {
+                writeln!(f, "  ~ {}", change)?;
+            }
+        }
+        if !self.items.is_empty() {
+            writeln!(f, "Play items")?;
+            for change in self.items.iter() {
+                match change {
+                    ItemChange::Added { index, clip } => writeln!(f, "  + [{}] {}", index, clip)?,
+                    ItemChange::Removed { index, clip } => writeln!(f, "  - [{}] {}", index, clip)?,
+                    ItemChange::Moved { from, to, clip } => {
+                        writeln!(f, "  > [{} -> {}] {}", from, to, clip)?
+                    }
+                    ItemChange::Changed {
+                        index_a,
+                        index_b,
+                        clip,
+                        fields,
+                        streams,
+                    } => {
+                        writeln!(f, "  ~ [{} / {}] {}", index_a, index_b, clip)?;
+                        for field in fields.iter() {
+                            writeln!(f, "      {}", field)?;
+                        }
+                        for stream in streams.iter() {
+                            match stream {
+                                StreamChange::Added { pid, stream } => {
+                                    writeln!(f, "      + stream {}: {}", pid, stream)?
+                                }
+                                StreamChange::Removed { pid, stream } => {
+                                    writeln!(f, "      - stream {}: {}", pid, stream)?
+                                }
+                                StreamChange::Changed { pid, fields } => {
+                                    for field in fields.iter() {
+                                        writeln!(f, "      ~ stream {}: {}", pid, field)?;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if !self.chapters.is_empty() {
+            writeln!(f, "Chapters")?;
+            for change in self.chapters.iter() {
+                match change {
+                    ChapterChange::Added {
+                        index,
+                        play_item_id,
+                        timestamp,
+                    } => writeln!(f, "  + [{}] item {} at {}", index, play_item_id, timestamp)?,
+                    ChapterChange::Removed {
+                        index,
+                        play_item_id,
+                        timestamp,
+                    } => writeln!(f, "  - [{}] item {} at {}", index, play_item_id, timestamp)?,
+                    ChapterChange::Changed {
+                        index_a,
+                        index_b,
+                        fields,
+                    } => {
+                        for field in fields.iter() {
+                            writeln!(f, "  ~ [{} / {}] {}", index_a, index_b, field)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{mpls, PlayItem, MPLS_00800, MPLS_00801};
+
+    fn audio(pid: u16, coding: u8, lang: &[u8; 3]) -> Stream {
+        Stream::from_attributes(pid, &[coding, 0x61, lang[0], lang[1], lang[2]]).unwrap()
+    }
+
+    fn graphics(pid: u16, lang: &[u8; 3]) -> Stream {
+        Stream::from_attributes(pid, &[0x90, lang[0], lang[1], lang[2]]).unwrap()
+    }
+
+    #[test]
+    fn identical_samples() {
+        let a = Parser::from_bytes(MPLS_00800).unwrap();
+        let b = Parser::parse(&mut std::io::Cursor::new(MPLS_00800)).unwrap();
+        let diff = diff(&a, &b);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "playlists are identical\n");
+    }
+
+    #[test]
+    fn sample_streams() {
+        let a = Parser::from_bytes(MPLS_00800).unwrap();
+        let b = Parser::from_bytes(MPLS_00801).unwrap();
+        let diff = diff(&a, &b);
+        assert!(diff.header.is_empty());
+        assert!(diff.chapters.is_empty());
+        let text = diff.to_string();
+        assert!(text.contains("- stream 4353: Dolby Digital Audio / eng / Multi-channel / 48 kHz"));
+        assert!(text.contains("~ stream 4788: position: 19 -> 4"));
+        assert!(!text.contains("AC3_AUDIO"));
+    }
+
+    #[test]
+    fn stream_changes() {
+        let a = [audio(0x1100, 0x81, b"eng"), audio(0x1101, 0x82, b"fra")];
+        let b = [audio(0x1101, 0x82, b"deu"), audio(0x1100, 0x81, b"eng")];
+        let changes = diff_streams(&a, &b);
+        assert_eq!(changes.len(), 2);
+        match &changes[1] {
+            StreamChange::Changed { pid, fields } => {
+                assert_eq!(*pid, 0x1101);
+                assert_eq!(fields[0].to_string(), "position: 2 -> 1");
+                assert_eq!(fields[1].to_string(), "language: fra -> deu");
+            }
+            change => panic!("unexpected {:?}", change),
+        }
+    }
+
+    #[test]
+    fn kind_change_is_removal_and_addition() {
+        let a = [audio(0x1200, 0x81, b"eng")];
+        let b = [graphics(0x1200, b"eng")];
+        let changes = diff_streams(&a, &b);
+        assert!(matches!(
+            changes.as_slice(),
+            [
+                StreamChange::Removed { pid: 0x1200, .. },
+                StreamChange::Added { pid: 0x1200, .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn inserted_chapter_mark() {
+        let playlist = |marks: &[(u16, u32)]| {
+            let item = PlayItem {
+                clip: "00001",
+                time_in: 0,
+                time_out: 45_000 * 600,
+                streams: &[],
+            };
+            Parser::from_bytes(&mpls(&[item], marks)).unwrap()
+        };
+        let a = playlist(&[(0, 0), (0, 45_000 * 300)]);
+        let b = playlist(&[(0, 0), (0, 45_000 * 120), (0, 45_000 * 300)]);
+
+        let diff = diff(&a, &b);
+        assert!(matches!(
+            diff.chapters.as_slice(),
+            [ChapterChange::Added {
+                index: 1,
+                play_item_id: 0,
+                timestamp,
+            }] if *timestamp == 120.0
+        ));
+        assert!(diff
+            .to_string()
+            .ends_with("Chapters\n  + [1] item 0 at 120\n"));
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufReader, Error, ErrorKind, Result, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    demux::{self, DemuxOptions, DemuxStats},
+    duplicates::DuplicateGroup,
+    lint::{self, LintReport},
+    parser::Parser,
+    playlist_reader::{self, PlaylistReader},
+    probe,
+    ranking::PlaylistRank,
+    scan, text_subtitles,
+    ts::{
+        clpi::ClipInfo, codec::StreamInfo, index::IndexTable, stream_file::StreamFileScan,
+        textst::TextSubtitle,
+    },
+    verify,
+    vfs::{self, DirFs, DiscFs},
+};
+
+#[derive(Debug)]
+pub struct DiscPlaylist {
+    pub name: String,
+    pub parser: Parser,
+}
+
+#[derive(Debug)]
+pub struct Disc {
+    /// Files of the disc, with `BDMV` at the root.
+    pub fs: Arc<dyn DiscFs>,
+    /// Folder or image file name, the label when the file system has none.
+    pub name: String,
+    pub playlists: Vec<DiscPlaylist>,
+    pub stream_scans: HashMap<String, StreamFileScan>,
+    pub stream_probes: HashMap<String, BTreeMap<u16, StreamInfo>>,
+    /// Stream files that could not be opened or read, with the reason.
+    pub stream_errors: BTreeMap<String, String>,
+}
+
+impl Disc {
+    /// Opens a disc from its root folder, its `BDMV` folder or a UDF or
+    /// ISO 9660 image, and parses every playlist in `BDMV/PLAYLIST`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if path.is_file() {
+            let fs = vfs::open_image(Arc::new(path.to_path_buf()))?;
+            return Self::from_fs(fs, file_name(path, true));
+        }
+        let root = Self::find_disc_root(path)?;
+        let name = file_name(root.root(), false);
+        Self::from_fs(Arc::new(root), name)
+    }
+
+    /// Opens a disc from any file system holding its `BDMV` folder.
+    pub fn from_fs(fs: Arc<dyn DiscFs>, name: String) -> Result<Self> {
+        let entries = playlist_files(fs.as_ref())?;
+        let mut playlists = Vec::with_capacity(entries.len());
+        for file in entries {
+            let name = file.to_uppercase();
+            let result = fs
+                .open(&vfs::join("BDMV/PLAYLIST", &file))
+                .and_then(|file| Parser::parse(&mut BufReader::new(file)));
+            match result {
+                Ok(parser) => playlists.push(DiscPlaylist { name, parser }),
+                Err(e) => error!(&name, "{}", e),
+            }
+        }
+
+        Ok(Disc {
+            fs,
+            name,
+            playlists,
+            stream_scans: HashMap::new(),
+            stream_probes: HashMap::new(),
+            stream_errors: BTreeMap::new(),
+        })
+    }
+
+    /// The folder containing `BDMV`, given either itself or `BDMV`.
+    fn find_disc_root(path: &Path) -> Result<DirFs> {
+        let candidates = [path, path.parent().unwrap_or(path)];
+        candidates
+            .into_iter()
+            .map(DirFs::new)
+            .find(|fs| fs.metadata("BDMV/PLAYLIST").is_ok_and(|m| m.is_dir))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("no BDMV/PLAYLIST folder under {}", path.display()),
+                )
+            })
+    }
+
+    pub fn label(&self) -> String {
+        self.fs.label().unwrap_or_else(|| self.name.clone())
+    }
+
+    /// Title from the disc library metadata (`META/DL/bdmt_eng.xml`).
+    pub fn title(&self) -> Option<String> {
+        let xml = self.fs.read("BDMV/META/DL/bdmt_eng.xml").ok()?;
+        let xml = String::from_utf8_lossy(&xml);
+        let start = xml.find("<di:name>")? + "<di:name>".len();
+        let end = start + xml[start..].find("</di:name>")?;
+        Some(xml[start..end].trim().to_owned())
+    }
+
+    /// SHA-1 of `AACS/Unit_Key_RO.inf` in hex, the ID disc databases and
+    /// key files use. `None` for discs without AACS.
+    pub fn disc_id(&self) -> Option<String> {
+        let data = self.fs.read("AACS/Unit_Key_RO.inf").ok()?;
+        Some(
+            sha1_smol::Sha1::from(data)
+                .digest()
+                .to_string()
+                .to_uppercase(),
+        )
+    }
+
+    /// The index table, when the disc has `BDMV/index.bdmv`.
+    pub fn index(&self) -> Result<IndexTable> {
+        let file = self.fs.open("BDMV/index.bdmv")?;
+        IndexTable::parse(&mut BufReader::new(file))
+    }
+
+    /// Clip information of a clip, given its `.clpi` file name.
+    pub fn clip_info(&self, name: &str) -> Result<ClipInfo> {
+        let path = playlist_reader::find_file(self.fs.as_ref(), "BDMV/CLIPINF", name)?;
+        ClipInfo::parse(&mut BufReader::new(self.fs.open(&path)?))
+    }
+
+    pub fn is_uhd(&self) -> bool {
+        self.playlists
+            .iter()
+            .any(|p| p.parser.mpls_header().magic_string() == "MPLS0300")
+    }
+
+    pub fn protection(&self) -> &'static str {
+        let is_dir = |path| self.fs.metadata(path).is_ok_and(|m| m.is_dir);
+        if is_dir("BDSVM") {
+            "BD+"
+        } else if is_dir("AACS") && self.is_uhd() {
+            "AACS2"
+        } else if is_dir("AACS") {
+            "AACS"
+        } else {
+            ""
+        }
+    }
+
+    pub fn extras(&self) -> Vec<&'static str> {
+        let mut extras = Vec::new();
+        if self.is_uhd() {
+            extras.push("Ultra HD");
+        }
+        if self.has_files("BDMV/BDJO") {
+            extras.push("BD-Java");
+        }
+        if self.has_files("BDMV/STREAM/SSIF") {
+            extras.push("3D");
+        }
+        extras
+    }
+
+    /// Total size of every file on the disc.
+    pub fn size(&self) -> u64 {
+        vfs::dir_size(self.fs.as_ref(), "")
+    }
+
+    fn has_files(&self, dir: &str) -> bool {
+        self.fs
+            .read_dir(dir)
+            .is_ok_and(|entries| !entries.is_empty())
+    }
+
+    /// Path of a stream file on the disc, see [`playlist_reader::find_file`].
+    pub fn stream_file_path(&self, name: &str) -> Result<String> {
+        playlist_reader::find_file(self.fs.as_ref(), "BDMV/STREAM", name)
+    }
+
+    pub fn stream_file_size(&self, name: &str) -> Option<u64> {
+        let path = self.stream_file_path(name).ok()?;
+        self.fs.metadata(&path).map(|meta| meta.len).ok()
+    }
+
+    /// Scans the stream files the playlists play, see
+    /// [`scan::scan_stream_files`].
+    pub fn scan_stream_files(&mut self) -> Result<()> {
+        scan::scan_stream_files(self)
+    }
+
+    /// Probes the audio and video streams the playlists play, see
+    /// [`probe::probe_streams`].
+    pub fn probe_streams(&mut self) -> Result<()> {
+        probe::probe_streams(self)
+    }
+
+    pub fn playlist(&self, name: &str) -> Option<&DiscPlaylist> {
+        self.playlists
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Opens the named playlist as one stream of source packets, see
+    /// [`PlaylistReader`].
+    pub fn playlist_reader(&self, name: &str, angle: usize) -> Result<PlaylistReader> {
+        let playlist = self.playlist(name).ok_or_else(|| {
+            Error::new(ErrorKind::NotFound, format!("no playlist named {}", name))
+        })?;
+        PlaylistReader::open(self.fs.clone(), &playlist.parser, angle)
+    }
+
+    /// Extracts one PID of the named playlist to `out`, see
+    /// [`demux::demux_playlist`].
+    pub fn demux<W: Write>(
+        &self,
+        name: &str,
+        angle: usize,
+        pid: u16,
+        options: DemuxOptions,
+        out: &mut W,
+    ) -> Result<DemuxStats> {
+        demux::demux_playlist(self, name, angle, pid, options, out)
+    }
+
+    /// Reads the text subtitle stream `pid` of the named playlist, see
+    /// [`text_subtitles::read`].
+    pub fn text_subtitles(&self, name: &str, pid: u16) -> Result<TextSubtitle> {
+        text_subtitles::read(self, name, pid)
+    }
+
+    /// Lints every playlist file of the disc, see [`lint::lint_disc`].
+    pub fn lint(&self) -> Result<Vec<(String, LintReport)>> {
+        lint::lint_disc(self.fs.as_ref())
+    }
+
+    /// Checks the playlists against the clip information and stream files
+    /// they use, see [`verify::verify`].
+    pub fn verify(&self) -> Result<Vec<(String, LintReport)>> {
+        verify::verify(self.fs.as_ref())
+    }
+
+    pub fn duplicate_groups(&self) -> Vec<DuplicateGroup> {
+        crate::duplicates::group_playlists(
+            self.playlists.iter().map(|p| (p.name.as_str(), &p.parser)),
+        )
+    }
+
+    /// Playlists that are exact copies of each other.
+    pub fn identical_playlists(&self) -> Vec<Vec<String>> {
+        crate::duplicates::identical_sets(
+            self.playlists.iter().map(|p| (p.name.as_str(), &p.parser)),
+        )
+    }
+
+    pub fn rank_playlists(&self) -> Vec<PlaylistRank> {
+        crate::ranking::rank_playlists(self.playlists.iter().map(|p| (p.name.as_str(), &p.parser)))
+    }
+}
+
+/// Disc folders and images at or under `path`, in path order. A folder
+/// holding `BDMV/PLAYLIST`, or a `BDMV` folder itself, is not searched
+/// further.
+pub fn find_discs(path: &Path) -> Vec<PathBuf> {
+    let mut discs = Vec::new();
+    if path.is_file() {
+        if is_image(path) {
+            discs.push(path.to_path_buf());
+        }
+        return discs;
+    }
+    if Disc::find_disc_root(path).is_ok() {
+        discs.push(path.to_path_buf());
+        return discs;
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return discs;
+    };
+    let mut entries = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() || is_image(&entry) {
+            discs.extend(find_discs(&entry));
+        }
+    }
+    discs
+}
+
+/// `.mpls` file names in `BDMV/PLAYLIST`, sorted.
+pub(crate) fn playlist_files(fs: &dyn DiscFs) -> Result<Vec<String>> {
+    let mut files = fs
+        .read_dir("BDMV/PLAYLIST")?
+        .into_iter()
+        .filter(|entry| !entry.is_dir && entry.name.to_lowercase().ends_with(".mpls"))
+        .map(|entry| entry.name)
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("iso") || ext.eq_ignore_ascii_case("img"))
+}
+
+fn file_name(path: &Path, stem: bool) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let name = if stem {
+        path.file_stem()
+    } else {
+        path.file_name()
+    };
+    name.map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{disc, mpls, PlayItem, MPLS_00801};
+
+    #[test]
+    fn finds_disc_folders_and_images() {
+        let root = std::env::temp_dir().join(format!("mpls-find-discs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["a/BDMV/PLAYLIST", "b/BDMV/PLAYLIST", "c/extra"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join("c/disc.ISO"), b"").unwrap();
+        std::fs::write(root.join("c/notes.txt"), b"").unwrap();
+
+        let found = find_discs(&root);
+        let bdmv = find_discs(&root.join("b/BDMV"));
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(found, ["a", "b", "c/disc.ISO"].map(|p| root.join(p)));
+        assert_eq!(bdmv, [root.join("b/BDMV")]);
+    }
+
+    #[test]
+    fn describes_disc() {
+        let playlist = mpls(
+            &[PlayItem {
+                clip: "00001",
+                time_in: 0,
+                time_out: 45_000,
+                streams: &[],
+            }],
+            &[],
+        );
+        let disc = disc(&[
+            ("BDSVM/00000.svm", b"SVM"),
+            ("AACS/Unit_Key_RO.inf", b"abc"),
+            ("BDMV/PLAYLIST/00001.mpls", &playlist),
+            ("BDMV/STREAM/SSIF/00001.ssif", b"3D"),
+        ]);
+        assert_eq!(disc.protection(), "BD+");
+        assert_eq!(disc.extras(), ["3D"]);
+        assert_eq!(disc.size(), 3 + 3 + playlist.len() as u64 + 2);
+        assert_eq!(disc.title(), None);
+
+        let uhd = crate::testing::disc(&[
+            ("AACS/Unit_Key_RO.inf", b"abc"),
+            ("BDMV/PLAYLIST/00801.mpls", MPLS_00801),
+        ]);
+        assert_eq!(uhd.protection(), "AACS2");
+        assert_eq!(uhd.extras(), ["Ultra HD"]);
+    }
+}
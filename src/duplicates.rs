@@ -0,0 +1,253 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::parser::Parser;
+
+/// How a playlist relates to the representative of its group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equivalence {
+    /// Same clips, in/out times and stream table.
+    Identical,
+    /// Same clips and in/out times, but a different stream table.
+    DifferentStreams,
+    /// Same clips and in/out times, played in a different order.
+    DifferentOrder,
+    /// Played in a different order and with a different stream table.
+    DifferentOrderAndStreams,
+}
+
+impl Equivalence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Equivalence::Identical => "identical",
+            Equivalence::DifferentStreams => "different_streams",
+            Equivalence::DifferentOrder => "different_order",
+            Equivalence::DifferentOrderAndStreams => "different_order_and_streams",
+        }
+    }
+}
+
+impl Display for Equivalence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub representative: String,
+    pub members: Vec<(String, Equivalence)>,
+}
+
+impl DuplicateGroup {
+    pub fn playlist_count(&self) -> usize {
+        self.members.len() + 1
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.representative == name || self.members.iter().any(|(n, _)| n == name)
+    }
+}
+
+/// The playlist `name` is an exact copy of, given the sets returned by
+/// [`identical_sets`].
+pub fn copy_of<'a>(sets: &'a [Vec<String>], name: &str) -> Option<&'a str> {
+    sets.iter()
+        .find(|set| set[1..].iter().any(|n| n == name))
+        .map(|set| set[0].as_str())
+}
+
+type Segment<'a> = (&'a str, u32, u32);
+
+struct Signature<'a> {
+    name: &'a str,
+    segments: Vec<Segment<'a>>,
+    streams: Vec<(u16, u8, String)>,
+}
+
+impl<'a> Signature<'a> {
+    fn new(name: &'a str, parser: &'a Parser) -> Self {
+        let segments = parser
+            .clips()
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    (c.time_in * 45_000.0).round() as u32,
+                    (c.time_out * 45_000.0).round() as u32,
+                )
+            })
+            .collect();
+        let mut streams = parser
+            .playlist_streams()
+            .values()
+            .map(|s| (s.pid(), s.stream_type() as u8, s.lang_code().to_owned()))
+            .collect::<Vec<_>>();
+        streams.sort();
+        Signature {
+            name,
+            segments,
+            streams,
+        }
+    }
+
+    fn unordered_segments(&self) -> Vec<Segment<'a>> {
+        let mut segments = self.segments.clone();
+        segments.sort_unstable();
+        segments
+    }
+
+    fn relation_to(&self, other: &Signature) -> Equivalence {
+        match (
+            self.segments == other.segments,
+            self.streams == other.streams,
+        ) {
+            (true, true) => Equivalence::Identical,
+            (true, false) => Equivalence::DifferentStreams,
+            (false, true) => Equivalence::DifferentOrder,
+            (false, false) => Equivalence::DifferentOrderAndStreams,
+        }
+    }
+}
+
+/// Groups playlists that play the same clip segments. The first playlist of
+/// each group (in input order) is its representative; every other member is
+/// classified against it. Playlists without an equivalent are not returned.
+pub fn group_playlists<'a, I>(playlists: I) -> Vec<DuplicateGroup>
+where
+    I: IntoIterator<Item = (&'a str, &'a Parser)>,
+{
+    let signatures = playlists
+        .into_iter()
+        .map(|(name, parser)| Signature::new(name, parser))
+        .filter(|s| !s.segments.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut order = Vec::new();
+    let mut buckets: HashMap<Vec<Segment>, Vec<&Signature>> = HashMap::new();
+    for signature in signatures.iter() {
+        let key = signature.unordered_segments();
+        if !buckets.contains_key(&key) {
+            order.push(key.clone());
+        }
+        buckets.entry(key).or_default().push(signature);
+    }
+
+    order
+        .into_iter()
+        .filter_map(|key| {
+            let bucket = buckets.remove(&key)?;
+            let (representative, rest) = bucket.split_first()?;
+            if rest.is_empty() {
+                return None;
+            }
+            Some(DuplicateGroup {
+                representative: representative.name.to_owned(),
+                members: rest
+                    .iter()
+                    .map(|s| (s.name.to_owned(), s.relation_to(representative)))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Sets of playlists that are exact copies of each other, with the same
+/// segments in the same order and the same stream table. Each set is in
+/// input order, so its first playlist is the one the others copy.
+pub fn identical_sets<'a, I>(playlists: I) -> Vec<Vec<String>>
+where
+    I: IntoIterator<Item = (&'a str, &'a Parser)>,
+{
+    let signatures = playlists
+        .into_iter()
+        .map(|(name, parser)| Signature::new(name, parser))
+        .filter(|s| !s.segments.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut sets: Vec<Vec<&Signature>> = Vec::new();
+    for signature in signatures.iter() {
+        match sets
+            .iter_mut()
+            .find(|set| set[0].relation_to(signature) == Equivalence::Identical)
+        {
+            Some(set) => set.push(signature),
+            None => sets.push(vec![signature]),
+        }
+    }
+    sets.into_iter()
+        .filter(|set| set.len() > 1)
+        .map(|set| set.iter().map(|s| s.name.to_owned()).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MPLS_00800, MPLS_00801};
+
+    fn signature<'a>(segments: &[Segment<'a>], streams: &[u16]) -> Signature<'a> {
+        Signature {
+            name: "",
+            segments: segments.to_vec(),
+            streams: streams.iter().map(|&pid| (pid, 0, String::new())).collect(),
+        }
+    }
+
+    #[test]
+    fn relation_checks_order_and_streams() {
+        let a = ("00001.M2TS", 0, 100);
+        let b = ("00002.M2TS", 0, 100);
+        let base = signature(&[a, b], &[0x1011, 0x1100]);
+
+        let same = signature(&[a, b], &[0x1011, 0x1100]);
+        let streams = signature(&[a, b], &[0x1011]);
+        let order = signature(&[b, a], &[0x1011, 0x1100]);
+        let both = signature(&[b, a], &[0x1011]);
+
+        assert_eq!(same.relation_to(&base), Equivalence::Identical);
+        assert_eq!(streams.relation_to(&base), Equivalence::DifferentStreams);
+        assert_eq!(order.relation_to(&base), Equivalence::DifferentOrder);
+        assert_eq!(
+            both.relation_to(&base),
+            Equivalence::DifferentOrderAndStreams
+        );
+        assert_eq!(
+            Equivalence::DifferentOrderAndStreams.to_string(),
+            "different_order_and_streams"
+        );
+    }
+
+    #[test]
+    fn groups_samples() {
+        let main = Parser::from_bytes(MPLS_00800).unwrap();
+        let other = Parser::from_bytes(MPLS_00801).unwrap();
+        let playlists = [
+            ("00800.MPLS", &main),
+            ("00801.MPLS", &other),
+            ("00802.MPLS", &other),
+        ];
+
+        let groups = group_playlists(playlists);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].representative, "00800.MPLS");
+        assert_eq!(
+            groups[0].members,
+            vec![
+                ("00801.MPLS".to_owned(), Equivalence::DifferentStreams),
+                ("00802.MPLS".to_owned(), Equivalence::DifferentStreams),
+            ]
+        );
+
+        let sets = identical_sets(playlists);
+        assert_eq!(sets, vec![vec!["00801.MPLS", "00802.MPLS"]]);
+        assert_eq!(copy_of(&sets, "00802.MPLS"), Some("00801.MPLS"));
+        assert_eq!(copy_of(&sets, "00801.MPLS"), None);
+    }
+
+    #[test]
+    fn single_playlists_are_not_grouped() {
+        let main = Parser::from_bytes(MPLS_00800).unwrap();
+        assert!(group_playlists([("00800.MPLS", &main)]).is_empty());
+        assert!(identical_sets([("00800.MPLS", &main)]).is_empty());
+    }
+}
@@ -0,0 +1,667 @@
+//! Annotated dump of a playlist file: every byte range with the field it
+//! belongs to and its decoded value.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Display};
+
+use crate::{
+    playlist_view::{ByteReader, ParseError, ParseErrorKind, ParseResult, StreamSection},
+    ts::{
+        angle::ClipAngleHeader,
+        mpls::MplsFileHeader,
+        plist::{ChapterHeader, ChapterSection, PlaylistHeader},
+        stream::Stream,
+        stream_clip::ClipHeader,
+        streams_header::StreamsHeader,
+        FromBinary,
+    },
+};
+
+const ROW_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// A decoded field.
+    Field,
+    /// A field whose meaning isn't known, like `_unk_0`.
+    Unknown,
+    /// Padding or reserved bytes.
+    Reserved,
+    /// Bytes the parser doesn't read at all.
+    Unparsed,
+}
+
+impl RegionKind {
+    /// One character marker for the dump.
+    pub fn marker(&self) -> char {
+        match self {
+            RegionKind::Field => ' ',
+            RegionKind::Unknown => '?',
+            RegionKind::Reserved => '-',
+            RegionKind::Unparsed => '!',
+        }
+    }
+}
+
+/// A byte range of the file and what it holds.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub offset: usize,
+    pub len: usize,
+    /// Dotted path of the field, like `play_item[0].stn.audio[1].pid`.
+    pub name: String,
+    pub value: String,
+    pub kind: RegionKind,
+}
+
+/// Every byte of a playlist, annotated.
+#[derive(Debug, Clone)]
+pub struct Explanation<'a> {
+    pub data: &'a [u8],
+    /// Regions in file order, covering the whole file.
+    pub regions: Vec<Region>,
+    /// Where parsing stopped early, everything after is unparsed.
+    pub error: Option<ParseError>,
+}
+
+impl<'a> Explanation<'a> {
+    /// Annotates `data`. Parse errors don't fail: the regions read up to
+    /// the error are kept and [`Explanation::error`] says where it stopped.
+    pub fn new(data: &'a [u8]) -> Self {
+        let mut annotator = Annotator {
+            data,
+            regions: Vec::new(),
+        };
+        let error = annotator.playlist().err();
+        let regions = annotator.finish();
+        Explanation {
+            data,
+            regions,
+            error,
+        }
+    }
+
+    /// Regions that are not decoded fields.
+    pub fn unknown(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter().filter(|r| r.kind != RegionKind::Field)
+    }
+}
+
+impl Display for Explanation<'_> {
+    /// One row per region and per 16 bytes of it: offset, marker, bytes,
+    /// then the field and its value on the region's first row.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for region in self.regions.iter() {
+            let bytes = &self.data[region.offset..region.offset + region.len];
+            for (row, chunk) in bytes.chunks(ROW_BYTES).enumerate() {
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+                write!(
+                    f,
+                    "{:08X} {} {:<width$}",
+                    region.offset + row * ROW_BYTES,
+                    region.kind.marker(),
+                    hex.join(" "),
+                    width = ROW_BYTES * 3 - 1
+                )?;
+                if row == 0 {
+                    write!(f, "  {}", region.name)?;
+                    if !region.value.is_empty() {
+                        write!(f, " = {}", region.value)?;
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+        if let Some(error) = self.error {
+            writeln!(f, "stopped: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+struct Annotator<'a> {
+    data: &'a [u8],
+    regions: Vec<Region>,
+}
+
+impl<'a> Annotator<'a> {
+    fn push(&mut self, offset: usize, len: usize, name: String, value: String, kind: RegionKind) {
+        if len > 0 {
+            self.regions.push(Region {
+                offset,
+                len,
+                name,
+                value,
+                kind,
+            });
+        }
+    }
+
+    fn field(
+        &mut self,
+        reader: &mut ByteReader<'a>,
+        len: usize,
+        name: String,
+        value: String,
+    ) -> ParseResult<&'a [u8]> {
+        let offset = reader.position();
+        let bytes = reader.bytes(len)?;
+        self.push(offset, len, name, value, RegionKind::Field);
+        Ok(bytes)
+    }
+
+    fn u8(&mut self, reader: &mut ByteReader<'a>, name: String) -> ParseResult<u8> {
+        let value = ByteReader::at(self.data, reader.position(), 1)?.u8()?;
+        self.field(reader, 1, name, value.to_string())?;
+        Ok(value)
+    }
+
+    fn u16(&mut self, reader: &mut ByteReader<'a>, name: String) -> ParseResult<u16> {
+        let value = ByteReader::at(self.data, reader.position(), 2)?.u16()?;
+        self.field(reader, 2, name, value.to_string())?;
+        Ok(value)
+    }
+
+    fn reserved(
+        &mut self,
+        reader: &mut ByteReader<'a>,
+        len: usize,
+        name: String,
+    ) -> ParseResult<()> {
+        let offset = reader.position();
+        reader.skip(len)?;
+        self.push(offset, len, name, String::new(), RegionKind::Reserved);
+        Ok(())
+    }
+
+    /// A fixed layout record, one region per field.
+    fn record<T: FromBinary>(
+        &mut self,
+        reader: &mut ByteReader<'a>,
+        prefix: &str,
+    ) -> ParseResult<T> {
+        let mut offset = reader.position();
+        let record: T = reader.record()?;
+        for field in record.fields() {
+            let (name, kind, value) = if field.name.starts_with("_unk") {
+                (field.name, RegionKind::Unknown, String::new())
+            } else if field.name.starts_with("_pad") {
+                (field.name, RegionKind::Reserved, String::new())
+            } else {
+                let name = field.name.trim_start_matches('_');
+                (name, RegionKind::Field, field.value)
+            };
+            self.push(
+                offset,
+                field.size,
+                format!("{}.{}", prefix, name),
+                value,
+                kind,
+            );
+            offset += field.size;
+        }
+        Ok(record)
+    }
+
+    /// Replaces the value of the last region named `name`.
+    fn describe(&mut self, name: &str, value: String) {
+        if let Some(region) = self.regions.iter_mut().rev().find(|r| r.name == name) {
+            region.value = value;
+        }
+    }
+
+    fn playlist(&mut self) -> ParseResult<()> {
+        let mut reader = ByteReader::new(self.data);
+        let header: MplsFileHeader = self.record(&mut reader, "header")?;
+        if !header.magic().starts_with(b"MPLS") {
+            return Err(ParseError::new(ParseErrorKind::BadMagic, 0));
+        }
+        let (playlist_offset, chapters_offset, extensions_offset) = header.offsets();
+
+        let mut reader = self.section(playlist_offset as usize)?;
+        let playlist: PlaylistHeader = self.record(&mut reader, "playlist")?;
+        for i in 0..playlist.item_count {
+            self.play_item(&mut reader, i)?;
+        }
+        for i in 0..playlist.subitem_count {
+            self.sub_path(&mut reader, i)?;
+        }
+
+        let mut reader = self.section(chapters_offset as usize)?;
+        let chapters: ChapterHeader = self.record(&mut reader, "marks")?;
+        for i in 0..chapters.mark_count {
+            let prefix = format!("marks.mark[{}]", i);
+            let mark: ChapterSection = self.record(&mut reader, &prefix)?;
+            self.describe(
+                &format!("{}.timestamp", prefix),
+                format!("{:.3}s", mark.timestamp()),
+            );
+            self.describe(
+                &format!("{}.duration", prefix),
+                format!("{:.3}s", mark.duration()),
+            );
+        }
+
+        if extensions_offset != 0 {
+            let mut reader = self.section(extensions_offset as usize)?;
+            self.u32_length(&mut reader, "extension_data.length")?;
+            let offset = reader.position();
+            let len = reader.remaining();
+            self.push(
+                offset,
+                len,
+                "extension_data".to_string(),
+                String::new(),
+                RegionKind::Unparsed,
+            );
+        }
+        Ok(())
+    }
+
+    /// A reader over the section at `offset`, bounded by its 32-bit length.
+    fn section(&self, offset: usize) -> ParseResult<ByteReader<'a>> {
+        let len = ByteReader::at(self.data, offset, 4)?.u32()? as usize;
+        ByteReader::at(self.data, offset, len + 4)
+    }
+
+    fn u32_length(&mut self, reader: &mut ByteReader<'a>, name: &str) -> ParseResult<u32> {
+        let value = ByteReader::at(self.data, reader.position(), 4)?.u32()?;
+        self.field(reader, 4, name.to_string(), value.to_string())?;
+        Ok(value)
+    }
+
+    fn sub_path(&mut self, reader: &mut ByteReader<'a>, index: u16) -> ParseResult<()> {
+        let prefix = format!("sub_path[{}]", index);
+        let offset = reader.position();
+        let len = ByteReader::at(self.data, offset, 4)?.u32()? as usize;
+        reader.skip(len + 4)?;
+        let mut sub_path = ByteReader::at(self.data, offset, len + 4)?;
+
+        self.u32_length(&mut sub_path, &format!("{}.length", prefix))?;
+        self.reserved(&mut sub_path, 1, format!("{}._pad", prefix))?;
+        self.u8(&mut sub_path, format!("{}.type", prefix))?;
+        self.u16(&mut sub_path, format!("{}.is_repeat", prefix))?;
+        self.reserved(&mut sub_path, 1, format!("{}._pad", prefix))?;
+        let count = self.u8(&mut sub_path, format!("{}.item_count", prefix))?;
+        for i in 0..count {
+            let prefix = format!("{}.item[{}]", prefix, i);
+            let offset = sub_path.position();
+            let len = ByteReader::at(self.data, offset, 2)?.u16()? as usize;
+            sub_path.skip(len + 2)?;
+            let mut item = ByteReader::at(self.data, offset, len + 2)?;
+
+            self.u16(&mut item, format!("{}.length", prefix))?;
+            let clip = format!("{:?}", item.clone().str(5)?);
+            self.field(&mut item, 5, format!("{}.clip_name", prefix), clip)?;
+            let codec = format!("{:?}", item.clone().str(4)?);
+            self.field(&mut item, 4, format!("{}.codec_id", prefix), codec)?;
+            // Connection condition and the multi-clip flag in the low bits.
+            let flags = format!("{:#010X}", item.clone().u32()?);
+            self.field(&mut item, 4, format!("{}.flags", prefix), flags)?;
+            self.u8(&mut item, format!("{}.stc_id", prefix))?;
+            for name in ["in_time", "out_time"] {
+                let ticks = item.clone().u32()?;
+                let value = format!("{:.3}s", ticks as f64 / 45000.0);
+                self.field(&mut item, 4, format!("{}.{}", prefix, name), value)?;
+            }
+            self.u16(&mut item, format!("{}.sync_play_item_id", prefix))?;
+            let ticks = item.clone().u32()?;
+            let value = format!("{:.3}s", ticks as f64 / 45000.0);
+            self.field(&mut item, 4, format!("{}.sync_start_pts", prefix), value)?;
+            // Extra clips of multi-clip sub play items.
+            let len = item.remaining();
+            if len > 0 {
+                self.reserved(&mut item, len, format!("{}.clips", prefix))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn play_item(&mut self, reader: &mut ByteReader<'a>, index: u16) -> ParseResult<()> {
+        let prefix = format!("play_item[{}]", index);
+        let offset = reader.position();
+        let len = ByteReader::at(self.data, offset, 2)?.u16()? as usize;
+        reader.skip(len + 2)?;
+        let mut item = ByteReader::at(self.data, offset, len + 2)?;
+
+        let header: ClipHeader = self.record(&mut item, &prefix)?;
+        self.describe(
+            &format!("{}.in_time", prefix),
+            format!("{:.3}s", header.time_in()),
+        );
+        self.describe(
+            &format!("{}.out_time", prefix),
+            format!("{:.3}s", header.time_out()),
+        );
+
+        if header.multi_angle() != 0 {
+            let count = self.u8(&mut item, format!("{}.angle_count", prefix))?;
+            self.reserved(&mut item, 1, format!("{}._pad", prefix))?;
+            for angle in 1..count {
+                let name = format!("{}.angle[{}]", prefix, angle);
+                self.record::<ClipAngleHeader>(&mut item, &name)?;
+            }
+        }
+
+        let stn = format!("{}.stn", prefix);
+        let stn_start = item.position();
+        let stn_len = ByteReader::at(self.data, stn_start, 2)?.u16()? as usize;
+        let mut reader = ByteReader::at(self.data, stn_start, stn_len + 2)?;
+        let counts: StreamsHeader = self.record(&mut reader, &stn)?;
+        let sections = [
+            (StreamSection::Video, "video", counts.stream_count_video),
+            (StreamSection::Audio, "audio", counts.stream_count_audio),
+            (
+                StreamSection::PresentationGraphics,
+                "pg",
+                counts.stream_count_pg,
+            ),
+            (
+                StreamSection::InteractiveGraphics,
+                "ig",
+                counts.stream_count_ig,
+            ),
+            (
+                StreamSection::SecondaryAudio,
+                "secondary_audio",
+                counts.stream_count_secondary_audio,
+            ),
+            (
+                StreamSection::SecondaryVideo,
+                "secondary_video",
+                counts.stream_count_secondary_video,
+            ),
+        ];
+        for (section, name, count) in sections {
+            for i in 0..count {
+                self.stream_entry(&mut reader, section, &format!("{}.{}[{}]", stn, name, i))?;
+            }
+        }
+        let offset = reader.position();
+        let rest = reader.remaining();
+        self.push(
+            offset,
+            rest,
+            format!("{}.rest", stn),
+            String::new(),
+            RegionKind::Unparsed,
+        );
+
+        // Anything after the STN table up to the end of the play item.
+        item.skip(stn_len + 2)?;
+        let offset = item.position();
+        let rest = item.remaining();
+        self.push(
+            offset,
+            rest,
+            format!("{}.rest", prefix),
+            String::new(),
+            RegionKind::Unparsed,
+        );
+        Ok(())
+    }
+
+    fn stream_entry(
+        &mut self,
+        stn: &mut ByteReader<'a>,
+        section: StreamSection,
+        prefix: &str,
+    ) -> ParseResult<()> {
+        let entry_len = self.u8(stn, format!("{}.entry_length", prefix))? as usize;
+        let mut entry = ByteReader::at(self.data, stn.position(), entry_len)?;
+        stn.skip(entry_len)?;
+        let entry_type = self.u8(&mut entry, format!("{}.entry_type", prefix))?;
+        match entry_type {
+            2 | 4 => {
+                self.u8(&mut entry, format!("{}.subpath_id", prefix))?;
+                self.u8(&mut entry, format!("{}.subclip_id", prefix))?;
+            }
+            3 => {
+                self.u8(&mut entry, format!("{}.subpath_id", prefix))?;
+            }
+            _ => {}
+        }
+        let pid = self.u16(&mut entry, format!("{}.pid", prefix))?;
+        self.describe(&format!("{}.pid", prefix), format!("{0} ({0:#06X})", pid));
+        let len = entry.remaining();
+        self.reserved(&mut entry, len, format!("{}._pad", prefix))?;
+
+        let attributes_len = self.u8(stn, format!("{}.attributes_length", prefix))? as usize;
+        let mut attributes = ByteReader::at(self.data, stn.position(), attributes_len)?;
+        stn.skip(attributes_len)?;
+        self.attributes(&mut attributes, pid, &format!("{}.attributes", prefix))?;
+
+        // Secondary streams list the primary streams they combine with.
+        let references = match section {
+            StreamSection::SecondaryAudio => 1,
+            StreamSection::SecondaryVideo => 2,
+            _ => 0,
+        };
+        for i in 0..references {
+            let name = format!("{}.references[{}]", prefix, i);
+            let count = self.u8(stn, format!("{}.count", name))? as usize;
+            self.reserved(stn, 1, format!("{}._pad", name))?;
+            let offset = stn.position();
+            let ids = stn.bytes(count)?;
+            let ids: Vec<String> = ids.iter().map(u8::to_string).collect();
+            self.push(
+                offset,
+                count,
+                format!("{}.ids", name),
+                ids.join(", "),
+                RegionKind::Field,
+            );
+            self.reserved(stn, count % 2, format!("{}._pad", name))?;
+        }
+        Ok(())
+    }
+
+    fn attributes(
+        &mut self,
+        reader: &mut ByteReader<'a>,
+        pid: u16,
+        prefix: &str,
+    ) -> ParseResult<()> {
+        let bytes = ByteReader::at(self.data, reader.position(), reader.remaining())?
+            .bytes(reader.remaining())?;
+        let Some(&coding_type) = bytes.first() else {
+            return Ok(());
+        };
+        let stream = Stream::from_attributes(pid, bytes);
+        let stream_type = match &stream {
+            Some(stream) => format!("{:?}", stream.stream_type()),
+            None => format!("{:#04X}", coding_type),
+        };
+        self.field(reader, 1, format!("{}.coding_type", prefix), stream_type)?;
+        match stream {
+            Some(Stream::VideoStream(video)) => {
+                let value = format!("{} {}", video.video_format, video.frame_rate);
+                self.field(reader, 1, format!("{}.format", prefix), value)?;
+                let value = video.aspect_ratio.to_string();
+                self.field(reader, 1, format!("{}.aspect_ratio", prefix), value)?;
+            }
+            Some(Stream::AudioStream(audio)) => {
+                let value = format!("{} {}", audio.channel_layout, audio.sample_rate);
+                self.field(reader, 1, format!("{}.format", prefix), value)?;
+                self.field(
+                    reader,
+                    3,
+                    format!("{}.language", prefix),
+                    audio.language.to_string(),
+                )?;
+            }
+            Some(Stream::GraphicsStream(graphics)) => {
+                self.field(
+                    reader,
+                    3,
+                    format!("{}.language", prefix),
+                    graphics.language.to_string(),
+                )?;
+            }
+            Some(Stream::TextStream(text)) => {
+                let value = text.character_code.to_string();
+                self.field(reader, 1, format!("{}.character_code", prefix), value)?;
+                self.field(
+                    reader,
+                    3,
+                    format!("{}.language", prefix),
+                    text.language.to_string(),
+                )?;
+            }
+            None => {}
+        }
+        let len = reader.remaining();
+        self.reserved(reader, len, format!("{}._pad", prefix))
+    }
+
+    /// Sorts the regions and fills the gaps between them with unparsed
+    /// ones, up to the end of the file.
+    fn finish(mut self) -> Vec<Region> {
+        self.regions.sort_by_key(|r| r.offset);
+        let mut regions = Vec::with_capacity(self.regions.len());
+        let mut end = 0;
+        for region in self.regions {
+            if region.offset > end {
+                regions.push(gap(end, region.offset - end));
+            }
+            end = end.max(region.offset + region.len);
+            regions.push(region);
+        }
+        if end < self.data.len() {
+            regions.push(gap(end, self.data.len() - end));
+        }
+        regions
+    }
+}
+
+fn gap(offset: usize, len: usize) -> Region {
+    Region {
+        offset,
+        len,
+        name: "unparsed".to_string(),
+        value: String::new(),
+        kind: RegionKind::Unparsed,
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::testing::{mpls, PlayItem, MPLS_00800};
+
+    /// Regions follow each other without gaps or overlaps up to the end.
+    fn assert_covers(explanation: &Explanation) {
+        let mut end = 0;
+        for region in explanation.regions.iter() {
+            assert_eq!(region.offset, end, "{}", region.name);
+            end += region.len;
+        }
+        assert_eq!(end, explanation.data.len());
+    }
+
+    fn region<'a>(explanation: &'a Explanation, name: &str) -> &'a Region {
+        explanation
+            .regions
+            .iter()
+            .find(|r| r.name == name)
+            .unwrap_or_else(|| panic!("no region {}", name))
+    }
+
+    #[test]
+    fn explains_sample() {
+        let explanation = Explanation::new(MPLS_00800);
+        assert_eq!(explanation.error, None);
+        assert_covers(&explanation);
+        assert_eq!(
+            region(&explanation, "header.magic_string").value,
+            "\"MPLS0300\""
+        );
+        assert_eq!(
+            region(&explanation, "play_item[0].item_name").value,
+            "\"00037\""
+        );
+        assert_eq!(
+            region(&explanation, "play_item[0].in_time").value,
+            "4198.375s"
+        );
+        let pid = region(&explanation, "play_item[0].stn.audio[0].pid");
+        assert_eq!(pid.value, "4352 (0x1100)");
+        assert_eq!(
+            region(&explanation, "extension_data").kind,
+            RegionKind::Unparsed
+        );
+        assert!(explanation.unknown().all(|r| r.kind != RegionKind::Field));
+
+        let dump = explanation.to_string();
+        let first = dump.lines().next().unwrap();
+        assert!(first.starts_with("00000000   4D 50 4C 53 30 33 30 30"));
+        assert!(first.ends_with("header.magic_string = \"MPLS0300\""));
+    }
+
+    #[test]
+    fn explains_secondary_stream_references() {
+        let data = mpls(
+            &[PlayItem {
+                clip: "00001",
+                time_in: 0,
+                time_out: 45_000,
+                streams: &[
+                    (4, 0x1A00, &[0xA1, 0x61, b'f', b'r', b'a']),
+                    (5, 0x1B00, &[0x1B, 0x61, 0x30]),
+                ],
+            }],
+            &[],
+        );
+        let explanation = Explanation::new(&data);
+        assert_eq!(explanation.error, None);
+        assert_covers(&explanation);
+        let audio = "play_item[0].stn.secondary_audio[0]";
+        assert_eq!(
+            region(&explanation, &format!("{}.attributes.language", audio)).value,
+            "fra"
+        );
+        assert_eq!(
+            region(&explanation, &format!("{}.references[0].ids", audio)).value,
+            "0"
+        );
+        let video = "play_item[0].stn.secondary_video[0]";
+        assert_eq!(
+            region(&explanation, &format!("{}.pid", video)).value,
+            "6912 (0x1B00)"
+        );
+        region(&explanation, &format!("{}.references[1].ids", video));
+        assert!(explanation
+            .unknown()
+            .all(|r| r.kind != RegionKind::Unparsed));
+    }
+
+    #[test]
+    fn keeps_regions_up_to_an_error() {
+        let truncated = &MPLS_00800[..200];
+        let explanation = Explanation::new(truncated);
+        assert_eq!(
+            explanation.error.map(|e| e.kind),
+            Some(ParseErrorKind::BadOffset)
+        );
+        assert_covers(&explanation);
+        region(&explanation, "header.playlist_offset");
+        assert_eq!(
+            explanation.regions.last().unwrap().kind,
+            RegionKind::Unparsed
+        );
+        assert!(explanation
+            .to_string()
+            .ends_with("stopped: section outside the file at offset 0x3A\n"));
+
+        let explanation = Explanation::new(b"HDMV0200");
+        assert_eq!(
+            explanation.error.map(|e| e.kind),
+            Some(ParseErrorKind::Truncated)
+        );
+        assert_covers(&explanation);
+    }
+}
@@ -1,6 +1,46 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 #[macro_use]
 pub mod macros;
 
+#[cfg(feature = "std")]
+pub mod chapters;
+#[cfg(feature = "std")]
+pub mod demux;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod disc;
+#[cfg(feature = "std")]
+pub mod duplicates;
+pub mod explain;
+#[cfg(feature = "std")]
+pub mod lint;
+#[cfg(feature = "console")]
 pub mod log;
+#[cfg(feature = "std")]
+pub mod model;
 pub mod parser;
+#[cfg(feature = "std")]
+pub mod playlist_reader;
+pub mod playlist_view;
+#[cfg(feature = "std")]
+pub mod probe;
+#[cfg(feature = "std")]
+pub mod ranking;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub mod scan;
+#[cfg(all(test, feature = "std"))]
+mod testing;
+#[cfg(feature = "std")]
+pub mod text_subtitles;
+pub mod trace;
 pub mod ts;
+#[cfg(feature = "std")]
+pub mod verify;
+#[cfg(feature = "std")]
+pub mod vfs;
@@ -0,0 +1,640 @@
+//! Checks a playlist file against the constraints of the format, for
+//! authoring errors a parser would let through.
+
+use std::{collections::BTreeSet, fmt::Display, io::Result};
+
+use serde::Serialize;
+
+use crate::{
+    disc,
+    playlist_view::{ByteReader, PlayItemView, PlaylistView, StreamEntry, StreamSection},
+    ts::{
+        plist::{ChapterHeader, ChapterSection},
+        stream_type::StreamType,
+        FromBinary,
+    },
+    vfs::{self, DiscFs},
+};
+
+/// The AppInfoPlayList section follows the file header.
+const APP_INFO_OFFSET: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Unusual but allowed.
+    Info,
+    /// Players are likely to cope, authoring tools shouldn't produce it.
+    Warning,
+    /// Breaks the format, players may refuse or misplay the playlist.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        f.pad(name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Short name of the check, like `mark_time`.
+    pub check: &'static str,
+    /// Dotted path of the structure, named as in `explain`.
+    pub location: String,
+    /// File offset of the structure.
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LintReport {
+    pub findings: Vec<Finding>,
+}
+
+impl LintReport {
+    pub fn push(
+        &mut self,
+        severity: Severity,
+        check: &'static str,
+        location: impl Into<String>,
+        offset: Option<usize>,
+        message: impl Into<String>,
+    ) {
+        self.findings.push(Finding {
+            severity,
+            check,
+            location: location.into(),
+            offset,
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// The most severe finding, `None` when there are none.
+    pub fn severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity).max()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Lints a playlist file. A file that doesn't parse gets a single error
+/// where parsing stopped.
+pub fn lint(data: &[u8]) -> LintReport {
+    let mut report = LintReport::default();
+    let view = match PlaylistView::parse(data) {
+        Ok(view) => view,
+        Err(error) => {
+            report.push(
+                Severity::Error,
+                "parse",
+                "",
+                Some(error.offset),
+                error.to_string(),
+            );
+            return report;
+        }
+    };
+    check_sections(&view, &mut report);
+    for (i, item) in view.play_items.iter().enumerate() {
+        check_play_item(i, item, &mut report);
+    }
+    check_item_overlap(&view, &mut report);
+    check_marks(&view, &mut report);
+    report
+}
+
+/// Sections in file order must not overlap each other or run past the end.
+/// Lints every playlist file of the disc in `fs`, including the ones that
+/// fail to parse, by upper case file name.
+pub fn lint_disc(fs: &dyn DiscFs) -> Result<Vec<(String, LintReport)>> {
+    let mut reports = Vec::new();
+    for file in disc::playlist_files(fs)? {
+        let data = fs.read(&vfs::join("BDMV/PLAYLIST", &file))?;
+        reports.push((file.to_uppercase(), lint(&data)));
+    }
+    Ok(reports)
+}
+
+fn check_sections(view: &PlaylistView, report: &mut LintReport) {
+    let (playlist, marks, extensions) = view.header.offsets();
+    let mut sections = vec![
+        ("app_info", APP_INFO_OFFSET),
+        ("playlist", playlist as usize),
+        ("marks", marks as usize),
+    ];
+    if extensions != 0 {
+        sections.push(("extension_data", extensions as usize));
+    }
+    sections.sort_by_key(|&(_, offset)| offset);
+
+    let mut previous: Option<(&str, usize)> = None;
+    for (name, offset) in sections {
+        let Ok(len) = ByteReader::at(view.data, offset, 4).and_then(|mut r| r.u32()) else {
+            report.push(
+                Severity::Error,
+                "section_bounds",
+                name,
+                Some(offset),
+                "section starts past the end of the file",
+            );
+            continue;
+        };
+        if let Some((previous, end)) = previous.filter(|&(_, end)| end > offset) {
+            report.push(
+                Severity::Error,
+                "section_overlap",
+                name,
+                Some(offset),
+                format!(
+                    "starts {} bytes before the end of {}",
+                    end - offset,
+                    previous
+                ),
+            );
+        }
+        let end = offset + 4 + len as usize;
+        if end > view.data.len() {
+            report.push(
+                Severity::Error,
+                "section_bounds",
+                name,
+                Some(offset),
+                format!(
+                    "{} byte section runs {} bytes past the end of the file",
+                    len,
+                    end - view.data.len()
+                ),
+            );
+        }
+        previous = Some((name, end));
+    }
+}
+
+fn check_play_item(index: usize, item: &PlayItemView, report: &mut LintReport) {
+    let location = format!("play_item[{}]", index);
+    let (time_in, time_out) = (item.header.time_in(), item.header.time_out());
+    if time_in >= time_out {
+        let what = if time_in == time_out {
+            "is empty"
+        } else {
+            "ends before it starts"
+        };
+        report.push(
+            Severity::Error,
+            "item_times",
+            &location,
+            Some(item.offset),
+            format!("{}, in {:.3}s out {:.3}s", what, time_in, time_out),
+        );
+    }
+
+    let counts = &item.stn_header;
+    let limits = [
+        (StreamSection::Video, counts.stream_count_video, 1),
+        (StreamSection::Audio, counts.stream_count_audio, 32),
+        (
+            StreamSection::InteractiveGraphics,
+            counts.stream_count_ig,
+            32,
+        ),
+        (
+            StreamSection::SecondaryAudio,
+            counts.stream_count_secondary_audio,
+            32,
+        ),
+        (
+            StreamSection::SecondaryVideo,
+            counts.stream_count_secondary_video,
+            32,
+        ),
+    ];
+    for (section, count, limit) in limits {
+        if count > limit {
+            report.push(
+                Severity::Error,
+                "stn_count",
+                format!("{}.stn", location),
+                Some(item.stn_offset),
+                format!(
+                    "{} {} streams, at most {} allowed",
+                    count,
+                    section.name(),
+                    limit
+                ),
+            );
+        }
+    }
+
+    // The view doesn't read picture-in-picture subtitle and Dolby Vision
+    // entries, which come last.
+    let declared_end = item.stn_offset + 2 + counts.stream_info_len() as usize;
+    let unread = counts.stream_count_pip != 0 || counts.stream_count_dolby_vision != 0;
+    if !unread && item.stn_end != declared_end {
+        report.push(
+            Severity::Warning,
+            "stn_length",
+            format!("{}.stn", location),
+            Some(item.stn_offset),
+            format!(
+                "stream_info_len is {} but the entries end {} bytes in",
+                counts.stream_info_len(),
+                item.stn_end - item.stn_offset - 2
+            ),
+        );
+    }
+
+    let mut seen = BTreeSet::new();
+    for (entry, location) in item.streams.iter().zip(stream_locations(&location, item)) {
+        check_stream(entry, &location, report);
+        let key = (
+            entry.section.name(),
+            entry.subpath_id,
+            entry.subclip_id,
+            entry.pid,
+        );
+        if !seen.insert(key) {
+            report.push(
+                Severity::Error,
+                "duplicate_pid",
+                location,
+                Some(entry.offset),
+                format!(
+                    "PID {:#06x} is listed twice among the {} streams",
+                    entry.pid,
+                    entry.section.name()
+                ),
+            );
+        }
+    }
+}
+
+/// Locations of a play item's stream entries, like `play_item[0].stn.pg[2]`.
+pub(crate) fn stream_locations(prefix: &str, item: &PlayItemView) -> Vec<String> {
+    let mut numbers = [0usize; 6];
+    item.streams
+        .iter()
+        .map(|entry| {
+            let number = &mut numbers[entry.section as usize];
+            *number += 1;
+            format!("{}.stn.{}[{}]", prefix, entry.section.name(), *number - 1)
+        })
+        .collect()
+}
+
+fn check_stream(entry: &StreamEntry, location: &str, report: &mut LintReport) {
+    let coding_type = entry.attributes.first().copied().unwrap_or_default();
+    let stream_type = entry.stream_type();
+    let allowed = match stream_type {
+        StreamType::Unknown => {
+            report.push(
+                Severity::Error,
+                "coding_type",
+                location,
+                Some(entry.offset),
+                format!("unknown coding type {:#04x}", coding_type),
+            );
+            return;
+        }
+        StreamType::MPEG1_VIDEO
+        | StreamType::MPEG2_VIDEO
+        | StreamType::AVC_VIDEO
+        | StreamType::MVC_VIDEO
+        | StreamType::HEVC_VIDEO
+        | StreamType::VC1_VIDEO => {
+            matches!(
+                entry.section,
+                StreamSection::Video | StreamSection::SecondaryVideo
+            )
+        }
+        StreamType::AC3_PLUS_SECONDARY_AUDIO | StreamType::DTS_HD_SECONDARY_AUDIO => {
+            entry.section == StreamSection::SecondaryAudio
+        }
+        StreamType::PRESENTATION_GRAPHICS | StreamType::SUBTITLE => {
+            entry.section == StreamSection::PresentationGraphics
+        }
+        StreamType::INTERACTIVE_GRAPHICS => entry.section == StreamSection::InteractiveGraphics,
+        _ => matches!(
+            entry.section,
+            StreamSection::Audio | StreamSection::SecondaryAudio
+        ),
+    };
+    if !allowed {
+        report.push(
+            Severity::Error,
+            "coding_type",
+            location,
+            Some(entry.offset),
+            format!(
+                "{} isn't allowed among the {} streams",
+                stream_type.codec_name(),
+                entry.section.name()
+            ),
+        );
+    } else if entry.to_stream().is_none() {
+        report.push(
+            Severity::Error,
+            "stream_attributes",
+            location,
+            Some(entry.offset),
+            format!(
+                "{} bytes of attributes are too short for {}",
+                entry.attributes.len(),
+                stream_type.codec_name()
+            ),
+        );
+    }
+    if !(1..=4).contains(&entry.entry_type) {
+        report.push(
+            Severity::Warning,
+            "stream_entry",
+            location,
+            Some(entry.offset),
+            format!("unknown stream entry type {}", entry.entry_type),
+        );
+    }
+}
+
+/// Play items of the same clip and STC sequence that play the same part of
+/// it twice.
+fn check_item_overlap(view: &PlaylistView, report: &mut LintReport) {
+    // The STC id follows the connection condition.
+    let stc_id = |item: &PlayItemView| item.raw.get(13).copied();
+    for (i, a) in view.play_items.iter().enumerate() {
+        for (j, b) in view.play_items.iter().enumerate().skip(i + 1) {
+            if a.clip_name != b.clip_name || stc_id(a) != stc_id(b) {
+                continue;
+            }
+            let start = a.header.time_in().max(b.header.time_in());
+            let end = a.header.time_out().min(b.header.time_out());
+            if start < end {
+                report.push(
+                    Severity::Warning,
+                    "item_overlap",
+                    format!("play_item[{}]", j),
+                    Some(b.offset),
+                    format!(
+                        "plays {:.3}s to {:.3}s of {} again after play_item[{}]",
+                        start, end, b.clip_name, i
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn check_marks(view: &PlaylistView, report: &mut LintReport) {
+    let (_, marks_offset, _) = view.header.offsets();
+    let clips = &view.play_items;
+    // Start of each play item on the playlist's timeline.
+    let mut starts = Vec::with_capacity(clips.len());
+    let mut total = 0.0;
+    for item in clips {
+        starts.push(total);
+        total += item.header.time_out() - item.header.time_in();
+    }
+
+    let mut last_time = None;
+    for (i, mark) in view.marks.iter().enumerate() {
+        let location = format!("marks.mark[{}]", i);
+        let offset = Some(marks_offset as usize + ChapterHeader::SIZE + i * ChapterSection::SIZE);
+        if !matches!(mark.mark_type, 1 | 2) {
+            report.push(
+                Severity::Warning,
+                "mark_type",
+                &location,
+                offset,
+                format!("unknown mark type {}", mark.mark_type),
+            );
+        }
+        let Some(item) = clips.get(mark.play_item_id as usize) else {
+            report.push(
+                Severity::Error,
+                "mark_item",
+                &location,
+                offset,
+                format!(
+                    "refers to play item {} of {}",
+                    mark.play_item_id,
+                    clips.len()
+                ),
+            );
+            continue;
+        };
+        let (time_in, time_out) = (item.header.time_in(), item.header.time_out());
+        if time_in >= time_out {
+            // Already an `item_times` error.
+            continue;
+        }
+        let time = mark.timestamp();
+        if time < time_in || time > time_out {
+            report.push(
+                Severity::Error,
+                "mark_time",
+                &location,
+                offset,
+                format!(
+                    "{:.3}s is outside play item {} ({:.3}s to {:.3}s)",
+                    time, mark.play_item_id, time_in, time_out
+                ),
+            );
+            continue;
+        }
+        let playlist_time = starts[mark.play_item_id as usize] + time - time_in;
+        if last_time.is_some_and(|last| playlist_time < last) {
+            report.push(
+                Severity::Warning,
+                "mark_order",
+                &location,
+                offset,
+                "comes before the previous mark",
+            );
+        }
+        last_time = Some(playlist_time);
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<7} ", self.severity)?;
+        match self.offset {
+            Some(offset) => write!(f, "{:08X} ", offset)?,
+            None => write!(f, "{:8} ", "")?,
+        }
+        if !self.location.is_empty() {
+            write!(f, "{}: ", self.location)?;
+        }
+        write!(f, "{} [{}]", self.message, self.check)
+    }
+}
+
+impl Display for LintReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no problems found");
+        }
+        for finding in self.findings.iter() {
+            writeln!(f, "{}", finding)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{disc, mpls, PlayItem, MPLS_00800, MPLS_00801};
+
+    const VIDEO: &[u8] = &[0x1B, 0x61, 0x30];
+    const AUDIO: &[u8] = &[0x81, 0x61, b'e', b'n', b'g'];
+
+    fn checks(report: &LintReport) -> Vec<(Severity, &str, &str)> {
+        report
+            .findings
+            .iter()
+            .map(|f| (f.severity, f.check, f.location.as_str()))
+            .collect()
+    }
+
+    fn item<'a>(clip: &'a str, time_in: u32, time_out: u32) -> PlayItem<'a> {
+        PlayItem {
+            clip,
+            time_in,
+            time_out,
+            streams: &[(0, 0x1011, VIDEO), (1, 0x1100, AUDIO)],
+        }
+    }
+
+    #[test]
+    fn passes_samples_and_clean_playlists() {
+        assert!(lint(MPLS_00800).is_empty());
+        assert!(lint(MPLS_00801).is_empty());
+        let report = lint(&mpls(&[item("00001", 0, 45_000)], &[(0, 0)]));
+        assert!(report.is_empty());
+        assert_eq!(report.severity(), None);
+        assert_eq!(report.to_string(), "no problems found\n");
+    }
+
+    #[test]
+    fn reports_unparsable_files() {
+        let report = lint(&MPLS_00800[..MPLS_00800.len() / 2]);
+        assert_eq!(checks(&report), [(Severity::Error, "parse", "")]);
+        assert!(report.findings[0].offset.is_some());
+    }
+
+    #[test]
+    fn checks_play_items_and_streams() {
+        let data = mpls(
+            &[
+                item("00001", 45_000, 45_000),
+                PlayItem {
+                    clip: "00002",
+                    time_in: 0,
+                    time_out: 45_000,
+                    streams: &[
+                        (0, 0x1100, AUDIO),
+                        (1, 0x1100, AUDIO),
+                        (1, 0x1100, AUDIO),
+                        (2, 0x1200, &[0x90]),
+                        (3, 0x1400, &[0x42]),
+                    ],
+                },
+                item("00002", 22_500, 90_000),
+            ],
+            &[],
+        );
+        let report = lint(&data);
+        assert_eq!(
+            checks(&report),
+            [
+                (Severity::Error, "item_times", "play_item[0]"),
+                (Severity::Error, "coding_type", "play_item[1].stn.video[0]"),
+                (
+                    Severity::Error,
+                    "duplicate_pid",
+                    "play_item[1].stn.audio[1]"
+                ),
+                (
+                    Severity::Error,
+                    "stream_attributes",
+                    "play_item[1].stn.pg[0]"
+                ),
+                (Severity::Error, "coding_type", "play_item[1].stn.ig[0]"),
+                (Severity::Warning, "item_overlap", "play_item[2]"),
+            ]
+        );
+        assert_eq!(report.severity(), Some(Severity::Error));
+        assert_eq!(
+            report.findings[5].message,
+            "plays 0.500s to 1.000s of 00002 again after play_item[1]"
+        );
+    }
+
+    #[test]
+    fn checks_marks() {
+        let data = mpls(
+            &[item("00001", 0, 45_000 * 10), item("00002", 0, 45_000 * 10)],
+            &[(1, 45_000), (0, 45_000), (0, 45_000 * 11), (2, 0)],
+        );
+        let report = lint(&data);
+        assert_eq!(
+            checks(&report),
+            [
+                (Severity::Warning, "mark_order", "marks.mark[1]"),
+                (Severity::Error, "mark_time", "marks.mark[2]"),
+                (Severity::Error, "mark_item", "marks.mark[3]"),
+            ]
+        );
+        let (_, marks_offset, _) = PlaylistView::parse(&data).unwrap().header.offsets();
+        let offset = marks_offset as usize + ChapterHeader::SIZE + ChapterSection::SIZE;
+        assert_eq!(report.findings[0].offset, Some(offset));
+        assert_eq!(
+            report.findings[0].to_string(),
+            format!(
+                "warning {:08X} marks.mark[1]: comes before the previous mark [mark_order]",
+                offset
+            )
+        );
+    }
+
+    #[test]
+    fn checks_section_bounds() {
+        // Extension data claimed right after the marks, running past the end.
+        let mut data = mpls(&[item("00001", 0, 45_000)], &[]);
+        let end = data.len() as u32;
+        data[16..20].copy_from_slice(&end.to_be_bytes());
+        data.extend_from_slice(&100u32.to_be_bytes());
+        let report = lint(&data);
+        assert!(report.findings.iter().any(|f| f.check == "section_bounds"
+            && f.location == "extension_data"
+            && f.severity == Severity::Error));
+    }
+
+    #[test]
+    fn lints_every_playlist_file() {
+        let disc = disc(&[
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/PLAYLIST/00099.mpls", b"MPLS0200"),
+        ]);
+        let reports = disc.lint().unwrap();
+        let names = reports
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["00099.MPLS", "00800.MPLS"]);
+        assert_eq!(reports[0].1.findings[0].check, "parse");
+        assert!(reports[1].1.is_empty());
+    }
+}
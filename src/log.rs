@@ -1,10 +1,27 @@
-use std::io::Write;
+use std::{
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 pub use termcolor::Color;
 use termcolor::{BufferWriter, ColorChoice, ColorSpec, WriteColor};
 
+use crate::trace::{Event, EventSink};
+
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+/// Turns the colors of [`key_val_print`] on or off, on by default.
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
 pub fn key_val_print(color: Option<Color>, key: &str, val: &str) {
-    let bufwtr = BufferWriter::stderr(ColorChoice::Always);
+    let choice = if COLOR.load(Ordering::Relaxed) {
+        ColorChoice::Always
+    } else {
+        ColorChoice::Never
+    };
+    let bufwtr = BufferWriter::stderr(choice);
     let mut buffer = bufwtr.buffer();
 
     buffer
@@ -20,3 +37,28 @@ pub fn key_val_print(color: Option<Color>, key: &str, val: &str) {
     writeln!(&mut buffer, " {}", val).ok();
     bufwtr.print(&buffer).ok();
 }
+
+/// Prints every parse event with [`key_val_print`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConsoleSink;
+
+impl EventSink for ConsoleSink {
+    fn event(&self, event: &Event<'_>) {
+        match event {
+            Event::Section { name, offset } => key_val_print(
+                Some(Color::Yellow),
+                name,
+                &offset.map(|o| format!("{:X}", o)).unwrap_or_default(),
+            ),
+            Event::Field { name, value } => {
+                key_val_print(Some(Color::Cyan), name, &value.to_string())
+            }
+            Event::Info { name, value } => {
+                key_val_print(Some(Color::Yellow), name, &value.to_string())
+            }
+            Event::Warning { name, message } => {
+                key_val_print(Some(Color::Red), name, &message.to_string())
+            }
+        }
+    }
+}
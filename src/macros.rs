@@ -1,38 +1,130 @@
 #![allow(unused)]
+#[cfg(feature = "std")]
 use std::io::Read;
 
+/// Emits a decoded field, see [`crate::trace`].
 macro_rules! log {
     ($name:expr, $fmt:expr, $($arg:tt)*) => {
-        crate::log::key_val_print(Some(crate::log::Color::Cyan), $name, &format!($fmt, $($arg)*))
+        if crate::trace::enabled() {
+            crate::trace::emit(&crate::trace::Event::Field {
+                name: $name,
+                value: ::core::format_args!($fmt, $($arg)*),
+            });
+        }
     };
 }
 macro_rules! error {
     ($name:expr, $fmt:expr, $($arg:tt)*) => {
-        crate::log::key_val_print(Some(crate::log::Color::Red), $name, &format!($fmt, $($arg)*))
+        if crate::trace::enabled() {
+            crate::trace::emit(&crate::trace::Event::Warning {
+                name: $name,
+                message: ::core::format_args!($fmt, $($arg)*),
+            });
+        }
     };
 }
 macro_rules! info {
     ($name:expr, $fmt:expr, $($arg:tt)*) => {
-        crate::log::key_val_print(Some(crate::log::Color::Yellow), $name, &format!($fmt, $($arg)*))
+        if crate::trace::enabled() {
+            crate::trace::emit(&crate::trace::Event::Info {
+                name: $name,
+                value: ::core::format_args!($fmt, $($arg)*),
+            });
+        }
+    };
+}
+
+/// Emits the start of a section at a file offset.
+macro_rules! section {
+    (at $offset:expr, $name:expr) => {
+        if crate::trace::enabled() {
+            crate::trace::emit(&crate::trace::Event::Section {
+                name: $name,
+                offset: Some($offset as u64),
+            });
+        }
     };
 }
 
-macro_rules! position {
-    ($ident:ident) => {
-        crate::log::key_val_print(
-            Some(crate::log::Color::Magenta),
-            "Reader Position",
-            &format!("{:X}", $ident.stream_position()?),
-        );
+/// Declares a struct read from and written to a fixed size big-endian
+/// record, field by field in declaration order. Every field type must
+/// implement [`BinaryField`](crate::ts::BinaryField).
+macro_rules! binary_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty,)*
+        }
+
+        impl crate::ts::FromBinary for $name {
+            const SIZE: usize = 0 $(+ <$ty as crate::ts::BinaryField>::SIZE)*;
+
+            fn from_bytes(bytes: &[u8]) -> Option<Self> {
+                if bytes.len() < Self::SIZE {
+                    return None;
+                }
+                let mut pos = 0;
+                $(
+                    let $field = <$ty as crate::ts::BinaryField>::decode(&bytes[pos..]);
+                    pos += <$ty as crate::ts::BinaryField>::SIZE;
+                )*
+                let _ = pos;
+                Some($name { $($field),* })
+            }
+
+            fn write_to(&self, out: &mut ::alloc::vec::Vec<u8>) {
+                $(crate::ts::BinaryField::encode(&self.$field, out);)*
+            }
+
+            fn fields(&self) -> ::alloc::vec::Vec<crate::ts::RecordField> {
+                ::alloc::vec![$(crate::ts::RecordField {
+                    name: stringify!($field),
+                    size: <$ty as crate::ts::BinaryField>::SIZE,
+                    value: crate::ts::BinaryField::describe(&self.$field),
+                }),*]
+            }
+        }
+    };
+}
+
+/// Declares accessors for bit ranges of an integer field, numbered from the
+/// least significant bit: `name: low..high`.
+macro_rules! bitfields {
+    (
+        $name:ident.$storage:ident: $ty:ty {
+            $($(#[$meta:meta])* $vis:vis $field:ident: $low:literal..$high:literal),* $(,)?
+        }
+    ) => {
+        ::paste::paste! {
+            impl $name {
+                $(
+                    $(#[$meta])*
+                    $vis fn $field(&self) -> $ty {
+                        (self.$storage >> $low) & (<$ty>::MAX >> (<$ty>::BITS - ($high - $low)))
+                    }
+
+                    $vis fn [<set_ $field>](&mut self, value: $ty) {
+                        let mask = (<$ty>::MAX >> (<$ty>::BITS - ($high - $low))) << $low;
+                        self.$storage = (self.$storage & !mask) | ((value << $low) & mask);
+                    }
+                )*
+            }
+        }
     };
 }
 
 macro_rules! impl_read {
     ($t:ty) => {
         ::paste::paste! {
+            #[cfg(feature = "std")]
             pub fn [<read_ $t>] <R: Read>(reader: &mut R) -> std::io::Result<$t> {
                 let mut buffer = [0; std::mem::size_of::<$t>()];
-                reader.read(&mut buffer)?;
+                reader.read_exact(&mut buffer)?;
                 Ok($t::from_be_bytes(buffer))
             }
         }
@@ -48,8 +140,9 @@ impl_read!(u16);
 impl_read!(u32);
 impl_read!(u64);
 
+#[cfg(feature = "std")]
 pub fn read_string<R: Read>(reader: &mut R, size: usize) -> std::io::Result<String> {
     let mut buffer = vec![0; size];
-    reader.read(&mut buffer)?;
+    reader.read_exact(&mut buffer)?;
     Ok(String::from_utf8_lossy(&buffer).to_string())
 }
@@ -0,0 +1,579 @@
+//! Serializable summaries of playlists and discs, for JSON output.
+
+use serde::Serialize;
+
+use crate::{
+    disc::Disc,
+    duplicates::copy_of,
+    parser::Parser,
+    report::stream_description,
+    ts::{
+        index::{IndexTable, TitleObject},
+        language_codes::get_language_code,
+        packet::SOURCE_PACKET_SIZE,
+        stream::Stream,
+    },
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistModel {
+    pub name: String,
+    /// Version string of the file, like `MPLS0300`.
+    pub version: String,
+    pub playback_type: u8,
+    pub playback_count: u16,
+    /// Seconds.
+    pub length: f64,
+    pub angle_count: usize,
+    pub flags: PlaylistFlags,
+    pub play_items: Vec<PlayItemModel>,
+    pub streams: Vec<StreamModel>,
+    pub chapters: Vec<ChapterModel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistFlags {
+    pub random_access: bool,
+    pub audio_mix_app: bool,
+    pub lossless_may_bypass_mixer: bool,
+    pub mvc_base_view_r: bool,
+    pub sdr_conversion_notification: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayItemModel {
+    pub clip: String,
+    /// Seconds on the clip's timeline.
+    pub time_in: f64,
+    pub time_out: f64,
+    /// Seconds from the start of the playlist.
+    pub relative_time_in: f64,
+    pub length: f64,
+    /// Clips of the other angles, from angle 1.
+    pub angles: Vec<String>,
+    pub pids: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamModel {
+    pub pid: u16,
+    /// `video`, `audio`, `graphics` or `text`.
+    pub kind: &'static str,
+    pub codec: &'static str,
+    /// ISO 639-2 code as stored on the disc.
+    pub language: Option<String>,
+    /// BCP 47 tag, `fr` for `fre` and `fra`.
+    pub language_tag: Option<String>,
+    pub language_name: Option<String>,
+    pub description: String,
+    /// Bits per second, known once the stream files are scanned.
+    pub bit_rate: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChapterModel {
+    pub number: usize,
+    /// Seconds from the start of the playlist.
+    pub time_in: f64,
+    pub length: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscModel {
+    pub name: String,
+    pub label: String,
+    pub title: Option<String>,
+    pub size: u64,
+    pub protection: &'static str,
+    pub extras: Vec<&'static str>,
+    pub main_feature: Option<String>,
+    pub playlists: Vec<PlaylistModel>,
+}
+
+impl PlaylistModel {
+    pub fn new(name: &str, parser: &Parser) -> Self {
+        let header = parser.mpls_header();
+        PlaylistModel {
+            name: name.to_owned(),
+            version: header.magic_string(),
+            playback_type: header.playback_type(),
+            playback_count: header.playback_count(),
+            length: parser.total_length(),
+            angle_count: parser.angle_count(),
+            flags: PlaylistFlags {
+                random_access: header.random_access() != 0,
+                audio_mix_app: header.audio_mix_app() != 0,
+                lossless_may_bypass_mixer: header.lossless_may_bypass_mixer() != 0,
+                mvc_base_view_r: header.mvc_base_view_r(),
+                sdr_conversion_notification: header.sdr_conversion_notification() != 0,
+            },
+            play_items: parser
+                .clips()
+                .iter()
+                .map(|clip| PlayItemModel {
+                    clip: clip.name.clone(),
+                    time_in: clip.time_in,
+                    time_out: clip.time_out,
+                    relative_time_in: clip.relative_time_in,
+                    length: clip.length,
+                    angles: clip.angles.iter().map(|a| a.name.clone()).collect(),
+                    pids: clip.streams.iter().map(Stream::pid).collect(),
+                })
+                .collect(),
+            streams: parser.streams().into_iter().map(StreamModel::new).collect(),
+            chapters: chapters(parser),
+        }
+    }
+}
+
+impl StreamModel {
+    pub fn new(stream: &Stream) -> Self {
+        let kind = match stream {
+            Stream::VideoStream(_) => "video",
+            Stream::AudioStream(_) => "audio",
+            Stream::GraphicsStream(_) => "graphics",
+            Stream::TextStream(_) => "text",
+        };
+        let language = stream.language();
+        StreamModel {
+            pid: stream.pid(),
+            kind,
+            codec: stream.stream_type().codec_name(),
+            language: language.map(|l| l.as_str().to_owned()),
+            language_tag: language.map(|l| l.bcp47().to_owned()),
+            language_name: language.map(|l| get_language_code(l.as_str())),
+            description: stream_description(stream),
+            bit_rate: Some(stream.stats().bit_rate).filter(|rate| *rate > 0),
+        }
+    }
+}
+
+/// Entry marks with their lengths, up to the end of the playlist.
+pub fn chapters(parser: &Parser) -> Vec<ChapterModel> {
+    let times = parser.chapter_times();
+    let length = parser.total_length();
+    times
+        .iter()
+        .enumerate()
+        .map(|(i, time_in)| ChapterModel {
+            number: i + 1,
+            time_in: *time_in,
+            length: times.get(i + 1).copied().unwrap_or(length) - time_in,
+        })
+        .collect()
+}
+
+impl DiscModel {
+    pub fn new(disc: &Disc) -> Self {
+        let main_feature = disc
+            .rank_playlists()
+            .into_iter()
+            .find(|rank| rank.is_main_feature())
+            .map(|rank| rank.name);
+        DiscModel {
+            name: disc.name.clone(),
+            label: disc.label(),
+            title: disc.title(),
+            size: disc.size(),
+            protection: disc.protection(),
+            extras: disc.extras(),
+            main_feature,
+            playlists: disc
+                .playlists
+                .iter()
+                .map(|p| PlaylistModel::new(&p.name, &p.parser))
+                .collect(),
+        }
+    }
+}
+
+/// One playlist of a disc catalogue.
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogRow {
+    /// Disc folder or image the playlist was read from.
+    pub disc: String,
+    pub disc_id: Option<String>,
+    pub disc_label: String,
+    pub playlist: String,
+    /// Seconds.
+    pub duration: f64,
+    pub item_count: usize,
+    pub video_format: Option<String>,
+    pub audio_languages: Vec<String>,
+    pub subtitle_languages: Vec<String>,
+    pub chapter_count: usize,
+    /// Ranking flags, plus `duplicate` for exact copies of another playlist.
+    pub flags: Vec<String>,
+}
+
+impl CatalogRow {
+    /// A row per playlist of `disc`, in playlist order.
+    pub fn for_disc(disc: &Disc, path: &str) -> Vec<CatalogRow> {
+        let disc_id = disc.disc_id();
+        let label = disc.label();
+        let ranks = disc.rank_playlists();
+        let identical = disc.identical_playlists();
+        disc.playlists
+            .iter()
+            .map(|playlist| {
+                let parser = &playlist.parser;
+                let streams = parser.streams();
+                let mut flags = ranks
+                    .iter()
+                    .find(|rank| rank.name == playlist.name)
+                    .map(|rank| rank.flags.iter().map(|f| f.as_str().to_owned()).collect())
+                    .unwrap_or_else(Vec::new);
+                if copy_of(&identical, &playlist.name).is_some() {
+                    flags.push("duplicate".to_owned());
+                }
+                CatalogRow {
+                    disc: path.to_owned(),
+                    disc_id: disc_id.clone(),
+                    disc_label: label.clone(),
+                    playlist: playlist.name.clone(),
+                    duration: parser.total_length(),
+                    item_count: parser.clips().len(),
+                    video_format: video_format(&streams),
+                    audio_languages: audio_languages(&streams),
+                    subtitle_languages: subtitle_languages(&streams),
+                    chapter_count: parser.chapter_times().len(),
+                    flags,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Codec and format of the first video stream.
+fn video_format(streams: &[&Stream]) -> Option<String> {
+    streams
+        .iter()
+        .find(|s| matches!(s, Stream::VideoStream(_)))
+        .map(|s| {
+            format!(
+                "{} / {}",
+                s.stream_type().codec_name(),
+                stream_description(s)
+            )
+        })
+}
+
+fn audio_languages(streams: &[&Stream]) -> Vec<String> {
+    languages(streams, |s| matches!(s, Stream::AudioStream(_)))
+}
+
+fn subtitle_languages(streams: &[&Stream]) -> Vec<String> {
+    languages(streams, |s| {
+        matches!(s, Stream::GraphicsStream(_) | Stream::TextStream(_))
+    })
+}
+
+/// Distinct language codes of the streams `kind` accepts, in stream order.
+fn languages(streams: &[&Stream], kind: fn(&Stream) -> bool) -> Vec<String> {
+    let mut codes = Vec::new();
+    for stream in streams.iter().filter(|s| kind(s)) {
+        let code = stream.lang_code();
+        if !code.is_empty() && !codes.iter().any(|c| c == code) {
+            codes.push(code.to_owned());
+        }
+    }
+    codes
+}
+
+/// What a disc holds and which playlist is the main feature, for reports
+/// built from the disc's small files alone.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscSummary {
+    pub name: String,
+    pub label: String,
+    pub title: Option<String>,
+    pub size: u64,
+    pub protection: &'static str,
+    pub extras: Vec<&'static str>,
+    pub main_feature: Option<String>,
+    pub index: Option<IndexModel>,
+    pub playlists: Vec<PlaylistSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexModel {
+    pub version: String,
+    pub first_playback: TitleModel,
+    pub top_menu: TitleModel,
+    pub titles: Vec<TitleModel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TitleModel {
+    /// `hdmv`, `bdj` or `unknown`.
+    pub kind: &'static str,
+    /// Movie object number, or `.bdjo` name.
+    pub object: String,
+    /// Whether a menu or the player's title search can select it.
+    pub accessible: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistSummary {
+    pub name: String,
+    /// Seconds.
+    pub length: f64,
+    pub item_count: usize,
+    pub angle_count: usize,
+    pub chapter_count: usize,
+    pub video_format: Option<String>,
+    pub audio_languages: Vec<String>,
+    pub subtitle_languages: Vec<String>,
+    /// Main feature ranking score, see [`crate::ranking`].
+    pub score: f64,
+    pub flags: Vec<&'static str>,
+    pub reasons: Vec<String>,
+    /// The playlist this one is an exact copy of.
+    pub duplicate_of: Option<String>,
+    pub clips: Vec<ClipSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipSummary {
+    pub name: String,
+    /// Seconds played from the clip.
+    pub length: f64,
+    /// Bytes of the stream file, or as recorded in the clip information
+    /// when the stream file is missing.
+    pub size: Option<u64>,
+    /// Bits per second, from the clip information.
+    pub recording_rate: Option<u64>,
+}
+
+impl DiscSummary {
+    pub fn new(disc: &Disc) -> Self {
+        let ranks = disc.rank_playlists();
+        let identical = disc.identical_playlists();
+        let playlists = ranks
+            .iter()
+            .filter_map(|rank| {
+                let parser = &disc.playlist(&rank.name)?.parser;
+                let streams = parser.streams();
+                Some(PlaylistSummary {
+                    name: rank.name.clone(),
+                    length: rank.length,
+                    item_count: rank.item_count,
+                    angle_count: parser.angle_count(),
+                    chapter_count: parser.chapter_times().len(),
+                    video_format: video_format(&streams),
+                    audio_languages: audio_languages(&streams),
+                    subtitle_languages: subtitle_languages(&streams),
+                    score: rank.score,
+                    flags: rank.flags.iter().map(|f| f.as_str()).collect(),
+                    reasons: rank.reasons.clone(),
+                    duplicate_of: copy_of(&identical, &rank.name).map(str::to_owned),
+                    clips: parser
+                        .clips()
+                        .iter()
+                        .map(|clip| {
+                            let info = disc.clip_info(&clip.stream_clip_file).ok();
+                            let recorded = info.as_ref().map(|info| {
+                                u64::from(info.source_packet_count) * SOURCE_PACKET_SIZE as u64
+                            });
+                            ClipSummary {
+                                name: clip.name.clone(),
+                                length: clip.length,
+                                size: disc.stream_file_size(&clip.stream_file).or(recorded),
+                                recording_rate: info
+                                    .map(|info| u64::from(info.ts_recording_rate) * 8),
+                            }
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+        DiscSummary {
+            name: disc.name.clone(),
+            label: disc.label(),
+            title: disc.title(),
+            size: disc.size(),
+            protection: disc.protection(),
+            extras: disc.extras(),
+            main_feature: ranks
+                .iter()
+                .find(|rank| rank.is_main_feature())
+                .map(|rank| rank.name.clone()),
+            index: disc.index().ok().map(|index| IndexModel::new(&index)),
+            playlists,
+        }
+    }
+}
+
+impl IndexModel {
+    pub fn new(index: &IndexTable) -> Self {
+        IndexModel {
+            version: index.version.clone(),
+            first_playback: TitleModel::new(&index.first_playback, true),
+            top_menu: TitleModel::new(&index.top_menu, true),
+            titles: index
+                .titles
+                .iter()
+                .map(|title| TitleModel::new(&title.object, title.access_type == 0))
+                .collect(),
+        }
+    }
+}
+
+impl TitleModel {
+    fn new(object: &TitleObject, accessible: bool) -> Self {
+        let (kind, object) = match object {
+            TitleObject::Hdmv { movie_object, .. } => ("hdmv", movie_object.to_string()),
+            TitleObject::BdJ { name, .. } => ("bdj", name.clone()),
+            TitleObject::Unknown(kind) => ("unknown", kind.to_string()),
+        };
+        TitleModel {
+            kind,
+            object,
+            accessible,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{clpi, disc, index_bdmv, mpls, PlayItem, MPLS_00800, MPLS_00801},
+        ts::index::TitleObject,
+    };
+
+    #[test]
+    fn models_sample() {
+        let parser = Parser::from_bytes(MPLS_00800).unwrap();
+        let model = PlaylistModel::new("00800.MPLS", &parser);
+        assert_eq!(model.version, "MPLS0300");
+        assert_eq!(model.play_items.len(), 1);
+        let item = &model.play_items[0];
+        assert_eq!(item.clip, "00037.M2TS");
+        assert_eq!(item.time_in, 4198.375);
+        assert_eq!(item.pids.len(), model.streams.len());
+        assert_eq!(model.streams[0].kind, "video");
+        let audio = &model.streams[1];
+        assert_eq!(
+            (audio.kind, audio.language.as_deref()),
+            ("audio", Some("eng"))
+        );
+        assert_eq!(audio.language_tag.as_deref(), Some("en"));
+        assert_eq!(audio.bit_rate, None);
+        assert_eq!(model.chapters.first().map(|c| c.time_in), Some(0.0));
+
+        let json = serde_json::to_value(&model).unwrap();
+        assert_eq!(json["streams"][1]["codec"], audio.codec);
+    }
+
+    #[test]
+    fn chapters_run_to_the_next_mark() {
+        let data = mpls(
+            &[
+                PlayItem {
+                    clip: "00001",
+                    time_in: 45_000,
+                    time_out: 45_000 * 11,
+                    streams: &[],
+                },
+                PlayItem {
+                    clip: "00002",
+                    time_in: 0,
+                    time_out: 45_000 * 20,
+                    streams: &[],
+                },
+            ],
+            &[(0, 45_000), (1, 45_000 * 5), (7, 0)],
+        );
+        let parser = Parser::from_bytes(&data).unwrap();
+        let chapters = chapters(&parser)
+            .into_iter()
+            .map(|c| (c.number, c.time_in, c.length))
+            .collect::<Vec<_>>();
+        // The mark of a missing play item is left out.
+        assert_eq!(chapters, [(1, 0.0, 15.0), (2, 15.0, 15.0)]);
+    }
+
+    #[test]
+    fn catalogs_disc() {
+        let disc = disc(&[
+            ("AACS/Unit_Key_RO.inf", b"abc"),
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/PLAYLIST/00801.mpls", MPLS_00801),
+            ("BDMV/PLAYLIST/00802.mpls", MPLS_00800),
+        ]);
+        let rows = CatalogRow::for_disc(&disc, "/discs/SAMPLE");
+        let names = rows.iter().map(|r| r.playlist.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["00800.MPLS", "00801.MPLS", "00802.MPLS"]);
+
+        let row = &rows[0];
+        assert_eq!(row.disc, "/discs/SAMPLE");
+        assert_eq!(
+            row.disc_id.as_deref(),
+            Some("A9993E364706816ABA3E25717850C26C9CD0D89D")
+        );
+        assert_eq!(row.disc_label, "SAMPLE");
+        assert_eq!(row.item_count, 1);
+        assert!(row.video_format.is_some());
+        assert_eq!(row.audio_languages.first().map(String::as_str), Some("eng"));
+        assert!(!row.subtitle_languages.is_empty());
+        assert!(!row.flags.iter().any(|f| f == "duplicate"));
+        assert!(rows[2].flags.iter().any(|f| f == "duplicate"));
+        assert!(!rows[1].flags.iter().any(|f| f == "duplicate"));
+    }
+
+    #[test]
+    fn summarizes_disc() {
+        let hdmv = |movie_object| TitleObject::Hdmv {
+            playback_type: 0,
+            movie_object,
+        };
+        let index = index_bdmv(&hdmv(0), &hdmv(1), &[(hdmv(2), 0), (hdmv(3), 1)]);
+        let clip_info = clpi(1000, (0, 45_000), &[(0x1011, 0x24)], &[]);
+        let disc = disc(&[
+            ("BDMV/index.bdmv", &index),
+            ("BDMV/CLIPINF/00037.clpi", &clip_info),
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/PLAYLIST/00801.mpls", MPLS_00801),
+            ("BDMV/PLAYLIST/00802.mpls", MPLS_00800),
+            (
+                "BDMV/META/DL/bdmt_eng.xml",
+                b"<di:title><di:name> Sample </di:name></di:title>",
+            ),
+        ]);
+        let summary = DiscSummary::new(&disc);
+        assert_eq!(summary.label, "SAMPLE");
+        assert_eq!(summary.title.as_deref(), Some("Sample"));
+        assert_eq!(summary.playlists.len(), 3);
+        assert_eq!(
+            summary.main_feature.as_deref(),
+            Some(summary.playlists[0].name.as_str())
+        );
+
+        let index = summary.index.unwrap();
+        assert_eq!(index.version, "0200");
+        let titles = index
+            .titles
+            .iter()
+            .map(|t| (t.kind, t.object.as_str(), t.accessible))
+            .collect::<Vec<_>>();
+        assert_eq!(titles, [("hdmv", "2", true), ("hdmv", "3", false)]);
+
+        let sample = summary
+            .playlists
+            .iter()
+            .find(|p| p.name == "00800.MPLS")
+            .unwrap();
+        assert_eq!(sample.duplicate_of, None);
+        let clip = &sample.clips[0];
+        assert_eq!(clip.name, "00037.M2TS");
+        assert_eq!(clip.size, Some(1000 * 192));
+        assert_eq!(clip.recording_rate, Some(384_000_000));
+        let copy = summary
+            .playlists
+            .iter()
+            .find(|p| p.name == "00802.MPLS")
+            .unwrap();
+        assert_eq!(copy.duplicate_of.as_deref(), Some("00800.MPLS"));
+    }
+}
@@ -1,344 +1,519 @@
-use std::{
-    collections::HashMap,
-    io::{Read, Result, Seek, SeekFrom},
-};
+use alloc::{collections::BTreeMap, format, vec::Vec};
 
+#[cfg(feature = "std")]
+use crate::{
+    playlist_view::{read_play_item, ByteReader, PlaylistBuffer},
+    ts::{stream::StreamStats, stream_file::StreamFileScan, FromBinary},
+};
 use crate::{
-    macros,
+    playlist_view::{ParseResult, PlayItemView, PlaylistView, StreamSection},
     ts::{
-        self,
-        angle::ClipAngleHeader,
+        codec::StreamInfo,
         mpls::MplsFileHeader,
-        plist::PlaylistHeader,
-        stream::{TSAudioStream, TSGraphicsStream, TSTextStream, TSVideoStream},
-        stream_clip::{ClipHeader, StreamClip},
-        streams_header::StreamsHeader,
-        FromBinary,
+        plist::{ChapterHeader, ChapterSection, PlaylistHeader},
+        stream::Stream,
+        stream_clip::StreamClip,
     },
 };
+#[cfg(feature = "std")]
+use std::io::{Read, Result, Seek, SeekFrom};
 
-use macros::*;
-use ts::{
-    plist::{ChapterHeader, ChapterSection},
-    stream::Stream,
-    stream_type::StreamType,
-};
+/// Playlist streams by PID. Without `std` there is no `HashMap`, so they are
+/// kept in a `BTreeMap`.
+#[cfg(feature = "std")]
+pub type StreamMap = std::collections::HashMap<u16, Stream>;
+#[cfg(not(feature = "std"))]
+pub type StreamMap = BTreeMap<u16, Stream>;
 
 #[derive(Debug, Default)]
 pub struct Parser {
     mpls_header: MplsFileHeader,
     playlist_header: PlaylistHeader,
     chapters_header: ChapterHeader,
-    playlist_streams: HashMap<u16, Stream>,
+    playlist_streams: StreamMap,
     clips: Vec<StreamClip>,
     chapters: Vec<ChapterSection>,
 }
 
 impl Parser {
-    pub fn total_length(&self) -> f64 {
-        self.clips.iter().fold(0.0, |acc, c| acc + c.length)
+    /// Reads an `.mpls` file from a stream, buffering only the sections
+    /// the playlist needs, see [`PlaylistBuffer`].
+    #[cfg(feature = "std")]
+    pub fn parse<R: Read>(reader: &mut R) -> Result<Self> {
+        let buffer = PlaylistBuffer::read(reader)?;
+        Ok(Self::from_view(&buffer.view()?))
+    }
+
+    /// Parses an `.mpls` file held in memory, see [`PlaylistView`].
+    pub fn from_bytes(data: &[u8]) -> ParseResult<Self> {
+        Ok(Self::from_view(&PlaylistView::parse(data)?))
     }
 
+    /// Builds the playlist from a parsed view, emitting each section and
+    /// field to [`crate::trace`].
+    pub fn from_view(view: &PlaylistView) -> Self {
+        let mut parser = Parser {
+            mpls_header: view.header.clone(),
+            playlist_header: view.playlist_header.clone(),
+            chapters_header: view.chapters_header.clone(),
+            chapters: view.marks.clone(),
+            ..Default::default()
+        };
+        trace_header(view);
+        trace_playlist_header(view);
+        for (i, item) in view.play_items.iter().enumerate() {
+            parser.push_play_item(i, item);
+        }
+        trace_marks(view);
+        parser
+    }
+
+    /// Reads the file header from the start of `reader`.
+    #[cfg(feature = "std")]
     pub fn read_header<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
-        self.mpls_header = MplsFileHeader::read_from_binary(reader)?;
-        let offsets = self.mpls_header.offsets();
-        log!("magic string", "{}", self.mpls_header.magic_string());
-        log!("Playlist offset", "{:X}", offsets.0);
-        log!("Marks offset", "{:X}", offsets.1);
-        log!("Extensions offset", "{:X}", offsets.2);
-        log!("Misc Flags", "{:X}", self.mpls_header.misc_flags);
-        assert_eq!(
-            MplsFileHeader::expected_size() as u64,
-            reader.stream_position()?
-        );
+        let buffer = buffer(reader)?;
+        let view = buffer.view()?;
+        self.mpls_header = view.header.clone();
+        trace_header(&view);
+        reader.seek(SeekFrom::Start(MplsFileHeader::SIZE as u64))?;
         Ok(())
     }
 
+    #[cfg(feature = "std")]
     pub fn read_playlist_header<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
-        reader.seek(SeekFrom::Start(self.mpls_header.playlist_offset.into()))?;
-        self.playlist_header = PlaylistHeader::read_from_binary(reader)?;
-        log!("play item entries", "{}", self.playlist_header.item_count());
-        log!(
-            "subpath entries",
-            "{}",
-            self.playlist_header.subitem_count()
-        );
-        assert_eq!(
-            (self.mpls_header.playlist_offset + PlaylistHeader::expected_size()) as u64,
-            reader.stream_position()?
-        );
+        let buffer = buffer(reader)?;
+        let view = buffer.view()?;
+        self.playlist_header = view.playlist_header.clone();
+        trace_playlist_header(&view);
         Ok(())
     }
 
+    /// Reads every play item, adding its clip and streams.
+    #[cfg(feature = "std")]
     pub fn read_play_item_entries<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
-        reader.seek(SeekFrom::Start(
-            (self.mpls_header.playlist_offset + PlaylistHeader::expected_size()) as u64,
-        ))?;
-
-        for i in 0..self.playlist_header.item_count {
-            info!(&format!("Playlist Item {}", i), "",);
-            self.read_stream_section(reader)?;
+        let buffer = buffer(reader)?;
+        let view = buffer.view()?;
+        for (i, item) in view.play_items.iter().enumerate() {
+            self.push_play_item(i, item);
         }
-
         Ok(())
     }
 
+    /// Reads the play item at the position of `reader`, adding its clip and
+    /// streams, and leaves `reader` after it.
+    #[cfg(feature = "std")]
     pub fn read_stream_section<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
-        let item_start = reader.stream_position()?;
+        let offset = reader.stream_position()? as usize;
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        let mut data = len.to_vec();
+        data.resize(2 + u16::from_be_bytes(len) as usize, 0);
+        reader.read_exact(&mut data[2..])?;
+        let mut item = read_play_item(&data, &mut ByteReader::new(&data))?;
+        item.offset = offset;
+        self.push_play_item(self.clips.len(), &item);
+        Ok(())
+    }
 
-        let stream_header = ClipHeader::read_from_binary(reader)?;
-        info!("Stream Section", "{}", stream_header.stream_file_name());
-        info!("Start", "{}", stream_header.time_in());
-        info!("End", "{}", stream_header.time_out());
-        info!("Duration", "{}", stream_header.duration());
+    #[cfg(feature = "std")]
+    pub fn read_chapters_header<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
+        let buffer = buffer(reader)?;
+        self.chapters_header = buffer.view()?.chapters_header.clone();
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn read_chapters<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
+        let buffer = buffer(reader)?;
+        let view = buffer.view()?;
+        self.chapters = view.marks.clone();
+        trace_marks(&view);
+        Ok(())
+    }
 
-        let stream_clip = StreamClip::new(
-            &stream_header,
+    /// Adds the clip of play item `index` and its streams.
+    fn push_play_item(&mut self, index: usize, item: &PlayItemView) {
+        section!(at item.offset, &format!("Playlist Item {}", index));
+        trace_play_item(item);
+        let mut clip = StreamClip::new(
+            &item.header,
             self.total_length(),
-            &stream_header.stream_file_name(),
-            &stream_header.stream_clip_file_name(),
+            &item.header.stream_file_name(),
+            &item.header.stream_clip_file_name(),
         );
-        let relative_length = stream_clip.relative_length;
-        self.clips.push(stream_clip);
-        let stream_clip = self.clips.last().unwrap();
+        for (index, angle) in item.angles.iter().enumerate() {
+            let mut angle_clip = clip.clone();
+            angle_clip.angle_index = index as i32 + 1;
+            angle_clip.name = format!("{}.M2TS", angle.clip_name);
+            angle_clip.stream_file = format!("{}.M2TS", angle.clip_name);
+            angle_clip.stream_clip_file = format!("{}.CLPI", angle.clip_name);
+            clip.angles.push(angle_clip);
+        }
+        let mut numbers = BTreeMap::<&str, usize>::new();
+        for entry in item.streams.iter() {
+            let identifier = section_identifier(entry.section);
+            let number = numbers.entry(identifier).or_default();
+            let name = format!("{} Stream {}", identifier, number);
+            *number += 1;
+            let Some(stream) = entry.to_stream() else {
+                error!(&name, "{}", "Returned None");
+                continue;
+            };
+            log!(&name, "{:?}", stream);
+            if !self.playlist_streams.contains_key(&stream.pid()) || clip.relative_length > 0.01 {
+                self.playlist_streams.insert(stream.pid(), stream.clone());
+            } else {
+                log!(&format!("Dup {} pid", name), "{}", stream.pid());
+            }
+            clip.streams.push(stream);
+        }
+        self.clips.push(clip);
+    }
+
+    pub fn total_length(&self) -> f64 {
+        self.clips.iter().fold(0.0, |acc, c| acc + c.length)
+    }
+
+    pub fn mpls_header(&self) -> &MplsFileHeader {
+        &self.mpls_header
+    }
 
-        let mut _angle_count = 0;
+    pub fn playlist_header(&self) -> &PlaylistHeader {
+        &self.playlist_header
+    }
 
-        if stream_header.multi_angle() > 0 {
-            log!("multi_angle", "{}", stream_header.multi_angle());
+    pub fn chapters_header(&self) -> &ChapterHeader {
+        &self.chapters_header
+    }
+
+    pub fn playlist_streams(&self) -> &StreamMap {
+        &self.playlist_streams
+    }
 
-            let angles = read_u8(reader)?;
-            reader.seek(SeekFrom::Current(1))?;
-            log!("angles", "{}", angles);
+    /// Fills clip and stream statistics from scans of the clips' stream
+    /// files. `lookup` maps a stream file name (`00001.M2TS`) to its scan.
+    #[cfg(feature = "std")]
+    pub fn apply_stream_scans<'a, F>(&mut self, mut lookup: F)
+    where
+        F: FnMut(&str) -> Option<&'a StreamFileScan>,
+    {
+        let mut totals: BTreeMap<u16, StreamStats> = BTreeMap::new();
+        for clip in self.clips.iter_mut() {
+            let Some(scan) = lookup(&clip.stream_file) else {
+                continue;
+            };
+            clip.file_size = scan.file_size;
+            clip.packet_count = scan.packet_count;
+            clip.payload_bytes = scan.payload_bytes;
+            clip.packet_seconds = scan.duration();
+
+            for stream in clip.streams.iter_mut() {
+                let Some(pid) = scan.pids.get(&stream.pid()) else {
+                    continue;
+                };
+                let seconds = clip.packet_seconds;
+                let active = pid.active_seconds();
+                *stream.stats_mut() = StreamStats::default();
+                stream
+                    .stats_mut()
+                    .add(pid.payload_bytes, pid.packet_count, seconds, active);
+                totals.entry(stream.pid()).or_default().add(
+                    pid.payload_bytes,
+                    pid.packet_count,
+                    seconds,
+                    active,
+                );
+            }
+        }
 
-            for angle in 0..angles {
-                let header = ClipAngleHeader::read_from_binary(reader)?;
-                let mut angle_clip = stream_clip.clone();
-                angle_clip.angle_index = angle as i32 + 1;
-                angle_clip.stream_file = format!("{}.M2TS", header.angle_name());
-                angle_clip.stream_clip_file = format!("{}.CLPI", header.angle_name());
+        for (pid, total) in totals {
+            if let Some(stream) = self.playlist_streams.get_mut(&pid) {
+                *stream.stats_mut() = total;
             }
+        }
+    }
 
-            if angles - 1 > _angle_count {
-                _angle_count = angles - 1;
+    /// Fills in the stream properties probed from each clip's elementary
+    /// streams. A playlist stream takes the info of the first clip that has
+    /// one.
+    pub fn apply_stream_probes<'a, F>(&mut self, mut lookup: F)
+    where
+        F: FnMut(&str) -> Option<&'a BTreeMap<u16, StreamInfo>>,
+    {
+        for clip in self.clips.iter_mut() {
+            let Some(probes) = lookup(&clip.stream_file) else {
+                continue;
+            };
+            for stream in clip.streams.iter_mut() {
+                stream.set_info(probes.get(&stream.pid()));
             }
-        } else {
-            log!("multi_angle", "{}", stream_header.multi_angle());
         }
 
-        let streams_header = StreamsHeader::read_from_binary(reader)?;
-        log!("stream_info_len", "{}", streams_header.stream_info_len());
-        log!(
-            "stream_count_video",
-            "{}",
-            streams_header.stream_count_video
-        );
-        log!(
-            "stream_count_audio",
-            "{}",
-            streams_header.stream_count_audio
-        );
-        log!("stream_count_pg", "{}", streams_header.stream_count_pg);
-        log!("stream_count_ig", "{}", streams_header.stream_count_ig);
-        log!(
-            "stream_count_secondary_audio",
-            "{}",
-            streams_header.stream_count_secondary_audio
-        );
-        log!(
-            "stream_count_secondary_video",
-            "{}",
-            streams_header.stream_count_secondary_video
-        );
-        log!("stream_count_pip", "{}", streams_header.stream_count_pip);
-
-        let mut loop_streams = |reader: &mut R, identifier: &str, len: u8| -> Result<()> {
-            for i in 0..len {
-                let stream = self.create_playlist_stream(reader)?;
-                if let Some(stream) = stream {
-                    log!(&format!("{} Stream {}", identifier, i), "{:?}", stream);
-
-                    if !self.playlist_streams.contains_key(&stream.pid()) || relative_length > 0.01
-                    {
-                        self.playlist_streams.insert(stream.pid(), stream);
-                    } else {
-                        log!(
-                            &format!("Dup {} Stream {} pid", identifier, i),
-                            "{}",
-                            stream.pid()
-                        );
+        for stream in self.playlist_streams.values_mut() {
+            let info = self
+                .clips
+                .iter()
+                .flat_map(|clip| clip.streams.iter())
+                .filter(|s| s.pid() == stream.pid())
+                .find_map(|s| s.info());
+            stream.set_info(info.as_ref());
+        }
+    }
+
+    /// Playlist streams in STN order, each PID once.
+    pub fn streams(&self) -> Vec<&Stream> {
+        let mut streams: Vec<&Stream> = Vec::new();
+        for clip in self.clips.iter() {
+            for stream in clip.streams.iter() {
+                if streams.iter().all(|s| s.pid() != stream.pid()) {
+                    if let Some(stream) = self.playlist_streams.get(&stream.pid()) {
+                        streams.push(stream);
                     }
-                } else {
-                    error!(
-                        &format!("{} Stream {}", identifier, i),
-                        "{}", "Returned None"
-                    )
                 }
             }
-            Ok(())
-        };
+        }
+        streams
+    }
 
-        loop_streams(reader, "Videos", streams_header.stream_count_video)?;
-        loop_streams(reader, "Audio", streams_header.stream_count_audio)?;
-        loop_streams(reader, "PG", streams_header.stream_count_pg)?;
-        loop_streams(reader, "IG", streams_header.stream_count_ig)?;
-        loop_streams(
-            reader,
-            "2nd Audio",
-            streams_header.stream_count_secondary_audio,
-        )?;
-        reader.seek(SeekFrom::Current(2))?;
-        loop_streams(
-            reader,
-            "2nd Video",
-            streams_header.stream_count_secondary_video,
-        )?;
-        reader.seek(SeekFrom::Current(6))?;
-
-        let current_pos = reader.stream_position()?;
-        reader.seek(SeekFrom::Current(
-            stream_header.item_len() as i64 - (current_pos as i64 - item_start as i64) + 2,
-        ))?;
+    /// Number of angles of the playlist, 1 when it has no multi-angle items.
+    pub fn angle_count(&self) -> usize {
+        1 + self.clips.iter().map(|c| c.angles.len()).max().unwrap_or(0)
+    }
 
-        Ok(())
+    pub fn clips(&self) -> &[StreamClip] {
+        &self.clips
     }
 
-    pub fn create_playlist_stream<R: Read + Seek>(
-        &mut self,
-        reader: &mut R,
-    ) -> Result<Option<Stream>> {
-        let header_len = read_u8(reader)? as u64;
-        let header_pos = reader.stream_position()?;
-        let header_type = read_u8(reader)?;
-
-        let mut pid = 0;
-        let mut _subpath_id = 0;
-        let mut _subclip_id = 0;
-
-        match header_type {
-            1 => {
-                pid = read_u16(reader)?;
-            }
-            2 => {
-                _subpath_id = read_u8(reader)?;
-                _subclip_id = read_u8(reader)?;
-                pid = read_u16(reader)?;
-            }
-            3 => {
-                _subpath_id = read_u8(reader)?;
-                pid = read_u16(reader)?;
-            }
-            4 => {
-                _subpath_id = read_u8(reader)?;
-                _subclip_id = read_u8(reader)?;
-                pid = read_u16(reader)?;
-            }
-            _ => error!("Invalid header type", "{}", header_type),
-        }
+    pub fn chapters(&self) -> &[ChapterSection] {
+        &self.chapters
+    }
 
-        reader.seek(SeekFrom::Start(header_pos + header_len))?;
-
-        let stream_len = read_u8(reader)? as u64;
-        let stream_pos = reader.stream_position()?;
-
-        let stream_type: StreamType = read_u8(reader)?.into();
-        let stream: Option<Stream> = match stream_type {
-            StreamType::MVC_VIDEO => None,
-            StreamType::HEVC_VIDEO
-            | StreamType::AVC_VIDEO
-            | StreamType::MPEG1_VIDEO
-            | StreamType::MPEG2_VIDEO
-            | StreamType::VC1_VIDEO => {
-                let format_flags = read_u8(reader)?;
-                let video_format = format_flags >> 4;
-                let frame_rate = format_flags & 0xF;
-                let aspect_ratio = read_u8(reader)? >> 4;
-                Some(Stream::VideoStream(TSVideoStream {
-                    pid,
-                    stream_type,
-                    video_format: video_format.into(),
-                    frame_rate: frame_rate.into(),
-                    aspect_ratio: aspect_ratio.into(),
-                }))
-            }
+    /// Entry marks as offsets from the start of the playlist, in seconds.
+    pub fn chapter_times(&self) -> Vec<f64> {
+        self.chapters
+            .iter()
+            .filter(|mark| mark.mark_type == 1)
+            .filter_map(|mark| {
+                let clip = self.clips.get(mark.play_item_id as usize)?;
+                Some(clip.relative_time_in + mark.timestamp() - clip.time_in)
+            })
+            .collect()
+    }
+}
 
-            StreamType::AC3_AUDIO
-            | StreamType::AC3_PLUS_AUDIO
-            | StreamType::AC3_PLUS_SECONDARY_AUDIO
-            | StreamType::AC3_TRUE_HD_AUDIO
-            | StreamType::DTS_AUDIO
-            | StreamType::DTS_HD_AUDIO
-            | StreamType::DTS_HD_MASTER_AUDIO
-            | StreamType::DTS_HD_SECONDARY_AUDIO
-            | StreamType::LPCM_AUDIO
-            | StreamType::MPEG1_AUDIO
-            | StreamType::MPEG2_AUDIO
-            | StreamType::MPEG2_AAC_AUDIO
-            | StreamType::MPEG4_AAC_AUDIO => {
-                let audio_format_flags = read_u8(reader)?;
-                let channel_layout = audio_format_flags >> 4;
-                let sample_rate = audio_format_flags & 0xF;
-
-                let lang_code = read_string(reader, 3)?;
-                Some(Stream::AudioStream(TSAudioStream {
-                    pid,
-                    stream_type,
-                    channel_layout: channel_layout.into(),
-                    sample_rate: sample_rate.into(),
-                    lang_code,
-                }))
-            }
+fn trace_header(view: &PlaylistView) {
+    let header = &view.header;
+    section!(at 0, "Header");
+    log!("magic string", "{}", header.magic_string());
+    log!("Playlist offset", "{:X}", header.playlist_offset);
+    log!("Marks offset", "{:X}", header.chapters_offset);
+    log!("Extensions offset", "{:X}", header.extnsions_offset);
+    log!("Misc Flags", "{:X}", header.misc_flags);
+}
 
-            StreamType::INTERACTIVE_GRAPHICS | StreamType::PRESENTATION_GRAPHICS => {
-                let lang_code = read_string(reader, 3)?;
-                Some(Stream::GraphicsStream(TSGraphicsStream {
-                    pid,
-                    stream_type,
-                    lang_code,
-                }))
-            }
+fn trace_playlist_header(view: &PlaylistView) {
+    section!(at view.header.playlist_offset, "Playlist");
+    log!("play item entries", "{}", view.playlist_header.item_count());
+    log!(
+        "subpath entries",
+        "{}",
+        view.playlist_header.subitem_count()
+    );
+}
 
-            StreamType::SUBTITLE => {
-                let _code = read_u8(reader)?;
-                let lang_code = read_string(reader, 3)?;
-                Some(Stream::TextStream(TSTextStream {
-                    pid,
-                    stream_type,
-                    lang_code,
-                }))
-            }
-            _ => None,
-        };
+fn trace_marks(view: &PlaylistView) {
+    section!(at view.header.chapters_offset, "Marks");
+    for (i, mark) in view.marks.iter().enumerate() {
+        log!(
+            &format!("Marker: {}", i),
+            "{:?}",
+            (mark.play_item_id, mark.timestamp(), mark.valid_duration())
+        );
+    }
+}
 
-        reader.seek(SeekFrom::Start(stream_pos + stream_len))?;
-        Ok(stream)
+/// Buffers the playlist from the start of `reader` for the `read_*`
+/// methods.
+#[cfg(feature = "std")]
+fn buffer<R: Read + Seek>(reader: &mut R) -> Result<PlaylistBuffer> {
+    reader.seek(SeekFrom::Start(0))?;
+    PlaylistBuffer::read(reader)
+}
+
+fn trace_play_item(item: &PlayItemView) {
+    let header = &item.header;
+    let multi_angle = header.multi_angle();
+    log!("Stream Section", "{}", header.stream_file_name());
+    log!("Start", "{}", header.time_in());
+    log!("End", "{}", header.time_out());
+    log!("Duration", "{}", header.duration());
+    log!("multi_angle", "{}", multi_angle);
+    if multi_angle > 0 {
+        // The count includes the play item's own clip, angle 0.
+        log!("angles", "{}", item.angles.len() + 1);
     }
 
-    pub fn read_chapters_header<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
-        reader.seek(SeekFrom::Start(self.mpls_header.chapters_offset.into()))?;
-        self.chapters_header = ChapterHeader::read_from_binary(reader)?;
-        assert_eq!(
-            (self.mpls_header.chapters_offset + ChapterHeader::expected_size()) as u64,
-            reader.stream_position()?
+    let counts = &item.stn_header;
+    log!("stream_info_len", "{}", counts.stream_info_len());
+    log!("stream_count_video", "{}", counts.stream_count_video);
+    log!("stream_count_audio", "{}", counts.stream_count_audio);
+    log!("stream_count_pg", "{}", counts.stream_count_pg);
+    log!("stream_count_ig", "{}", counts.stream_count_ig);
+    log!(
+        "stream_count_secondary_audio",
+        "{}",
+        counts.stream_count_secondary_audio
+    );
+    log!(
+        "stream_count_secondary_video",
+        "{}",
+        counts.stream_count_secondary_video
+    );
+    log!("stream_count_pip", "{}", counts.stream_count_pip);
+    log!(
+        "stream_count_dolby_vision",
+        "{}",
+        counts.stream_count_dolby_vision
+    );
+}
+
+/// How stream entries of each STN list are named in trace events.
+fn section_identifier(section: StreamSection) -> &'static str {
+    match section {
+        StreamSection::Video => "Videos",
+        StreamSection::Audio => "Audio",
+        StreamSection::PresentationGraphics => "PG",
+        StreamSection::InteractiveGraphics => "IG",
+        StreamSection::SecondaryAudio => "2nd Audio",
+        StreamSection::SecondaryVideo => "2nd Video",
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::io::{Cursor, ErrorKind};
+
+    use super::*;
+    use crate::{
+        diff::diff,
+        playlist_view::ParseErrorKind,
+        testing::{mpls, PlayItem, MPLS_00800, MPLS_00801},
+    };
+
+    /// Parses with both the streaming and the in-memory parser and checks
+    /// they agree.
+    fn parse_both(data: &[u8]) -> Parser {
+        let streamed = Parser::parse(&mut &data[..]).unwrap();
+        let parsed = Parser::from_bytes(data).unwrap();
+        assert!(diff(&streamed, &parsed).is_empty());
+        assert_eq!(streamed.clips().len(), parsed.clips().len());
+        assert_eq!(streamed.chapter_times(), parsed.chapter_times());
+        parsed
+    }
+
+    fn pids(parser: &Parser) -> Vec<u16> {
+        parser.streams().iter().map(|s| s.pid()).collect()
+    }
+
+    #[test]
+    fn parses_samples() {
+        let parser = parse_both(MPLS_00800);
+        assert_eq!(parser.clips().len(), 1);
+        assert_eq!(parser.clips()[0].stream_file, "00037.M2TS");
+        assert_eq!(parser.streams().len(), 30);
+        assert_eq!(parser.angle_count(), 1);
+
+        let other = parse_both(MPLS_00801);
+        assert_eq!(other.streams().len(), 7);
+        assert_eq!(other.clips()[0].stream_file, "00037.M2TS");
+    }
+
+    #[test]
+    fn parses_secondary_streams() {
+        let data = mpls(
+            &[PlayItem {
+                clip: "00001",
+                time_in: 450_000,
+                time_out: 900_000,
+                streams: &[
+                    (0, 0x1011, &[0x1B, 0x61, 0x30]),
+                    (1, 0x1100, &[0x81, 0x61, b'e', b'n', b'g']),
+                    (4, 0x1A00, &[0xA1, 0x61, b'f', b'r', b'a']),
+                    (5, 0x1B00, &[0x1B, 0x61, 0x30]),
+                    (2, 0x1200, &[0x90, b'd', b'e', b'u']),
+                ],
+            }],
+            &[(0, 450_000)],
         );
-        Ok(())
+        let parser = parse_both(&data);
+        assert_eq!(pids(&parser), [0x1011, 0x1100, 0x1200, 0x1A00, 0x1B00]);
+        assert_eq!(parser.chapter_times(), [0.0]);
+        assert_eq!(parser.total_length(), 10.0);
     }
 
-    pub fn read_chapters<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
-        reader.seek(SeekFrom::Start(
-            (self.mpls_header.chapters_offset + ChapterHeader::expected_size()) as u64,
-        ))?;
-
-        for i in 0..self.chapters_header.mark_count() {
-            let mark = ChapterSection::read_from_binary(reader)?;
-            log!(
-                &format!("Marker: {}", i),
-                "{:?}",
-                (mark.play_item_id, mark.timestamp(), mark.valid_duration())
+    #[test]
+    fn reads_section_by_section() {
+        for data in [MPLS_00800, MPLS_00801] {
+            let mut reader = Cursor::new(data);
+            let mut parser = Parser::default();
+            parser.read_header(&mut reader).unwrap();
+            parser.read_playlist_header(&mut reader).unwrap();
+            parser.read_play_item_entries(&mut reader).unwrap();
+            parser.read_chapters_header(&mut reader).unwrap();
+            parser.read_chapters(&mut reader).unwrap();
+            let parsed = Parser::from_bytes(data).unwrap();
+            assert!(diff(&parser, &parsed).is_empty());
+            assert_eq!(parser.chapter_times(), parsed.chapter_times());
+            assert_eq!(
+                parser.playlist_streams().len(),
+                parsed.playlist_streams().len()
             );
-            self.chapters.push(mark);
+
+            // Play items one at a time, from the first one on.
+            let mut items = Parser::default();
+            items.read_header(&mut reader).unwrap();
+            items.read_playlist_header(&mut reader).unwrap();
+            let first =
+                (parser.mpls_header().playlist_offset as usize + PlaylistHeader::SIZE) as u64;
+            reader.seek(SeekFrom::Start(first)).unwrap();
+            for _ in 0..parser.playlist_header().item_count() {
+                items.read_stream_section(&mut reader).unwrap();
+            }
+            assert_eq!(items.clips().len(), parsed.clips().len());
+            assert_eq!(items.streams().len(), parsed.streams().len());
+            assert_eq!(items.total_length(), parsed.total_length());
         }
+    }
 
-        Ok(())
+    #[test]
+    fn rejects_malformed_playlists() {
+        let truncated = &MPLS_00800[..MPLS_00800.len() / 2];
+        let error = Parser::parse(&mut &truncated[..]).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        let error = Parser::from_bytes(truncated).err().unwrap();
+        assert_eq!(error.kind, ParseErrorKind::BadOffset);
+
+        let mut data = MPLS_00800.to_vec();
+        data[..4].copy_from_slice(b"HDMV");
+        assert_eq!(
+            Parser::parse(&mut &data[..]).err().unwrap().kind(),
+            ErrorKind::InvalidData
+        );
+        assert_eq!(
+            Parser::from_bytes(&data).err().unwrap().kind,
+            ParseErrorKind::BadMagic
+        );
+
+        // A section claiming nearly 4 GiB.
+        let mut data = MPLS_00800.to_vec();
+        data[58..62].copy_from_slice(&0xFFFF_FFF0u32.to_be_bytes());
+        assert_eq!(
+            Parser::parse(&mut &data[..]).err().unwrap().kind(),
+            ErrorKind::UnexpectedEof
+        );
+        assert_eq!(
+            Parser::from_bytes(&data).err().unwrap().kind,
+            ParseErrorKind::BadOffset
+        );
     }
 }
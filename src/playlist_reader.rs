@@ -0,0 +1,450 @@
+use std::{
+    fmt,
+    io::{BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    sync::Arc,
+};
+
+use crate::{
+    parser::Parser,
+    ts::{
+        clpi::ClipInfo,
+        packet::{PTS_CLOCK, PTS_MODULUS, SOURCE_PACKET_SIZE},
+        stream_clip::StreamClip,
+        stream_file,
+    },
+    vfs::{self, DiscFs, ReadSeek},
+};
+
+/// The part of one stream file that a play item covers.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    /// Path of the stream file on the disc.
+    pub path: String,
+    /// Byte range in the stream file, aligned to source packets.
+    pub start: u64,
+    pub end: u64,
+    /// Offset of the segment in the playlist stream.
+    pub offset: u64,
+    /// In and out points in the clip and where the item starts in the
+    /// playlist, in seconds.
+    pub time_in: f64,
+    pub time_out: f64,
+    pub playlist_time: f64,
+}
+
+impl Segment {
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+}
+
+/// Presents the play items of a playlist as one seekable stream of source
+/// packets, cut at the in and out points of each item.
+pub struct PlaylistReader {
+    fs: Arc<dyn DiscFs>,
+    segments: Vec<Segment>,
+    position: u64,
+    len: u64,
+    /// The open segment, its file and the file position reads continue at.
+    current: Option<(usize, Box<dyn ReadSeek>, u64)>,
+}
+
+impl fmt::Debug for PlaylistReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PlaylistReader")
+            .field("fs", &self.fs)
+            .field("segments", &self.segments)
+            .field("position", &self.position)
+            .field("len", &self.len)
+            .field("current", &self.current.as_ref().map(|(index, ..)| index))
+            .finish()
+    }
+}
+
+impl PlaylistReader {
+    /// `fs` holds the disc's `BDMV` folder. Play items with fewer angles
+    /// than `angle` play their first angle.
+    pub fn open(fs: Arc<dyn DiscFs>, parser: &Parser, angle: usize) -> Result<Self> {
+        if angle >= parser.angle_count() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "angle {} out of range, the playlist has {}",
+                    angle,
+                    parser.angle_count()
+                ),
+            ));
+        }
+
+        let mut segments = Vec::with_capacity(parser.clips().len());
+        let mut offset = 0;
+        for clip in parser.clips() {
+            let clip = clip.angle(angle);
+            let path = find_file(fs.as_ref(), "BDMV/STREAM", &clip.stream_file)?;
+            let (start, end) = clip_range(fs.as_ref(), &path, clip)?;
+            if end < start {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{}: out point before in point", clip.stream_file),
+                ));
+            }
+            segments.push(Segment {
+                path,
+                start,
+                end,
+                offset,
+                time_in: clip.time_in,
+                time_out: clip.time_out,
+                playlist_time: clip.relative_time_in,
+            });
+            offset += end - start;
+        }
+
+        Ok(PlaylistReader {
+            fs,
+            segments,
+            position: 0,
+            len: offset,
+            current: None,
+        })
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reads the byte range of one of the reader's segments on its own.
+    pub fn open_segment(&self, segment: &Segment) -> Result<impl Read> {
+        let mut file = self.fs.open(&segment.path)?;
+        file.seek(SeekFrom::Start(segment.start))?;
+        Ok(file.take(segment.len()))
+    }
+
+    fn segment_at(&self, position: u64) -> Option<usize> {
+        let index = self
+            .segments
+            .partition_point(|s| s.offset + s.len() <= position);
+        (index < self.segments.len()).then_some(index)
+    }
+}
+
+impl Read for PlaylistReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let Some(index) = self.segment_at(self.position) else {
+            return Ok(0);
+        };
+        let Some(segment) = self.segments.get(index) else {
+            return Ok(0);
+        };
+        let within = self.position - segment.offset;
+        let (file, file_position) = match &mut self.current {
+            Some((current, file, file_position)) if *current == index => (file, file_position),
+            current => {
+                let file = self.fs.open(&segment.path)?;
+                let (_, file, file_position) = current.insert((index, file, 0));
+                (file, file_position)
+            }
+        };
+        // Sequential reads carry on where the last one stopped, only a seek
+        // of the reader or a new segment moves the file.
+        if *file_position != segment.start + within {
+            *file_position = file.seek(SeekFrom::Start(segment.start + within))?;
+        }
+        let wanted = (segment.len() - within).min(buf.len() as u64) as usize;
+        let read = file.read(&mut buf[..wanted])?;
+        *file_position += read as u64;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for PlaylistReader {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "seek before the start of the playlist",
+            )),
+        }
+    }
+}
+
+/// Looks `name` up in `dir` on the disc. File system lookups ignore case,
+/// so rips with lower case names are found too.
+pub fn find_file(fs: &dyn DiscFs, dir: &str, name: &str) -> Result<String> {
+    let path = vfs::join(dir, name);
+    if fs.exists(&path) {
+        Ok(path)
+    } else {
+        Err(Error::new(
+            ErrorKind::NotFound,
+            format!("{} not found in {}", name, dir),
+        ))
+    }
+}
+
+fn to_pts(seconds: f64) -> u64 {
+    (seconds * PTS_CLOCK).round() as u64
+}
+
+/// Byte range of the play item in its stream file, from the clip's EP_map
+/// when it has a `.clpi` and by scanning the PCR when the `.clpi` is missing
+/// or has no EP_map. A `.clpi` that fails to parse is an error.
+fn clip_range(fs: &dyn DiscFs, path: &str, clip: &StreamClip) -> Result<(u64, u64)> {
+    let size = fs.metadata(path)?.len;
+    let size = size - size % SOURCE_PACKET_SIZE as u64;
+    let (in_pts, out_pts) = (to_pts(clip.time_in), to_pts(clip.time_out));
+
+    let clip_info = match find_file(fs, "BDMV/CLIPINF", &clip.stream_clip_file)
+        .and_then(|path| ClipInfo::parse(&mut BufReader::new(fs.open(&path)?)))
+    {
+        Ok(clip_info) => Some(clip_info),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(Error::new(
+                e.kind(),
+                format!("{}: {}", clip.stream_clip_file, e),
+            ))
+        }
+    };
+    if let Some(ep_map) = clip_info.as_ref().and_then(|c| c.main_ep_map()) {
+        let to_offset = |spn: u32| (u64::from(spn) * SOURCE_PACKET_SIZE as u64).min(size);
+        let start = ep_map.entry_before(in_pts).map(|e| to_offset(e.spn));
+        let end = ep_map.entry_after(out_pts).map(|e| to_offset(e.spn));
+        return Ok((start.unwrap_or(0), end.unwrap_or(size)));
+    }
+
+    let mut start = None;
+    let mut end = None;
+    let mut offset = 0;
+    stream_file::for_each_packet(&mut BufReader::new(fs.open(path)?), |packet| {
+        if let Some(pcr) = packet.pcr {
+            let pts = (pcr / 300) % PTS_MODULUS;
+            if pts <= in_pts {
+                start = Some(offset);
+            } else if pts > out_pts {
+                end = Some(offset);
+                return false;
+            }
+        }
+        offset += SOURCE_PACKET_SIZE as u64;
+        true
+    })?;
+    Ok((start.unwrap_or(0), end.unwrap_or(size)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Read,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::{
+        disc::Disc,
+        testing::{clpi, disc, source_packet, MPLS_00800},
+        vfs::{DirEntry, MemoryFs},
+    };
+
+    // In and out points of the sample playlist's only play item.
+    const IN_PTS: u64 = 377_853_750;
+    const OUT_PTS: u64 = 1_177_815_412;
+
+    fn stream() -> Vec<u8> {
+        (0..10u8)
+            .flat_map(|i| source_packet(0x1011, false, None, &[i; 184]))
+            .collect()
+    }
+
+    fn with_entries(entries: &[(u64, u32)]) -> Disc {
+        let clip_info = clpi(10, (0, 0), &[(0x1011, 0x24)], entries);
+        disc(&[
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/STREAM/00037.m2ts", &stream()),
+            ("BDMV/CLIPINF/00037.clpi", &clip_info),
+        ])
+    }
+
+    #[test]
+    fn cuts_segments_at_entry_points() {
+        let disc = with_entries(&[
+            (IN_PTS - 512 * 600, 2),
+            (IN_PTS + 512 * 600, 4),
+            (OUT_PTS - 512 * 400, 6),
+            (OUT_PTS + 512 * 600, 8),
+        ]);
+        let mut reader = disc.playlist_reader("00800.MPLS", 0).unwrap();
+        let segment = &reader.segments()[0];
+        assert_eq!(segment.path, "BDMV/STREAM/00037.M2TS");
+        assert_eq!((segment.start, segment.end), (2 * 192, 8 * 192));
+        assert_eq!(reader.len(), 6 * 192);
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, stream()[2 * 192..8 * 192]);
+
+        reader.seek(SeekFrom::End(-192)).unwrap();
+        let mut packet = [0; 192];
+        reader.read_exact(&mut packet).unwrap();
+        assert_eq!(packet[191], 7);
+        assert_eq!(reader.read(&mut packet).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-10_000)).is_err());
+    }
+
+    #[test]
+    fn falls_back_to_pcr() {
+        let mut stream = source_packet(0x1001, false, Some((IN_PTS - 90_000) * 300), &[]);
+        stream.extend(source_packet(
+            0x1001,
+            false,
+            Some((IN_PTS + 90_000) * 300),
+            &[],
+        ));
+        stream.extend(source_packet(
+            0x1001,
+            false,
+            Some((OUT_PTS + 90_000) * 300),
+            &[],
+        ));
+        let disc = disc(&[
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/STREAM/00037.M2TS", &stream),
+        ]);
+        let reader = disc.playlist_reader("00800.MPLS", 0).unwrap();
+        let segment = &reader.segments()[0];
+        assert_eq!((segment.start, segment.end), (0, 2 * 192));
+        let mut data = Vec::new();
+        reader
+            .open_segment(segment)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, stream[..2 * 192]);
+    }
+
+    #[test]
+    fn rejects_out_point_before_in_point() {
+        let disc = with_entries(&[(IN_PTS - 512 * 600, 8), (OUT_PTS + 512 * 600, 2)]);
+        let error = disc.playlist_reader("00800.MPLS", 0).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_missing_files_and_angles() {
+        let disc = disc(&[("BDMV/PLAYLIST/00800.mpls", MPLS_00800)]);
+        let error = disc.playlist_reader("00800.MPLS", 0).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        let error = disc.playlist_reader("00800.MPLS", 1).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn surfaces_damaged_clip_info() {
+        let disc = disc(&[
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/STREAM/00037.m2ts", &stream()),
+            ("BDMV/CLIPINF/00037.clpi", b"HDMV0200"),
+        ]);
+        let error = disc.playlist_reader("00800.MPLS", 0).unwrap_err();
+        assert_ne!(error.kind(), ErrorKind::NotFound);
+        assert!(error.to_string().starts_with("00037.CLPI: "), "{}", error);
+    }
+
+    /// Counts the seeks on the files it opens.
+    #[derive(Debug)]
+    struct SeekCounter {
+        fs: MemoryFs,
+        seeks: Arc<AtomicUsize>,
+    }
+
+    struct CountedFile {
+        file: Box<dyn ReadSeek>,
+        seeks: Arc<AtomicUsize>,
+    }
+
+    impl Read for CountedFile {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            self.file.read(buf)
+        }
+    }
+
+    impl Seek for CountedFile {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+            self.seeks.fetch_add(1, Ordering::Relaxed);
+            self.file.seek(pos)
+        }
+    }
+
+    impl DiscFs for SeekCounter {
+        fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+            self.fs.read_dir(path)
+        }
+
+        fn open(&self, path: &str) -> Result<Box<dyn ReadSeek>> {
+            Ok(Box::new(CountedFile {
+                file: self.fs.open(path)?,
+                seeks: self.seeks.clone(),
+            }))
+        }
+    }
+
+    #[test]
+    fn seeks_only_when_the_position_jumps() {
+        let mut fs = MemoryFs::new();
+        fs.insert("BDMV/PLAYLIST/00800.mpls", MPLS_00800);
+        fs.insert("BDMV/STREAM/00037.m2ts", &stream()[..]);
+        let entries = [(IN_PTS - 512 * 600, 2), (OUT_PTS + 512 * 600, 8)];
+        fs.insert(
+            "BDMV/CLIPINF/00037.clpi",
+            clpi(10, (0, 0), &[(0x1011, 0x24)], &entries),
+        );
+        let seeks = Arc::new(AtomicUsize::new(0));
+        let fs = Arc::new(SeekCounter {
+            fs,
+            seeks: seeks.clone(),
+        });
+        let disc = Disc::from_fs(fs, "SAMPLE".to_owned()).unwrap();
+        let mut reader = disc.playlist_reader("00800.MPLS", 0).unwrap();
+        // Parsing the playlist and clip information seeks too.
+        seeks.store(0, Ordering::Relaxed);
+
+        let mut chunk = [0; 100];
+        let mut data = Vec::new();
+        loop {
+            match reader.read(&mut chunk).unwrap() {
+                0 => break,
+                read => data.extend_from_slice(&chunk[..read]),
+            }
+        }
+        assert_eq!(data, stream()[2 * 192..8 * 192]);
+        assert_eq!(seeks.load(Ordering::Relaxed), 1);
+
+        reader.seek(SeekFrom::Start(192)).unwrap();
+        reader.read_exact(&mut chunk).unwrap();
+        reader.read_exact(&mut chunk).unwrap();
+        assert_eq!(chunk[..92], [3; 92]);
+        assert_eq!(seeks.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,714 @@
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use crate::ts::{
+    language::Language,
+    mpls::MplsFileHeader,
+    plist::{ChapterHeader, ChapterSection, PlaylistHeader},
+    stream::Stream,
+    stream_clip::ClipHeader,
+    stream_type::StreamType,
+    streams_header::StreamsHeader,
+    FromBinary,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The data ends inside a structure.
+    Truncated,
+    /// Not an `MPLS` file.
+    BadMagic,
+    /// A section offset or length points outside the file.
+    BadOffset,
+    /// A clip name that isn't text.
+    BadText,
+}
+
+/// Why a playlist couldn't be parsed, and the file offset where it went
+/// wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub offset: usize,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, offset: usize) -> Self {
+        ParseError { kind, offset }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            ParseErrorKind::Truncated => "truncated data",
+            ParseErrorKind::BadMagic => "not a playlist file",
+            ParseErrorKind::BadOffset => "section outside the file",
+            ParseErrorKind::BadText => "invalid text",
+        };
+        write!(f, "{} at offset {:#X}", what, self.offset)
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+#[cfg(feature = "std")]
+impl From<ParseError> for io::Error {
+    fn from(error: ParseError) -> Self {
+        let kind = match error.kind {
+            ParseErrorKind::Truncated => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// Big-endian reads from a slice that keep track of the offset, relative
+/// to the start of the file, for errors.
+#[derive(Debug, Clone)]
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        ByteReader { data, position: 0 }
+    }
+
+    /// A reader over `len` bytes at `offset`.
+    pub fn at(data: &'a [u8], offset: usize, len: usize) -> ParseResult<Self> {
+        let end = offset
+            .checked_add(len)
+            .filter(|end| *end <= data.len())
+            .ok_or(ParseError::new(ParseErrorKind::BadOffset, offset))?;
+        Ok(ByteReader {
+            data: &data[..end],
+            position: offset,
+        })
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn bytes(&mut self, len: usize) -> ParseResult<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .ok_or(ParseError::new(ParseErrorKind::BadOffset, self.position))?;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or(ParseError::new(ParseErrorKind::Truncated, self.position))?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn skip(&mut self, len: usize) -> ParseResult<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    pub fn u8(&mut self) -> ParseResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> ParseResult<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> ParseResult<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn str(&mut self, len: usize) -> ParseResult<&'a str> {
+        let position = self.position;
+        core::str::from_utf8(self.bytes(len)?)
+            .map_err(|_| ParseError::new(ParseErrorKind::BadText, position))
+    }
+
+    pub fn record<T: FromBinary>(&mut self) -> ParseResult<T> {
+        let position = self.position;
+        T::from_bytes(self.bytes(T::SIZE)?)
+            .ok_or(ParseError::new(ParseErrorKind::Truncated, position))
+    }
+}
+
+/// Which list of the STN table a stream entry is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamSection {
+    Video,
+    Audio,
+    PresentationGraphics,
+    InteractiveGraphics,
+    SecondaryAudio,
+    SecondaryVideo,
+}
+
+impl StreamSection {
+    /// Name of the list, as in `explain`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamSection::Video => "video",
+            StreamSection::Audio => "audio",
+            StreamSection::PresentationGraphics => "pg",
+            StreamSection::InteractiveGraphics => "ig",
+            StreamSection::SecondaryAudio => "secondary_audio",
+            StreamSection::SecondaryVideo => "secondary_video",
+        }
+    }
+}
+
+/// One stream of a play item's STN table, borrowing its attributes.
+#[derive(Debug, Clone)]
+pub struct StreamEntry<'a> {
+    pub offset: usize,
+    pub section: StreamSection,
+    /// 1 for the play item's clip, 2 to 4 for sub paths.
+    pub entry_type: u8,
+    pub pid: u16,
+    pub subpath_id: Option<u8>,
+    pub subclip_id: Option<u8>,
+    /// The stream attributes, starting with the coding type.
+    pub attributes: &'a [u8],
+}
+
+impl<'a> StreamEntry<'a> {
+    pub fn stream_type(&self) -> StreamType {
+        self.attributes.first().copied().unwrap_or_default().into()
+    }
+
+    /// ISO 639-2 language code of audio, graphics and text streams.
+    pub fn lang_code(&self) -> Option<&'a str> {
+        core::str::from_utf8(self.language_bytes()?).ok()
+    }
+
+    /// The language code checked and lowercased, `und` when it isn't a
+    /// valid code.
+    pub fn language(&self) -> Option<Language> {
+        self.language_bytes().map(Language::from_attribute)
+    }
+
+    fn language_bytes(&self) -> Option<&'a [u8]> {
+        let at = match self.section {
+            StreamSection::Video | StreamSection::SecondaryVideo => return None,
+            StreamSection::Audio | StreamSection::SecondaryAudio => 2,
+            // Text subtitles have their character code first.
+            _ if self.stream_type() == StreamType::SUBTITLE => 2,
+            _ => 1,
+        };
+        self.attributes.get(at..at + 3)
+    }
+
+    pub fn to_stream(&self) -> Option<Stream> {
+        Stream::from_attributes(self.pid, self.attributes)
+    }
+}
+
+/// An alternative clip of a multi-angle play item.
+#[derive(Debug, Clone, Copy)]
+pub struct AngleView<'a> {
+    pub clip_name: &'a str,
+    pub codec_id: &'a str,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayItemView<'a> {
+    pub offset: usize,
+    /// The whole play item, length field included.
+    pub raw: &'a [u8],
+    pub header: ClipHeader,
+    pub clip_name: &'a str,
+    pub codec_id: &'a str,
+    /// Angles after the first, which is the play item's own clip.
+    pub angles: Vec<AngleView<'a>>,
+    /// File offset of the STN table and its header.
+    pub stn_offset: usize,
+    pub stn_header: StreamsHeader,
+    /// Where the last stream entry ends, which should be the end of the
+    /// table.
+    pub stn_end: usize,
+    pub streams: Vec<StreamEntry<'a>>,
+}
+
+/// A clip played alongside the play items, like a text subtitle file.
+#[derive(Debug, Clone, Copy)]
+pub struct SubPlayItemView<'a> {
+    pub offset: usize,
+    pub clip_name: &'a str,
+    pub codec_id: &'a str,
+    /// 45 kHz ticks on the clip's timeline.
+    pub time_in: u32,
+    pub time_out: u32,
+    /// Play item the sub play item starts in, and where in it (45 kHz
+    /// ticks on that play item's clip timeline).
+    pub sync_play_item_id: u16,
+    pub sync_start_pts: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubPathView<'a> {
+    pub offset: usize,
+    /// 4 for text subtitles, 5 to 7 for out-of-mux streams.
+    pub sub_path_type: u8,
+    pub is_repeat: bool,
+    pub items: Vec<SubPlayItemView<'a>>,
+}
+
+/// A playlist parsed in place from its bytes: names and raw sections borrow
+/// from the input.
+#[derive(Debug, Clone)]
+pub struct PlaylistView<'a> {
+    pub data: &'a [u8],
+    pub header: MplsFileHeader,
+    pub playlist_header: PlaylistHeader,
+    pub play_items: Vec<PlayItemView<'a>>,
+    pub sub_paths: Vec<SubPathView<'a>>,
+    pub chapters_header: ChapterHeader,
+    pub marks: Vec<ChapterSection>,
+    /// The extension data section, empty when the playlist has none.
+    pub extension_data: &'a [u8],
+}
+
+impl<'a> PlaylistView<'a> {
+    pub fn parse(data: &'a [u8]) -> ParseResult<Self> {
+        let header: MplsFileHeader = ByteReader::new(data).record()?;
+        if !header.magic().starts_with(b"MPLS") {
+            return Err(ParseError::new(ParseErrorKind::BadMagic, 0));
+        }
+        let (playlist_offset, chapters_offset, extensions_offset) = header.offsets();
+
+        let mut reader = section(data, playlist_offset as usize)?;
+        let playlist_header: PlaylistHeader = reader.record()?;
+        let mut play_items = Vec::with_capacity(playlist_header.item_count as usize);
+        for _ in 0..playlist_header.item_count {
+            play_items.push(read_play_item(data, &mut reader)?);
+        }
+        let mut sub_paths = Vec::with_capacity(playlist_header.subitem_count as usize);
+        for _ in 0..playlist_header.subitem_count {
+            sub_paths.push(read_sub_path(data, &mut reader)?);
+        }
+
+        let mut reader = section(data, chapters_offset as usize)?;
+        let chapters_header: ChapterHeader = reader.record()?;
+        let mut marks = Vec::with_capacity(chapters_header.mark_count as usize);
+        for _ in 0..chapters_header.mark_count {
+            marks.push(reader.record()?);
+        }
+
+        // Missing when only the sections above were buffered.
+        let extension_data = match extensions_offset as usize {
+            0 => None,
+            offset => section(data, offset).ok().map(|mut reader| {
+                let len = reader.remaining();
+                reader.bytes(len).unwrap_or_default()
+            }),
+        };
+        let extension_data = extension_data.unwrap_or_default();
+
+        Ok(PlaylistView {
+            data,
+            header,
+            playlist_header,
+            play_items,
+            sub_paths,
+            chapters_header,
+            marks,
+            extension_data,
+        })
+    }
+}
+
+/// A reader over the section at `offset`, bounded by its 32-bit length
+/// field, which it includes.
+fn section(data: &[u8], offset: usize) -> ParseResult<ByteReader<'_>> {
+    let len = ByteReader::at(data, offset, 4)?.u32()? as usize;
+    let len = len
+        .checked_add(4)
+        .ok_or(ParseError::new(ParseErrorKind::BadOffset, offset))?;
+    ByteReader::at(data, offset, len)
+}
+
+pub(crate) fn read_play_item<'a>(
+    data: &'a [u8],
+    reader: &mut ByteReader<'a>,
+) -> ParseResult<PlayItemView<'a>> {
+    let offset = reader.position();
+    let len = ByteReader::at(data, offset, 2)?.u16()? as usize;
+    let raw = reader.bytes(len + 2)?;
+    let mut item = ByteReader::at(data, offset, len + 2)?;
+
+    let header: ClipHeader = item.record()?;
+    let mut names = ByteReader::at(data, offset + 2, 9)?;
+    let clip_name = names.str(5)?;
+    let codec_id = names.str(4)?;
+
+    let mut angles = Vec::new();
+    if header.multi_angle() != 0 {
+        // The count includes the play item's own clip.
+        let count = item.u8()?;
+        item.skip(1)?;
+        for _ in 1..count {
+            let clip_name = item.str(5)?;
+            let codec_id = item.str(4)?;
+            item.skip(1)?;
+            angles.push(AngleView {
+                clip_name,
+                codec_id,
+            });
+        }
+    }
+
+    let stn_start = item.position();
+    let stn_len = ByteReader::at(data, stn_start, 2)?.u16()? as usize;
+    let mut stn = ByteReader::at(data, stn_start, stn_len + 2)?;
+    let stn_header: StreamsHeader = stn.record()?;
+    let counts = &stn_header;
+    let sections = [
+        (StreamSection::Video, counts.stream_count_video),
+        (StreamSection::Audio, counts.stream_count_audio),
+        (StreamSection::PresentationGraphics, counts.stream_count_pg),
+        (StreamSection::InteractiveGraphics, counts.stream_count_ig),
+        (
+            StreamSection::SecondaryAudio,
+            counts.stream_count_secondary_audio,
+        ),
+        (
+            StreamSection::SecondaryVideo,
+            counts.stream_count_secondary_video,
+        ),
+    ];
+    let mut streams = Vec::new();
+    for (section, count) in sections {
+        for _ in 0..count {
+            streams.push(read_stream_entry(data, &mut stn, section)?);
+        }
+    }
+
+    Ok(PlayItemView {
+        offset,
+        raw,
+        header,
+        clip_name,
+        codec_id,
+        angles,
+        stn_offset: stn_start,
+        stn_end: stn.position(),
+        stn_header,
+        streams,
+    })
+}
+
+fn read_sub_path<'a>(data: &'a [u8], reader: &mut ByteReader<'a>) -> ParseResult<SubPathView<'a>> {
+    let offset = reader.position();
+    let len = reader.u32()? as usize;
+    let section_len = len
+        .checked_add(4)
+        .ok_or(ParseError::new(ParseErrorKind::BadOffset, offset))?;
+    let mut sub_path = ByteReader::at(data, offset, section_len)?;
+    reader.skip(len)?;
+    // The length field and a reserved byte.
+    sub_path.skip(5)?;
+    let sub_path_type = sub_path.u8()?;
+    let is_repeat = sub_path.u16()? & 1 != 0;
+    sub_path.skip(1)?;
+    let count = sub_path.u8()?;
+    let mut items = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let item_offset = sub_path.position();
+        let len = sub_path.u16()? as usize;
+        let mut item = ByteReader::at(data, item_offset, len + 2)?;
+        sub_path.skip(len)?;
+        item.skip(2)?;
+        let clip_name = item.str(5)?;
+        let codec_id = item.str(4)?;
+        // Connection condition, multi-clip flag and STC id.
+        item.skip(5)?;
+        items.push(SubPlayItemView {
+            offset: item_offset,
+            clip_name,
+            codec_id,
+            time_in: item.u32()?,
+            time_out: item.u32()?,
+            sync_play_item_id: item.u16()?,
+            sync_start_pts: item.u32()?,
+        });
+    }
+    Ok(SubPathView {
+        offset,
+        sub_path_type,
+        is_repeat,
+        items,
+    })
+}
+
+fn read_stream_entry<'a>(
+    data: &'a [u8],
+    stn: &mut ByteReader<'a>,
+    section: StreamSection,
+) -> ParseResult<StreamEntry<'a>> {
+    let offset = stn.position();
+    let entry_len = stn.u8()? as usize;
+    let mut entry = ByteReader::at(data, offset + 1, entry_len)?;
+    stn.skip(entry_len)?;
+    let entry_type = entry.u8()?;
+    let (subpath_id, subclip_id) = match entry_type {
+        2 | 4 => (Some(entry.u8()?), Some(entry.u8()?)),
+        3 => (Some(entry.u8()?), None),
+        _ => (None, None),
+    };
+    let pid = entry.u16()?;
+
+    let attributes_len = stn.u8()? as usize;
+    let attributes = stn.bytes(attributes_len)?;
+
+    // Secondary streams list the primary streams they combine with.
+    match section {
+        StreamSection::SecondaryAudio => skip_references(stn)?,
+        StreamSection::SecondaryVideo => {
+            skip_references(stn)?;
+            skip_references(stn)?;
+        }
+        _ => {}
+    }
+
+    Ok(StreamEntry {
+        offset,
+        section,
+        entry_type,
+        pid,
+        subpath_id,
+        subclip_id,
+        attributes,
+    })
+}
+
+/// Skips a count, a reserved byte and the referenced stream numbers,
+/// padded to an even length.
+fn skip_references(stn: &mut ByteReader<'_>) -> ParseResult<()> {
+    let count = stn.u8()? as usize;
+    stn.skip(1 + count + count % 2)
+}
+
+/// Bytes of a playlist read from a stream, up to the end of its marks.
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistBuffer {
+    data: Vec<u8>,
+}
+
+impl PlaylistBuffer {
+    /// Reads a playlist from a source that can't seek, buffering only
+    /// the header, the play list and the marks. The extension data after
+    /// them is not read.
+    #[cfg(feature = "std")]
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut buffer = PlaylistBuffer::default();
+        buffer.fill(reader, MplsFileHeader::SIZE)?;
+        let header = MplsFileHeader::from_bytes(&buffer.data)
+            .ok_or(ParseError::new(ParseErrorKind::Truncated, 0))?;
+        if !header.magic().starts_with(b"MPLS") {
+            return Err(ParseError::new(ParseErrorKind::BadMagic, 0).into());
+        }
+        let (playlist_offset, chapters_offset, _) = header.offsets();
+        for offset in [playlist_offset as usize, chapters_offset as usize] {
+            let bad_offset = ParseError::new(ParseErrorKind::BadOffset, offset);
+            buffer.fill(reader, offset.checked_add(4).ok_or(bad_offset)?)?;
+            let len = ByteReader::at(&buffer.data, offset, 4)?.u32()? as usize;
+            let end = (offset + 4).checked_add(len).ok_or(bad_offset)?;
+            buffer.fill(reader, end)?;
+        }
+        Ok(buffer)
+    }
+
+    /// Reads until the buffer holds `len` bytes. The buffer only grows
+    /// with what the reader returns, whatever length the file claims.
+    #[cfg(feature = "std")]
+    fn fill<R: Read>(&mut self, reader: &mut R, len: usize) -> io::Result<()> {
+        let start = self.data.len();
+        if start >= len {
+            return Ok(());
+        }
+        let wanted = (len - start) as u64;
+        let read = reader.take(wanted).read_to_end(&mut self.data)?;
+        if (read as u64) < wanted {
+            return Err(ParseError::new(ParseErrorKind::Truncated, start + read).into());
+        }
+        Ok(())
+    }
+
+    /// Wraps a playlist already in memory.
+    pub fn new(data: Vec<u8>) -> Self {
+        PlaylistBuffer { data }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn view(&self) -> ParseResult<PlaylistView<'_>> {
+        PlaylistView::parse(&self.data)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::testing::{mpls, mpls_with_sub_paths, PlayItem, SubPlayItem, MPLS_00800};
+
+    #[test]
+    fn reads_bounded_fields() {
+        let data = [0, 1, 2, 3, 4, 5, b'a', b'b', 0xFF];
+        let mut reader = ByteReader::at(&data, 1, 7).unwrap();
+        assert_eq!(reader.u8().unwrap(), 1);
+        assert_eq!(reader.u16().unwrap(), 0x0203);
+        assert_eq!(reader.remaining(), 4);
+        assert_eq!(reader.u16().unwrap(), 0x0405);
+        assert_eq!(reader.str(2).unwrap(), "ab");
+        assert_eq!(
+            reader.u8().unwrap_err(),
+            ParseError::new(ParseErrorKind::Truncated, 8)
+        );
+
+        let mut reader = ByteReader::at(&data, 8, 1).unwrap();
+        assert_eq!(reader.str(1).unwrap_err().kind, ParseErrorKind::BadText);
+        assert_eq!(
+            ByteReader::at(&data, 5, usize::MAX).unwrap_err(),
+            ParseError::new(ParseErrorKind::BadOffset, 5)
+        );
+
+        let mut reader = ByteReader::at(&data, 2, 4).unwrap();
+        assert_eq!(
+            reader.bytes(usize::MAX).unwrap_err(),
+            ParseError::new(ParseErrorKind::BadOffset, 2)
+        );
+        assert_eq!(reader.position(), 2);
+    }
+
+    #[test]
+    fn views_sample() {
+        let view = PlaylistView::parse(MPLS_00800).unwrap();
+        assert_eq!(view.play_items.len(), 1);
+        let item = &view.play_items[0];
+        assert_eq!((item.clip_name, item.codec_id), ("00037", "M2TS"));
+        assert_eq!(item.offset, view.header.playlist_offset as usize + 10);
+        assert_eq!(item.raw.len(), item.header.item_len() as usize + 2);
+        assert!(item.stn_end <= item.stn_offset + item.stn_header.stream_info_len() as usize + 2);
+        assert_eq!(item.streams.len(), 30);
+        assert_eq!(item.streams[0].section, StreamSection::Video);
+        let audio = &item.streams[1];
+        assert_eq!(audio.section, StreamSection::Audio);
+        assert_eq!(audio.lang_code().map(str::len), Some(3));
+        assert_eq!(
+            view.sub_paths.len(),
+            view.playlist_header.subitem_count() as usize
+        );
+        assert_eq!(view.marks.len(), view.chapters_header.mark_count() as usize);
+    }
+
+    #[test]
+    fn views_hand_built_playlist() {
+        let data = mpls(
+            &[PlayItem {
+                clip: "00002",
+                time_in: 0,
+                time_out: 45_000,
+                streams: &[(2, 0x1200, &[0x90, b'f', b'r', b'e'])],
+            }],
+            &[(0, 0), (0, 22_500)],
+        );
+        let view = PlaylistView::parse(&data).unwrap();
+        let entry = &view.play_items[0].streams[0];
+        assert_eq!(entry.pid, 0x1200);
+        assert_eq!(entry.section, StreamSection::PresentationGraphics);
+        assert_eq!(entry.lang_code(), Some("fre"));
+        assert_eq!(view.marks.len(), 2);
+        assert!(view.extension_data.is_empty());
+
+        let mut bad_name = data.clone();
+        let at = view.play_items[0].offset + 2;
+        bad_name[at] = 0xFF;
+        assert_eq!(
+            PlaylistView::parse(&bad_name).unwrap_err(),
+            ParseError::new(ParseErrorKind::BadText, at)
+        );
+    }
+
+    #[test]
+    fn views_text_subtitle_sub_paths() {
+        let data = mpls_with_sub_paths(
+            &[PlayItem {
+                clip: "00001",
+                time_in: 0,
+                time_out: 45_000 * 60,
+                streams: &[(2, 0x1800, &[0x92, 0x01, b'e', b'n', b'g'])],
+            }],
+            &[SubPlayItem {
+                clip: "00002",
+                time_in: 45_000,
+                time_out: 45_000 * 61,
+                sync_play_item_id: 0,
+                sync_start_pts: 90_000,
+            }],
+            &[],
+        );
+        let view = PlaylistView::parse(&data).unwrap();
+        let entry = &view.play_items[0].streams[0];
+        assert_eq!(
+            (entry.entry_type, entry.subpath_id, entry.subclip_id),
+            (2, Some(0), Some(0))
+        );
+        assert_eq!(entry.stream_type(), StreamType::SUBTITLE);
+
+        let sub_path = &view.sub_paths[0];
+        assert_eq!((sub_path.sub_path_type, sub_path.is_repeat), (4, false));
+        let item = &sub_path.items[0];
+        assert_eq!((item.clip_name, item.codec_id), ("00002", "M2TS"));
+        assert_eq!((item.time_in, item.time_out), (45_000, 45_000 * 61));
+        assert_eq!((item.sync_play_item_id, item.sync_start_pts), (0, 90_000));
+
+        // A sub play item running past its sub path.
+        let at = sub_path.offset + 10;
+        let mut data = data.clone();
+        data[at..at + 2].copy_from_slice(&0x0100u16.to_be_bytes());
+        assert_eq!(
+            PlaylistView::parse(&data).unwrap_err().kind,
+            ParseErrorKind::BadOffset
+        );
+    }
+
+    #[test]
+    fn buffers_sections_from_a_stream() {
+        let buffer = PlaylistBuffer::read(&mut &MPLS_00800[..]).unwrap();
+        let header = MplsFileHeader::from_bytes(MPLS_00800).unwrap();
+        let end = header.chapters_offset as usize
+            + 4
+            + u32::from_be_bytes(
+                MPLS_00800[header.chapters_offset as usize..][..4]
+                    .try_into()
+                    .unwrap(),
+            ) as usize;
+        assert_eq!(buffer.data(), &MPLS_00800[..end]);
+        let view = buffer.view().unwrap();
+        assert_eq!(view.play_items.len(), 1);
+
+        // A length past the end of the stream fails without reading on.
+        let mut data = MPLS_00800.to_vec();
+        let at = header.playlist_offset as usize;
+        data[at..at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let error = PlaylistBuffer::read(&mut &data[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let error = PlaylistBuffer::read(&mut &b"MPLS0200"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,132 @@
+//! Codec details of the audio and video streams a disc's playlists play,
+//! read from the start of their stream files.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Result},
+};
+
+use crate::{
+    disc::Disc,
+    scan,
+    ts::{
+        codec::{self, StreamInfo},
+        stream::Stream,
+        stream_file,
+        stream_type::StreamType,
+    },
+};
+
+/// Elementary stream bytes collected per PID for probing.
+const PROBE_BYTES: usize = 256 * 1024;
+/// PID of the Dolby Vision enhancement layer on UHD discs.
+const DOLBY_VISION_EL_PID: u16 = 0x1015;
+/// Stop reading a stream file for probing after this many bytes.
+const PROBE_MAX_READ: u64 = 64 * 1024 * 1024;
+
+/// Probes the audio and video elementary streams of every stream file
+/// referenced by a playlist (each file once, reading only its start) and
+/// fills the results into the playlists' streams. Files that fail are
+/// recorded in `stream_errors` like for [`scan::scan_stream_files`].
+pub fn probe_streams(disc: &mut Disc) -> Result<()> {
+    let mut files: BTreeMap<String, BTreeMap<u16, _>> = BTreeMap::new();
+    for playlist in disc.playlists.iter() {
+        for clip in playlist.parser.clips() {
+            let pids = files.entry(clip.stream_file.to_owned()).or_default();
+            for stream in clip.streams.iter() {
+                if matches!(stream, Stream::AudioStream(_) | Stream::VideoStream(_)) {
+                    pids.insert(stream.pid(), stream.stream_type());
+                }
+                if stream.stream_type() == StreamType::HEVC_VIDEO {
+                    pids.insert(DOLBY_VISION_EL_PID, StreamType::HEVC_VIDEO);
+                }
+            }
+        }
+    }
+    files.retain(|_, pids| !pids.is_empty());
+    let names = files.keys().cloned().collect::<Vec<_>>();
+
+    let mut first_error = None;
+    for (name, pids) in files {
+        if disc.stream_probes.contains_key(&name) || disc.stream_errors.contains_key(&name) {
+            continue;
+        }
+        info!("Probing", "{}", name);
+        let streams = disc
+            .stream_file_path(&name)
+            .and_then(|path| disc.fs.open(&path))
+            .and_then(|file| {
+                stream_file::read_elementary_streams(
+                    &mut BufReader::new(file),
+                    &pids.keys().copied().collect::<Vec<_>>(),
+                    PROBE_BYTES,
+                    PROBE_MAX_READ,
+                )
+            });
+        let streams = match streams {
+            Ok(streams) => streams,
+            Err(e) => {
+                error!(&name, "{}", e);
+                first_error.get_or_insert(e.kind());
+                disc.stream_errors.insert(name, e.to_string());
+                continue;
+            }
+        };
+        let mut probes = pids
+            .into_iter()
+            .filter_map(|(pid, stream_type)| {
+                let data = streams.get(&pid)?;
+                Some((pid, codec::probe(stream_type, data)?))
+            })
+            .collect::<BTreeMap<_, _>>();
+        // The enhancement layer carries the RPU of dual layer streams.
+        if let Some(StreamInfo::Video(el)) = probes.remove(&DOLBY_VISION_EL_PID) {
+            for info in probes.values_mut() {
+                if let StreamInfo::Video(video) = info {
+                    video.dolby_vision_el = true;
+                    video.dolby_vision_rpu |= el.dolby_vision_rpu;
+                }
+            }
+        }
+        disc.stream_probes.insert(name, probes);
+    }
+
+    let probes = &disc.stream_probes;
+    for playlist in disc.playlists.iter_mut() {
+        playlist.parser.apply_stream_probes(|name| probes.get(name));
+    }
+    scan::check_readable(disc, &names, first_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+    use crate::testing::{disc, pes, pes_packets, MPLS_00800};
+
+    #[test]
+    fn probe_fills_audio_info() {
+        let frame = crate::ts::codec::ac3::tests::ac3_frame();
+        let stream = pes_packets(0x1101, &pes(0xBD, Some(0), &frame));
+        let mut disc = disc(&[
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/STREAM/00037.m2ts", &stream),
+        ]);
+        disc.probe_streams().unwrap();
+        let info = disc.playlists[0].parser.playlist_streams()[&0x1101].info();
+        match info {
+            Some(StreamInfo::Audio(audio)) => assert_eq!(audio.bit_rate, 448_000),
+            info => panic!("unexpected {:?}", info),
+        }
+    }
+
+    #[test]
+    fn probe_records_missing_stream_files() {
+        let mut disc = disc(&[("BDMV/PLAYLIST/00800.mpls", MPLS_00800)]);
+        let error = disc.probe_streams().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(disc.stream_probes.is_empty());
+        assert!(disc.stream_errors.contains_key("00037.M2TS"));
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use crate::{
+    parser::Parser,
+    ts::{stream::Stream, stream_clip::Duration},
+};
+
+/// Playlists shorter than this are never considered for the main feature.
+const MIN_FEATURE_LENGTH: f64 = 15.0 * 60.0;
+/// How many playlists have to share the same clips in a different order
+/// before they are treated as playlist obfuscation.
+const OBFUSCATION_GROUP_SIZE: usize = 3;
+
+const REPEATED_CLIP_PENALTY: f64 = 5.0;
+const DECOY_PENALTY: f64 = 25.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankFlag {
+    MainFeature,
+    Short,
+    RepeatedClips,
+    ObfuscationGroup,
+    LikelyDecoy,
+}
+
+impl RankFlag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RankFlag::MainFeature => "main_feature",
+            RankFlag::Short => "short",
+            RankFlag::RepeatedClips => "repeated_clips",
+            RankFlag::ObfuscationGroup => "obfuscation_group",
+            RankFlag::LikelyDecoy => "likely_decoy",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaylistRank {
+    pub name: String,
+    pub score: f64,
+    pub length: f64,
+    pub item_count: usize,
+    pub stream_count: usize,
+    pub chapter_count: usize,
+    pub flags: Vec<RankFlag>,
+    pub reasons: Vec<String>,
+}
+
+impl PlaylistRank {
+    pub fn is_main_feature(&self) -> bool {
+        self.flags.contains(&RankFlag::MainFeature)
+    }
+
+    pub fn is_decoy(&self) -> bool {
+        self.flags.contains(&RankFlag::LikelyDecoy)
+    }
+}
+
+struct Candidate<'a> {
+    rank: PlaylistRank,
+    clip_names: Vec<&'a str>,
+}
+
+/// Scores every playlist and returns them best first. The best playlist that
+/// is long enough and not a likely decoy is flagged as the main feature.
+pub fn rank_playlists<'a, I>(playlists: I) -> Vec<PlaylistRank>
+where
+    I: IntoIterator<Item = (&'a str, &'a Parser)>,
+{
+    let mut candidates = playlists
+        .into_iter()
+        .map(|(name, parser)| Candidate {
+            rank: PlaylistRank {
+                name: name.to_owned(),
+                score: 0.0,
+                length: parser.total_length(),
+                item_count: parser.clips().len(),
+                stream_count: parser
+                    .playlist_streams()
+                    .values()
+                    .filter(|s| {
+                        matches!(
+                            s,
+                            Stream::AudioStream(_)
+                                | Stream::GraphicsStream(_)
+                                | Stream::TextStream(_)
+                        )
+                    })
+                    .count(),
+                chapter_count: parser.chapters().len(),
+                flags: Vec::new(),
+                reasons: Vec::new(),
+            },
+            clip_names: parser.clips().iter().map(|c| c.name.as_str()).collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let longest = candidates.iter().map(|c| c.rank.length).fold(0.0, f64::max);
+
+    for candidate in candidates.iter_mut() {
+        score_candidate(candidate, longest);
+    }
+    flag_obfuscation(&mut candidates);
+
+    let mut ranks = candidates.into_iter().map(|c| c.rank).collect::<Vec<_>>();
+    ranks.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    let top_is_decoy = ranks.first().is_some_and(PlaylistRank::is_decoy);
+    if let Some(best) = ranks
+        .iter_mut()
+        .find(|rank| rank.length >= MIN_FEATURE_LENGTH && !rank.is_decoy())
+    {
+        let reason = if top_is_decoy {
+            "highest score of the non-decoy playlists, selected as main feature"
+        } else {
+            "highest score, selected as main feature"
+        };
+        best.flags.insert(0, RankFlag::MainFeature);
+        best.reasons.insert(0, reason.to_owned());
+    }
+
+    ranks
+}
+
+fn score_candidate(candidate: &mut Candidate, longest: f64) {
+    let rank = &mut candidate.rank;
+    if longest > 0.0 {
+        rank.score = rank.length / longest * 100.0;
+    }
+
+    if rank.length >= longest && longest > 0.0 {
+        rank.reasons.push(format!(
+            "longest playlist ({})",
+            Duration::from_secs_f64(rank.length)
+        ));
+    } else {
+        rank.reasons
+            .push(format!("{:.1}% of the longest playlist", rank.score));
+    }
+
+    if rank.length < MIN_FEATURE_LENGTH {
+        rank.flags.push(RankFlag::Short);
+        rank.reasons.push("too short to be a feature".to_owned());
+    }
+
+    let mut seen = HashMap::new();
+    for name in candidate.clip_names.iter() {
+        *seen.entry(*name).or_insert(0usize) += 1;
+    }
+    let repeats = seen.values().map(|n| n - 1).sum::<usize>();
+    if repeats > 0 {
+        rank.score -= (repeats as f64 * REPEATED_CLIP_PENALTY).min(DECOY_PENALTY);
+        rank.flags.push(RankFlag::RepeatedClips);
+        rank.reasons
+            .push(format!("plays {} clip(s) more than once", repeats));
+    }
+
+    if rank.stream_count > 0 {
+        rank.score += rank.stream_count.min(40) as f64 * 0.25;
+        rank.reasons
+            .push(format!("{} audio/subtitle stream(s)", rank.stream_count));
+    }
+
+    if rank.chapter_count > 1 {
+        rank.score += 2.0;
+        rank.reasons
+            .push(format!("{} chapters", rank.chapter_count));
+    }
+}
+
+/// Obfuscated discs ship many playlists that play the same clips in a
+/// shuffled order. Within such a group only the playlist whose clips come
+/// closest to authoring (file number) order is kept as a candidate.
+fn flag_obfuscation(candidates: &mut [Candidate]) {
+    let mut groups: HashMap<Vec<&str>, Vec<usize>> = HashMap::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        if candidate.clip_names.len() < 2 {
+            continue;
+        }
+        let mut key = candidate.clip_names.clone();
+        key.sort_unstable();
+        groups.entry(key).or_default().push(i);
+    }
+
+    for members in groups.values() {
+        if members.len() < OBFUSCATION_GROUP_SIZE {
+            continue;
+        }
+        let first_order = &candidates[members[0]].clip_names;
+        if members
+            .iter()
+            .all(|&i| &candidates[i].clip_names == first_order)
+        {
+            continue;
+        }
+
+        let inversions = members
+            .iter()
+            .map(|&i| (i, count_inversions(&candidates[i].clip_names)))
+            .collect::<Vec<_>>();
+        let keep = inversions
+            .iter()
+            .min_by(|a, b| {
+                a.1.cmp(&b.1)
+                    .then_with(|| candidates[a.0].rank.name.cmp(&candidates[b.0].rank.name))
+            })
+            .map(|(i, _)| *i);
+
+        for (i, inv) in inversions {
+            let rank = &mut candidates[i].rank;
+            rank.flags.push(RankFlag::ObfuscationGroup);
+            rank.reasons.push(format!(
+                "one of {} playlists playing the same clips in a different order",
+                members.len()
+            ));
+            if Some(i) == keep {
+                rank.reasons.push(format!(
+                    "closest to clip file order in its group ({} inversion(s))",
+                    inv
+                ));
+            } else {
+                rank.score -= DECOY_PENALTY;
+                rank.flags.push(RankFlag::LikelyDecoy);
+                rank.reasons
+                    .push(format!("likely decoy, clip order has {} inversion(s)", inv));
+            }
+        }
+    }
+}
+
+fn count_inversions(names: &[&str]) -> usize {
+    let keys = names
+        .iter()
+        .map(|name| name.trim_end_matches(".M2TS").parse::<u32>().ok())
+        .collect::<Vec<_>>();
+    let mut inversions = 0;
+    for i in 0..names.len() {
+        for j in i + 1..names.len() {
+            let out_of_order = match (keys[i], keys[j]) {
+                (Some(a), Some(b)) => a > b,
+                _ => names[i] > names[j],
+            };
+            if out_of_order {
+                inversions += 1;
+            }
+        }
+    }
+    inversions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{mpls, PlayItem, MPLS_00800, MPLS_00801};
+
+    fn candidate<'a>(name: &str, length: f64, clips: &[&'a str]) -> Candidate<'a> {
+        Candidate {
+            rank: PlaylistRank {
+                name: name.to_owned(),
+                score: 0.0,
+                length,
+                item_count: clips.len(),
+                stream_count: 0,
+                chapter_count: 0,
+                flags: Vec::new(),
+                reasons: Vec::new(),
+            },
+            clip_names: clips.to_vec(),
+        }
+    }
+
+    #[test]
+    fn ranks_samples() {
+        let main = Parser::from_bytes(MPLS_00800).unwrap();
+        let other = Parser::from_bytes(MPLS_00801).unwrap();
+        let ranks = rank_playlists([("00801.MPLS", &other), ("00800.MPLS", &main)]);
+
+        assert_eq!(ranks.len(), 2);
+        assert_eq!(ranks[0].name, "00800.MPLS");
+        assert!(ranks[0].is_main_feature());
+        assert!(!ranks[1].is_main_feature());
+        assert_eq!(ranks[0].stream_count, 29);
+        assert!(ranks[0].score > ranks[1].score);
+    }
+
+    #[test]
+    fn skips_decoys_for_the_main_feature() {
+        // Three playlists playing the same clips in a different order. The
+        // one most out of file order plays longer cuts and so scores highest
+        // even after the decoy penalty.
+        let playlist = |order: [&'static str; 3], seconds: u32| {
+            let items = order.map(|clip| PlayItem {
+                clip,
+                time_in: 0,
+                time_out: 45_000 * seconds,
+                streams: &[],
+            });
+            Parser::from_bytes(&mpls(&items, &[])).unwrap()
+        };
+        let parsers = [
+            playlist(["00003", "00002", "00001"], 1500),
+            playlist(["00001", "00002", "00003"], 900),
+            playlist(["00002", "00001", "00003"], 900),
+        ];
+        let ranks = rank_playlists([
+            ("00012.MPLS", &parsers[0]),
+            ("00011.MPLS", &parsers[1]),
+            ("00010.MPLS", &parsers[2]),
+        ]);
+
+        assert_eq!(ranks[0].name, "00012.MPLS");
+        assert!(ranks[0].is_decoy());
+        assert!(!ranks[0].is_main_feature());
+        let main = ranks.iter().filter(|rank| rank.is_main_feature());
+        assert_eq!(
+            main.map(|rank| rank.name.as_str()).collect::<Vec<_>>(),
+            ["00011.MPLS"]
+        );
+    }
+
+    #[test]
+    fn no_main_feature_without_candidates() {
+        assert!(rank_playlists(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn short_and_repeated() {
+        let mut short = candidate("00001.MPLS", 60.0, &["00001.M2TS", "00001.M2TS"]);
+        score_candidate(&mut short, 3600.0);
+        assert!(short.rank.flags.contains(&RankFlag::Short));
+        assert!(short.rank.flags.contains(&RankFlag::RepeatedClips));
+        assert!(short.rank.score < 60.0 / 3600.0 * 100.0);
+    }
+
+    #[test]
+    fn obfuscation_keeps_file_order() {
+        let mut candidates = vec![
+            candidate(
+                "00010.MPLS",
+                7200.0,
+                &["00003.M2TS", "00001.M2TS", "00002.M2TS"],
+            ),
+            candidate(
+                "00011.MPLS",
+                7200.0,
+                &["00001.M2TS", "00002.M2TS", "00003.M2TS"],
+            ),
+            candidate(
+                "00012.MPLS",
+                7200.0,
+                &["00002.M2TS", "00003.M2TS", "00001.M2TS"],
+            ),
+        ];
+        flag_obfuscation(&mut candidates);
+
+        assert!(!candidates[1].rank.is_decoy());
+        assert!(candidates[0].rank.is_decoy());
+        assert!(candidates[2].rank.is_decoy());
+        assert!(candidates
+            .iter()
+            .all(|c| c.rank.flags.contains(&RankFlag::ObfuscationGroup)));
+    }
+
+    #[test]
+    fn inversions() {
+        assert_eq!(count_inversions(&["00001.M2TS", "00002.M2TS"]), 0);
+        assert_eq!(
+            count_inversions(&["00010.M2TS", "00002.M2TS", "00001.M2TS"]),
+            3
+        );
+        assert_eq!(count_inversions(&["b", "a"]), 1);
+    }
+}
@@ -0,0 +1,449 @@
+use std::fmt::Write;
+
+use crate::{
+    disc::{Disc, DiscPlaylist},
+    duplicates::copy_of,
+    ts::{language_codes::get_language_code, stream::Stream},
+};
+
+const BDINFO_VERSION: &str = concat!("mpls-tool ", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportMode {
+    /// Every section of the BDInfo playlist report followed by its quick
+    /// summary, wrapped for forum pasting.
+    Full,
+    /// Only the QUICK SUMMARY block.
+    QuickSummary,
+}
+
+/// Builds a BDInfo style report for the given playlists. Unknown playlist
+/// names are skipped, and exact copies of a playlist already reported are
+/// only listed in that playlist's report.
+pub fn generate(disc: &Disc, playlists: &[&str], mode: ReportMode) -> String {
+    let mut out = String::new();
+    if mode == ReportMode::Full {
+        write_disc_info(&mut out, disc);
+    }
+    let identical = disc.identical_playlists();
+    let mut reported = Vec::new();
+    for playlist in playlists.iter().filter_map(|name| disc.playlist(name)) {
+        let original = copy_of(&identical, &playlist.name).unwrap_or(&playlist.name);
+        if reported.contains(&original) {
+            continue;
+        }
+        reported.push(original);
+        let copies = identical
+            .iter()
+            .find(|set| set[0] == original)
+            .map(|set| {
+                set.iter()
+                    .map(String::as_str)
+                    .filter(|copy| *copy != playlist.name)
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new);
+        match mode {
+            ReportMode::Full => write_playlist_report(&mut out, disc, playlist, &copies),
+            ReportMode::QuickSummary => write_quick_summary(&mut out, disc, playlist, &copies),
+        }
+    }
+    out
+}
+
+/// Report for the playlist the ranking picks as main feature.
+pub fn generate_main_feature(disc: &Disc, mode: ReportMode) -> Option<String> {
+    let ranks = disc.rank_playlists();
+    let main = ranks.iter().find(|rank| rank.is_main_feature())?;
+    Some(generate(disc, &[main.name.as_str()], mode))
+}
+
+fn write_disc_info(out: &mut String, disc: &Disc) {
+    writeln!(out, "{:<16}{}", "Disc Title:", disc_title(disc)).ok();
+    writeln!(out, "{:<16}{}", "Disc Label:", disc.label()).ok();
+    writeln!(
+        out,
+        "{:<16}{} bytes",
+        "Disc Size:",
+        group_digits(disc.size())
+    )
+    .ok();
+    writeln!(out, "{:<16}{}", "Protection:", disc.protection()).ok();
+    let extras = disc.extras();
+    if !extras.is_empty() {
+        writeln!(out, "{:<16}{}", "Extras:", extras.join(", ")).ok();
+    }
+    writeln!(out, "{:<16}{}", "BDInfo:", BDINFO_VERSION).ok();
+    writeln!(out).ok();
+}
+
+fn write_playlist_report(out: &mut String, disc: &Disc, playlist: &DiscPlaylist, copies: &[&str]) {
+    let parser = &playlist.parser;
+    let length = parser.total_length();
+    let size = playlist_size(disc, playlist);
+
+    writeln!(out, "********************").ok();
+    writeln!(out, "PLAYLIST: {}", playlist.name).ok();
+    writeln!(out, "********************").ok();
+    writeln!(out).ok();
+    writeln!(out, "<--- BEGIN FORUMS PASTE --->").ok();
+    writeln!(out, "[code]").ok();
+    writeln!(out).ok();
+
+    writeln!(out, "DISC INFO:").ok();
+    writeln!(out).ok();
+    write_disc_info(out, disc);
+
+    writeln!(out, "PLAYLIST REPORT:").ok();
+    writeln!(out).ok();
+    writeln!(out, "{:<24}{}", "Name:", playlist.name).ok();
+    if !copies.is_empty() {
+        writeln!(out, "{:<24}{}", "Identical Playlists:", copies.join(", ")).ok();
+    }
+    writeln!(out, "{:<24}{} (h:m:s.ms)", "Length:", format_time(length)).ok();
+    writeln!(out, "{:<24}{} bytes", "Size:", group_digits(size)).ok();
+    writeln!(
+        out,
+        "{:<24}{:.2} Mbps",
+        "Total Bitrate:",
+        bit_rate(size, length) / 1_000_000.0
+    )
+    .ok();
+    writeln!(out).ok();
+
+    let streams = parser.streams();
+    let video = streams
+        .iter()
+        .filter(|s| matches!(s, Stream::VideoStream(_)))
+        .collect::<Vec<_>>();
+    let audio = streams
+        .iter()
+        .filter(|s| matches!(s, Stream::AudioStream(_)))
+        .collect::<Vec<_>>();
+    let subtitles = streams
+        .iter()
+        .filter(|s| matches!(s, Stream::GraphicsStream(_) | Stream::TextStream(_)))
+        .collect::<Vec<_>>();
+
+    if !video.is_empty() {
+        writeln!(out, "VIDEO:").ok();
+        writeln!(out).ok();
+        writeln!(out, "{:<24}{:<20}{:<16}", "Codec", "Bitrate", "Description").ok();
+        writeln!(out, "{:<24}{:<20}{:<16}", "-----", "-------", "-----------").ok();
+        for stream in video {
+            writeln!(
+                out,
+                "{:<24}{:<20}{:<16}",
+                stream.stream_type().codec_name(),
+                format!("{} kbps", stream_kbps(stream)),
+                stream_description(stream)
+            )
+            .ok();
+        }
+        writeln!(out).ok();
+    }
+
+    for (title, streams) in [("AUDIO:", audio), ("SUBTITLES:", subtitles)] {
+        if streams.is_empty() {
+            continue;
+        }
+        writeln!(out, "{}", title).ok();
+        writeln!(out).ok();
+        writeln!(
+            out,
+            "{:<32}{:<16}{:<16}{:<16}",
+            "Codec", "Language", "Bitrate", "Description"
+        )
+        .ok();
+        writeln!(
+            out,
+            "{:<32}{:<16}{:<16}{:<16}",
+            "-----", "--------", "-------", "-----------"
+        )
+        .ok();
+        for stream in streams {
+            writeln!(
+                out,
+                "{:<32}{:<16}{:<16}{:<16}",
+                stream.stream_type().codec_name(),
+                get_language_code(stream.lang_code()),
+                format!("{} kbps", stream_kbps(stream)),
+                stream_description(stream)
+            )
+            .ok();
+        }
+        writeln!(out).ok();
+    }
+
+    writeln!(out, "FILES:").ok();
+    writeln!(out).ok();
+    writeln!(
+        out,
+        "{:<16}{:<16}{:<16}{:<16}{:<16}",
+        "Name", "Time In", "Length", "Size", "Total Bitrate"
+    )
+    .ok();
+    writeln!(
+        out,
+        "{:<16}{:<16}{:<16}{:<16}{:<16}",
+        "----", "-------", "------", "----", "-------------"
+    )
+    .ok();
+    for clip in parser.clips() {
+        let size = clip_size(disc, &clip.name, clip.file_size);
+        writeln!(
+            out,
+            "{:<16}{:<16}{:<16}{:<16}{:<16}",
+            clip.display_name(),
+            format_time(clip.relative_time_in),
+            format_time(clip.length),
+            group_digits(size),
+            group_digits((bit_rate(size, clip.length) / 1000.0).round() as u64)
+        )
+        .ok();
+    }
+    writeln!(out).ok();
+
+    let chapters = parser.chapter_times();
+    if !chapters.is_empty() {
+        writeln!(out, "CHAPTERS:").ok();
+        writeln!(out).ok();
+        writeln!(out, "{:<16}{:<16}{:<16}", "Number", "Time In", "Length").ok();
+        writeln!(out, "{:<16}{:<16}{:<16}", "------", "-------", "------").ok();
+        for (i, time_in) in chapters.iter().enumerate() {
+            let time_out = chapters.get(i + 1).copied().unwrap_or(length);
+            writeln!(
+                out,
+                "{:<16}{:<16}{:<16}",
+                i + 1,
+                format_time(*time_in),
+                format_time(time_out - time_in)
+            )
+            .ok();
+        }
+        writeln!(out).ok();
+    }
+
+    write_quick_summary(out, disc, playlist, copies);
+    writeln!(out, "[/code]").ok();
+    writeln!(out, "<---- END FORUMS PASTE ---->").ok();
+    writeln!(out).ok();
+}
+
+fn write_quick_summary(out: &mut String, disc: &Disc, playlist: &DiscPlaylist, copies: &[&str]) {
+    let parser = &playlist.parser;
+    let length = parser.total_length();
+    let size = playlist_size(disc, playlist);
+
+    writeln!(out, "QUICK SUMMARY:").ok();
+    writeln!(out).ok();
+    writeln!(out, "Disc Title: {}", disc_title(disc)).ok();
+    writeln!(out, "Disc Label: {}", disc.label()).ok();
+    writeln!(out, "Disc Size: {} bytes", group_digits(disc.size())).ok();
+    writeln!(out, "Protection: {}", disc.protection()).ok();
+    let extras = disc.extras();
+    if !extras.is_empty() {
+        writeln!(out, "Extras: {}", extras.join(", ")).ok();
+    }
+    writeln!(out, "BDInfo: {}", BDINFO_VERSION).ok();
+    writeln!(out, "Playlist: {}", playlist.name).ok();
+    if !copies.is_empty() {
+        writeln!(out, "Identical Playlists: {}", copies.join(", ")).ok();
+    }
+    writeln!(out, "Size: {} bytes", group_digits(size)).ok();
+    writeln!(out, "Length: {}", format_time(length)).ok();
+    writeln!(
+        out,
+        "Total Bitrate: {:.2} Mbps",
+        bit_rate(size, length) / 1_000_000.0
+    )
+    .ok();
+
+    for stream in parser.streams() {
+        let line = match stream {
+            Stream::VideoStream(_) => format!(
+                "Video: {} / {} kbps / {}",
+                stream.stream_type().codec_name(),
+                stream_kbps(stream),
+                stream_description(stream)
+            ),
+            Stream::AudioStream(_) => format!(
+                "Audio: {} / {} / {} kbps / {}",
+                get_language_code(stream.lang_code()),
+                stream.stream_type().codec_name(),
+                stream_kbps(stream),
+                stream_description(stream)
+            ),
+            Stream::GraphicsStream(_) | Stream::TextStream(_) => format!(
+                "Subtitle: {} / {:.3} kbps",
+                get_language_code(stream.lang_code()),
+                stream.stats().bit_rate as f64 / 1000.0
+            ),
+        };
+        writeln!(out, "{}", line.trim_end_matches([' ', '/'])).ok();
+    }
+    writeln!(out).ok();
+}
+
+fn disc_title(disc: &Disc) -> String {
+    disc.title().unwrap_or_else(|| disc.label())
+}
+
+// Stream bit rates are only known once the transport streams are scanned.
+fn stream_kbps(stream: &Stream) -> u64 {
+    (stream.stats().bit_rate as f64 / 1000.0).round() as u64
+}
+
+pub(crate) fn stream_description(stream: &Stream) -> String {
+    let parts = match stream {
+        Stream::VideoStream(s) => match s.info.as_ref() {
+            Some(info) => vec![info.description()],
+            None => vec![
+                s.video_format.to_string(),
+                s.frame_rate.to_string(),
+                s.aspect_ratio.to_string(),
+            ],
+        },
+        Stream::AudioStream(s) => match s.info.as_ref() {
+            Some(info) => vec![info.description()],
+            None => vec![s.channel_layout.to_string(), s.sample_rate.to_string()],
+        },
+        Stream::TextStream(s) => vec![s.character_code.to_string()],
+        Stream::GraphicsStream(_) => vec![],
+    };
+    parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+fn playlist_size(disc: &Disc, playlist: &DiscPlaylist) -> u64 {
+    playlist
+        .parser
+        .clips()
+        .iter()
+        .map(|clip| clip_size(disc, &clip.name, clip.file_size))
+        .sum()
+}
+
+fn clip_size(disc: &Disc, name: &str, scanned: u64) -> u64 {
+    if scanned > 0 {
+        scanned
+    } else {
+        disc.stream_file_size(name).unwrap_or(0)
+    }
+}
+
+fn bit_rate(size: u64, seconds: f64) -> f64 {
+    if seconds > 0.0 {
+        size as f64 * 8.0 / seconds
+    } else {
+        0.0
+    }
+}
+
+/// `h:mm:ss.fff`, the way BDInfo prints times.
+pub fn format_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+fn group_digits(value: u64) -> String {
+    let digits = value.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{disc, MPLS_00800, MPLS_00801};
+
+    fn sample_disc() -> Disc {
+        disc(&[
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/PLAYLIST/00801.mpls", MPLS_00801),
+            ("BDMV/PLAYLIST/00802.mpls", MPLS_00801),
+        ])
+    }
+
+    #[test]
+    fn collapses_identical_playlists() {
+        let disc = sample_disc();
+        let report = generate(
+            &disc,
+            &["00801.MPLS", "00802.MPLS", "00800.MPLS"],
+            ReportMode::QuickSummary,
+        );
+        assert_eq!(report.matches("QUICK SUMMARY:").count(), 2);
+        assert!(report.contains("Playlist: 00801.MPLS\nIdentical Playlists: 00802.MPLS\n"));
+        assert!(!report.contains("Playlist: 00802.MPLS"));
+    }
+
+    #[test]
+    fn full_report_sections() {
+        let disc = sample_disc();
+        let report = generate(&disc, &["00800.MPLS", "99999.MPLS"], ReportMode::Full);
+        assert_eq!(report.matches("PLAYLIST REPORT:").count(), 1);
+        for section in ["VIDEO:", "AUDIO:", "SUBTITLES:", "FILES:", "CHAPTERS:"] {
+            assert!(report.contains(section), "missing {}", section);
+        }
+        assert!(report.contains("Disc Label:     SAMPLE"));
+    }
+
+    #[test]
+    fn quick_summary_of_sample() {
+        let disc = disc(&[
+            ("AACS/Unit_Key_RO.inf", b"abc"),
+            ("BDMV/BDJO/00000.bdjo", b"BDJO"),
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+        ]);
+        let report = generate(&disc, &["00800.MPLS"], ReportMode::QuickSummary);
+        for line in [
+            "Protection: AACS2\n",
+            "Extras: Ultra HD, BD-Java\n",
+            "Playlist: 00800.MPLS\n",
+            "Length: 2:28:08.463\n",
+            "Video: MPEG-H HEVC Video / 0 kbps / 2160p / 23.976 fps\n",
+            "Audio: English / DTS-HD Master Audio / 0 kbps / Multi-channel / 48 kHz\n",
+            "Subtitle: Portuguese / 0.000 kbps\n",
+        ] {
+            assert!(report.contains(line), "missing {:?}", line);
+        }
+        assert_eq!(report.matches("\nAudio: ").count(), 10);
+        assert_eq!(report.matches("\nSubtitle: ").count(), 19);
+    }
+
+    #[test]
+    fn full_report_of_sample() {
+        let disc = disc(&[("BDMV/PLAYLIST/00800.mpls", MPLS_00800)]);
+        let report = generate(&disc, &["00800.MPLS"], ReportMode::Full);
+        assert!(report.contains("<--- BEGIN FORUMS PASTE --->"));
+        assert!(report.contains("Protection:     \n"));
+        assert!(report.contains("Length:                 2:28:08.463 (h:m:s.ms)\n"));
+        assert!(report.contains("\n00037.M2TS      0:00:00.000     2:28:08.463     0 "));
+        assert!(report.contains("\n2               0:06:09.744     0:06:55.665     \n"));
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_time(8888.4629), "2:28:08.463");
+        assert_eq!(format_time(-1.0), "0:00:00.000");
+        assert_eq!(group_digits(1234567), "1,234,567");
+        assert_eq!(group_digits(123), "123");
+        assert_eq!(bit_rate(1000, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,124 @@
+//! Measured bit rates of the stream files a disc's playlists play.
+
+use std::io::{BufReader, Error, ErrorKind, Result};
+
+use crate::{disc::Disc, ts::stream_file};
+
+/// Scans every stream file referenced by a playlist (each file once) and
+/// fills the measured bit rates into the playlists. Files that are missing or
+/// fail to read are recorded in `stream_errors` and skipped, it is an error
+/// only when none of them could be read.
+pub fn scan_stream_files(disc: &mut Disc) -> Result<()> {
+    let mut names = disc
+        .playlists
+        .iter()
+        .flat_map(|p| p.parser.clips().iter().map(|c| c.stream_file.to_owned()))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+
+    let mut first_error = None;
+    for name in names.iter() {
+        if disc.stream_scans.contains_key(name) || disc.stream_errors.contains_key(name) {
+            continue;
+        }
+        info!("Scanning", "{}", name);
+        let scan = disc
+            .stream_file_path(name)
+            .and_then(|path| disc.fs.open(&path))
+            .and_then(|file| stream_file::scan(&mut BufReader::new(file)));
+        match scan {
+            Ok(scan) => {
+                disc.stream_scans.insert(name.clone(), scan);
+            }
+            Err(e) => {
+                error!(name, "{}", e);
+                first_error.get_or_insert(e.kind());
+                disc.stream_errors.insert(name.clone(), e.to_string());
+            }
+        }
+    }
+
+    let scans = &disc.stream_scans;
+    for playlist in disc.playlists.iter_mut() {
+        playlist.parser.apply_stream_scans(|name| scans.get(name));
+    }
+    check_readable(disc, &names, first_error)
+}
+
+/// Fails when there were stream files to read and every one of them is in
+/// `stream_errors`, with the kind of the first failure seen.
+pub(crate) fn check_readable(
+    disc: &Disc,
+    names: &[String],
+    first_error: Option<ErrorKind>,
+) -> Result<()> {
+    if names.is_empty()
+        || names
+            .iter()
+            .any(|name| !disc.stream_errors.contains_key(name))
+    {
+        return Ok(());
+    }
+    Err(Error::new(
+        first_error.unwrap_or(ErrorKind::Other),
+        format!("none of the {} stream file(s) could be read", names.len()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use crate::testing::{disc, mpls, pes, pes_packets, source_packet, PlayItem, MPLS_00800};
+
+    #[test]
+    fn scan_records_missing_stream_files() {
+        let mut disc = disc(&[("BDMV/PLAYLIST/00800.mpls", MPLS_00800)]);
+        let error = disc.scan_stream_files().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(disc.stream_scans.is_empty());
+        assert!(disc.stream_errors.contains_key("00037.M2TS"));
+
+        // Already failed files are not read again, and still fail the scan.
+        assert_eq!(
+            disc.scan_stream_files().unwrap_err().kind(),
+            ErrorKind::Other
+        );
+    }
+
+    #[test]
+    fn scan_fills_bit_rates() {
+        let mut stream = source_packet(0x1001, false, Some(0), &[]);
+        stream.extend(pes_packets(0x1100, &pes(0xBD, Some(0), &[0; 1000])));
+        stream.extend(source_packet(0x1001, false, Some(27_000_000), &[]));
+        let mut disc = disc(&[
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/STREAM/00037.m2ts", &stream),
+        ]);
+        disc.scan_stream_files().unwrap();
+        assert!(disc.stream_errors.is_empty());
+        assert_eq!(disc.stream_scans["00037.M2TS"].packet_count, 8);
+        let stream = &disc.playlists[0].parser.playlist_streams()[&0x1100];
+        assert!(stream.stats().bit_rate > 0);
+    }
+
+    #[test]
+    fn scan_fails_only_when_no_file_is_read() {
+        let item = |clip| PlayItem {
+            clip,
+            time_in: 0,
+            time_out: 45_000,
+            streams: &[],
+        };
+        let playlist = mpls(&[item("00001"), item("00002")], &[]);
+        let stream = source_packet(0x1001, false, Some(0), &[]);
+        let mut disc = disc(&[
+            ("BDMV/PLAYLIST/00001.mpls", &playlist),
+            ("BDMV/STREAM/00001.m2ts", &stream),
+        ]);
+        disc.scan_stream_files().unwrap();
+        assert!(disc.stream_scans.contains_key("00001.M2TS"));
+        assert!(disc.stream_errors.contains_key("00002.M2TS"));
+    }
+}
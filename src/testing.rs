@@ -0,0 +1,375 @@
+//! Fixtures shared by the unit tests: the sample playlists, discs held in
+//! memory and hand-built transport stream packets.
+
+use std::sync::Arc;
+
+use crate::{disc::Disc, ts::index::TitleObject, vfs::MemoryFs};
+
+pub const MPLS_00800: &[u8] = include_bytes!("../00800.mpls");
+pub const MPLS_00801: &[u8] = include_bytes!("../00801.mpls");
+
+/// A disc holding the given `BDMV` relative files.
+pub fn disc(files: &[(&str, &[u8])]) -> Disc {
+    let mut fs = MemoryFs::new().with_label("SAMPLE");
+    for (path, data) in files {
+        fs.insert(path, *data);
+    }
+    Disc::from_fs(Arc::new(fs), "SAMPLE".to_owned()).unwrap()
+}
+
+/// One 192 byte source packet. Payloads shorter than a packet are padded
+/// with adaptation field stuffing.
+pub fn source_packet(pid: u16, start: bool, pcr: Option<u64>, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0, 0, 0, 0, 0x47];
+    packet.push(if start { 0x40 } else { 0 } | (pid >> 8) as u8);
+    packet.push(pid as u8);
+    if pcr.is_none() && payload.len() == 184 {
+        packet.push(0x10);
+    } else {
+        packet.push(0x30);
+        let length = 183 - payload.len();
+        packet.push(length as u8);
+        if let Some(pcr) = pcr {
+            assert!(length >= 7);
+            let (base, extension) = (pcr / 300, pcr % 300);
+            packet.extend_from_slice(&[
+                0x10,
+                (base >> 25) as u8,
+                (base >> 17) as u8,
+                (base >> 9) as u8,
+                (base >> 1) as u8,
+                ((base & 1) << 7) as u8 | 0x7E | (extension >> 8) as u8,
+                extension as u8,
+            ]);
+            packet.resize(packet.len() + length - 7, 0xFF);
+        } else if length > 0 {
+            packet.push(0);
+            packet.resize(packet.len() + length - 1, 0xFF);
+        }
+    }
+    packet.extend_from_slice(payload);
+    assert_eq!(packet.len(), 192);
+    packet
+}
+
+/// A PES packet with an optional PTS.
+pub fn pes(stream_id: u8, pts: Option<u64>, data: &[u8]) -> Vec<u8> {
+    let header_len = if pts.is_some() { 5 } else { 0 };
+    let length = (3 + header_len + data.len()).min(0xFFFF) as u16;
+    let mut pes = vec![0, 0, 1, stream_id];
+    pes.extend_from_slice(&length.to_be_bytes());
+    pes.push(0x80);
+    pes.push(if pts.is_some() { 0x80 } else { 0 });
+    pes.push(header_len as u8);
+    if let Some(pts) = pts {
+        pes.extend_from_slice(&[
+            0x21 | ((pts >> 29) & 0x0E) as u8,
+            (pts >> 22) as u8,
+            ((pts >> 14) & 0xFE) as u8 | 1,
+            (pts >> 7) as u8,
+            ((pts << 1) & 0xFE) as u8 | 1,
+        ]);
+    }
+    pes.extend_from_slice(data);
+    pes
+}
+
+/// Source packets carrying `pes` on `pid`.
+pub fn pes_packets(pid: u16, pes: &[u8]) -> Vec<u8> {
+    pes.chunks(184)
+        .enumerate()
+        .flat_map(|(i, chunk)| source_packet(pid, i == 0, None, chunk))
+        .collect()
+}
+
+/// MSB-first bit writer for building codec headers.
+#[derive(Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, count: u32, value: u64) -> &mut Self {
+        for i in (0..count).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+        self
+    }
+
+    /// Unsigned Exp-Golomb code.
+    pub fn ue(&mut self, value: u32) -> &mut Self {
+        let code = u64::from(value) + 1;
+        let bits = 64 - code.leading_zeros();
+        self.put(bits - 1, 0).put(bits, code)
+    }
+
+    /// The bytes written, zero padded to `len`.
+    pub fn finish(&self, len: usize) -> Vec<u8> {
+        let mut bytes = self.bytes.clone();
+        bytes.resize(bytes.len().max(len), 0);
+        bytes
+    }
+}
+
+/// An Annex B NAL unit: start code, `header` and `rbsp` with emulation
+/// prevention bytes inserted.
+pub fn nal_unit(header: &[u8], rbsp: &[u8]) -> Vec<u8> {
+    let mut unit = vec![0, 0, 0, 1];
+    unit.extend_from_slice(header);
+    let mut zeros = 0;
+    for &byte in rbsp {
+        if zeros >= 2 && byte <= 3 {
+            unit.push(3);
+            zeros = 0;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        unit.push(byte);
+    }
+    unit
+}
+
+/// A clip information file with one STC sequence (45 kHz times), one
+/// program of `(pid, coding_type)` streams and an EP_map for the first
+/// stream made of `(pts, spn)` entry points.
+pub fn clpi(
+    packets: u32,
+    presentation: (u32, u32),
+    streams: &[(u16, u8)],
+    entries: &[(u64, u32)],
+) -> Vec<u8> {
+    let mut clip_info = Vec::new();
+    clip_info.extend_from_slice(&16u32.to_be_bytes());
+    clip_info.extend_from_slice(&[0, 0, 1, 1, 0, 0, 0, 0]);
+    clip_info.extend_from_slice(&48_000_000u32.to_be_bytes());
+    clip_info.extend_from_slice(&packets.to_be_bytes());
+
+    let mut sequence = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0];
+    sequence.extend_from_slice(&0x1001u16.to_be_bytes());
+    sequence.extend_from_slice(&0u32.to_be_bytes());
+    sequence.extend_from_slice(&presentation.0.to_be_bytes());
+    sequence.extend_from_slice(&presentation.1.to_be_bytes());
+
+    let mut program = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0x01, 0x00];
+    program.push(streams.len() as u8);
+    program.push(0);
+    for (pid, coding_type) in streams {
+        program.extend_from_slice(&pid.to_be_bytes());
+        program.extend_from_slice(&[5, *coding_type, 0, 0, 0, 0]);
+    }
+
+    // EP_map: one coarse entry per fine entry, table right after the
+    // stream header.
+    let mut cpi = vec![0, 0, 0, 1, 0, 1];
+    let ep_map_start = cpi.len();
+    cpi.extend_from_slice(&[0, 1]);
+    let pid = streams.first().map_or(0x1011, |s| s.0);
+    cpi.extend_from_slice(&pid.to_be_bytes());
+    let address = 14u64;
+    let count = entries.len() as u64;
+    let packed = (1 << 50) | (count << 34) | (count << 16) | (address >> 16);
+    cpi.extend_from_slice(&packed.to_be_bytes());
+    cpi.extend_from_slice(&(address as u16).to_be_bytes());
+    assert_eq!(cpi.len() - ep_map_start, address as usize);
+    cpi.extend_from_slice(&(4 + 8 * count as u32).to_be_bytes());
+    for (i, (pts, spn)) in entries.iter().enumerate() {
+        let coarse = ((i as u32) << 14) | ((pts >> 19) & 0x3FFF) as u32;
+        cpi.extend_from_slice(&coarse.to_be_bytes());
+        cpi.extend_from_slice(&spn.to_be_bytes());
+    }
+    for (pts, spn) in entries {
+        let fine = (((pts >> 9) & 0x7FF) as u32) << 17 | (spn & 0x1_FFFF);
+        cpi.extend_from_slice(&fine.to_be_bytes());
+    }
+
+    let sequence_start = 40 + clip_info.len();
+    let program_start = sequence_start + sequence.len();
+    let cpi_start = program_start + program.len();
+    let mut data = b"HDMV0200".to_vec();
+    for start in [sequence_start, program_start, cpi_start] {
+        data.extend_from_slice(&(start as u32).to_be_bytes());
+    }
+    data.resize(40, 0);
+    data.extend(clip_info);
+    data.extend(sequence);
+    data.extend(program);
+    data.extend(cpi);
+    data
+}
+
+/// A play item of a playlist built with [`mpls`].
+pub struct PlayItem<'a> {
+    pub clip: &'a str,
+    /// In and out points, in 45 kHz ticks.
+    pub time_in: u32,
+    pub time_out: u32,
+    /// STN entries: the list (video, audio, PG, IG, secondary audio and
+    /// secondary video, from 0), the PID and the attributes.
+    pub streams: &'a [(usize, u16, &'a [u8])],
+}
+
+/// A text subtitle sub path of a playlist built with [`mpls_with_sub_paths`].
+pub struct SubPlayItem<'a> {
+    pub clip: &'a str,
+    /// In and out points on the clip, in 45 kHz ticks.
+    pub time_in: u32,
+    pub time_out: u32,
+    /// Play item the clip starts in, and when on that item's clip.
+    pub sync_play_item_id: u16,
+    pub sync_start_pts: u32,
+}
+
+/// An `.mpls` file with the given play items and entry marks (play item,
+/// 45 kHz time).
+pub fn mpls(items: &[PlayItem], marks: &[(u16, u32)]) -> Vec<u8> {
+    mpls_with_sub_paths(items, &[], marks)
+}
+
+/// An `.mpls` file with a text subtitle sub path per sub play item. Text
+/// subtitle streams (coding type 0x92) refer to the first sub path.
+pub fn mpls_with_sub_paths(
+    items: &[PlayItem],
+    sub_items: &[SubPlayItem],
+    marks: &[(u16, u32)],
+) -> Vec<u8> {
+    let mut playlist = vec![0, 0, 0, 0, 0, 0];
+    playlist.extend_from_slice(&(items.len() as u16).to_be_bytes());
+    playlist.extend_from_slice(&(sub_items.len() as u16).to_be_bytes());
+    for item in items {
+        let mut stn = vec![0; 16];
+        for list in 0..6 {
+            for (_, pid, attributes) in item.streams.iter().filter(|s| s.0 == list) {
+                if attributes.first() == Some(&0x92) {
+                    stn.extend_from_slice(&[9, 2, 0, 0]);
+                    stn.extend_from_slice(&pid.to_be_bytes());
+                    stn.extend_from_slice(&[0; 4]);
+                } else {
+                    stn.extend_from_slice(&[9, 1]);
+                    stn.extend_from_slice(&pid.to_be_bytes());
+                    stn.extend_from_slice(&[0; 6]);
+                }
+                stn.push(attributes.len() as u8);
+                stn.extend_from_slice(attributes);
+                // Secondary streams reference one primary stream per list.
+                match list {
+                    4 => stn.extend_from_slice(&[1, 0, 0, 0]),
+                    5 => stn.extend_from_slice(&[1, 0, 0, 0, 1, 0, 0, 0]),
+                    _ => {}
+                }
+                stn[4 + list] += 1;
+            }
+        }
+        let stn_len = (stn.len() - 2) as u16;
+        stn[..2].copy_from_slice(&stn_len.to_be_bytes());
+
+        let mut entry = item.clip.as_bytes().to_vec();
+        entry.extend_from_slice(b"M2TS");
+        entry.extend_from_slice(&[0, 1, 0]);
+        entry.extend_from_slice(&item.time_in.to_be_bytes());
+        entry.extend_from_slice(&item.time_out.to_be_bytes());
+        entry.extend_from_slice(&[0; 12]);
+        entry.extend(stn);
+        playlist.extend_from_slice(&(entry.len() as u16).to_be_bytes());
+        playlist.extend(entry);
+    }
+    for item in sub_items {
+        let mut entry = item.clip.as_bytes().to_vec();
+        entry.extend_from_slice(b"M2TS");
+        entry.extend_from_slice(&[0; 5]);
+        entry.extend_from_slice(&item.time_in.to_be_bytes());
+        entry.extend_from_slice(&item.time_out.to_be_bytes());
+        entry.extend_from_slice(&item.sync_play_item_id.to_be_bytes());
+        entry.extend_from_slice(&item.sync_start_pts.to_be_bytes());
+        let mut sub_path = vec![0, 0, 0, 0, 0, 4, 0, 0, 0, 1];
+        sub_path.extend_from_slice(&(entry.len() as u16).to_be_bytes());
+        sub_path.extend(entry);
+        let sub_path_len = (sub_path.len() - 4) as u32;
+        sub_path[..4].copy_from_slice(&sub_path_len.to_be_bytes());
+        playlist.extend(sub_path);
+    }
+    let playlist_len = (playlist.len() - 4) as u32;
+    playlist[..4].copy_from_slice(&playlist_len.to_be_bytes());
+
+    let mut chapters = ((2 + 14 * marks.len()) as u32).to_be_bytes().to_vec();
+    chapters.extend_from_slice(&(marks.len() as u16).to_be_bytes());
+    for (item, time) in marks {
+        chapters.extend_from_slice(&[0, 1]);
+        chapters.extend_from_slice(&item.to_be_bytes());
+        chapters.extend_from_slice(&time.to_be_bytes());
+        chapters.extend_from_slice(&[0xFF, 0xFF, 0, 0, 0, 0]);
+    }
+
+    let mut data = b"MPLS0200".to_vec();
+    let playlist_offset = 58;
+    let chapters_offset = playlist_offset + playlist.len();
+    for offset in [playlist_offset, chapters_offset, 0] {
+        data.extend_from_slice(&(offset as u32).to_be_bytes());
+    }
+    data.resize(40, 0);
+    data.extend_from_slice(&14u32.to_be_bytes());
+    data.extend_from_slice(&[0, 1]);
+    data.resize(playlist_offset, 0);
+    data.extend(playlist);
+    data.extend(chapters);
+    data
+}
+
+/// An `index.bdmv` file for a 1080p24 disc with the given first playback,
+/// top menu and `(object, access type)` titles.
+pub fn index_bdmv(
+    first_playback: &TitleObject,
+    top_menu: &TitleObject,
+    titles: &[(TitleObject, u8)],
+) -> Vec<u8> {
+    let mut indexes = Vec::new();
+    index_object(&mut indexes, first_playback, 0);
+    index_object(&mut indexes, top_menu, 0);
+    indexes.extend_from_slice(&(titles.len() as u16).to_be_bytes());
+    for (object, access_type) in titles {
+        index_object(&mut indexes, object, *access_type);
+    }
+
+    // AppInfoBDMV: 34 bytes after its length, most of them provider data.
+    let indexes_start = 40 + 4 + 34;
+    let mut data = b"INDX0200".to_vec();
+    data.extend_from_slice(&(indexes_start as u32).to_be_bytes());
+    data.resize(40, 0);
+    data.extend_from_slice(&34u32.to_be_bytes());
+    data.extend_from_slice(&[0x40, 0x61]);
+    data.resize(indexes_start, 0);
+    data.extend_from_slice(&(indexes.len() as u32).to_be_bytes());
+    data.extend(indexes);
+    data
+}
+
+fn index_object(data: &mut Vec<u8>, object: &TitleObject, access_type: u8) {
+    let (object_type, playback_type) = match object {
+        TitleObject::Hdmv { playback_type, .. } => (1, *playback_type),
+        TitleObject::BdJ { playback_type, .. } => (2, *playback_type),
+        TitleObject::Unknown(object_type) => (*object_type, 0),
+    };
+    let head = u32::from(object_type) << 30 | u32::from(access_type) << 28;
+    data.extend_from_slice(&head.to_be_bytes());
+    data.extend_from_slice(&(u16::from(playback_type) << 14).to_be_bytes());
+    match object {
+        TitleObject::Hdmv { movie_object, .. } => {
+            data.extend_from_slice(&movie_object.to_be_bytes());
+            data.extend_from_slice(&[0; 4]);
+        }
+        TitleObject::BdJ { name, .. } => {
+            data.extend_from_slice(name.as_bytes());
+            data.push(0);
+        }
+        TitleObject::Unknown(_) => data.extend_from_slice(&[0; 6]),
+    }
+}
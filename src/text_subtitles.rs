@@ -0,0 +1,133 @@
+//! Text subtitle streams, read from the sub path clips that carry them.
+
+use std::io::{BufReader, Error, ErrorKind, Result};
+
+use crate::{
+    demux,
+    disc::Disc,
+    playlist_reader,
+    playlist_view::PlaylistView,
+    ts::{packet::PesHeader, stream::Stream, stream_type::StreamType, textst::TextSubtitle},
+    vfs,
+};
+
+/// Reads the text subtitle stream `pid` of the named playlist from its
+/// sub path clip, with dialog times on the playlist's timeline.
+pub fn read(disc: &Disc, name: &str, pid: u16) -> Result<TextSubtitle> {
+    let playlist = disc
+        .playlist(name)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no playlist named {}", name)))?;
+    let data = disc.fs.read(&vfs::join("BDMV/PLAYLIST", &playlist.name))?;
+    let view = PlaylistView::parse(&data)?;
+    let entry = view
+        .play_items
+        .iter()
+        .flat_map(|item| &item.streams)
+        .find(|entry| entry.pid == pid && entry.stream_type() == StreamType::SUBTITLE)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("{} has no text subtitle stream with PID {:#06x}", name, pid),
+            )
+        })?;
+    let item = entry
+        .subpath_id
+        .and_then(|id| view.sub_paths.get(id as usize))
+        .and_then(|sub_path| sub_path.items.get(entry.subclip_id.unwrap_or(0) as usize))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("text subtitle stream {:#06x} has no sub path clip", pid),
+            )
+        })?;
+    let character_code = match entry.to_stream() {
+        Some(Stream::TextStream(stream)) => stream.character_code,
+        _ => Default::default(),
+    };
+
+    let path = playlist_reader::find_file(
+        disc.fs.as_ref(),
+        "BDMV/STREAM",
+        &format!("{}.M2TS", item.clip_name),
+    )?;
+    let mut subtitle = TextSubtitle::new(character_code);
+    let mut reader = BufReader::new(disc.fs.open(&path)?);
+    demux::for_each_pes(&mut reader, pid, |data| {
+        match PesHeader::parse(data).and_then(|header| data.get(header.header_len..)) {
+            Some(payload) => subtitle.push_payload(payload),
+            None => Ok(()),
+        }
+    })?;
+
+    // The sub play item starts at its sync point in the play item.
+    let sync_time = playlist
+        .parser
+        .clips()
+        .get(item.sync_play_item_id as usize)
+        .map(|clip| clip.relative_time_in + item.sync_start_pts as f64 / 45_000.0 - clip.time_in)
+        .unwrap_or_default();
+    subtitle.shift(sync_time - item.time_in as f64 / 45_000.0);
+    Ok(subtitle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{disc, mpls_with_sub_paths, pes_packets, PlayItem, SubPlayItem},
+        ts::{
+            stream_type::CharacterCode,
+            textst::tests::{dialog, dialog_style, inline},
+        },
+    };
+
+    #[test]
+    fn reads_text_subtitles_on_the_playlist_timeline() {
+        // The sub path clip starts 2 s into its file and plays from 10 s
+        // into the play item, which starts at 20 s on its clip.
+        let playlist = mpls_with_sub_paths(
+            &[PlayItem {
+                clip: "00001",
+                time_in: 45_000 * 20,
+                time_out: 45_000 * 80,
+                streams: &[
+                    (0, 0x1011, &[0x1B, 0x61, 0x30]),
+                    (2, 0x1800, &[0x92, 0x01, b'e', b'n', b'g']),
+                ],
+            }],
+            &[SubPlayItem {
+                clip: "00002",
+                time_in: 45_000 * 2,
+                time_out: 45_000 * 62,
+                sync_play_item_id: 0,
+                sync_start_pts: 45_000 * 30,
+            }],
+            &[],
+        );
+        let text = inline(0x01, b"Hello");
+        let mut stream = Vec::new();
+        for payload in [
+            dialog_style(1),
+            dialog(90_000 * 5, 90_000 * 7, &[(0, &text)]),
+        ] {
+            // Private stream 2, without the optional PES header.
+            let mut pes = vec![0, 0, 1, 0xBF];
+            pes.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+            pes.extend(payload);
+            stream.extend(pes_packets(0x1800, &pes));
+        }
+        let disc = disc(&[
+            ("BDMV/PLAYLIST/00001.mpls", &playlist),
+            ("BDMV/STREAM/00002.m2ts", &stream),
+        ]);
+
+        let subtitle = disc.text_subtitles("00001.MPLS", 0x1800).unwrap();
+        assert_eq!(subtitle.character_code, CharacterCode::CHARACTERCODE_UTF8);
+        assert!(subtitle.style.is_some());
+        let dialog = &subtitle.dialogs[0];
+        assert_eq!((dialog.start, dialog.end), (13.0, 15.0));
+
+        let error = disc.text_subtitles("00001.MPLS", 0x1011).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+}
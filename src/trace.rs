@@ -0,0 +1,168 @@
+//! Structured events emitted while parsing.
+//!
+//! Parsing is silent by default. Events go to the sink installed with
+//! [`set_sink`], and to the `log` and `tracing` facades when those features
+//! are enabled.
+
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{
+    boxed::Box,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Event<'a> {
+    /// A section of the file starts, at `offset` when the reader knows it.
+    Section { name: &'a str, offset: Option<u64> },
+    /// A field was decoded.
+    Field {
+        name: &'a str,
+        value: fmt::Arguments<'a>,
+    },
+    /// Progress outside of a single file, like scanning a stream file.
+    Info {
+        name: &'a str,
+        value: fmt::Arguments<'a>,
+    },
+    /// Something unexpected that was skipped or worked around.
+    Warning {
+        name: &'a str,
+        message: fmt::Arguments<'a>,
+    },
+}
+
+/// Receives every event, from any thread.
+pub trait EventSink: Send + Sync {
+    fn event(&self, event: &Event<'_>);
+}
+
+#[cfg(feature = "std")]
+static SINK: RwLock<Option<Box<dyn EventSink>>> = RwLock::new(None);
+#[cfg(feature = "std")]
+static HAS_SINK: AtomicBool = AtomicBool::new(false);
+
+/// Sends events to `sink`, replacing any previous sink.
+#[cfg(feature = "std")]
+pub fn set_sink(sink: impl EventSink + 'static) {
+    if let Ok(mut current) = SINK.write() {
+        *current = Some(Box::new(sink));
+        HAS_SINK.store(true, Ordering::Release);
+    }
+}
+
+/// Removes the sink installed with [`set_sink`].
+#[cfg(feature = "std")]
+pub fn clear_sink() {
+    if let Ok(mut current) = SINK.write() {
+        HAS_SINK.store(false, Ordering::Release);
+        *current = None;
+    }
+}
+
+/// Whether anything receives events, so callers can skip building them.
+pub fn enabled() -> bool {
+    #[cfg(feature = "std")]
+    if HAS_SINK.load(Ordering::Acquire) {
+        return true;
+    }
+    cfg!(any(feature = "log", feature = "tracing"))
+}
+
+/// Sends `event` to the sink and the enabled facades.
+#[cfg_attr(
+    not(any(feature = "std", feature = "log", feature = "tracing")),
+    allow(unused_variables)
+)]
+pub fn emit(event: &Event<'_>) {
+    #[cfg(feature = "std")]
+    if HAS_SINK.load(Ordering::Acquire) {
+        if let Ok(sink) = SINK.read() {
+            if let Some(sink) = sink.as_ref() {
+                sink.event(event);
+            }
+        }
+    }
+
+    #[cfg(feature = "log")]
+    match event {
+        Event::Section { name, offset } => match offset {
+            Some(offset) => ::log::debug!("{} at {:#X}", name, offset),
+            None => ::log::debug!("{}", name),
+        },
+        Event::Field { name, value } => ::log::trace!("{}: {}", name, value),
+        Event::Info { name, value } => ::log::info!("{}: {}", name, value),
+        Event::Warning { name, message } => ::log::warn!("{}: {}", name, message),
+    }
+
+    #[cfg(feature = "tracing")]
+    match event {
+        Event::Section { name, offset } => ::tracing::debug!(section = name, offset = ?offset),
+        Event::Field { name, value } => ::tracing::trace!(field = name, value = %value),
+        Event::Info { name, value } => ::tracing::info!(name, value = %value),
+        Event::Warning { name, message } => ::tracing::warn!(name, message = %message),
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::{string::String, sync::Mutex, vec::Vec};
+
+    use super::*;
+    use crate::{
+        parser::Parser,
+        testing::{mpls, PlayItem},
+    };
+
+    static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct Collect;
+
+    impl EventSink for Collect {
+        fn event(&self, event: &Event<'_>) {
+            let line = match event {
+                Event::Section { name, offset } => format!("section {} {:?}", name, offset),
+                Event::Field { name, value } => format!("field {}: {}", name, value),
+                Event::Info { name, value } => format!("info {}: {}", name, value),
+                Event::Warning { name, message } => format!("warning {}: {}", name, message),
+            };
+            EVENTS.lock().unwrap().push(line);
+        }
+    }
+
+    #[test]
+    fn sends_parse_events_to_the_sink() {
+        let data = mpls(
+            &[PlayItem {
+                clip: "00009",
+                time_in: 0,
+                time_out: 45_000,
+                // An unknown coding type.
+                streams: &[(1, 0x1100, &[0x7F, 0x61, b'e', b'n', b'g'])],
+            }],
+            &[(0, 0)],
+        );
+
+        set_sink(Collect);
+        assert!(enabled());
+        Parser::from_bytes(&data).unwrap();
+        clear_sink();
+        assert_eq!(enabled(), cfg!(any(feature = "log", feature = "tracing")));
+
+        let events = EVENTS.lock().unwrap();
+        for expected in [
+            "section Header Some(0)",
+            "section Playlist Some(58)",
+            "section Playlist Item 0 Some(68)",
+            "field Stream Section: 00009.M2TS",
+            "field stream_count_audio: 1",
+            "warning Audio Stream 0: Returned None",
+            "field Marker: 0: (0, 0.0, None)",
+        ] {
+            assert!(events.iter().any(|e| e == expected), "{}", expected);
+        }
+    }
+}
@@ -1,13 +1,13 @@
-use std::fmt::{Debug, Display};
-
-use super::FromBinary;
-
-#[repr(C, packed)]
-#[derive(Default)]
-pub struct ClipAngleHeader {
-    angle_name: [u8; 5],
-    angle_type: [u8; 4],
-    _unk_0: [u8; 1],
+use alloc::string::{String, ToString};
+use core::fmt::{Debug, Display};
+
+binary_struct! {
+    #[derive(Default)]
+    pub struct ClipAngleHeader {
+        angle_name: [u8; 5],
+        angle_type: [u8; 4],
+        _unk_0: [u8; 1],
+    }
 }
 
 impl ClipAngleHeader {
@@ -19,18 +19,14 @@ impl ClipAngleHeader {
     }
 }
 
-impl FromBinary for ClipAngleHeader {
-    fn sanitize(&mut self) {}
-}
-
 impl Display for ClipAngleHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:?}", FriendlyClipAngleHeader::new(self))
     }
 }
 
 impl Debug for ClipAngleHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:#?}", FriendlyClipAngleHeader::new(self))
     }
 }
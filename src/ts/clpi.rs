@@ -0,0 +1,315 @@
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+
+use crate::macros::*;
+
+/// One STC sequence: a stretch of the clip with a continuous system clock.
+#[derive(Debug, Clone, Default)]
+pub struct StcSequence {
+    pub pcr_pid: u16,
+    pub spn_stc_start: u32,
+    /// 45 kHz, like the playlist's in and out times.
+    pub presentation_start_time: u32,
+    pub presentation_end_time: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AtcSequence {
+    pub spn_atc_start: u32,
+    pub stc_sequences: Vec<StcSequence>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProgramStream {
+    pub pid: u16,
+    pub coding_type: u8,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub spn_program_sequence_start: u32,
+    pub program_map_pid: u16,
+    pub streams: Vec<ProgramStream>,
+}
+
+/// An entry point: a source packet number where decoding can start, and
+/// the 90 kHz PTS of the access unit found there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntryPoint {
+    pub pts: u64,
+    pub spn: u32,
+    pub is_angle_change_point: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EpMapStream {
+    pub pid: u16,
+    pub ep_stream_type: u8,
+    pub entries: Vec<EntryPoint>,
+}
+
+impl EpMapStream {
+    /// Last entry point at or before `pts`.
+    pub fn entry_before(&self, pts: u64) -> Option<&EntryPoint> {
+        let index = self.entries.partition_point(|e| e.pts <= pts);
+        self.entries.get(index.checked_sub(1)?)
+    }
+
+    /// First entry point at or after `pts`.
+    pub fn entry_after(&self, pts: u64) -> Option<&EntryPoint> {
+        let index = self.entries.partition_point(|e| e.pts < pts);
+        self.entries.get(index)
+    }
+}
+
+/// Clip information file (`.clpi`): the clip's source packet count, its
+/// sequences, programs and the EP_map used to seek by time.
+#[derive(Debug, Clone, Default)]
+pub struct ClipInfo {
+    pub type_indicator: String,
+    pub version: String,
+    pub clip_stream_type: u8,
+    pub application_type: u8,
+    pub ts_recording_rate: u32,
+    pub source_packet_count: u32,
+    pub atc_sequences: Vec<AtcSequence>,
+    pub programs: Vec<Program>,
+    pub ep_map: Vec<EpMapStream>,
+}
+
+impl ClipInfo {
+    pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let type_indicator = read_string(reader, 4)?;
+        if type_indicator != "HDMV" {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("not a clip information file: {:?}", type_indicator),
+            ));
+        }
+        let version = read_string(reader, 4)?;
+        let sequence_info_start = read_u32(reader)?;
+        let program_info_start = read_u32(reader)?;
+        let cpi_start = read_u32(reader)?;
+
+        let mut clip = ClipInfo {
+            type_indicator,
+            version,
+            ..Default::default()
+        };
+
+        // ClipInfo follows the 40 byte header.
+        reader.seek(SeekFrom::Start(40))?;
+        let _length = read_u32(reader)?;
+        reader.seek(SeekFrom::Current(2))?;
+        clip.clip_stream_type = read_u8(reader)?;
+        clip.application_type = read_u8(reader)?;
+        reader.seek(SeekFrom::Current(4))?;
+        clip.ts_recording_rate = read_u32(reader)?;
+        clip.source_packet_count = read_u32(reader)?;
+
+        clip.read_sequence_info(reader, sequence_info_start)?;
+        clip.read_program_info(reader, program_info_start)?;
+        clip.read_cpi(reader, cpi_start)?;
+        Ok(clip)
+    }
+
+    fn read_sequence_info<R: Read + Seek>(&mut self, reader: &mut R, start: u32) -> Result<()> {
+        reader.seek(SeekFrom::Start(start.into()))?;
+        let _length = read_u32(reader)?;
+        reader.seek(SeekFrom::Current(1))?;
+        for _ in 0..read_u8(reader)? {
+            let spn_atc_start = read_u32(reader)?;
+            let stc_count = read_u8(reader)?;
+            let _offset_stc_id = read_u8(reader)?;
+            let mut stc_sequences = Vec::with_capacity(stc_count as usize);
+            for _ in 0..stc_count {
+                stc_sequences.push(StcSequence {
+                    pcr_pid: read_u16(reader)?,
+                    spn_stc_start: read_u32(reader)?,
+                    presentation_start_time: read_u32(reader)?,
+                    presentation_end_time: read_u32(reader)?,
+                });
+            }
+            self.atc_sequences.push(AtcSequence {
+                spn_atc_start,
+                stc_sequences,
+            });
+        }
+        Ok(())
+    }
+
+    fn read_program_info<R: Read + Seek>(&mut self, reader: &mut R, start: u32) -> Result<()> {
+        reader.seek(SeekFrom::Start(start.into()))?;
+        let _length = read_u32(reader)?;
+        reader.seek(SeekFrom::Current(1))?;
+        for _ in 0..read_u8(reader)? {
+            let spn_program_sequence_start = read_u32(reader)?;
+            let program_map_pid = read_u16(reader)?;
+            let stream_count = read_u8(reader)?;
+            let _group_count = read_u8(reader)?;
+            let mut streams = Vec::with_capacity(stream_count as usize);
+            for _ in 0..stream_count {
+                let pid = read_u16(reader)?;
+                let coding_info_len = read_u8(reader)?;
+                let coding_type = read_u8(reader)?;
+                reader.seek(SeekFrom::Current(coding_info_len as i64 - 1))?;
+                streams.push(ProgramStream { pid, coding_type });
+            }
+            self.programs.push(Program {
+                spn_program_sequence_start,
+                program_map_pid,
+                streams,
+            });
+        }
+        Ok(())
+    }
+
+    fn read_cpi<R: Read + Seek>(&mut self, reader: &mut R, start: u32) -> Result<()> {
+        reader.seek(SeekFrom::Start(start.into()))?;
+        let length = read_u32(reader)?;
+        if length == 0 {
+            return Ok(());
+        }
+        let cpi_type = read_u16(reader)? & 0xF;
+        if cpi_type != 1 {
+            return Ok(());
+        }
+
+        let ep_map_start = reader.stream_position()?;
+        reader.seek(SeekFrom::Current(1))?;
+        let stream_count = read_u8(reader)?;
+        let mut headers = Vec::with_capacity(stream_count as usize);
+        for _ in 0..stream_count {
+            let pid = read_u16(reader)?;
+            let packed = read_u64(reader)?;
+            // 10 reserved bits, then EP_stream_type, the coarse and fine
+            // entry counts and the table's address, relative to the EP_map.
+            let ep_stream_type = ((packed >> 50) & 0xF) as u8;
+            let coarse_count = ((packed >> 34) & 0xFFFF) as usize;
+            let fine_count = ((packed >> 16) & 0x3_FFFF) as usize;
+            let address = (((packed & 0xFFFF) << 16) as u32) | u32::from(read_u16(reader)?);
+            headers.push((pid, ep_stream_type, coarse_count, fine_count, address));
+        }
+
+        for (pid, ep_stream_type, coarse_count, fine_count, address) in headers {
+            let table_start = ep_map_start + u64::from(address);
+            reader.seek(SeekFrom::Start(table_start))?;
+            let fine_start = read_u32(reader)?;
+
+            let mut coarse = Vec::with_capacity(coarse_count);
+            for _ in 0..coarse_count {
+                let value = read_u32(reader)?;
+                let fine_id = (value >> 14) as usize;
+                let pts_coarse = u64::from(value & 0x3FFF);
+                coarse.push((fine_id, pts_coarse, read_u32(reader)?));
+            }
+
+            reader.seek(SeekFrom::Start(table_start + u64::from(fine_start)))?;
+            let mut entries = Vec::with_capacity(fine_count);
+            let mut coarse_index = 0;
+            for fine_id in 0..fine_count {
+                let value = read_u32(reader)?;
+                while coarse_index + 1 < coarse.len() && coarse[coarse_index + 1].0 <= fine_id {
+                    coarse_index += 1;
+                }
+                let Some(&(_, pts_coarse, spn_coarse)) = coarse.get(coarse_index) else {
+                    break;
+                };
+                let pts_fine = u64::from((value >> 17) & 0x7FF);
+                let spn_fine = value & 0x1_FFFF;
+                entries.push(EntryPoint {
+                    // The coarse PTS's lowest bit overlaps the fine PTS.
+                    pts: ((pts_coarse & !1) << 19) + (pts_fine << 9),
+                    spn: (spn_coarse & !0x1_FFFF) + spn_fine,
+                    is_angle_change_point: value >> 31 == 1,
+                });
+            }
+
+            self.ep_map.push(EpMapStream {
+                pid,
+                ep_stream_type,
+                entries,
+            });
+        }
+        Ok(())
+    }
+
+    /// EP_map of the first stream that has entries, normally the video.
+    pub fn main_ep_map(&self) -> Option<&EpMapStream> {
+        self.ep_map.iter().find(|s| !s.entries.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::clpi;
+
+    fn sample() -> Vec<u8> {
+        clpi(
+            2500,
+            (188_926_875, 588_907_705),
+            &[(0x1011, 0x24), (0x1100, 0x86)],
+            &[
+                (377_853_440, 0),
+                (378_880_000, 1_000),
+                (1_177_600_000, 140_000),
+            ],
+        )
+    }
+
+    #[test]
+    fn parses_clip_info() {
+        let clip = ClipInfo::parse(&mut Cursor::new(sample())).unwrap();
+        assert_eq!(clip.version, "0200");
+        assert_eq!(clip.source_packet_count, 2500);
+        assert_eq!(clip.ts_recording_rate, 48_000_000);
+
+        let stc = &clip.atc_sequences[0].stc_sequences[0];
+        assert_eq!(stc.pcr_pid, 0x1001);
+        assert_eq!(stc.presentation_end_time, 588_907_705);
+
+        let streams = &clip.programs[0].streams;
+        assert_eq!(streams.len(), 2);
+        assert_eq!((streams[1].pid, streams[1].coding_type), (0x1100, 0x86));
+
+        let ep_map = clip.main_ep_map().unwrap();
+        assert_eq!(ep_map.pid, 0x1011);
+        let entries = ep_map
+            .entries
+            .iter()
+            .map(|e| (e.pts, e.spn))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                (377_853_440, 0),
+                (378_880_000, 1_000),
+                (1_177_600_000, 140_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn looks_up_entry_points() {
+        let clip = ClipInfo::parse(&mut Cursor::new(sample())).unwrap();
+        let ep_map = clip.main_ep_map().unwrap();
+        assert_eq!(ep_map.entry_before(378_000_000).unwrap().spn, 0);
+        assert_eq!(ep_map.entry_before(378_880_000).unwrap().spn, 1_000);
+        assert!(ep_map.entry_before(1).is_none());
+        assert_eq!(ep_map.entry_after(378_000_000).unwrap().spn, 1_000);
+        assert!(ep_map.entry_after(1_200_000_000).is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let mut data = sample();
+        data[0] = b'X';
+        let error = ClipInfo::parse(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let data = sample();
+        assert!(ClipInfo::parse(&mut Cursor::new(&data[..data.len() - 6])).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+use super::{bits::BitReader, AudioInfo};
+use alloc::boxed::Box;
+
+const SYNC: u16 = 0x0B77;
+const MAX_FRAMES: usize = 64;
+
+const AC3_BIT_RATES: [u32; 19] = [
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640,
+];
+const SAMPLE_RATES: [u32; 3] = [48_000, 44_100, 32_000];
+const EAC3_BLOCKS: [u32; 4] = [1, 2, 3, 6];
+
+// Speaker locations in E-AC-3 `chanmap` bit order (bit 0 is the MSB).
+const LOC_L: u16 = 1 << 15;
+const LOC_C: u16 = 1 << 14;
+const LOC_R: u16 = 1 << 13;
+const LOC_LS: u16 = 1 << 12;
+const LOC_RS: u16 = 1 << 11;
+const LOC_CS: u16 = 1 << 8;
+const LOC_LFE2: u16 = 1 << 1;
+const LOC_LFE: u16 = 1;
+/// Locations in `chanmap` that stand for a channel pair.
+const PAIRS: u16 = (1 << 10) | (1 << 9) | (1 << 6) | (1 << 5) | (1 << 4) | (1 << 2);
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    bsid: u8,
+    /// 0 independent, 1 dependent, 2 AC-3 converted (E-AC-3 only).
+    stream_type: u8,
+    size: usize,
+    sample_rate: u32,
+    bit_rate: u32,
+    acmod: u8,
+    lfe: bool,
+    dial_norm: i8,
+    chanmap: Option<u16>,
+}
+
+impl Frame {
+    fn parse(data: &[u8]) -> Option<Frame> {
+        let mut r = BitReader::new(data);
+        if r.bits(16) as u16 != SYNC {
+            return None;
+        }
+        let bsid = (data.get(5)? >> 3) & 0x1F;
+        let frame = if bsid <= 10 {
+            r.skip(16); // crc1
+            let fscod = r.bits(2) as usize;
+            let frmsizecod = r.bits(6) as usize;
+            r.skip(5 + 3); // bsid, bsmod
+            let acmod = r.bits(3) as u8;
+            if acmod & 0x1 != 0 && acmod != 0x1 {
+                r.skip(2); // cmixlev
+            }
+            if acmod & 0x4 != 0 {
+                r.skip(2); // surmixlev
+            }
+            if acmod == 0x2 {
+                r.skip(2); // dsurmod
+            }
+            let lfe = r.bit();
+            let dial_norm = r.bits(5) as u8;
+
+            let sample_rate = *SAMPLE_RATES.get(fscod)?;
+            let kbps = *AC3_BIT_RATES.get(frmsizecod >> 1)?;
+            let words = match fscod {
+                0 => kbps * 2,
+                1 => kbps * 2 * 48_000 / 44_100 + (frmsizecod as u32 & 1),
+                _ => kbps * 3,
+            };
+            Frame {
+                bsid,
+                stream_type: 0,
+                size: words as usize * 2,
+                sample_rate,
+                bit_rate: kbps * 1000,
+                acmod,
+                lfe,
+                dial_norm: dial_norm_db(dial_norm),
+                chanmap: None,
+            }
+        } else if bsid <= 16 {
+            let stream_type = r.bits(2) as u8;
+            r.skip(3); // substreamid
+            let size = (r.bits(11) as usize + 1) * 2;
+            let fscod = r.bits(2) as usize;
+            let (sample_rate, blocks) = if fscod == 3 {
+                let fscod2 = r.bits(2) as usize;
+                (*SAMPLE_RATES.get(fscod2)? / 2, 6)
+            } else {
+                (*SAMPLE_RATES.get(fscod)?, EAC3_BLOCKS[r.bits(2) as usize])
+            };
+            let acmod = r.bits(3) as u8;
+            let lfe = r.bit();
+            r.skip(5); // bsid
+            let dial_norm = r.bits(5) as u8;
+            if r.bit() {
+                r.skip(8); // compr
+            }
+            if acmod == 0 {
+                r.skip(5); // dialnorm2
+                if r.bit() {
+                    r.skip(8); // compr2
+                }
+            }
+            let chanmap = if stream_type == 1 && r.bit() {
+                Some(r.bits(16) as u16)
+            } else {
+                None
+            };
+            Frame {
+                bsid,
+                stream_type,
+                size,
+                sample_rate,
+                bit_rate: (size as u32 * 8 * sample_rate) / (blocks * 256),
+                acmod,
+                lfe,
+                dial_norm: dial_norm_db(dial_norm),
+                chanmap,
+            }
+        } else {
+            return None;
+        };
+
+        if r.overrun || frame.size < 6 {
+            None
+        } else {
+            Some(frame)
+        }
+    }
+
+    fn locations(&self) -> u16 {
+        let mut locations = match self.acmod {
+            0 | 2 => LOC_L | LOC_R,
+            1 => LOC_C,
+            3 => LOC_L | LOC_C | LOC_R,
+            4 => LOC_L | LOC_R | LOC_CS,
+            5 => LOC_L | LOC_C | LOC_R | LOC_CS,
+            6 => LOC_L | LOC_R | LOC_LS | LOC_RS,
+            _ => LOC_L | LOC_C | LOC_R | LOC_LS | LOC_RS,
+        };
+        if self.lfe {
+            locations |= LOC_LFE;
+        }
+        locations
+    }
+
+    fn info(&self) -> AudioInfo {
+        let locations = self.locations();
+        AudioInfo {
+            channel_count: channel_count(locations),
+            lfe: self.lfe,
+            sample_rate: self.sample_rate,
+            bit_rate: self.bit_rate,
+            dial_norm: Some(self.dial_norm),
+            ..Default::default()
+        }
+    }
+}
+
+fn dial_norm_db(value: u8) -> i8 {
+    if value == 0 {
+        -31
+    } else {
+        -(value as i8)
+    }
+}
+
+fn channel_count(locations: u16) -> u8 {
+    let full_range = locations & !(LOC_LFE | LOC_LFE2);
+    (full_range.count_ones() + (full_range & PAIRS).count_ones()) as u8
+}
+
+/// Looks for Dolby Atmos object metadata (OAMD or JOC payloads) in the
+/// EMDF containers of an E-AC-3 frame.
+fn has_atmos(frame: &[u8]) -> bool {
+    frame.windows(2).enumerate().any(|(i, w)| {
+        if w != [0x58, 0x38] {
+            return false;
+        }
+        let mut r = BitReader::new(&frame[i + 2..]);
+        let length = r.bits(16) as usize;
+        if length == 0 || length > frame.len() {
+            return false;
+        }
+        if r.bits(2) == 3 {
+            return false; // emdf_version we do not understand
+        }
+        if r.bits(3) == 7 {
+            return false; // key_id with variable bits
+        }
+        let payload_id = r.bits(5);
+        !r.overrun && matches!(payload_id, 11 | 14)
+    })
+}
+
+/// Probes AC-3 and E-AC-3 streams. Blu-ray Dolby Digital Plus carries an
+/// AC-3 or E-AC-3 independent substream plus an optional dependent substream
+/// for the extra channels of 7.1 mixes.
+pub fn probe(data: &[u8]) -> Option<AudioInfo> {
+    let mut pos = data.windows(2).position(|w| w == SYNC.to_be_bytes())?;
+    let mut independent: Option<Frame> = None;
+    let mut dependent: Option<Frame> = None;
+    let mut atmos = false;
+
+    for _ in 0..MAX_FRAMES {
+        let Some(frame) = data.get(pos..).and_then(Frame::parse) else {
+            let next = data
+                .get(pos + 1..)
+                .and_then(|rest| rest.windows(2).position(|w| w == SYNC.to_be_bytes()));
+            match next {
+                Some(skip) => {
+                    pos += 1 + skip;
+                    continue;
+                }
+                None => break,
+            }
+        };
+        if frame.bsid > 10 {
+            atmos |= has_atmos(&data[pos..(pos + frame.size).min(data.len())]);
+        }
+        if frame.stream_type == 1 {
+            dependent.get_or_insert(frame);
+        } else {
+            independent.get_or_insert(frame);
+        }
+        pos += frame.size;
+    }
+
+    let base = independent?;
+    let mut info = base.info();
+    if let Some(dependent) = dependent {
+        let locations = base.locations() | dependent.chanmap.unwrap_or(dependent.locations());
+        info.channel_count = channel_count(locations);
+        info.lfe = locations & (LOC_LFE | LOC_LFE2) != 0;
+        info.bit_rate = base.bit_rate + dependent.bit_rate;
+        if base.bsid <= 10 {
+            info.core = Some(Box::new(base.info()));
+        }
+    }
+    info.has_atmos = atmos;
+    Some(info)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::testing::BitWriter;
+
+    /// 448 kbps 48 kHz AC-3 frame, 3/2 with LFE.
+    pub(crate) fn ac3_frame() -> alloc::vec::Vec<u8> {
+        BitWriter::new()
+            .put(16, SYNC as u64)
+            .put(16, 0) // crc1
+            .put(2, 0) // fscod
+            .put(6, 30) // frmsizecod
+            .put(5, 8) // bsid
+            .put(3, 0) // bsmod
+            .put(3, 7) // acmod
+            .put(2, 0) // cmixlev
+            .put(2, 0) // surmixlev
+            .put(1, 1) // lfeon
+            .put(5, 27) // dialnorm
+            .finish(1792)
+    }
+
+    fn eac3_frame(stream_type: u64, acmod: u64, chanmap: Option<u16>) -> alloc::vec::Vec<u8> {
+        let mut w = BitWriter::new();
+        w.put(16, SYNC as u64)
+            .put(2, stream_type)
+            .put(3, 0) // substreamid
+            .put(11, 383) // frmsiz, 768 bytes
+            .put(2, 0) // fscod
+            .put(2, 3) // numblkscod
+            .put(3, acmod)
+            .put(1, 0) // lfeon
+            .put(5, 16) // bsid
+            .put(5, 31) // dialnorm
+            .put(1, 0); // compre
+        if let Some(chanmap) = chanmap {
+            w.put(1, 1).put(16, chanmap as u64);
+        }
+        w.finish(768)
+    }
+
+    #[test]
+    fn ac3_surround() {
+        let info = probe(&ac3_frame()).unwrap();
+        assert_eq!(info.channel_description(), "5.1");
+        assert_eq!(info.sample_rate, 48_000);
+        assert_eq!(info.bit_rate, 448_000);
+        assert_eq!(info.dial_norm, Some(-27));
+        assert!(info.core.is_none());
+    }
+
+    #[test]
+    fn dependent_substream_adds_channels() {
+        let mut data = ac3_frame();
+        data.extend(eac3_frame(1, 2, Some(1 << 9)));
+        let info = probe(&data).unwrap();
+        assert_eq!(info.channel_description(), "7.1");
+        assert_eq!(info.bit_rate, 448_000 + 768 * 8 * 48_000 / (6 * 256));
+        assert_eq!(info.core.unwrap().channel_description(), "5.1");
+    }
+
+    #[test]
+    fn eac3_independent() {
+        let info = probe(&eac3_frame(0, 2, None)).unwrap();
+        assert_eq!(info.channel_description(), "2.0");
+        assert_eq!(info.dial_norm, Some(-31));
+        assert!(!info.has_atmos);
+    }
+
+    #[test]
+    fn skips_garbage_and_rejects_truncated_frames() {
+        let mut data = vec![0x0B, 0x77, 0xFF, 0xFF, 0xFF, 0xFF];
+        data.extend(ac3_frame());
+        assert_eq!(probe(&data).unwrap().bit_rate, 448_000);
+
+        assert!(probe(&ac3_frame()[..5]).is_none());
+        assert!(probe(&[0; 64]).is_none());
+    }
+}
@@ -0,0 +1,130 @@
+/// MSB-first bit reader over a byte slice. Reads past the end yield zero
+/// bits and set `overrun`, so header parsers can check once at the end.
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    pub overrun: bool,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            pos: 0,
+            overrun: false,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        (self.data.len() * 8).saturating_sub(self.pos)
+    }
+
+    pub fn bit(&mut self) -> bool {
+        self.bits(1) == 1
+    }
+
+    pub fn bits(&mut self, count: u32) -> u32 {
+        let mut value = 0u32;
+        for _ in 0..count {
+            let byte = self.pos / 8;
+            let bit = match self.data.get(byte) {
+                Some(b) => (b >> (7 - self.pos % 8)) & 1,
+                None => {
+                    self.overrun = true;
+                    0
+                }
+            };
+            value = (value << 1) | u32::from(bit);
+            self.pos += 1;
+        }
+        value
+    }
+
+    pub fn skip(&mut self, count: usize) {
+        self.pos += count;
+        if self.pos > self.data.len() * 8 {
+            self.overrun = true;
+        }
+    }
+
+    /// Unsigned Exp-Golomb code, as used by H.264 and HEVC.
+    pub fn ue(&mut self) -> u32 {
+        let mut zeros = 0;
+        while !self.bit() {
+            zeros += 1;
+            if zeros > 31 || self.overrun {
+                self.overrun = true;
+                return 0;
+            }
+        }
+        if zeros == 0 {
+            0
+        } else {
+            ((1u64 << zeros) - 1 + u64::from(self.bits(zeros))) as u32
+        }
+    }
+
+    /// Signed Exp-Golomb code.
+    pub fn se(&mut self) -> i32 {
+        let value = self.ue();
+        if value % 2 == 1 {
+            value.div_ceil(2) as i32
+        } else {
+            -((value / 2) as i32)
+        }
+    }
+}
+
+/// Positions of a 4 byte big-endian sync word in `data`.
+pub fn find_sync(data: &[u8], sync: u32) -> impl Iterator<Item = usize> + '_ {
+    let pattern = sync.to_be_bytes();
+    data.windows(4)
+        .enumerate()
+        .filter(move |(_, w)| *w == pattern)
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_bits() {
+        let mut r = BitReader::new(&[0b1010_0000, 0xFF]);
+        assert!(r.bit());
+        assert_eq!(r.bits(3), 0b010);
+        r.skip(4);
+        assert_eq!(r.bits(8), 0xFF);
+        assert_eq!(r.remaining(), 0);
+        assert!(!r.overrun);
+        assert_eq!(r.bits(2), 0);
+        assert!(r.overrun);
+    }
+
+    #[test]
+    fn exp_golomb() {
+        // 1, 010, 011, 00100, 00101
+        let mut r = BitReader::new(&[0b1010_0110, 0b0100_0010, 0b1000_0000]);
+        assert_eq!(r.ue(), 0);
+        assert_eq!(r.ue(), 1);
+        assert_eq!(r.ue(), 2);
+        assert_eq!(r.se(), 2);
+        assert_eq!(r.se(), -2);
+        assert!(!r.overrun);
+
+        let mut r = BitReader::new(&[0; 8]);
+        assert_eq!(r.ue(), 0);
+        assert!(r.overrun);
+    }
+
+    #[test]
+    fn finds_sync_words() {
+        let data = [0, 0x7F, 0xFE, 0x80, 0x01, 0x7F, 0xFE, 0x80, 0x01];
+        assert_eq!(find_sync(&data, 0x7FFE_8001).collect::<Vec<_>>(), [1, 5]);
+        assert_eq!(find_sync(&data[..4], 0x7FFE_8001).count(), 0);
+    }
+}
@@ -0,0 +1,341 @@
+use super::{
+    bits::{find_sync, BitReader},
+    AudioInfo,
+};
+use alloc::{boxed::Box, vec::Vec};
+
+const CORE_SYNC: u32 = 0x7FFE_8001;
+const SUBSTREAM_SYNC: u32 = 0x6458_2025;
+
+// Extension sync words found inside the extension substream.
+const XLL_SYNC: u32 = 0x41A2_9547;
+const XBR_SYNC: u32 = 0x655E_315E;
+const LBR_SYNC: u32 = 0x0A80_1921;
+const X96_SYNC: u32 = 0x1D95_F262;
+const XXCH_SYNC: u32 = 0x4700_4A03;
+const XCH_SYNC: u32 = 0x5A5A_5A5A;
+/// Marks the object based (DTS:X) part of an XLL stream.
+const DTS_X_SYNC: u32 = 0x0200_0850;
+
+const CORE_CHANNELS: [u8; 16] = [1, 2, 2, 2, 2, 3, 3, 4, 4, 5, 6, 6, 6, 7, 8, 8];
+const CORE_SAMPLE_RATES: [u32; 16] = [
+    0, 8_000, 16_000, 32_000, 0, 0, 11_025, 22_050, 44_100, 0, 0, 12_000, 24_000, 48_000, 0, 0,
+];
+const CORE_BIT_RATES: [u32; 29] = [
+    32_000, 56_000, 64_000, 96_000, 112_000, 128_000, 192_000, 224_000, 256_000, 320_000, 384_000,
+    448_000, 512_000, 576_000, 640_000, 768_000, 960_000, 1_024_000, 1_152_000, 1_280_000,
+    1_344_000, 1_408_000, 1_411_200, 1_472_000, 1_536_000, 1_920_000, 2_048_000, 3_072_000,
+    3_840_000,
+];
+const CORE_BIT_DEPTHS: [u8; 8] = [16, 16, 20, 20, 0, 24, 24, 0];
+const SUBSTREAM_SAMPLE_RATES: [u32; 16] = [
+    8_000, 16_000, 32_000, 64_000, 128_000, 22_050, 44_100, 88_200, 176_400, 352_800, 12_000,
+    24_000, 48_000, 96_000, 192_000, 384_000,
+];
+
+// Speaker activity mask bits that carry a pair of channels.
+const SPEAKER_PAIRS: u32 = 0b1010_1110_0110_0110;
+const SPEAKER_LFE: u32 = (1 << 3) | (1 << 12);
+
+fn parse_core(data: &[u8]) -> Option<AudioInfo> {
+    let mut r = BitReader::new(data);
+    if r.bits(32) != CORE_SYNC {
+        return None;
+    }
+    r.skip(1 + 5); // FTYPE, SHORT
+    let crc_present = r.bit();
+    r.skip(7 + 14); // NBLKS, FSIZE
+    let amode = r.bits(6) as usize;
+    let sfreq = r.bits(4) as usize;
+    let rate = r.bits(5) as usize;
+    r.skip(1 + 1 + 1 + 1 + 1); // fixed bit, DYNF, TIMEF, AUXF, HDCD
+    let ext_audio_id = r.bits(3);
+    let ext_audio = r.bit();
+    r.skip(1); // ASPF
+    let lff = r.bits(2);
+    r.skip(1); // HFLAG
+    if crc_present {
+        r.skip(16);
+    }
+    r.skip(1); // FILTS
+    let version = r.bits(4);
+    r.skip(2); // CHIST
+    let pcmr = r.bits(3) as usize;
+    r.skip(1 + 1); // SUMF, SUMS
+    let dial_norm = r.bits(4) as i8;
+    if r.overrun {
+        return None;
+    }
+
+    let mut extensions = Vec::new();
+    if ext_audio {
+        match ext_audio_id {
+            0 => extensions.push("XCh"),
+            2 => extensions.push("X96"),
+            6 => extensions.push("XXCH"),
+            _ => {}
+        }
+    }
+    let mut channel_count = CORE_CHANNELS.get(amode).copied().unwrap_or(0);
+    if extensions.contains(&"XCh") {
+        channel_count += 1;
+    }
+
+    Some(AudioInfo {
+        channel_count,
+        lfe: lff == 1 || lff == 2,
+        sample_rate: CORE_SAMPLE_RATES[sfreq],
+        bit_depth: Some(CORE_BIT_DEPTHS[pcmr]).filter(|d| *d > 0),
+        bit_rate: CORE_BIT_RATES.get(rate).copied().unwrap_or(0),
+        dial_norm: match version {
+            7 => Some(-dial_norm),
+            6 => Some(-(16 + dial_norm)),
+            _ => None,
+        },
+        extensions,
+        ..Default::default()
+    })
+}
+
+/// Reads the first asset descriptor of an extension substream header.
+fn parse_substream(data: &[u8]) -> Option<AudioInfo> {
+    let mut r = BitReader::new(data);
+    if r.bits(32) != SUBSTREAM_SYNC {
+        return None;
+    }
+    r.skip(8); // UserDefinedBits
+    let substream_index = r.bits(2);
+    let (header_bits, size_bits): (usize, usize) = if r.bit() { (12, 20) } else { (8, 16) };
+    r.skip(header_bits); // nuExtSSHeaderSize
+    r.skip(size_bits); // nuExtSSFsize
+
+    let static_fields = r.bit();
+    let mut assets = 1;
+    if static_fields {
+        r.skip(2 + 3); // nuRefClockCode, nuExSSFrameDurationCode
+        if r.bit() {
+            r.skip(32 + 4); // nuTimeStamp, nLSB
+        }
+        let presentations = r.bits(3) + 1;
+        assets = r.bits(3) + 1;
+        let mut active_masks = Vec::new();
+        for _ in 0..presentations {
+            active_masks.push(r.bits(substream_index + 1));
+        }
+        for mask in active_masks {
+            for index in 0..=substream_index {
+                if (mask >> index) & 1 == 1 {
+                    r.skip(8); // nuActiveAssetMask
+                }
+            }
+        }
+        if r.bit() {
+            r.skip(2); // nuMixMetadataAdjLevel
+            let mask_bits = (r.bits(2) + 1) << 2;
+            let configs = r.bits(2) + 1;
+            r.skip((mask_bits * configs) as usize);
+        }
+    }
+    r.skip(size_bits * assets as usize); // nuAssetFsize
+
+    // Asset descriptor.
+    r.skip(9 + 3); // nuAssetDescriptFsize, nuAssetIndex
+    if !static_fields {
+        return None;
+    }
+    if r.bit() {
+        r.skip(4); // nuAssetTypeDescriptor
+    }
+    if r.bit() {
+        r.skip(24); // LanguageDescriptor
+    }
+    if r.bit() {
+        let text_bytes = r.bits(10) + 1;
+        r.skip(text_bytes as usize * 8);
+    }
+    let bit_depth = r.bits(5) as u8 + 1;
+    let sample_rate = SUBSTREAM_SAMPLE_RATES[r.bits(4) as usize];
+    let total_channels = r.bits(8) as u8 + 1;
+    let mut speaker_mask = None;
+    if r.bit() {
+        // bOne2OneMapChannels2Speakers
+        if total_channels > 2 {
+            r.skip(1); // bEmbeddedStereoFlag
+        }
+        if total_channels > 6 {
+            r.skip(1); // bEmbeddedSixChFlag
+        }
+        if r.bit() {
+            let mask_bits = (r.bits(2) + 1) << 2;
+            speaker_mask = Some(r.bits(mask_bits));
+        }
+    }
+    if r.overrun {
+        return None;
+    }
+
+    let (channel_count, lfe) = match speaker_mask {
+        Some(mask) => {
+            let lfe_count = (mask & SPEAKER_LFE).count_ones() as u8;
+            let full_range = mask & !SPEAKER_LFE;
+            let count = (full_range.count_ones() + (full_range & SPEAKER_PAIRS).count_ones()) as u8;
+            (
+                count.max(total_channels.saturating_sub(lfe_count)),
+                lfe_count > 0,
+            )
+        }
+        None => (total_channels, false),
+    };
+
+    Some(AudioInfo {
+        channel_count,
+        lfe,
+        sample_rate,
+        bit_depth: Some(bit_depth),
+        ..Default::default()
+    })
+}
+
+/// Probes DTS, DTS-HD High Resolution, DTS-HD Master Audio and DTS Express.
+/// The result describes the extension substream when there is one, with the
+/// core (if any) attached.
+pub fn probe(data: &[u8]) -> Option<AudioInfo> {
+    let core = find_sync(data, CORE_SYNC).find_map(|pos| parse_core(&data[pos..]));
+    let substream_pos = find_sync(data, SUBSTREAM_SYNC).collect::<Vec<_>>();
+    let substream = substream_pos
+        .iter()
+        .find_map(|&pos| parse_substream(&data[pos..]));
+
+    let Some(first) = substream_pos.first() else {
+        return core;
+    };
+    let extension_data = &data[*first..];
+    let mut extensions = Vec::new();
+    for (sync, name) in [
+        (XLL_SYNC, "XLL"),
+        (XBR_SYNC, "XBR"),
+        (LBR_SYNC, "LBR"),
+        (X96_SYNC, "X96"),
+        (XXCH_SYNC, "XXCH"),
+        (XCH_SYNC, "XCh"),
+    ] {
+        if find_sync(extension_data, sync).next().is_some() {
+            extensions.push(name);
+        }
+    }
+    let has_dts_x =
+        extensions.contains(&"XLL") && find_sync(extension_data, DTS_X_SYNC).next().is_some();
+
+    let mut info = match (substream, core.as_ref()) {
+        (Some(info), _) => info,
+        (None, Some(core)) => core.clone(),
+        (None, None) => return None,
+    };
+    info.extensions = extensions;
+    info.has_dts_x = has_dts_x;
+    // The extension substream is variable rate, the measured rate applies.
+    info.bit_rate = 0;
+    if let Some(core) = core {
+        info.dial_norm = core.dial_norm;
+        info.core = Some(Box::new(core));
+    }
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::BitWriter;
+
+    /// 768 kbps 48 kHz 24-bit DTS core, 3/2 with LFE.
+    fn core_frame() -> Vec<u8> {
+        BitWriter::new()
+            .put(32, CORE_SYNC as u64)
+            .put(1 + 5, 0) // FTYPE, SHORT
+            .put(1, 0) // CPF
+            .put(7 + 14, 0) // NBLKS, FSIZE
+            .put(6, 9) // AMODE
+            .put(4, 13) // SFREQ
+            .put(5, 15) // RATE
+            .put(5, 0)
+            .put(3, 0) // EXT_AUDIO_ID
+            .put(1, 0) // EXT_AUDIO
+            .put(1, 0) // ASPF
+            .put(2, 1) // LFF
+            .put(1, 0) // HFLAG
+            .put(1, 0) // FILTS
+            .put(4, 7) // VERNUM
+            .put(2, 0) // CHIST
+            .put(3, 5) // PCMR
+            .put(2, 0) // SUMF, SUMS
+            .put(4, 4) // DIALNORM
+            .finish(32)
+    }
+
+    /// Extension substream header with one 24-bit 48 kHz 7.1 asset.
+    fn substream_header() -> Vec<u8> {
+        BitWriter::new()
+            .put(32, SUBSTREAM_SYNC as u64)
+            .put(8, 0)
+            .put(2, 0) // nExtSSIndex
+            .put(1, 0) // bHeaderSizeType
+            .put(8, 0)
+            .put(16, 0)
+            .put(1, 1) // bStaticFieldsPresent
+            .put(2 + 3, 0)
+            .put(1, 0) // bTimeStampFlag
+            .put(3, 0) // nuNumAudioPresnt - 1
+            .put(3, 0) // nuNumAssets - 1
+            .put(1, 1) // nuActiveExSSMask
+            .put(8, 1) // nuActiveAssetMask
+            .put(1, 0) // bMixMetadataEnbl
+            .put(16, 0) // nuAssetFsize
+            .put(9 + 3, 0)
+            .put(1, 0) // bAssetTypeDescrPresent
+            .put(1, 0) // bLanguageDescrPresent
+            .put(1, 0) // bInfoTextPresent
+            .put(5, 23) // nuBitResolution - 1
+            .put(4, 12) // nuMaxSampleRate
+            .put(8, 7) // nuTotalNumChs - 1
+            .put(1, 1) // bOne2OneMapChannels2Speakers
+            .put(1, 0) // bEmbeddedStereoFlag
+            .put(1, 0) // bEmbeddedSixChFlag
+            .put(1, 1) // bSpkrMaskEnabled
+            .put(2, 3) // nuNumBits4SAMask
+            .put(16, 0x4F) // nuSpkrActivityMask
+            .finish(48)
+    }
+
+    #[test]
+    fn core_only() {
+        let info = probe(&core_frame()).unwrap();
+        assert_eq!(info.channel_description(), "5.1");
+        assert_eq!(info.sample_rate, 48_000);
+        assert_eq!(info.bit_rate, 768_000);
+        assert_eq!(info.bit_depth, Some(24));
+        assert_eq!(info.dial_norm, Some(-4));
+        assert!(info.core.is_none());
+    }
+
+    #[test]
+    fn master_audio_with_core() {
+        let mut data = core_frame();
+        data.extend(substream_header());
+        data.extend(XLL_SYNC.to_be_bytes());
+        let info = probe(&data).unwrap();
+        assert_eq!(info.channel_description(), "7.1");
+        assert_eq!(info.bit_depth, Some(24));
+        assert_eq!(info.bit_rate, 0);
+        assert_eq!(info.extensions, ["XLL"]);
+        assert!(!info.has_dts_x);
+        assert_eq!(info.dial_norm, Some(-4));
+        assert_eq!(info.core.unwrap().channel_description(), "5.1");
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        assert!(probe(&core_frame()[..8]).is_none());
+        assert!(probe(&substream_header()[..12]).is_none());
+        assert!(probe(&[0xFF; 64]).is_none());
+    }
+}
@@ -0,0 +1,227 @@
+use super::{bits::BitReader, nal, VideoInfo};
+use alloc::{borrow::ToOwned, format, string::String};
+
+const NAL_SEI: u8 = 6;
+const NAL_SPS: u8 = 7;
+
+fn profile_name(profile_idc: u32) -> String {
+    match profile_idc {
+        66 => "Baseline".to_owned(),
+        77 => "Main".to_owned(),
+        88 => "Extended".to_owned(),
+        100 => "High".to_owned(),
+        110 => "High 10".to_owned(),
+        122 => "High 4:2:2".to_owned(),
+        244 => "High 4:4:4 Predictive".to_owned(),
+        118 => "Multiview High".to_owned(),
+        128 => "Stereo High".to_owned(),
+        other => format!("Profile {}", other),
+    }
+}
+
+fn skip_scaling_list(r: &mut BitReader, size: usize) {
+    let mut last = 8;
+    let mut next = 8;
+    for _ in 0..size {
+        if next != 0 {
+            next = (last + r.se() + 256) % 256;
+        }
+        if next != 0 {
+            last = next;
+        }
+    }
+}
+
+fn parse_sps(rbsp: &[u8], info: &mut VideoInfo) -> Option<()> {
+    let mut r = BitReader::new(rbsp);
+    let profile_idc = r.bits(8);
+    r.skip(8); // constraint_set flags
+    let level_idc = r.bits(8);
+    r.ue(); // seq_parameter_set_id
+
+    let mut chroma_format_idc = 1;
+    let mut bit_depth = 8;
+    if matches!(
+        profile_idc,
+        100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+    ) {
+        chroma_format_idc = r.ue();
+        if chroma_format_idc == 3 {
+            r.skip(1); // separate_colour_plane_flag
+        }
+        bit_depth = r.ue() + 8;
+        r.ue(); // bit_depth_chroma_minus8
+        r.skip(1); // qpprime_y_zero_transform_bypass_flag
+        if r.bit() {
+            let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+            for i in 0..lists {
+                if r.bit() {
+                    skip_scaling_list(&mut r, if i < 6 { 16 } else { 64 });
+                }
+            }
+        }
+    }
+    r.ue(); // log2_max_frame_num_minus4
+    match r.ue() {
+        0 => {
+            r.ue(); // log2_max_pic_order_cnt_lsb_minus4
+        }
+        1 => {
+            r.skip(1); // delta_pic_order_always_zero_flag
+            r.se();
+            r.se();
+            for _ in 0..r.ue() {
+                r.se();
+            }
+        }
+        _ => {}
+    }
+    r.ue(); // max_num_ref_frames
+    r.skip(1); // gaps_in_frame_num_value_allowed_flag
+    let width_mbs = r.ue() + 1;
+    let height_map_units = r.ue() + 1;
+    let frame_mbs_only = r.bit();
+    if !frame_mbs_only {
+        r.skip(1); // mb_adaptive_frame_field_flag
+    }
+    r.skip(1); // direct_8x8_inference_flag
+
+    let (crop_x, crop_y) = match chroma_format_idc {
+        1 => (2, 2),
+        2 => (2, 1),
+        _ => (1, 1),
+    };
+    let crop_y = crop_y * if frame_mbs_only { 1 } else { 2 };
+    let mut width = width_mbs * 16;
+    let mut height = height_map_units * 16 * if frame_mbs_only { 1 } else { 2 };
+    if r.bit() {
+        let (left, right, top, bottom) = (r.ue(), r.ue(), r.ue(), r.ue());
+        width = width.saturating_sub((left + right) * crop_x);
+        height = height.saturating_sub((top + bottom) * crop_y);
+    }
+
+    if r.bit() {
+        // VUI
+        if r.bit() && r.bits(8) == 255 {
+            r.skip(32); // sar_width, sar_height
+        }
+        if r.bit() {
+            r.skip(1); // overscan_appropriate_flag
+        }
+        if r.bit() {
+            r.skip(3 + 1); // video_format, video_full_range_flag
+            if r.bit() {
+                info.colour_primaries = Some(r.bits(8) as u8);
+                info.transfer_characteristics = Some(r.bits(8) as u8);
+                info.matrix_coefficients = Some(r.bits(8) as u8);
+            }
+        }
+        if r.bit() {
+            r.ue(); // chroma_sample_loc_type_top_field
+            r.ue();
+        }
+        if r.bit() {
+            let num_units_in_tick = r.bits(32);
+            let time_scale = r.bits(32);
+            if num_units_in_tick > 0 {
+                info.frame_rate = Some(time_scale as f64 / (2.0 * num_units_in_tick as f64));
+            }
+        }
+    }
+    if r.overrun {
+        return None;
+    }
+
+    info.profile = profile_name(profile_idc);
+    info.level = format!("{}", level_idc as f64 / 10.0);
+    info.width = width;
+    info.height = height;
+    info.interlaced = !frame_mbs_only;
+    info.chroma_format = super::chroma_format(chroma_format_idc);
+    info.bit_depth = Some(bit_depth as u8);
+    Some(())
+}
+
+/// Probes an H.264 (AVC) stream for its sequence parameter set and HDR
+/// SEI messages.
+pub fn probe(data: &[u8]) -> Option<VideoInfo> {
+    let mut info = VideoInfo::default();
+    let mut found = false;
+    for unit in nal::units(data) {
+        let Some(header) = unit.first() else {
+            continue;
+        };
+        match header & 0x1F {
+            NAL_SPS if !found => {
+                found = parse_sps(&nal::unescape(&unit[1..]), &mut info).is_some();
+            }
+            NAL_SEI => nal::read_sei(&nal::unescape(&unit[1..]), &mut info),
+            _ => {}
+        }
+    }
+    found.then_some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{nal_unit, BitWriter};
+
+    fn sps() -> alloc::vec::Vec<u8> {
+        let mut w = BitWriter::new();
+        w.put(8, 100) // profile_idc
+            .put(8, 0)
+            .put(8, 41) // level_idc
+            .ue(0)
+            .ue(1) // chroma_format_idc
+            .ue(0)
+            .ue(0)
+            .put(1, 0)
+            .put(1, 0) // seq_scaling_matrix_present_flag
+            .ue(0)
+            .ue(0) // pic_order_cnt_type
+            .ue(0)
+            .ue(4)
+            .put(1, 0)
+            .ue(119) // pic_width_in_mbs_minus1
+            .ue(67) // pic_height_in_map_units_minus1
+            .put(1, 1) // frame_mbs_only_flag
+            .put(1, 1)
+            .put(1, 1) // frame_cropping_flag
+            .ue(0)
+            .ue(0)
+            .ue(0)
+            .ue(4)
+            .put(1, 1) // vui_parameters_present_flag
+            .put(1, 0)
+            .put(1, 0)
+            .put(1, 1) // video_signal_type_present_flag
+            .put(4, 0)
+            .put(1, 1)
+            .put(8, 1)
+            .put(8, 1)
+            .put(8, 1)
+            .put(1, 0)
+            .put(1, 1) // timing_info_present_flag
+            .put(32, 1001)
+            .put(32, 48_000)
+            .put(1, 1);
+        nal_unit(&[0x67], &w.finish(0))
+    }
+
+    #[test]
+    fn high_profile_sps() {
+        let info = probe(&sps()).unwrap();
+        assert_eq!(
+            info.description(),
+            "1920x1080p / 23.976 fps / High @ Level 4.1 / 4:2:0 / 8 bits / BT.709"
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_sps() {
+        let sps = sps();
+        assert!(probe(&sps[..12]).is_none());
+        assert!(probe(&[0, 0, 1, 0x65, 0x88]).is_none());
+    }
+}
@@ -0,0 +1,315 @@
+use super::{bits::BitReader, nal, VideoInfo};
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+
+const NAL_SPS: u8 = 33;
+const NAL_PREFIX_SEI: u8 = 39;
+const NAL_SUFFIX_SEI: u8 = 40;
+/// Dolby Vision uses the unspecified NAL types for its RPU and for an
+/// enhancement layer muxed into the base layer stream.
+const NAL_DOLBY_VISION_RPU: u8 = 62;
+const NAL_DOLBY_VISION_EL: u8 = 63;
+
+fn profile_name(profile_idc: u32) -> String {
+    match profile_idc {
+        1 => "Main".to_owned(),
+        2 => "Main 10".to_owned(),
+        3 => "Main Still Picture".to_owned(),
+        4 => "Range Extensions".to_owned(),
+        other => format!("Profile {}", other),
+    }
+}
+
+fn skip_scaling_list_data(r: &mut BitReader) {
+    for size_id in 0..4 {
+        let step = if size_id == 3 { 3 } else { 1 };
+        for _ in (0..6).step_by(step) {
+            if !r.bit() {
+                r.ue(); // scaling_list_pred_matrix_id_delta
+                continue;
+            }
+            let coefficients = 64.min(1 << (4 + (size_id << 1)));
+            if size_id > 1 {
+                r.se(); // scaling_list_dc_coef_minus8
+            }
+            for _ in 0..coefficients {
+                r.se();
+            }
+        }
+    }
+}
+
+/// Skips the short term reference picture sets, which have to be walked to
+/// reach the VUI. Returns `None` on malformed input.
+fn skip_short_term_ref_pic_sets(r: &mut BitReader, count: u32) -> Option<()> {
+    let mut delta_pocs: Vec<u32> = Vec::with_capacity(count as usize);
+    for index in 0..count as usize {
+        if index != 0 && r.bit() {
+            // inter_ref_pic_set_prediction_flag
+            r.skip(1); // delta_rps_sign
+            r.ue(); // abs_delta_rps_minus1
+            let reference = *delta_pocs.get(index - 1)?;
+            let mut pocs = 0;
+            for _ in 0..=reference {
+                let used = r.bit();
+                if used || r.bit() {
+                    pocs += 1;
+                }
+            }
+            delta_pocs.push(pocs);
+        } else {
+            let negative = r.ue();
+            let positive = r.ue();
+            if negative > 16 || positive > 16 {
+                return None;
+            }
+            for _ in 0..negative + positive {
+                r.ue(); // delta_poc_minus1
+                r.skip(1); // used_by_curr_pic_flag
+            }
+            delta_pocs.push(negative + positive);
+        }
+        if r.overrun {
+            return None;
+        }
+    }
+    Some(())
+}
+
+fn parse_sps(rbsp: &[u8], info: &mut VideoInfo) -> Option<()> {
+    let mut r = BitReader::new(rbsp);
+    r.skip(4); // sps_video_parameter_set_id
+    let max_sub_layers = r.bits(3);
+    r.skip(1); // sps_temporal_id_nesting_flag
+
+    // profile_tier_level
+    r.skip(2); // general_profile_space
+    let high_tier = r.bit();
+    let profile_idc = r.bits(5);
+    r.skip(32 + 48); // compatibility and constraint flags
+    let level_idc = r.bits(8);
+    let mut sub_layers = Vec::new();
+    for _ in 0..max_sub_layers {
+        sub_layers.push((r.bit(), r.bit()));
+    }
+    if max_sub_layers > 0 {
+        r.skip(2 * (8 - max_sub_layers as usize));
+    }
+    for (profile_present, level_present) in sub_layers {
+        if profile_present {
+            r.skip(88);
+        }
+        if level_present {
+            r.skip(8);
+        }
+    }
+
+    r.ue(); // sps_seq_parameter_set_id
+    let chroma_format_idc = r.ue();
+    if chroma_format_idc == 3 {
+        r.skip(1); // separate_colour_plane_flag
+    }
+    let mut width = r.ue();
+    let mut height = r.ue();
+    if r.bit() {
+        let (sub_width, sub_height) = match chroma_format_idc {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        };
+        let (left, right, top, bottom) = (r.ue(), r.ue(), r.ue(), r.ue());
+        width = width.saturating_sub((left + right) * sub_width);
+        height = height.saturating_sub((top + bottom) * sub_height);
+    }
+    let bit_depth = r.ue() + 8;
+    r.ue(); // bit_depth_chroma_minus8
+    let log2_max_poc_lsb = r.ue() + 4;
+    let ordering_info_present = r.bit();
+    let first = if ordering_info_present {
+        0
+    } else {
+        max_sub_layers
+    };
+    for _ in first..=max_sub_layers {
+        r.ue(); // sps_max_dec_pic_buffering_minus1
+        r.ue(); // sps_max_num_reorder_pics
+        r.ue(); // sps_max_latency_increase_plus1
+    }
+    for _ in 0..6 {
+        r.ue(); // coding and transform block sizes, hierarchy depths
+    }
+    if r.bit() && r.bit() {
+        // scaling_list_enabled_flag, sps_scaling_list_data_present_flag
+        skip_scaling_list_data(&mut r);
+    }
+    r.skip(1 + 1); // amp_enabled_flag, sample_adaptive_offset_enabled_flag
+    if r.bit() {
+        // pcm_enabled_flag
+        r.skip(4 + 4);
+        r.ue();
+        r.ue();
+        r.skip(1);
+    }
+    let short_term_sets = r.ue();
+    skip_short_term_ref_pic_sets(&mut r, short_term_sets)?;
+    if r.bit() {
+        for _ in 0..r.ue() {
+            r.skip(log2_max_poc_lsb as usize + 1); // lt_ref_pic_poc_lsb_sps, used flag
+        }
+    }
+    r.skip(1 + 1); // sps_temporal_mvp_enabled_flag, strong_intra_smoothing_enabled_flag
+
+    let mut field_seq = false;
+    if r.bit() {
+        // VUI
+        if r.bit() && r.bits(8) == 255 {
+            r.skip(32); // sar_width, sar_height
+        }
+        if r.bit() {
+            r.skip(1); // overscan_appropriate_flag
+        }
+        if r.bit() {
+            r.skip(3 + 1); // video_format, video_full_range_flag
+            if r.bit() {
+                info.colour_primaries = Some(r.bits(8) as u8);
+                info.transfer_characteristics = Some(r.bits(8) as u8);
+                info.matrix_coefficients = Some(r.bits(8) as u8);
+            }
+        }
+        if r.bit() {
+            r.ue(); // chroma_sample_loc_type_top_field
+            r.ue();
+        }
+        r.skip(1); // neutral_chroma_indication_flag
+        field_seq = r.bit();
+        r.skip(1); // frame_field_info_present_flag
+        if r.bit() {
+            for _ in 0..4 {
+                r.ue(); // default display window offsets
+            }
+        }
+        if r.bit() {
+            let num_units_in_tick = r.bits(32);
+            let time_scale = r.bits(32);
+            if num_units_in_tick > 0 {
+                info.frame_rate = Some(time_scale as f64 / num_units_in_tick as f64);
+            }
+        }
+    }
+    if r.overrun {
+        return None;
+    }
+
+    info.profile = profile_name(profile_idc);
+    info.level = format!("{}", level_idc as f64 / 30.0);
+    info.tier = Some(if high_tier { "High" } else { "Main" });
+    info.width = width;
+    info.height = if field_seq { height * 2 } else { height };
+    info.interlaced = field_seq;
+    info.chroma_format = super::chroma_format(chroma_format_idc);
+    info.bit_depth = Some(bit_depth as u8);
+    Some(())
+}
+
+/// Probes an HEVC stream for its sequence parameter set, HDR SEI messages
+/// and Dolby Vision NAL units.
+pub fn probe(data: &[u8]) -> Option<VideoInfo> {
+    let mut info = VideoInfo::default();
+    let mut found = false;
+    for unit in nal::units(data) {
+        if unit.len() < 2 {
+            continue;
+        }
+        match (unit[0] >> 1) & 0x3F {
+            NAL_SPS if !found => {
+                found = parse_sps(&nal::unescape(&unit[2..]), &mut info).is_some();
+            }
+            NAL_PREFIX_SEI | NAL_SUFFIX_SEI => nal::read_sei(&nal::unescape(&unit[2..]), &mut info),
+            NAL_DOLBY_VISION_RPU => info.dolby_vision_rpu = true,
+            NAL_DOLBY_VISION_EL => info.dolby_vision_el = true,
+            _ => {}
+        }
+    }
+    found.then_some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{nal_unit, BitWriter};
+
+    fn sps() -> Vec<u8> {
+        let mut w = BitWriter::new();
+        w.put(4, 0)
+            .put(3, 0) // sps_max_sub_layers_minus1
+            .put(1, 1)
+            .put(2, 0)
+            .put(1, 0) // general_tier_flag
+            .put(5, 2) // general_profile_idc
+            .put(32, 0x2000_0000)
+            .put(48, 0x9000_0000_0000)
+            .put(8, 153) // general_level_idc
+            .ue(0)
+            .ue(1) // chroma_format_idc
+            .ue(3840)
+            .ue(2160)
+            .put(1, 0) // conformance_window_flag
+            .ue(2) // bit_depth_luma_minus8
+            .ue(2)
+            .ue(4)
+            .put(1, 1) // sps_sub_layer_ordering_info_present_flag
+            .ue(4)
+            .ue(2)
+            .ue(0);
+        for _ in 0..6 {
+            w.ue(1);
+        }
+        w.put(1, 0) // scaling_list_enabled_flag
+            .put(2, 0)
+            .put(1, 0) // pcm_enabled_flag
+            .ue(0) // num_short_term_ref_pic_sets
+            .put(1, 0) // long_term_ref_pics_present_flag
+            .put(2, 0)
+            .put(1, 1) // vui_parameters_present_flag
+            .put(1, 0)
+            .put(1, 0)
+            .put(1, 1) // video_signal_type_present_flag
+            .put(4, 0)
+            .put(1, 1)
+            .put(8, 9)
+            .put(8, 16)
+            .put(8, 9)
+            .put(1, 0)
+            .put(1, 0)
+            .put(1, 0) // field_seq_flag
+            .put(1, 0)
+            .put(1, 0)
+            .put(1, 1) // vui_timing_info_present_flag
+            .put(32, 1001)
+            .put(32, 24_000)
+            .put(1, 1);
+        nal_unit(&[NAL_SPS << 1, 1], &w.finish(0))
+    }
+
+    #[test]
+    fn main10_sps_with_dolby_vision() {
+        let mut data = sps();
+        data.extend(nal_unit(
+            &[NAL_PREFIX_SEI << 1, 1],
+            &[144, 4, 0x03, 0xE8, 0x01, 0x90, 0x80],
+        ));
+        data.extend(nal_unit(&[NAL_DOLBY_VISION_RPU << 1, 1], &[0x19, 0x08]));
+        let info = probe(&data).unwrap();
+        assert_eq!(
+            info.description(),
+            "3840x2160p / 23.976 fps / Main 10 @ Level 5.1 @ Main / 4:2:0 / 10 bits / \
+             Dolby Vision / HDR10 / BT.2020 / MaxCLL: 1000 nits / MaxFALL: 400 nits"
+        );
+        assert!(!info.dolby_vision_el);
+    }
+
+    #[test]
+    fn rejects_truncated_sps() {
+        assert!(probe(&sps()[..20]).is_none());
+        assert!(probe(&nal_unit(&[NAL_DOLBY_VISION_EL << 1, 1], &[1])).is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use super::AudioInfo;
+
+/// Probes the 4 byte header that starts every Blu-ray LPCM PES payload.
+pub fn probe(data: &[u8]) -> Option<AudioInfo> {
+    let header = data.get(0..4)?;
+    let (channel_count, lfe) = match header[2] >> 4 {
+        1 => (1, false),
+        3 => (2, false),
+        4 | 5 => (3, false),
+        6 | 7 => (4, false),
+        8 => (5, false),
+        9 => (5, true),
+        10 => (7, false),
+        11 => (7, true),
+        _ => return None,
+    };
+    let sample_rate = match header[2] & 0xF {
+        1 => 48_000,
+        4 => 96_000,
+        5 => 192_000,
+        _ => return None,
+    };
+    let bit_depth = match header[3] >> 6 {
+        1 => 16,
+        2 => 20,
+        3 => 24,
+        _ => return None,
+    };
+    let channels = u32::from(channel_count) + u32::from(lfe);
+
+    Some(AudioInfo {
+        channel_count,
+        lfe,
+        sample_rate,
+        bit_depth: Some(bit_depth),
+        bit_rate: channels * sample_rate * u32::from(bit_depth),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stereo_header() {
+        let info = probe(&[0x03, 0xC0, 0x31, 0x40]).unwrap();
+        assert_eq!(info.channel_description(), "2.0");
+        assert_eq!(info.sample_rate, 48_000);
+        assert_eq!(info.bit_depth, Some(16));
+        assert_eq!(info.bit_rate, 1_536_000);
+    }
+
+    #[test]
+    fn surround_header() {
+        let info = probe(&[0x0B, 0x40, 0xB4, 0xC0]).unwrap();
+        assert_eq!(info.channel_description(), "7.1");
+        assert_eq!(info.sample_rate, 96_000);
+        assert_eq!(info.bit_depth, Some(24));
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(probe(&[0x03, 0xC0, 0x31]).is_none());
+        assert!(probe(&[0x03, 0xC0, 0x21, 0x40]).is_none());
+        assert!(probe(&[0x03, 0xC0, 0x32, 0x40]).is_none());
+        assert!(probe(&[0x03, 0xC0, 0x31, 0x00]).is_none());
+    }
+}
@@ -0,0 +1,297 @@
+use super::stream_type::StreamType;
+use alloc::{borrow::ToOwned, boxed::Box, format, string::String, vec, vec::Vec};
+
+pub mod ac3;
+pub mod bits;
+pub mod dts;
+pub mod h264;
+pub mod hevc;
+pub mod lpcm;
+pub mod mpeg2;
+pub mod nal;
+pub mod truehd;
+pub mod vc1;
+
+/// Audio properties read from the elementary stream itself, as opposed to
+/// the coarse values declared in the playlist's STN table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioInfo {
+    /// Full range channels, not counting LFE.
+    pub channel_count: u8,
+    pub lfe: bool,
+    pub sample_rate: u32,
+    pub bit_depth: Option<u8>,
+    /// Nominal bit rate in bits per second, 0 when variable or unknown.
+    pub bit_rate: u32,
+    /// Dialogue normalisation in dB (a negative value).
+    pub dial_norm: Option<i8>,
+    pub has_atmos: bool,
+    pub has_dts_x: bool,
+    /// Extensions on top of the core, e.g. `XLL` or `XCh` for DTS.
+    pub extensions: Vec<&'static str>,
+    /// Backwards compatible core (AC-3 in TrueHD, DTS core in DTS-HD).
+    pub core: Option<Box<AudioInfo>>,
+}
+
+impl AudioInfo {
+    /// Channel layout as BDInfo prints it, e.g. `5.1` or `2.0`.
+    pub fn channel_description(&self) -> String {
+        format!("{}.{}", self.channel_count, u8::from(self.lfe))
+    }
+
+    pub fn description(&self) -> String {
+        let mut parts = vec![self.channel_description()];
+        if self.sample_rate > 0 {
+            parts.push(format!("{} kHz", self.sample_rate as f64 / 1000.0));
+        }
+        if self.bit_rate > 0 {
+            parts.push(format!("{} kbps", self.bit_rate / 1000));
+        }
+        if let Some(bit_depth) = self.bit_depth {
+            parts.push(format!("{}-bit", bit_depth));
+        }
+        if let Some(dial_norm) = self.dial_norm {
+            parts.push(format!("DN {}dB", dial_norm));
+        }
+        if self.has_atmos {
+            parts.push("Dolby Atmos".to_owned());
+        }
+        if self.has_dts_x {
+            parts.push("DTS:X".to_owned());
+        }
+        let mut description = parts.join(" / ");
+        if let Some(core) = self.core.as_ref() {
+            description.push_str(&format!(" (Core: {})", core.description()));
+        }
+        description
+    }
+}
+
+/// SMPTE ST 2086 mastering display colour volume. Chromaticities are in
+/// 0.00002 units (G, B, R order), luminance in 0.0001 cd/m².
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MasteringDisplay {
+    pub primaries: [(u16, u16); 3],
+    pub white_point: (u16, u16),
+    pub max_luminance: u32,
+    pub min_luminance: u32,
+}
+
+impl MasteringDisplay {
+    /// Name of the colour space the primaries match, if any.
+    pub fn colour_space(&self) -> Option<&'static str> {
+        match self.primaries[0] {
+            (8500, 39850) => Some("BT.2020"),
+            (13250, 34500) => Some("Display P3"),
+            (15000, 30000) => Some("BT.709"),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        format!(
+            "{}, {:.4}-{} cd/m2",
+            self.colour_space().unwrap_or("Custom"),
+            self.min_luminance as f64 / 10_000.0,
+            self.max_luminance / 10_000
+        )
+    }
+}
+
+/// CTA-861.3 content light level, both in cd/m².
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ContentLightLevel {
+    pub max_cll: u16,
+    pub max_fall: u16,
+}
+
+/// Video properties read from the sequence headers and SEI messages of the
+/// elementary stream.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VideoInfo {
+    pub profile: String,
+    pub level: String,
+    /// HEVC only.
+    pub tier: Option<&'static str>,
+    pub width: u32,
+    pub height: u32,
+    pub interlaced: bool,
+    pub frame_rate: Option<f64>,
+    pub chroma_format: Option<&'static str>,
+    pub bit_depth: Option<u8>,
+    /// ISO/IEC 23091-2 code points.
+    pub colour_primaries: Option<u8>,
+    pub transfer_characteristics: Option<u8>,
+    pub matrix_coefficients: Option<u8>,
+    pub mastering_display: Option<MasteringDisplay>,
+    pub content_light: Option<ContentLightLevel>,
+    pub hdr10_plus: bool,
+    pub dolby_vision_rpu: bool,
+    /// Enhancement layer, either muxed in the same PID or in its own PID.
+    pub dolby_vision_el: bool,
+}
+
+impl VideoInfo {
+    /// HDR formats present, e.g. `Dolby Vision / HDR10`.
+    pub fn hdr_format(&self) -> Option<String> {
+        let mut formats = Vec::new();
+        if self.dolby_vision_rpu {
+            formats.push(if self.dolby_vision_el {
+                "Dolby Vision FEL/MEL"
+            } else {
+                "Dolby Vision"
+            });
+        }
+        if self.hdr10_plus {
+            formats.push("HDR10+");
+        }
+        match self.transfer_characteristics {
+            Some(16) => formats.push("HDR10"),
+            Some(18) => formats.push("HLG"),
+            _ => {}
+        }
+        (!formats.is_empty()).then(|| formats.join(" / "))
+    }
+
+    pub fn description(&self) -> String {
+        let mut parts = vec![format!(
+            "{}x{}{}",
+            self.width,
+            self.height,
+            if self.interlaced { "i" } else { "p" }
+        )];
+        if let Some(frame_rate) = self.frame_rate {
+            parts.push(format!("{:.3} fps", frame_rate));
+        }
+        let mut profile = format!("{} @ Level {}", self.profile, self.level);
+        if let Some(tier) = self.tier {
+            profile.push_str(&format!(" @ {}", tier));
+        }
+        parts.push(profile);
+        if let Some(chroma_format) = self.chroma_format {
+            parts.push(chroma_format.to_owned());
+        }
+        if let Some(bit_depth) = self.bit_depth {
+            parts.push(format!("{} bits", bit_depth));
+        }
+        if let Some(hdr) = self.hdr_format() {
+            parts.push(hdr);
+        }
+        if let Some(primaries) = self.colour_primaries.and_then(colour_primaries_name) {
+            parts.push(primaries.to_owned());
+        }
+        if let Some(display) = self.mastering_display.as_ref() {
+            parts.push(format!("Mastering display: {}", display.description()));
+        }
+        if let Some(light) = self.content_light.as_ref() {
+            parts.push(format!("MaxCLL: {} nits", light.max_cll));
+            parts.push(format!("MaxFALL: {} nits", light.max_fall));
+        }
+        parts.join(" / ")
+    }
+}
+
+pub fn chroma_format(idc: u32) -> Option<&'static str> {
+    match idc {
+        0 => Some("4:0:0"),
+        1 => Some("4:2:0"),
+        2 => Some("4:2:2"),
+        3 => Some("4:4:4"),
+        _ => None,
+    }
+}
+
+pub fn colour_primaries_name(code: u8) -> Option<&'static str> {
+    match code {
+        1 => Some("BT.709"),
+        5 => Some("BT.601 PAL"),
+        6 => Some("BT.601 NTSC"),
+        9 => Some("BT.2020"),
+        12 => Some("Display P3"),
+        _ => None,
+    }
+}
+
+/// What probing an elementary stream found, by stream kind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamInfo {
+    Audio(AudioInfo),
+    Video(VideoInfo),
+}
+
+/// Probes the start of an elementary stream of the given type.
+pub fn probe(stream_type: StreamType, data: &[u8]) -> Option<StreamInfo> {
+    if let Some(video) = probe_video(stream_type, data) {
+        return Some(StreamInfo::Video(video));
+    }
+    probe_audio(stream_type, data).map(StreamInfo::Audio)
+}
+
+/// Probes the start of a video elementary stream. `data` is the PES
+/// payload with the PES headers removed.
+pub fn probe_video(stream_type: StreamType, data: &[u8]) -> Option<VideoInfo> {
+    match stream_type {
+        StreamType::MPEG1_VIDEO | StreamType::MPEG2_VIDEO => mpeg2::probe(data),
+        StreamType::AVC_VIDEO | StreamType::MVC_VIDEO => h264::probe(data),
+        StreamType::HEVC_VIDEO => hevc::probe(data),
+        StreamType::VC1_VIDEO => vc1::probe(data),
+        _ => None,
+    }
+}
+
+/// Probes the start of an audio elementary stream. `data` is the PES
+/// payload with the PES headers removed.
+pub fn probe_audio(stream_type: StreamType, data: &[u8]) -> Option<AudioInfo> {
+    match stream_type {
+        StreamType::AC3_AUDIO
+        | StreamType::AC3_PLUS_AUDIO
+        | StreamType::AC3_PLUS_SECONDARY_AUDIO => ac3::probe(data),
+        StreamType::AC3_TRUE_HD_AUDIO => truehd::probe(data),
+        StreamType::DTS_AUDIO
+        | StreamType::DTS_HD_AUDIO
+        | StreamType::DTS_HD_MASTER_AUDIO
+        | StreamType::DTS_HD_SECONDARY_AUDIO => dts::probe(data),
+        StreamType::LPCM_AUDIO => lpcm::probe(data),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audio_description() {
+        let info = AudioInfo {
+            channel_count: 7,
+            lfe: true,
+            sample_rate: 48_000,
+            bit_depth: Some(24),
+            has_atmos: true,
+            core: Some(Box::new(AudioInfo {
+                channel_count: 5,
+                lfe: true,
+                sample_rate: 48_000,
+                bit_rate: 640_000,
+                dial_norm: Some(-27),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        assert_eq!(
+            info.description(),
+            "7.1 / 48 kHz / 24-bit / Dolby Atmos (Core: 5.1 / 48 kHz / 640 kbps / DN -27dB)"
+        );
+    }
+
+    #[test]
+    fn probe_dispatches_on_stream_type() {
+        let lpcm = [0x03, 0xC0, 0x31, 0x40];
+        assert!(matches!(
+            probe(StreamType::LPCM_AUDIO, &lpcm),
+            Some(StreamInfo::Audio(_))
+        ));
+        assert!(probe(StreamType::AC3_AUDIO, &lpcm).is_none());
+        assert!(probe(StreamType::PRESENTATION_GRAPHICS, &lpcm).is_none());
+    }
+}
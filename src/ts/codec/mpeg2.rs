@@ -0,0 +1,169 @@
+use super::{bits::BitReader, VideoInfo};
+use alloc::borrow::ToOwned;
+
+const SEQUENCE_HEADER: [u8; 4] = [0x00, 0x00, 0x01, 0xB3];
+const EXTENSION: [u8; 4] = [0x00, 0x00, 0x01, 0xB5];
+const PICTURE: [u8; 4] = [0x00, 0x00, 0x01, 0x00];
+const SEQUENCE_EXTENSION: u32 = 1;
+const SEQUENCE_DISPLAY_EXTENSION: u32 = 2;
+
+const FRAME_RATES: [f64; 9] = [
+    0.0,
+    24_000.0 / 1001.0,
+    24.0,
+    25.0,
+    30_000.0 / 1001.0,
+    30.0,
+    50.0,
+    60_000.0 / 1001.0,
+    60.0,
+];
+
+fn profile_name(profile: u32) -> &'static str {
+    match profile {
+        1 => "High",
+        2 => "Spatially Scalable",
+        3 => "SNR Scalable",
+        4 => "Main",
+        5 => "Simple",
+        _ => "Unknown",
+    }
+}
+
+fn level_name(level: u32) -> &'static str {
+    match level {
+        4 => "High",
+        6 => "High 1440",
+        8 => "Main",
+        10 => "Low",
+        _ => "Unknown",
+    }
+}
+
+/// Probes an MPEG-1 or MPEG-2 video stream from its sequence header and,
+/// for MPEG-2, the sequence and sequence display extensions.
+pub fn probe(data: &[u8]) -> Option<VideoInfo> {
+    let pos = data.windows(4).position(|w| w == SEQUENCE_HEADER)?;
+    let header = &data[pos + 4..];
+    let mut r = BitReader::new(header);
+    let mut width = r.bits(12);
+    let mut height = r.bits(12);
+    r.skip(4); // aspect_ratio_information
+    let frame_rate_code = r.bits(4) as usize;
+    if r.overrun {
+        return None;
+    }
+
+    let mut info = VideoInfo {
+        profile: "Main".to_owned(),
+        level: "Main".to_owned(),
+        frame_rate: FRAME_RATES
+            .get(frame_rate_code)
+            .copied()
+            .filter(|f| *f > 0.0),
+        chroma_format: Some("4:2:0"),
+        bit_depth: Some(8),
+        ..Default::default()
+    };
+
+    // Extensions after the first picture belong to pictures, not the
+    // sequence.
+    let end = header
+        .windows(4)
+        .position(|w| w == PICTURE)
+        .unwrap_or(header.len());
+    let sequence = &header[..end];
+    let mut pos = 0;
+    while let Some(next) = sequence[pos..].windows(4).position(|w| w == EXTENSION) {
+        pos += next + 4;
+        let mut r = BitReader::new(&sequence[pos..]);
+        match r.bits(4) {
+            SEQUENCE_EXTENSION => {
+                r.skip(1); // escape bit
+                info.profile = profile_name(r.bits(3)).to_owned();
+                info.level = level_name(r.bits(4)).to_owned();
+                info.interlaced = !r.bit();
+                info.chroma_format = super::chroma_format(r.bits(2));
+                width |= r.bits(2) << 12;
+                height |= r.bits(2) << 12;
+            }
+            SEQUENCE_DISPLAY_EXTENSION => {
+                r.skip(3); // video_format
+                if r.bit() {
+                    info.colour_primaries = Some(r.bits(8) as u8);
+                    info.transfer_characteristics = Some(r.bits(8) as u8);
+                    info.matrix_coefficients = Some(r.bits(8) as u8);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    info.width = width;
+    info.height = height;
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::BitWriter;
+    use alloc::vec::Vec;
+
+    fn sequence(frame_rate_code: u64, extension: bool) -> Vec<u8> {
+        let mut data = SEQUENCE_HEADER.to_vec();
+        data.extend(
+            BitWriter::new()
+                .put(12, 1920)
+                .put(12, 1080)
+                .put(4, 3) // aspect_ratio_information
+                .put(4, frame_rate_code)
+                .finish(8),
+        );
+        if extension {
+            data.extend(EXTENSION);
+            data.extend(
+                BitWriter::new()
+                    .put(4, SEQUENCE_EXTENSION as u64)
+                    .put(1, 0)
+                    .put(3, 4) // Main
+                    .put(4, 4) // High
+                    .put(1, 0) // progressive_sequence
+                    .put(2, 1) // chroma_format
+                    .put(2, 0)
+                    .put(2, 0)
+                    .finish(6),
+            );
+        }
+        data.extend(PICTURE);
+        data.extend(EXTENSION);
+        data.extend([0x8F, 0xFF]);
+        data
+    }
+
+    #[test]
+    fn mpeg2_sequence() {
+        let info = probe(&sequence(4, true)).unwrap();
+        assert_eq!((info.width, info.height), (1920, 1080));
+        assert!(info.interlaced);
+        assert_eq!(info.profile, "Main");
+        assert_eq!(info.level, "High");
+        assert_eq!(
+            info.description(),
+            "1920x1080i / 29.970 fps / Main @ Level High / 4:2:0 / 8 bits"
+        );
+    }
+
+    #[test]
+    fn mpeg1_sequence() {
+        let info = probe(&sequence(3, false)).unwrap();
+        assert!(!info.interlaced);
+        assert_eq!(info.frame_rate, Some(25.0));
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        assert!(probe(&sequence(4, false)[..6]).is_none());
+        assert!(probe(&[0; 16]).is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use super::{ContentLightLevel, MasteringDisplay, VideoInfo};
+use alloc::vec::Vec;
+
+/// Splits an Annex B byte stream into NAL units (start codes removed).
+pub fn units(data: &[u8]) -> impl Iterator<Item = &[u8]> + '_ {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            starts.push(i + 3);
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    let ends = starts
+        .iter()
+        .skip(1)
+        .map(|start| start - 3)
+        .chain(core::iter::once(data.len()))
+        .collect::<Vec<_>>();
+    starts.into_iter().zip(ends).map(move |(start, end)| {
+        let mut unit = &data[start..end];
+        // Trailing zero of a 4 byte start code.
+        while let [rest @ .., 0] = unit {
+            unit = rest;
+        }
+        unit
+    })
+}
+
+/// Removes emulation prevention bytes (`00 00 03`) from a NAL unit.
+pub fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &byte in data {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        out.push(byte);
+    }
+    out
+}
+
+/// Reads the SEI messages of an (unescaped) SEI RBSP, filling in HDR
+/// metadata. Shared by H.264 and HEVC, which use the same payload syntax.
+pub fn read_sei(rbsp: &[u8], info: &mut VideoInfo) {
+    let mut pos = 0;
+    while pos < rbsp.len() && rbsp[pos] != 0x80 {
+        let Some(payload_type) = read_sei_value(rbsp, &mut pos) else {
+            return;
+        };
+        let Some(size) = read_sei_value(rbsp, &mut pos) else {
+            return;
+        };
+        let Some(payload) = rbsp.get(pos..pos + size) else {
+            return;
+        };
+        pos += size;
+
+        match payload_type {
+            // user_data_registered_itu_t_t35, HDR10+ is ST 2094-40.
+            4 if payload.starts_with(&[0xB5, 0x00, 0x3C, 0x00, 0x01, 0x04]) => {
+                info.hdr10_plus = true;
+            }
+            // mastering_display_colour_volume
+            137 if size >= 24 => {
+                let u16_at = |i: usize| u16::from_be_bytes([payload[i], payload[i + 1]]);
+                let u32_at = |i: usize| {
+                    u32::from_be_bytes([payload[i], payload[i + 1], payload[i + 2], payload[i + 3]])
+                };
+                info.mastering_display = Some(MasteringDisplay {
+                    primaries: [
+                        (u16_at(0), u16_at(2)),
+                        (u16_at(4), u16_at(6)),
+                        (u16_at(8), u16_at(10)),
+                    ],
+                    white_point: (u16_at(12), u16_at(14)),
+                    max_luminance: u32_at(16),
+                    min_luminance: u32_at(20),
+                });
+            }
+            // content_light_level_info
+            144 if size >= 4 => {
+                info.content_light = Some(ContentLightLevel {
+                    max_cll: u16::from_be_bytes([payload[0], payload[1]]),
+                    max_fall: u16::from_be_bytes([payload[2], payload[3]]),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn read_sei_value(data: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut value = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value += byte as usize;
+        if byte != 0xFF {
+            return Some(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn splits_units() {
+        let data = [
+            0xAA, 0, 0, 0, 1, 0x67, 1, 2, 0, 0, 1, 0x68, 3, 0, 0, 0, 1, 0x06,
+        ];
+        let units = units(&data).collect::<Vec<_>>();
+        assert_eq!(units, [&[0x67, 1, 2][..], &[0x68, 3], &[0x06]]);
+        assert_eq!(super::units(&[0, 0]).count(), 0);
+    }
+
+    #[test]
+    fn removes_emulation_prevention() {
+        assert_eq!(
+            unescape(&[0, 0, 3, 1, 0, 0, 3, 0, 3]),
+            [0, 0, 1, 0, 0, 0, 3]
+        );
+    }
+
+    #[test]
+    fn reads_hdr_sei() {
+        let mut rbsp = vec![137, 24];
+        for value in [8500u16, 39850, 6550, 2300, 35400, 14600, 15635, 16450] {
+            rbsp.extend_from_slice(&value.to_be_bytes());
+        }
+        rbsp.extend_from_slice(&10_000_000u32.to_be_bytes());
+        rbsp.extend_from_slice(&50u32.to_be_bytes());
+        rbsp.extend_from_slice(&[144, 4, 0x03, 0xE8, 0x01, 0x90]);
+        rbsp.extend_from_slice(&[4, 7, 0xB5, 0x00, 0x3C, 0x00, 0x01, 0x04, 0x00]);
+        rbsp.push(0x80);
+
+        let mut info = VideoInfo::default();
+        read_sei(&rbsp, &mut info);
+        let display = info.mastering_display.unwrap();
+        assert_eq!(display.colour_space(), Some("BT.2020"));
+        assert_eq!(display.description(), "BT.2020, 0.0050-1000 cd/m2");
+        assert_eq!(
+            info.content_light,
+            Some(ContentLightLevel {
+                max_cll: 1000,
+                max_fall: 400
+            })
+        );
+        assert!(info.hdr10_plus);
+    }
+
+    #[test]
+    fn ignores_truncated_sei() {
+        let mut info = VideoInfo::default();
+        read_sei(&[137, 24, 0, 1, 2], &mut info);
+        read_sei(&[0xFF, 0xFF], &mut info);
+        assert_eq!(info, VideoInfo::default());
+    }
+}
@@ -0,0 +1,134 @@
+use super::{
+    ac3,
+    bits::{find_sync, BitReader},
+    AudioInfo,
+};
+use alloc::boxed::Box;
+
+const MAJOR_SYNC: u32 = 0xF872_6FBA;
+const SIGNATURE: u32 = 0xB752;
+
+/// Channels carried by each bit of a presentation channel assignment.
+const ASSIGNMENT_CHANNELS: [u8; 13] = [2, 1, 1, 2, 2, 2, 2, 1, 1, 2, 2, 1, 1];
+const ASSIGNMENT_LFE: u32 = (1 << 2) | (1 << 12);
+
+fn sample_rate(code: u32) -> Option<u32> {
+    match code {
+        0 => Some(48_000),
+        1 => Some(96_000),
+        2 => Some(192_000),
+        8 => Some(44_100),
+        9 => Some(88_200),
+        10 => Some(176_400),
+        _ => None,
+    }
+}
+
+/// Parses the format info following a major sync word.
+fn parse_major_sync(data: &[u8]) -> Option<AudioInfo> {
+    let mut r = BitReader::new(data);
+    if r.bits(32) != MAJOR_SYNC {
+        return None;
+    }
+    let sample_rate = sample_rate(r.bits(4))?;
+    r.skip(1 + 1 + 2 + 2 + 2); // multichannel types, channel modifiers
+    let assignment_6ch = r.bits(5);
+    r.skip(2); // 8ch_presentation_channel_modifier
+    let assignment_8ch = r.bits(13);
+    if r.bits(16) != SIGNATURE {
+        return None;
+    }
+    r.skip(16 + 16); // flags, reserved
+    r.skip(1 + 15); // variable_rate, peak_data_rate
+    let substreams = r.bits(4);
+    r.skip(2); // reserved
+    let extended_substream_info = r.bits(2);
+    let substream_info = r.bits(8);
+    if r.overrun {
+        return None;
+    }
+
+    let assignment = if assignment_8ch != 0 {
+        assignment_8ch
+    } else {
+        assignment_6ch
+    };
+    let channel_count = ASSIGNMENT_CHANNELS
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| assignment & (1 << bit) != 0 && ASSIGNMENT_LFE & (1 << bit) == 0)
+        .map(|(_, channels)| channels)
+        .sum();
+
+    Some(AudioInfo {
+        channel_count,
+        lfe: assignment & ASSIGNMENT_LFE != 0,
+        sample_rate,
+        // A 16 channel presentation only exists for object audio.
+        has_atmos: substreams == 4 && (substream_info & 0x80 != 0 || extended_substream_info != 0),
+        ..Default::default()
+    })
+}
+
+/// Probes Dolby TrueHD. On Blu-ray the TrueHD access units are interleaved
+/// with the frames of an AC-3 core in the same PID.
+pub fn probe(data: &[u8]) -> Option<AudioInfo> {
+    let mut info = find_sync(data, MAJOR_SYNC).find_map(|pos| parse_major_sync(&data[pos..]))?;
+    info.core = ac3::probe(data).map(Box::new);
+    if let Some(core) = info.core.as_ref() {
+        info.dial_norm = core.dial_norm;
+    }
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::BitWriter;
+    use alloc::vec::Vec;
+
+    fn major_sync(assignment_8ch: u64, substreams: u64, substream_info: u64) -> Vec<u8> {
+        BitWriter::new()
+            .put(32, MAJOR_SYNC as u64)
+            .put(4, 0) // audio_sampling_frequency
+            .put(8, 0)
+            .put(5, 0) // 6ch_presentation_channel_assignment
+            .put(2, 0)
+            .put(13, assignment_8ch)
+            .put(16, SIGNATURE as u64)
+            .put(32, 0)
+            .put(16, 0)
+            .put(4, substreams)
+            .put(2, 0)
+            .put(2, 0) // extended_substream_info
+            .put(8, substream_info)
+            .finish(32)
+    }
+
+    #[test]
+    fn surround() {
+        let info = probe(&major_sync(0b1111, 3, 0)).unwrap();
+        assert_eq!(info.channel_description(), "5.1");
+        assert_eq!(info.sample_rate, 48_000);
+        assert!(!info.has_atmos);
+        assert!(info.core.is_none());
+    }
+
+    #[test]
+    fn atmos_with_ac3_core() {
+        let mut data = crate::ts::codec::ac3::tests::ac3_frame();
+        data.extend(major_sync(0b1111, 4, 0x80));
+        let info = probe(&data).unwrap();
+        assert!(info.has_atmos);
+        assert_eq!(info.dial_norm, Some(-27));
+        assert_eq!(info.core.unwrap().bit_rate, 448_000);
+    }
+
+    #[test]
+    fn rejects_bad_signature() {
+        let mut data = major_sync(0b1111, 3, 0);
+        data[9] ^= 0xFF;
+        assert!(probe(&data).is_none());
+        assert!(probe(&major_sync(0b1111, 3, 0)[..10]).is_none());
+    }
+}
@@ -0,0 +1,130 @@
+use super::{bits::BitReader, VideoInfo};
+use alloc::{borrow::ToOwned, string::ToString};
+
+const SEQUENCE_HEADER: [u8; 4] = [0x00, 0x00, 0x01, 0x0F];
+const ADVANCED_PROFILE: u32 = 3;
+
+fn frame_rate(numerator: u32, denominator: u32) -> Option<f64> {
+    let numerator = match numerator {
+        1 => 24_000.0,
+        2 => 25_000.0,
+        3 => 30_000.0,
+        4 => 50_000.0,
+        5 => 60_000.0,
+        6 => 48_000.0,
+        7 => 72_000.0,
+        _ => return None,
+    };
+    match denominator {
+        1 => Some(numerator / 1000.0),
+        2 => Some(numerator / 1001.0),
+        _ => None,
+    }
+}
+
+/// Probes the advanced profile sequence header of a VC-1 stream, the only
+/// profile allowed on Blu-ray.
+pub fn probe(data: &[u8]) -> Option<VideoInfo> {
+    let pos = data.windows(4).position(|w| w == SEQUENCE_HEADER)?;
+    let mut r = BitReader::new(&data[pos + 4..]);
+    if r.bits(2) != ADVANCED_PROFILE {
+        return None;
+    }
+    let level = r.bits(3);
+    let chroma_format = r.bits(2);
+    r.skip(3 + 5 + 1); // frmrtq_postproc, bitrtq_postproc, postprocflag
+    let width = (r.bits(12) + 1) * 2;
+    let height = (r.bits(12) + 1) * 2;
+    r.skip(1); // pulldown
+    let interlaced = r.bit();
+    r.skip(1 + 1 + 1 + 1); // tfcntrflag, finterpflag, reserved, psf
+
+    let mut info = VideoInfo {
+        profile: "Advanced".to_owned(),
+        level: level.to_string(),
+        width,
+        height,
+        interlaced,
+        chroma_format: super::chroma_format(chroma_format),
+        bit_depth: Some(8),
+        ..Default::default()
+    };
+    if r.bit() {
+        // display_ext
+        r.skip(14 + 14); // disp_horiz_size, disp_vert_size
+        if r.bit() && r.bits(4) == 15 {
+            r.skip(16); // aspect_horiz_size, aspect_vert_size
+        }
+        if r.bit() {
+            info.frame_rate = if r.bit() {
+                // frameratexp, in 1/32 Hz steps
+                Some((r.bits(16) + 1) as f64 / 32.0)
+            } else {
+                let numerator = r.bits(8);
+                frame_rate(numerator, r.bits(4))
+            };
+        }
+        if r.bit() {
+            info.colour_primaries = Some(r.bits(8) as u8);
+            info.transfer_characteristics = Some(r.bits(8) as u8);
+            info.matrix_coefficients = Some(r.bits(8) as u8);
+        }
+    }
+    if r.overrun {
+        None
+    } else {
+        Some(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::BitWriter;
+    use alloc::vec::Vec;
+
+    fn sequence(profile: u64) -> Vec<u8> {
+        let mut data = SEQUENCE_HEADER.to_vec();
+        data.extend(
+            BitWriter::new()
+                .put(2, profile)
+                .put(3, 3) // level
+                .put(2, 1) // colordiff_format
+                .put(3 + 5 + 1, 0)
+                .put(12, 959) // max_coded_width
+                .put(12, 539) // max_coded_height
+                .put(1, 0) // pulldown
+                .put(1, 0) // interlace
+                .put(4, 0)
+                .put(1, 1) // display_ext
+                .put(14 + 14, 0)
+                .put(1, 0) // aspect_ratio_flag
+                .put(1, 1) // framerate_flag
+                .put(1, 0) // framerateind
+                .put(8, 1) // frameratenr
+                .put(4, 2) // frameratedr
+                .put(1, 1) // color_format_flag
+                .put(8, 1)
+                .put(8, 1)
+                .put(8, 1)
+                .finish(16),
+        );
+        data
+    }
+
+    #[test]
+    fn advanced_profile() {
+        let info = probe(&sequence(ADVANCED_PROFILE as u64)).unwrap();
+        assert_eq!((info.width, info.height), (1920, 1080));
+        assert_eq!(info.level, "3");
+        assert_eq!(info.frame_rate, Some(24_000.0 / 1001.0));
+        assert_eq!(info.colour_primaries, Some(1));
+        assert_eq!(info.chroma_format, Some("4:2:0"));
+    }
+
+    #[test]
+    fn rejects_other_profiles_and_truncation() {
+        assert!(probe(&sequence(0)).is_none());
+        assert!(probe(&sequence(ADVANCED_PROFILE as u64)[..8]).is_none());
+    }
+}
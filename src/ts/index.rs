@@ -0,0 +1,208 @@
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+
+use crate::macros::*;
+
+/// What a title runs: a movie object or a BD-J object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TitleObject {
+    /// Movie object number in `MovieObject.bdmv`.
+    Hdmv {
+        playback_type: u8,
+        movie_object: u16,
+    },
+    /// Name of the `.bdjo` file in `BDMV/BDJO`, without extension.
+    BdJ { playback_type: u8, name: String },
+    /// An object type this parser doesn't know.
+    Unknown(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Title {
+    pub object: TitleObject,
+    /// Whether the title may be selected from a menu or by the player's
+    /// title search (0), or only jumped to by the disc (1 and 3).
+    pub access_type: u8,
+}
+
+/// The disc's index table (`BDMV/index.bdmv`): what plays on insertion,
+/// the top menu and the titles.
+#[derive(Debug, Clone)]
+pub struct IndexTable {
+    pub type_indicator: String,
+    pub version: String,
+    /// Prefer 3D output when the disc has it.
+    pub initial_output_mode_3d: bool,
+    pub video_format: u8,
+    pub frame_rate: u8,
+    pub first_playback: TitleObject,
+    pub top_menu: TitleObject,
+    pub titles: Vec<Title>,
+}
+
+impl IndexTable {
+    pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let type_indicator = read_string(reader, 4)?;
+        if type_indicator != "INDX" {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("not an index file: {:?}", type_indicator),
+            ));
+        }
+        let version = read_string(reader, 4)?;
+        let indexes_start = read_u32(reader)?;
+
+        // AppInfoBDMV follows the 40 byte header.
+        reader.seek(SeekFrom::Start(40))?;
+        let _length = read_u32(reader)?;
+        let flags = read_u8(reader)?;
+        let video = read_u8(reader)?;
+
+        reader.seek(SeekFrom::Start(indexes_start.into()))?;
+        let _length = read_u32(reader)?;
+        let (first_playback, _) = read_object(reader)?;
+        let (top_menu, _) = read_object(reader)?;
+        let count = read_u16(reader)?;
+        let mut titles = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let (object, access_type) = read_object(reader)?;
+            titles.push(Title {
+                object,
+                access_type,
+            });
+        }
+
+        Ok(IndexTable {
+            type_indicator,
+            version,
+            initial_output_mode_3d: flags & 0x40 != 0,
+            video_format: video >> 4,
+            frame_rate: video & 0x0F,
+            first_playback,
+            top_menu,
+            titles,
+        })
+    }
+
+    /// Whether any of the disc's objects is BD-J.
+    pub fn has_bdj(&self) -> bool {
+        [&self.first_playback, &self.top_menu]
+            .into_iter()
+            .chain(self.titles.iter().map(|t| &t.object))
+            .any(|object| matches!(object, TitleObject::BdJ { .. }))
+    }
+}
+
+/// A 12 byte object reference, with the access type that titles store in
+/// the bits after the object type.
+fn read_object<R: Read>(reader: &mut R) -> Result<(TitleObject, u8)> {
+    let head = read_u32(reader)?;
+    let object_type = (head >> 30) as u8;
+    let access_type = ((head >> 28) & 0x3) as u8;
+    let playback_type = (read_u16(reader)? >> 14) as u8;
+    let object = match object_type {
+        1 => {
+            let movie_object = read_u16(reader)?;
+            read_u32(reader)?;
+            TitleObject::Hdmv {
+                playback_type,
+                movie_object,
+            }
+        }
+        2 => {
+            let name = read_string(reader, 5)?;
+            read_u8(reader)?;
+            TitleObject::BdJ {
+                playback_type,
+                name,
+            }
+        }
+        other => {
+            read_string(reader, 6)?;
+            TitleObject::Unknown(other)
+        }
+    };
+    Ok((object, access_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::testing::index_bdmv;
+
+    fn sample() -> Vec<u8> {
+        index_bdmv(
+            &TitleObject::Hdmv {
+                playback_type: 0,
+                movie_object: 0,
+            },
+            &TitleObject::BdJ {
+                playback_type: 3,
+                name: "00000".to_owned(),
+            },
+            &[
+                (
+                    TitleObject::Hdmv {
+                        playback_type: 1,
+                        movie_object: 2,
+                    },
+                    0,
+                ),
+                (TitleObject::Unknown(3), 1),
+            ],
+        )
+    }
+
+    #[test]
+    fn parses_titles() {
+        let index = IndexTable::parse(&mut Cursor::new(sample())).unwrap();
+        assert_eq!(index.type_indicator, "INDX");
+        assert_eq!(index.version, "0200");
+        assert!(index.initial_output_mode_3d);
+        assert_eq!((index.video_format, index.frame_rate), (6, 1));
+        assert_eq!(
+            index.first_playback,
+            TitleObject::Hdmv {
+                playback_type: 0,
+                movie_object: 0
+            }
+        );
+        assert_eq!(
+            index.top_menu,
+            TitleObject::BdJ {
+                playback_type: 3,
+                name: "00000".to_owned()
+            }
+        );
+        assert_eq!(
+            index.titles,
+            [
+                Title {
+                    object: TitleObject::Hdmv {
+                        playback_type: 1,
+                        movie_object: 2
+                    },
+                    access_type: 0
+                },
+                Title {
+                    object: TitleObject::Unknown(3),
+                    access_type: 1
+                },
+            ]
+        );
+        assert!(index.has_bdj());
+    }
+
+    #[test]
+    fn rejects_malformed_index() {
+        let mut data = sample();
+        data[..4].copy_from_slice(b"MOBJ");
+        let error = IndexTable::parse(&mut Cursor::new(data)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let data = sample();
+        let error = IndexTable::parse(&mut Cursor::new(&data[..data.len() - 6])).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,205 @@
+//! ISO 639 language codes of audio, graphics and text streams.
+
+use core::fmt::{self, Display};
+
+use super::language_codes::language_name;
+
+/// ISO 639-2 codes that have a separate bibliographic form, as
+/// `(bibliographic, terminologic)`. Discs use either.
+#[rustfmt::skip]
+const BIBLIOGRAPHIC: &[(&str, &str)] = &[
+    ("alb", "sqi"), ("arm", "hye"), ("baq", "eus"), ("bur", "mya"), ("chi", "zho"), ("cze", "ces"),
+    ("dut", "nld"), ("fre", "fra"), ("geo", "kat"), ("ger", "deu"), ("gre", "ell"), ("ice", "isl"),
+    ("mac", "mkd"), ("mao", "mri"), ("may", "msa"), ("per", "fas"), ("rum", "ron"), ("slo", "slk"),
+    ("tib", "bod"), ("wel", "cym"),
+];
+
+/// ISO 639-1 codes, as `(639-1, terminologic 639-2)`.
+#[rustfmt::skip]
+const ISO_639_1: &[(&str, &str)] = &[
+    ("aa", "aar"), ("ab", "abk"), ("ae", "ave"), ("af", "afr"), ("ak", "aka"), ("am", "amh"),
+    ("an", "arg"), ("ar", "ara"), ("as", "asm"), ("av", "ava"), ("ay", "aym"), ("az", "aze"),
+    ("ba", "bak"), ("be", "bel"), ("bg", "bul"), ("bh", "bih"), ("bi", "bis"), ("bm", "bam"),
+    ("bn", "ben"), ("bo", "bod"), ("br", "bre"), ("bs", "bos"), ("ca", "cat"), ("ce", "che"),
+    ("ch", "cha"), ("co", "cos"), ("cr", "cre"), ("cs", "ces"), ("cu", "chu"), ("cv", "chv"),
+    ("cy", "cym"), ("da", "dan"), ("de", "deu"), ("dv", "div"), ("dz", "dzo"), ("ee", "ewe"),
+    ("el", "ell"), ("en", "eng"), ("eo", "epo"), ("es", "spa"), ("et", "est"), ("eu", "eus"),
+    ("fa", "fas"), ("ff", "ful"), ("fi", "fin"), ("fj", "fij"), ("fo", "fao"), ("fr", "fra"),
+    ("fy", "fry"), ("ga", "gle"), ("gd", "gla"), ("gl", "glg"), ("gn", "grn"), ("gu", "guj"),
+    ("gv", "glv"), ("ha", "hau"), ("he", "heb"), ("hi", "hin"), ("ho", "hmo"), ("hr", "hrv"),
+    ("ht", "hat"), ("hu", "hun"), ("hy", "hye"), ("hz", "her"), ("ia", "ina"), ("id", "ind"),
+    ("ie", "ile"), ("ig", "ibo"), ("ii", "iii"), ("ik", "ipk"), ("io", "ido"), ("is", "isl"),
+    ("it", "ita"), ("iu", "iku"), ("ja", "jpn"), ("jv", "jav"), ("ka", "kat"), ("kg", "kon"),
+    ("ki", "kik"), ("kj", "kua"), ("kk", "kaz"), ("kl", "kal"), ("km", "khm"), ("kn", "kan"),
+    ("ko", "kor"), ("kr", "kau"), ("ks", "kas"), ("ku", "kur"), ("kv", "kom"), ("kw", "cor"),
+    ("ky", "kir"), ("la", "lat"), ("lb", "ltz"), ("lg", "lug"), ("li", "lim"), ("ln", "lin"),
+    ("lo", "lao"), ("lt", "lit"), ("lu", "lub"), ("lv", "lav"), ("mg", "mlg"), ("mh", "mah"),
+    ("mi", "mri"), ("mk", "mkd"), ("ml", "mal"), ("mn", "mon"), ("mr", "mar"), ("ms", "msa"),
+    ("mt", "mlt"), ("my", "mya"), ("na", "nau"), ("nb", "nob"), ("nd", "nde"), ("ne", "nep"),
+    ("ng", "ndo"), ("nl", "nld"), ("nn", "nno"), ("no", "nor"), ("nr", "nbl"), ("nv", "nav"),
+    ("ny", "nya"), ("oc", "oci"), ("oj", "oji"), ("om", "orm"), ("or", "ori"), ("os", "oss"),
+    ("pa", "pan"), ("pi", "pli"), ("pl", "pol"), ("ps", "pus"), ("pt", "por"), ("qu", "que"),
+    ("rm", "roh"), ("rn", "run"), ("ro", "ron"), ("ru", "rus"), ("rw", "kin"), ("sa", "san"),
+    ("sc", "srd"), ("sd", "snd"), ("se", "sme"), ("sg", "sag"), ("si", "sin"), ("sk", "slk"),
+    ("sl", "slv"), ("sm", "smo"), ("sn", "sna"), ("so", "som"), ("sq", "sqi"), ("sr", "srp"),
+    ("ss", "ssw"), ("st", "sot"), ("su", "sun"), ("sv", "swe"), ("sw", "swa"), ("ta", "tam"),
+    ("te", "tel"), ("tg", "tgk"), ("th", "tha"), ("ti", "tir"), ("tk", "tuk"), ("tl", "tgl"),
+    ("tn", "tsn"), ("to", "ton"), ("tr", "tur"), ("ts", "tso"), ("tt", "tat"), ("tw", "twi"),
+    ("ty", "tah"), ("ug", "uig"), ("uk", "ukr"), ("ur", "urd"), ("uz", "uzb"), ("ve", "ven"),
+    ("vi", "vie"), ("vo", "vol"), ("wa", "wln"), ("wo", "wol"), ("xh", "xho"), ("yi", "yid"),
+    ("yo", "yor"), ("za", "zha"), ("zh", "zho"), ("zu", "zul"),
+];
+
+/// An ISO 639-2 language code: three lowercase ASCII letters, kept in the
+/// form the disc stores it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Language([u8; 3]);
+
+impl Language {
+    /// `und`, for streams whose language is missing or not a valid code.
+    pub const UNDETERMINED: Language = Language(*b"und");
+
+    /// A three letter ISO 639-2 or two letter ISO 639-1 code, in any case.
+    /// `None` for anything else.
+    pub fn parse(code: &[u8]) -> Option<Self> {
+        match *code {
+            [a, b] => {
+                let short = [letter(a)?, letter(b)?];
+                let (_, long) = ISO_639_1.iter().find(|(s, _)| s.as_bytes() == short)?;
+                Self::parse(long.as_bytes())
+            }
+            [a, b, c] => Some(Language([letter(a)?, letter(b)?, letter(c)?])),
+            _ => None,
+        }
+    }
+
+    /// The language of stream attributes, `und` when they don't hold a
+    /// valid three letter code.
+    pub fn from_attribute(code: &[u8]) -> Self {
+        match code.len() {
+            3 => Self::parse(code).unwrap_or(Self::UNDETERMINED),
+            _ => Self::UNDETERMINED,
+        }
+    }
+
+    /// The code as stored, bibliographic or terminologic.
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.0).unwrap_or("und")
+    }
+
+    /// The terminologic form, `fra` for `fre`. Other codes are unchanged.
+    pub fn terminologic(&self) -> Self {
+        BIBLIOGRAPHIC
+            .iter()
+            .find(|(b, _)| *b == self.as_str())
+            .and_then(|(_, t)| Self::parse(t.as_bytes()))
+            .unwrap_or(*self)
+    }
+
+    /// The bibliographic form, `fre` for `fra`. Other codes are unchanged.
+    pub fn bibliographic(&self) -> Self {
+        BIBLIOGRAPHIC
+            .iter()
+            .find(|(_, t)| *t == self.as_str())
+            .and_then(|(b, _)| Self::parse(b.as_bytes()))
+            .unwrap_or(*self)
+    }
+
+    /// The two letter ISO 639-1 code, for languages that have one.
+    pub fn iso639_1(&self) -> Option<&'static str> {
+        let terminologic = self.terminologic();
+        ISO_639_1
+            .iter()
+            .find(|(_, t)| *t == terminologic.as_str())
+            .map(|(short, _)| *short)
+    }
+
+    /// BCP 47 tag: the ISO 639-1 code when there is one, as mkvmerge and
+    /// browsers expect, and the ISO 639-2 code otherwise.
+    pub fn bcp47(&self) -> &str {
+        // Every code with a bibliographic form also has an ISO 639-1 code,
+        // so the fallback is always terminologic.
+        self.iso639_1().unwrap_or(self.as_str())
+    }
+
+    /// English name, `None` for codes that aren't assigned.
+    pub fn name(&self) -> Option<&'static str> {
+        language_name(self.as_str())
+    }
+
+    pub fn is_undetermined(&self) -> bool {
+        *self == Self::UNDETERMINED
+    }
+
+    /// Whether both codes name the same language, `fre` matching `fra`.
+    pub fn same_as(&self, other: &Language) -> bool {
+        self.terminologic() == other.terminologic()
+    }
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Self::UNDETERMINED
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn letter(byte: u8) -> Option<u8> {
+    byte.is_ascii_alphabetic()
+        .then(|| byte.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ts::stream::Stream;
+
+    fn code(code: &str) -> Language {
+        Language::parse(code.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn parses_two_and_three_letter_codes() {
+        assert_eq!(code("ENG").as_str(), "eng");
+        assert_eq!(code("fr").as_str(), "fra");
+        assert_eq!(Language::parse(b"xx"), None);
+        assert_eq!(Language::parse(b"en1"), None);
+        assert_eq!(Language::parse(b"engl"), None);
+        assert_eq!(Language::from_attribute(b"\0\0\0"), Language::UNDETERMINED);
+        assert_eq!(Language::from_attribute(b"fr"), Language::UNDETERMINED);
+        assert!(Language::default().is_undetermined());
+    }
+
+    #[test]
+    fn converts_between_forms() {
+        assert_eq!(code("fre").terminologic(), code("fra"));
+        assert_eq!(code("fra").bibliographic(), code("fre"));
+        assert_eq!(code("eng").terminologic(), code("eng"));
+        assert!(code("ger").same_as(&code("deu")));
+        assert!(!code("ger").same_as(&code("dut")));
+
+        assert_eq!(code("chi").iso639_1(), Some("zh"));
+        assert_eq!(code("chi").bcp47(), "zh");
+        assert_eq!(code("haw").bcp47(), "haw");
+        assert_eq!(code("und").iso639_1(), None);
+        assert_eq!(code("fre").name(), Some("French"));
+        assert_eq!(code("qqq").name(), None);
+        assert_eq!(code("spa").to_string(), "spa");
+    }
+
+    #[test]
+    fn reads_stream_attributes() {
+        let audio = Stream::from_attributes(0x1100, &[0x81, 0x61, b'F', b'R', b'E']).unwrap();
+        assert_eq!(audio.language(), Some(&code("fre")));
+        assert_eq!(audio.lang_code(), "fre");
+        let graphics = Stream::from_attributes(0x1200, &[0x90, 0xFF, 0, 0]).unwrap();
+        assert!(graphics.language().unwrap().is_undetermined());
+        let video = Stream::from_attributes(0x1011, &[0x1B, 0x61, 0x30]).unwrap();
+        assert_eq!((video.language(), video.lang_code()), (None, ""));
+    }
+}
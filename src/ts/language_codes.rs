@@ -1,4 +1,12 @@
+use alloc::{borrow::ToOwned, string::String};
+
+/// English name of an ISO 639-2 code, or the code itself when unknown.
 pub fn get_language_code(code: &str) -> String {
+    language_name(code).unwrap_or(code).to_owned()
+}
+
+/// English name of an ISO 639-2 code, bibliographic or terminologic.
+pub fn language_name(code: &str) -> Option<&'static str> {
     let matched = match code {
         "abk" => "Abkhazian",
         "ace" => "Achinese",
@@ -459,7 +467,7 @@ pub fn get_language_code(code: &str) -> String {
         "zha" => "Zhuang",
         "zul" => "Zulu",
         "zun" => "Zuni",
-        _ => code,
+        _ => return None,
     };
-    matched.to_owned()
+    Some(matched)
 }
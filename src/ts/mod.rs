@@ -1,26 +1,210 @@
-use std::fmt::Debug;
+use alloc::{format, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{Error, ErrorKind, Read, Result};
 
 pub mod angle;
+#[cfg(feature = "std")]
+pub mod clpi;
+pub mod codec;
+#[cfg(feature = "std")]
+pub mod index;
+pub mod language;
 pub mod language_codes;
 pub mod mpls;
+pub mod packet;
 pub mod plist;
 pub mod stream;
 pub mod stream_clip;
+#[cfg(feature = "std")]
+pub mod stream_file;
 pub mod stream_type;
 pub mod streams_header;
+#[cfg(feature = "std")]
+pub mod textst;
 
-pub trait FromBinary
-where
-    Self: Sized + Default + Debug,
-{
-    fn read_from_binary<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let size_of_struct = std::mem::size_of::<Self>();
-        let mut buf: Vec<u8> = vec![0u8; size_of_struct];
-        reader.read_exact(buf.as_mut_slice())?;
-        let mut obj: Self = unsafe { std::ptr::read(buf.as_ptr() as *const _) };
-        obj.sanitize();
-        Ok(obj)
+/// A record with a fixed big-endian layout, declared with `binary_struct!`.
+pub trait FromBinary: Sized {
+    /// Encoded size in bytes.
+    const SIZE: usize;
+
+    /// Decodes the record from the start of `bytes`, `None` when too short.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+
+    fn write_to(&self, out: &mut Vec<u8>);
+
+    /// Every field in file order, with its decoded value.
+    fn fields(&self) -> Vec<RecordField>;
+
+    #[cfg(feature = "std")]
+    fn read_from_binary<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut buf = vec![0u8; Self::SIZE];
+        reader.read_exact(&mut buf)?;
+        Self::from_bytes(&buf).ok_or_else(|| Error::from(ErrorKind::UnexpectedEof))
+    }
+
+    fn to_binary(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        self.write_to(&mut out);
+        out
     }
+}
+
+/// A field of a decoded record, for annotated dumps.
+#[derive(Debug, Clone)]
+pub struct RecordField {
+    pub name: &'static str,
+    pub size: usize,
+    pub value: String,
+}
+
+/// A field of a [`FromBinary`] record.
+pub trait BinaryField: Sized {
+    const SIZE: usize;
+
+    /// Decodes the field from the start of `bytes`, which holds at least
+    /// `SIZE` bytes.
+    fn decode(bytes: &[u8]) -> Self;
+
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// The value as shown in annotated dumps.
+    fn describe(&self) -> String;
+}
+
+macro_rules! impl_binary_field {
+    ($($t:ty),*) => {
+        $(
+            impl BinaryField for $t {
+                const SIZE: usize = core::mem::size_of::<$t>();
+
+                fn decode(bytes: &[u8]) -> Self {
+                    let mut buffer = [0; core::mem::size_of::<$t>()];
+                    buffer.copy_from_slice(&bytes[..Self::SIZE]);
+                    <$t>::from_be_bytes(buffer)
+                }
+
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn describe(&self) -> String {
+                    format!("{}", self)
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_field!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl<const N: usize> BinaryField for [u8; N] {
+    const SIZE: usize = N;
 
-    fn sanitize(&mut self);
+    fn decode(bytes: &[u8]) -> Self {
+        let mut buffer = [0; N];
+        buffer.copy_from_slice(&bytes[..N]);
+        buffer
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    /// Quoted text when every byte is printable ASCII, hex otherwise.
+    fn describe(&self) -> String {
+        if !self.is_empty() && self.iter().all(|b| (0x20..0x7F).contains(b)) {
+            format!("{:?}", String::from_utf8_lossy(self))
+        } else {
+            let hex: Vec<String> = self.iter().map(|b| format!("{:02X}", b)).collect();
+            hex.join(" ")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{mpls::MplsFileHeader, plist::PlaylistHeader, stream_clip::ClipHeader, *};
+    use crate::testing::MPLS_00800;
+
+    binary_struct! {
+        #[derive(Debug, Default, PartialEq)]
+        struct Record {
+            byte: u8,
+            word: u16,
+            signed: i32,
+            tag: [u8; 3],
+        }
+    }
+
+    bitfields! {
+        Record.word: u16 {
+            high: 12..16,
+            low: 0..4,
+        }
+    }
+
+    #[test]
+    fn decodes_and_encodes_big_endian_records() {
+        let bytes = [1, 0x12, 0x34, 0xFF, 0xFF, 0xFF, 0xFE, b'a', b'b', b'c', 9];
+        let record = Record::from_bytes(&bytes).unwrap();
+        assert_eq!(Record::SIZE, 10);
+        assert_eq!(
+            record,
+            Record {
+                byte: 1,
+                word: 0x1234,
+                signed: -2,
+                tag: *b"abc",
+            }
+        );
+        assert_eq!(record.to_binary(), bytes[..10]);
+        assert_eq!(Record::from_bytes(&bytes[..9]), None);
+
+        let fields = record
+            .fields()
+            .into_iter()
+            .map(|field| (field.name, field.size, field.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("byte", 1, "1".to_owned()),
+                ("word", 2, "4660".to_owned()),
+                ("signed", 4, "-2".to_owned()),
+                ("tag", 3, "\"abc\"".to_owned()),
+            ]
+        );
+        assert_eq!([0u8, 0x7F].describe(), "00 7F");
+    }
+
+    #[test]
+    fn reads_and_sets_bitfields() {
+        let mut record = Record {
+            word: 0x1234,
+            ..Record::default()
+        };
+        assert_eq!((record.high(), record.low()), (0x1, 0x4));
+        record.set_high(0xA);
+        record.set_low(0x1F);
+        assert_eq!(record.word, 0xA23F);
+    }
+
+    #[test]
+    fn decodes_sample_records() {
+        let header = MplsFileHeader::from_bytes(MPLS_00800).unwrap();
+        assert!(header.magic_string().starts_with("MPLS"));
+        assert_eq!(header.to_binary(), MPLS_00800[..MplsFileHeader::SIZE]);
+
+        let offset = header.playlist_offset as usize;
+        let playlist = PlaylistHeader::from_bytes(&MPLS_00800[offset..]).unwrap();
+        assert_eq!(playlist.item_count(), 1);
+        let clip = ClipHeader::from_bytes(&MPLS_00800[offset + PlaylistHeader::SIZE..]).unwrap();
+        assert_eq!(clip.stream_file_name(), "00037.M2TS");
+        assert_eq!(clip.item_type(), "M2TS");
+        assert_eq!(clip.time_in(), 4198.375);
+
+        let mut reader = &MPLS_00800[..MplsFileHeader::SIZE - 1];
+        let error = MplsFileHeader::read_from_binary(&mut reader).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
 }
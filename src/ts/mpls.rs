@@ -1,27 +1,39 @@
-use std::fmt::{Debug, Display};
+use alloc::string::{String, ToString};
+use core::fmt::{Debug, Display};
 
 use super::FromBinary;
 
-#[repr(C, packed)]
-#[derive(Default, Clone)]
-pub struct MplsFileHeader {
-    magic_string: [u8; 8],     // 0x0
-    pub playlist_offset: u32,  // 0x8
-    pub chapters_offset: u32,  // 0xC
-    pub extnsions_offset: u32, // 0x10
-    _unk_0: [u8; 20],
-    _playlist_len: u32,
-    _unk_1: u8,
-    _playback_type: u8,
-    _playback_count: u16,
-    _operation_mask: [u8; 8],
-    pub misc_flags: u8, // 0x38
-    _unk_2: u8,
+binary_struct! {
+    #[derive(Default, Clone)]
+    pub struct MplsFileHeader {
+        magic_string: [u8; 8],     // 0x0
+        pub playlist_offset: u32,  // 0x8
+        pub chapters_offset: u32,  // 0xC
+        pub extnsions_offset: u32, // 0x10
+        _unk_0: [u8; 20],
+        _playlist_len: u32,
+        _unk_1: u8,
+        _playback_type: u8,
+        _playback_count: u16,
+        _operation_mask: [u8; 8],
+        pub misc_flags: u8, // 0x38
+        _unk_2: u8,
+    }
+}
+
+bitfields! {
+    MplsFileHeader.misc_flags: u8 {
+        pub random_access: 7..8,
+        pub audio_mix_app: 6..7,
+        pub lossless_may_bypass_mixer: 5..6,
+        pub mvc_base_view_r_flag: 4..5,
+        pub sdr_conversion_notification: 3..4,
+    }
 }
 
 impl MplsFileHeader {
     pub const fn expected_size() -> u32 {
-        58
+        Self::SIZE as u32
     }
 
     pub fn offsets(&self) -> (u32, u32, u32) {
@@ -31,31 +43,35 @@ impl MplsFileHeader {
             self.extnsions_offset,
         )
     }
+    pub fn magic(&self) -> &[u8; 8] {
+        &self.magic_string
+    }
     pub fn magic_string(&self) -> String {
         String::from_utf8_lossy(&self.magic_string).to_string()
     }
-    pub fn mvc_base_view_r(&self) -> bool {
-        (self.misc_flags & (1 << 4)) == 1
+    pub fn playback_type(&self) -> u8 {
+        self._playback_type
     }
-}
-
-impl FromBinary for MplsFileHeader {
-    fn sanitize(&mut self) {
-        self.playlist_offset = self.playlist_offset.swap_bytes();
-        self.chapters_offset = self.chapters_offset.swap_bytes();
-        self.extnsions_offset = self.extnsions_offset.swap_bytes();
-        self.misc_flags = self.misc_flags.swap_bytes();
+    /// Number of plays for random and shuffle playback types.
+    pub fn playback_count(&self) -> u16 {
+        self._playback_count
+    }
+    pub fn operation_mask(&self) -> [u8; 8] {
+        self._operation_mask
+    }
+    pub fn mvc_base_view_r(&self) -> bool {
+        self.mvc_base_view_r_flag() != 0
     }
 }
 
 impl Display for MplsFileHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:?}", FriendlyMplsFileHeader::new(self))
     }
 }
 
 impl Debug for MplsFileHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:#?}", FriendlyMplsFileHeader::new(self))
     }
 }
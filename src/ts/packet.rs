@@ -0,0 +1,144 @@
+/// BDAV source packet: a 4 byte TP_extra_header followed by a 188 byte
+/// MPEG transport stream packet.
+pub const SOURCE_PACKET_SIZE: usize = 192;
+pub const TS_PACKET_SIZE: usize = 188;
+pub const SYNC_BYTE: u8 = 0x47;
+
+/// PCR runs at 27 MHz and wraps at 2^33 * 300.
+pub const PCR_CLOCK: f64 = 27_000_000.0;
+pub const PCR_MODULUS: u64 = (1 << 33) * 300;
+/// PTS/DTS run at 90 kHz and wrap at 2^33.
+pub const PTS_CLOCK: f64 = 90_000.0;
+pub const PTS_MODULUS: u64 = 1 << 33;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SourcePacket<'a> {
+    pub arrival_time: u32,
+    pub pid: u16,
+    pub payload_unit_start: bool,
+    pub continuity_counter: u8,
+    pub pcr: Option<u64>,
+    pub payload: &'a [u8],
+}
+
+impl<'a> SourcePacket<'a> {
+    /// Parses a 192 byte source packet, returning `None` when the sync byte
+    /// is missing or the adaptation field runs past the end of the packet.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < SOURCE_PACKET_SIZE || data[4] != SYNC_BYTE {
+            return None;
+        }
+        let arrival_time = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) & 0x3FFF_FFFF;
+        let ts = &data[4..SOURCE_PACKET_SIZE];
+
+        let pid = (u16::from(ts[1] & 0x1F) << 8) | u16::from(ts[2]);
+        let payload_unit_start = ts[1] & 0x40 != 0;
+        let adaptation_field_control = (ts[3] >> 4) & 0x3;
+        let continuity_counter = ts[3] & 0xF;
+
+        let mut offset = 4;
+        let mut pcr = None;
+        if adaptation_field_control & 0x2 != 0 {
+            let length = ts[4] as usize;
+            if offset + 1 + length > TS_PACKET_SIZE {
+                return None;
+            }
+            if length >= 7 && ts[5] & 0x10 != 0 {
+                let base = (u64::from(ts[6]) << 25)
+                    | (u64::from(ts[7]) << 17)
+                    | (u64::from(ts[8]) << 9)
+                    | (u64::from(ts[9]) << 1)
+                    | (u64::from(ts[10]) >> 7);
+                let extension = (u64::from(ts[10] & 0x1) << 8) | u64::from(ts[11]);
+                pcr = Some(base * 300 + extension);
+            }
+            offset += 1 + length;
+        }
+        let payload = if adaptation_field_control & 0x1 != 0 {
+            &ts[offset..]
+        } else {
+            &ts[TS_PACKET_SIZE..]
+        };
+
+        Some(SourcePacket {
+            arrival_time,
+            pid,
+            payload_unit_start,
+            continuity_counter,
+            pcr,
+            payload,
+        })
+    }
+}
+
+/// Fields of a PES packet header. `header_len` is the offset of the
+/// elementary stream data from the start of the PES packet.
+#[derive(Debug, Clone, Copy)]
+pub struct PesHeader {
+    pub stream_id: u8,
+    pub packet_length: u16,
+    pub pts: Option<u64>,
+    pub dts: Option<u64>,
+    pub header_len: usize,
+}
+
+impl PesHeader {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 6 || data[0..3] != [0, 0, 1] {
+            return None;
+        }
+        let stream_id = data[3];
+        let packet_length = u16::from_be_bytes([data[4], data[5]]);
+
+        // Streams without the optional PES header.
+        if matches!(
+            stream_id,
+            0xBC | 0xBE | 0xBF | 0xF0 | 0xF1 | 0xF2 | 0xF8 | 0xFF
+        ) {
+            return Some(PesHeader {
+                stream_id,
+                packet_length,
+                pts: None,
+                dts: None,
+                header_len: 6,
+            });
+        }
+
+        if data.len() < 9 {
+            return None;
+        }
+        let flags = data[7] >> 6;
+        let header_len = 9 + data[8] as usize;
+        let pts = if flags & 0x2 != 0 && data.len() >= 14 {
+            Some(read_timestamp(&data[9..14]))
+        } else {
+            None
+        };
+        let dts = if flags == 0x3 && data.len() >= 19 {
+            Some(read_timestamp(&data[14..19]))
+        } else {
+            None
+        };
+
+        Some(PesHeader {
+            stream_id,
+            packet_length,
+            pts,
+            dts,
+            header_len,
+        })
+    }
+}
+
+fn read_timestamp(data: &[u8]) -> u64 {
+    (u64::from(data[0] >> 1 & 0x7) << 30)
+        | (u64::from(data[1]) << 22)
+        | (u64::from(data[2] >> 1) << 15)
+        | (u64::from(data[3]) << 7)
+        | u64::from(data[4] >> 1)
+}
+
+/// Forward distance between two clock values that wrap at `modulus`.
+pub fn clock_delta(from: u64, to: u64, modulus: u64) -> u64 {
+    (to + modulus - from) % modulus
+}
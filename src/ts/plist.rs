@@ -1,28 +1,21 @@
-use std::fmt::{Debug, Display};
+use core::fmt::{Debug, Display};
 
 /// https://en.wikibooks.org/wiki/User:Bdinfo/mpls
 use super::{stream_clip::Duration, FromBinary};
 
-#[repr(C, packed)]
-#[derive(Default, Debug, Clone)]
-pub struct PlaylistHeader {
-    pub length: u32,
-    _unk_0: u16,
-    pub item_count: u16,
-    pub subitem_count: u16,
-}
-
-impl FromBinary for PlaylistHeader {
-    fn sanitize(&mut self) {
-        self.length = self.length.swap_bytes();
-        self.item_count = self.item_count.swap_bytes();
-        self.subitem_count = self.subitem_count.swap_bytes();
+binary_struct! {
+    #[derive(Default, Debug, Clone)]
+    pub struct PlaylistHeader {
+        pub length: u32,
+        _unk_0: u16,
+        pub item_count: u16,
+        pub subitem_count: u16,
     }
 }
 
 impl PlaylistHeader {
     pub const fn expected_size() -> u32 {
-        10
+        Self::SIZE as u32
     }
     pub fn item_count(&self) -> u16 {
         self.item_count
@@ -32,23 +25,17 @@ impl PlaylistHeader {
     }
 }
 
-#[repr(C, packed)]
-#[derive(Default, Debug, Clone)]
-pub struct ChapterHeader {
-    pub length: u32,
-    pub mark_count: u16,
-}
-
-impl FromBinary for ChapterHeader {
-    fn sanitize(&mut self) {
-        self.length = self.length.swap_bytes();
-        self.mark_count = self.mark_count.swap_bytes();
+binary_struct! {
+    #[derive(Default, Debug, Clone)]
+    pub struct ChapterHeader {
+        pub length: u32,
+        pub mark_count: u16,
     }
 }
 
 impl ChapterHeader {
     pub const fn expected_size() -> u32 {
-        6
+        Self::SIZE as u32
     }
 
     pub fn length(&self) -> u32 {
@@ -60,38 +47,30 @@ impl ChapterHeader {
     }
 }
 
-#[repr(C, packed)]
-#[derive(Default, Clone)]
-pub struct ChapterSection {
-    _unk_0: u8,
-    pub mark_type: u8,
-    pub play_item_id: u16,
-    timestamp: i32,
-    _entry_espid: u16,
-    duration: i32,
+binary_struct! {
+    #[derive(Default, Clone)]
+    pub struct ChapterSection {
+        _unk_0: u8,
+        pub mark_type: u8,
+        pub play_item_id: u16,
+        timestamp: i32,
+        _entry_espid: u16,
+        duration: i32,
+    }
 }
 
 impl Display for ChapterSection {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:?}", FriendlyChapterSection::new(self))
     }
 }
 
 impl Debug for ChapterSection {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:#?}", FriendlyChapterSection::new(self))
     }
 }
 
-impl FromBinary for ChapterSection {
-    fn sanitize(&mut self) {
-        self.play_item_id = self.play_item_id.swap_bytes();
-        self.timestamp = self.timestamp.swap_bytes();
-        self._entry_espid = self._entry_espid.swap_bytes();
-        self.duration = self.duration.swap_bytes();
-    }
-}
-
 impl ChapterSection {
     pub fn timestamp(&self) -> f64 {
         let timestamp = if self.timestamp < 0 {
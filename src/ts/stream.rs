@@ -1,5 +1,7 @@
+use super::codec::{AudioInfo, StreamInfo, VideoInfo};
+use super::language::Language;
 use super::stream_type::{
-    AspectRatio, ChannelLayout, FrameRate, SampleRate, StreamType, VideoFormat,
+    AspectRatio, ChannelLayout, CharacterCode, FrameRate, SampleRate, StreamType, VideoFormat,
 };
 
 // #[derive(Default, Debug)]
@@ -20,39 +22,77 @@ use super::stream_type::{
 //     pub angle_index: i32,
 // }
 
-#[derive(Debug)]
+/// Measured from the transport stream, all zero until the clip's stream
+/// file has been scanned.
+#[derive(Debug, Clone, Default)]
+pub struct StreamStats {
+    pub payload_bytes: u64,
+    pub packet_count: u64,
+    pub packet_seconds: f64,
+    pub active_seconds: f64,
+    pub bit_rate: u64,
+    pub active_bit_rate: u64,
+}
+
+impl StreamStats {
+    pub fn add(&mut self, payload_bytes: u64, packet_count: u64, seconds: f64, active: f64) {
+        self.payload_bytes += payload_bytes;
+        self.packet_count += packet_count;
+        self.packet_seconds += seconds;
+        self.active_seconds += active;
+
+        let bits = self.payload_bytes as f64 * 8.0;
+        if self.packet_seconds > 0.0 {
+            self.bit_rate = (bits / self.packet_seconds + 0.5) as u64;
+        }
+        if self.active_seconds > 0.0 {
+            self.active_bit_rate = (bits / self.active_seconds + 0.5) as u64;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TSVideoStream {
     pub pid: u16,
     pub stream_type: StreamType,
     pub video_format: VideoFormat,
     pub aspect_ratio: AspectRatio,
     pub frame_rate: FrameRate,
+    pub stats: StreamStats,
+    /// Filled in by probing the elementary stream, see `Disc::probe_streams`.
+    pub info: Option<VideoInfo>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TSAudioStream {
     pub pid: u16,
     pub stream_type: StreamType,
     pub channel_layout: ChannelLayout,
     pub sample_rate: SampleRate,
-    pub lang_code: String,
+    pub language: Language,
+    pub stats: StreamStats,
+    /// Filled in by probing the elementary stream, see `Disc::probe_streams`.
+    pub info: Option<AudioInfo>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TSGraphicsStream {
     pub pid: u16,
     pub stream_type: StreamType,
-    pub lang_code: String,
+    pub language: Language,
+    pub stats: StreamStats,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TSTextStream {
     pub pid: u16,
     pub stream_type: StreamType,
-    pub lang_code: String,
+    pub character_code: CharacterCode,
+    pub language: Language,
+    pub stats: StreamStats,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Stream {
     VideoStream(TSVideoStream),
     AudioStream(TSAudioStream),
@@ -61,6 +101,77 @@ pub enum Stream {
 }
 
 impl Stream {
+    /// Builds a stream from the stream attributes of an STN entry, starting
+    /// with the coding type. `None` for unsupported or truncated entries.
+    pub fn from_attributes(pid: u16, attributes: &[u8]) -> Option<Self> {
+        let stream_type: StreamType = (*attributes.first()?).into();
+        let language = |at: usize| attributes.get(at..at + 3).map(Language::from_attribute);
+        let stats = StreamStats::default();
+        match stream_type {
+            StreamType::HEVC_VIDEO
+            | StreamType::AVC_VIDEO
+            | StreamType::MPEG1_VIDEO
+            | StreamType::MPEG2_VIDEO
+            | StreamType::VC1_VIDEO => {
+                let format_flags = *attributes.get(1)?;
+                let aspect_ratio = *attributes.get(2)? >> 4;
+                Some(Stream::VideoStream(TSVideoStream {
+                    pid,
+                    stream_type,
+                    video_format: (format_flags >> 4).into(),
+                    frame_rate: (format_flags & 0xF).into(),
+                    aspect_ratio: aspect_ratio.into(),
+                    stats,
+                    info: None,
+                }))
+            }
+
+            StreamType::AC3_AUDIO
+            | StreamType::AC3_PLUS_AUDIO
+            | StreamType::AC3_PLUS_SECONDARY_AUDIO
+            | StreamType::AC3_TRUE_HD_AUDIO
+            | StreamType::DTS_AUDIO
+            | StreamType::DTS_HD_AUDIO
+            | StreamType::DTS_HD_MASTER_AUDIO
+            | StreamType::DTS_HD_SECONDARY_AUDIO
+            | StreamType::LPCM_AUDIO
+            | StreamType::MPEG1_AUDIO
+            | StreamType::MPEG2_AUDIO
+            | StreamType::MPEG2_AAC_AUDIO
+            | StreamType::MPEG4_AAC_AUDIO => {
+                let format_flags = *attributes.get(1)?;
+                Some(Stream::AudioStream(TSAudioStream {
+                    pid,
+                    stream_type,
+                    channel_layout: (format_flags >> 4).into(),
+                    sample_rate: (format_flags & 0xF).into(),
+                    language: language(2)?,
+                    stats,
+                    info: None,
+                }))
+            }
+
+            StreamType::INTERACTIVE_GRAPHICS | StreamType::PRESENTATION_GRAPHICS => {
+                Some(Stream::GraphicsStream(TSGraphicsStream {
+                    pid,
+                    stream_type,
+                    language: language(1)?,
+                    stats,
+                }))
+            }
+
+            // The character code precedes the language.
+            StreamType::SUBTITLE => Some(Stream::TextStream(TSTextStream {
+                pid,
+                stream_type,
+                character_code: (*attributes.get(1)?).into(),
+                language: language(2)?,
+                stats,
+            })),
+            _ => None,
+        }
+    }
+
     pub fn pid(&self) -> u16 {
         match self {
             Stream::VideoStream(s) => s.pid,
@@ -70,6 +181,66 @@ impl Stream {
         }
     }
 
+    /// ISO 639-2 code as stored on the disc, empty for video.
+    pub fn lang_code(&self) -> &str {
+        self.language().map_or("", |language| language.as_str())
+    }
+
+    /// Language of audio, graphics and text streams.
+    pub fn language(&self) -> Option<&Language> {
+        match self {
+            Stream::VideoStream(_) => None,
+            Stream::AudioStream(s) => Some(&s.language),
+            Stream::GraphicsStream(s) => Some(&s.language),
+            Stream::TextStream(s) => Some(&s.language),
+        }
+    }
+
+    pub fn stats(&self) -> &StreamStats {
+        match self {
+            Stream::VideoStream(s) => &s.stats,
+            Stream::AudioStream(s) => &s.stats,
+            Stream::GraphicsStream(s) => &s.stats,
+            Stream::TextStream(s) => &s.stats,
+        }
+    }
+
+    pub fn stats_mut(&mut self) -> &mut StreamStats {
+        match self {
+            Stream::VideoStream(s) => &mut s.stats,
+            Stream::AudioStream(s) => &mut s.stats,
+            Stream::GraphicsStream(s) => &mut s.stats,
+            Stream::TextStream(s) => &mut s.stats,
+        }
+    }
+
+    /// Sets the probed info, if it matches the kind of stream.
+    pub fn set_info(&mut self, info: Option<&StreamInfo>) {
+        match self {
+            Stream::VideoStream(s) => {
+                s.info = match info {
+                    Some(StreamInfo::Video(info)) => Some(info.clone()),
+                    _ => None,
+                }
+            }
+            Stream::AudioStream(s) => {
+                s.info = match info {
+                    Some(StreamInfo::Audio(info)) => Some(info.clone()),
+                    _ => None,
+                }
+            }
+            Stream::GraphicsStream(_) | Stream::TextStream(_) => {}
+        }
+    }
+
+    pub fn info(&self) -> Option<StreamInfo> {
+        match self {
+            Stream::VideoStream(s) => s.info.clone().map(StreamInfo::Video),
+            Stream::AudioStream(s) => s.info.clone().map(StreamInfo::Audio),
+            Stream::GraphicsStream(_) | Stream::TextStream(_) => None,
+        }
+    }
+
     pub fn stream_type(&self) -> StreamType {
         match self {
             Stream::VideoStream(s) => s.stream_type,
@@ -1,9 +1,7 @@
-use std::{
-    fmt::{Debug, Display},
-    ptr,
-};
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+use core::fmt::{Debug, Display};
 
-use super::FromBinary;
+use super::stream::Stream;
 
 #[derive(Debug)]
 pub struct Duration {
@@ -14,7 +12,7 @@ pub struct Duration {
 }
 
 impl Display for Duration {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "{}:{:0width$}:{:0width$}.{}",
@@ -29,9 +27,9 @@ impl Display for Duration {
 
 impl Duration {
     pub fn from_secs_f64(secs: f64) -> Self {
-        let std_dur = std::time::Duration::from_secs_f64(secs);
+        let std_dur = core::time::Duration::from_secs_f64(secs);
         let fracs = std_dur.subsec_nanos();
-        let seconds = secs.floor() as u64;
+        let seconds = secs as u64;
         let hours = seconds / 60 / 60;
         let minutes = seconds / 60 % 60;
         let secs = seconds % 60;
@@ -44,23 +42,31 @@ impl Duration {
     }
 }
 
-#[repr(C, packed)]
-#[derive(Default, Clone)]
-pub struct ClipHeader {
-    pub item_len: i16,
-    pub item_name: [u8; 5],
-    pub item_type: [u8; 4],
-    _unk: [u8; 1],
-    pub ma_c_flags: u8,
-    _unk_1: [u8; 1],
-    pub in_time: i32,
-    pub out_time: i32,
-    _unk_2: [u8; 12],
+binary_struct! {
+    #[derive(Default, Clone)]
+    pub struct ClipHeader {
+        pub item_len: i16,
+        pub item_name: [u8; 5],
+        pub item_type: [u8; 4],
+        _unk: [u8; 1],
+        pub ma_c_flags: u8,
+        _unk_1: [u8; 1],
+        pub in_time: i32,
+        pub out_time: i32,
+        _unk_2: [u8; 12],
+    }
+}
+
+bitfields! {
+    ClipHeader.ma_c_flags: u8 {
+        pub multi_angle: 4..5,
+        pub condition: 0..4,
+    }
 }
 
 impl ClipHeader {
     pub fn item_len(&self) -> i16 {
-        unsafe { ptr::read_unaligned(ptr::addr_of!(self.item_len)) }
+        self.item_len
     }
     pub fn stream_file_name(&self) -> String {
         format!("{}.M2TS", self.item_name())
@@ -74,12 +80,6 @@ impl ClipHeader {
     pub fn item_type(&self) -> String {
         String::from_utf8(self.item_type.to_vec()).unwrap_or_default()
     }
-    pub fn multi_angle(&self) -> u8 {
-        (self.ma_c_flags >> 4) & 0x01
-    }
-    pub fn condition(&self) -> u8 {
-        self.ma_c_flags & 0xF
-    }
     pub fn time_in(&self) -> f64 {
         let in_time = if self.in_time < 0 {
             self.in_time & 0x7FFF_FFFF
@@ -103,22 +103,14 @@ impl ClipHeader {
     }
 }
 
-impl FromBinary for ClipHeader {
-    fn sanitize(&mut self) {
-        self.item_len = self.item_len.swap_bytes();
-        self.in_time = self.in_time.swap_bytes();
-        self.out_time = self.out_time.swap_bytes();
-    }
-}
-
 impl Display for ClipHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:?}", FriendlyClipHeader::new(self))
     }
 }
 
 impl Debug for ClipHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:#?}", FriendlyClipHeader::new(self))
     }
 }
@@ -175,6 +167,9 @@ pub struct StreamClip {
     pub stream_file: String,
     // TSStreamClipFile StreamClipFile = null;
     pub stream_clip_file: String,
+    pub streams: Vec<Stream>,
+    /// Clips of the other angles of a multi-angle play item, from angle 1.
+    pub angles: Vec<StreamClip>,
 }
 
 impl StreamClip {
@@ -200,6 +195,15 @@ impl StreamClip {
 
         sc
     }
+    /// The clip played for `angle`, falling back to angle 0 for play items
+    /// with fewer angles.
+    pub fn angle(&self, angle: usize) -> &StreamClip {
+        match angle {
+            0 => self,
+            _ => self.angles.get(angle - 1).unwrap_or(self),
+        }
+    }
+
     pub fn display_name(&self) -> String {
         self.name.to_owned()
     }
@@ -208,7 +212,7 @@ impl StreamClip {
     }
     pub fn packet_bit_rate(&self) -> f64 {
         if self.packet_seconds > 0.0 {
-            ((self.packet_size() as f64 * 8.0) / self.packet_seconds).round()
+            ((self.packet_size() as f64 * 8.0) / self.packet_seconds + 0.5) as u64 as f64
         } else {
             0.0
         }
@@ -0,0 +1,262 @@
+use std::{
+    collections::BTreeMap,
+    io::{ErrorKind, Read, Result},
+};
+
+use super::packet::{
+    clock_delta, PesHeader, SourcePacket, PCR_CLOCK, PCR_MODULUS, PTS_CLOCK, PTS_MODULUS,
+    SOURCE_PACKET_SIZE, SYNC_BYTE,
+};
+
+/// Clock jumps larger than this are treated as discontinuities.
+const MAX_CLOCK_GAP: f64 = 10.0;
+
+#[derive(Debug, Default, Clone)]
+pub struct PidStats {
+    pub packet_count: u64,
+    pub payload_bytes: u64,
+    pub pes_count: u64,
+    first_pts: Option<u64>,
+    last_pts: Option<u64>,
+    pts_elapsed: u64,
+}
+
+impl PidStats {
+    /// Seconds between the first and the last presentation timestamp.
+    pub fn active_seconds(&self) -> f64 {
+        self.pts_elapsed as f64 / PTS_CLOCK
+    }
+
+    fn add_pts(&mut self, pts: u64) {
+        let first = *self.first_pts.get_or_insert(pts);
+        if let Some(last) = self.last_pts {
+            let step = clock_delta(last, pts, PTS_MODULUS);
+            if step as f64 / PTS_CLOCK > MAX_CLOCK_GAP && step < PTS_MODULUS / 2 {
+                // Discontinuity, restart the span from here.
+                self.first_pts = Some(pts);
+                self.last_pts = Some(pts);
+                return;
+            }
+        }
+        let offset = clock_delta(first, pts, PTS_MODULUS);
+        // PTS of reordered frames can run backwards, keep the furthest one.
+        if offset < PTS_MODULUS / 2 && offset > self.pts_elapsed {
+            self.pts_elapsed = offset;
+        }
+        self.last_pts = Some(pts);
+    }
+}
+
+/// Packet, payload and timing statistics of one `.m2ts` file.
+#[derive(Debug, Default, Clone)]
+pub struct StreamFileScan {
+    pub file_size: u64,
+    pub packet_count: u64,
+    pub payload_bytes: u64,
+    pub sync_errors: u64,
+    pub pids: BTreeMap<u16, PidStats>,
+    last_pcr: Option<u64>,
+    pcr_elapsed: u64,
+}
+
+impl StreamFileScan {
+    /// Duration from the PCR, falling back to the longest PTS span.
+    pub fn duration(&self) -> f64 {
+        if self.pcr_elapsed > 0 {
+            self.pcr_elapsed as f64 / PCR_CLOCK
+        } else {
+            self.pids
+                .values()
+                .map(|p| p.active_seconds())
+                .fold(0.0, f64::max)
+        }
+    }
+
+    pub fn bit_rate(&self) -> f64 {
+        let duration = self.duration();
+        if duration > 0.0 {
+            (self.packet_count * SOURCE_PACKET_SIZE as u64 * 8) as f64 / duration
+        } else {
+            0.0
+        }
+    }
+
+    fn add_packet(&mut self, packet: &SourcePacket) {
+        self.packet_count += 1;
+        self.payload_bytes += packet.payload.len() as u64;
+
+        if let Some(pcr) = packet.pcr {
+            if let Some(last) = self.last_pcr {
+                let step = clock_delta(last, pcr, PCR_MODULUS);
+                if (step as f64 / PCR_CLOCK) < MAX_CLOCK_GAP {
+                    self.pcr_elapsed += step;
+                }
+            }
+            self.last_pcr = Some(pcr);
+        }
+
+        let stats = self.pids.entry(packet.pid).or_default();
+        stats.packet_count += 1;
+        stats.payload_bytes += packet.payload.len() as u64;
+        if packet.payload_unit_start {
+            if let Some(pes) = PesHeader::parse(packet.payload) {
+                stats.pes_count += 1;
+                if let Some(pts) = pes.pts {
+                    stats.add_pts(pts);
+                }
+            }
+        }
+    }
+}
+
+/// Walks the source packets of a BDAV stream. `visit` returns `false` to
+/// stop early. Returns the number of bytes read and how many times the
+/// reader had to resynchronise on a sync byte.
+pub fn for_each_packet<R, F>(reader: &mut R, mut visit: F) -> Result<(u64, u64)>
+where
+    R: Read,
+    F: FnMut(&SourcePacket) -> bool,
+{
+    let mut buffer = vec![0u8; SOURCE_PACKET_SIZE * 1024];
+    let mut filled = 0;
+    let mut bytes_read = 0;
+    let mut sync_errors = 0;
+
+    loop {
+        let read = match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        bytes_read += read as u64;
+        filled += read;
+
+        let mut pos = 0;
+        while filled - pos >= SOURCE_PACKET_SIZE {
+            match SourcePacket::parse(&buffer[pos..pos + SOURCE_PACKET_SIZE]) {
+                Some(packet) => {
+                    if !visit(&packet) {
+                        return Ok((bytes_read, sync_errors));
+                    }
+                    pos += SOURCE_PACKET_SIZE;
+                }
+                None => {
+                    sync_errors += 1;
+                    pos += 1;
+                    while pos + 4 < filled && buffer[pos + 4] != SYNC_BYTE {
+                        pos += 1;
+                    }
+                }
+            }
+        }
+        buffer.copy_within(pos..filled, 0);
+        filled -= pos;
+    }
+
+    Ok((bytes_read, sync_errors))
+}
+
+/// Scans a whole `.m2ts` file, counting packets and payload per PID.
+pub fn scan<R: Read>(reader: &mut R) -> Result<StreamFileScan> {
+    let mut scan = StreamFileScan::default();
+    let (file_size, sync_errors) = for_each_packet(reader, |packet| {
+        scan.add_packet(packet);
+        true
+    })?;
+    scan.file_size = file_size;
+    scan.sync_errors = sync_errors;
+    Ok(scan)
+}
+
+/// Collects the start of the elementary streams of `pids`, PES headers
+/// removed, until each has `limit` bytes or `max_read` bytes of the file
+/// have been read. Data before the first PES packet of a PID is dropped.
+pub fn read_elementary_streams<R: Read>(
+    reader: &mut R,
+    pids: &[u16],
+    limit: usize,
+    max_read: u64,
+) -> Result<BTreeMap<u16, Vec<u8>>> {
+    let mut streams: BTreeMap<u16, Vec<u8>> = BTreeMap::new();
+    let mut started: BTreeMap<u16, bool> = pids.iter().map(|pid| (*pid, false)).collect();
+    let mut read = 0u64;
+
+    for_each_packet(reader, |packet| {
+        read += SOURCE_PACKET_SIZE as u64;
+        if let Some(started) = started.get_mut(&packet.pid) {
+            let data = streams.entry(packet.pid).or_default();
+            if data.len() < limit {
+                if packet.payload_unit_start {
+                    if let Some(pes) = PesHeader::parse(packet.payload) {
+                        *started = true;
+                        data.extend_from_slice(packet.payload.get(pes.header_len..).unwrap_or(&[]));
+                    }
+                } else if *started {
+                    data.extend_from_slice(packet.payload);
+                }
+            }
+        }
+        read < max_read
+            && pids
+                .iter()
+                .any(|pid| streams.get(pid).map(|d| d.len()).unwrap_or(0) < limit)
+    })?;
+
+    Ok(streams)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{pes, pes_packets, source_packet};
+
+    fn sample() -> Vec<u8> {
+        let mut data = source_packet(0x1001, false, Some(0), &[]);
+        data.extend(pes_packets(0x1100, &pes(0xBD, Some(0), &[1; 300])));
+        data.extend(source_packet(0x1001, false, Some(27_000_000), &[]));
+        data.extend(pes_packets(0x1100, &pes(0xBD, Some(45_000), &[2; 10])));
+        data
+    }
+
+    #[test]
+    fn scans_packets() {
+        let data = sample();
+        let scan = scan(&mut data.as_slice()).unwrap();
+        assert_eq!(scan.file_size, data.len() as u64);
+        assert_eq!(scan.packet_count, 5);
+        assert_eq!(scan.sync_errors, 0);
+        assert_eq!(scan.duration(), 1.0);
+        assert_eq!(scan.bit_rate(), 5.0 * 192.0 * 8.0);
+
+        let audio = &scan.pids[&0x1100];
+        assert_eq!(audio.packet_count, 3);
+        assert_eq!(audio.pes_count, 2);
+        assert_eq!(audio.active_seconds(), 0.5);
+    }
+
+    #[test]
+    fn resynchronises_after_garbage() {
+        let mut data = vec![0x12, 0x34, 0x56];
+        data.extend(sample());
+        data.splice(195..195, [0xAA; 7]);
+        let scan = scan(&mut data.as_slice()).unwrap();
+        assert_eq!(scan.packet_count, 5);
+        assert!(scan.sync_errors > 0);
+    }
+
+    #[test]
+    fn collects_elementary_streams() {
+        let mut data = source_packet(0x1100, false, None, &[9; 184]);
+        data.extend(sample());
+        let streams =
+            read_elementary_streams(&mut data.as_slice(), &[0x1100], 1024, u64::MAX).unwrap();
+        let audio = &streams[&0x1100];
+        assert_eq!(audio.len(), 310);
+        assert!(audio[..300].iter().all(|&b| b == 1));
+
+        let streams =
+            read_elementary_streams(&mut data.as_slice(), &[0x1100], 16, u64::MAX).unwrap();
+        assert_eq!(streams[&0x1100].len(), 184 - 14);
+    }
+}
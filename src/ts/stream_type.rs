@@ -1,4 +1,6 @@
 #![allow(unused, non_camel_case_types)]
+use core::fmt::Display;
+
 macro_rules! impl_default {
     ($ident:ident) => {
         impl Default for $ident {
@@ -10,7 +12,7 @@ macro_rules! impl_default {
 }
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum StreamType {
     Unknown = 0,
     MPEG1_VIDEO = 0x01,
@@ -74,7 +76,7 @@ impl From<u8> for StreamType {
 }
 
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VideoFormat {
     Unknown = 0x00,
     VIDEOFORMAT_480i = 0x01,
@@ -107,7 +109,7 @@ impl From<u8> for VideoFormat {
 }
 
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FrameRate {
     Unknown = 0x00,
     FRAMERATE_23_976 = 0x01,
@@ -136,7 +138,7 @@ impl From<u8> for FrameRate {
 }
 
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChannelLayout {
     Unknown = 0x00,
     CHANNELLAYOUT_MONO = 0x01,
@@ -161,7 +163,7 @@ impl From<u8> for ChannelLayout {
 }
 
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SampleRate {
     Unknown = 0x00,
     SAMPLERATE_48 = 0x01,
@@ -188,7 +190,7 @@ impl From<u8> for SampleRate {
 }
 
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AspectRatio {
     Unknown = 0x00,
     ASPECT_4_3 = 0x02,
@@ -210,9 +212,162 @@ impl From<u8> for AspectRatio {
     }
 }
 
+impl StreamType {
+    /// Codec name as printed by BDInfo.
+    pub fn codec_name(&self) -> &'static str {
+        match self {
+            StreamType::Unknown => "Unknown",
+            StreamType::MPEG1_VIDEO => "MPEG-1 Video",
+            StreamType::MPEG2_VIDEO => "MPEG-2 Video",
+            StreamType::AVC_VIDEO => "MPEG-4 AVC Video",
+            StreamType::MVC_VIDEO => "MPEG-4 MVC Video",
+            StreamType::HEVC_VIDEO => "MPEG-H HEVC Video",
+            StreamType::VC1_VIDEO => "VC-1 Video",
+            StreamType::MPEG1_AUDIO => "MPEG-1 Audio",
+            StreamType::MPEG2_AUDIO => "MPEG-2 Audio",
+            StreamType::MPEG2_AAC_AUDIO => "MPEG-2 AAC Audio",
+            StreamType::MPEG4_AAC_AUDIO => "MPEG-4 AAC Audio",
+            StreamType::LPCM_AUDIO => "LPCM Audio",
+            StreamType::AC3_AUDIO => "Dolby Digital Audio",
+            StreamType::AC3_PLUS_AUDIO | StreamType::AC3_PLUS_SECONDARY_AUDIO => {
+                "Dolby Digital Plus Audio"
+            }
+            StreamType::AC3_TRUE_HD_AUDIO => "Dolby TrueHD Audio",
+            StreamType::DTS_AUDIO => "DTS Audio",
+            StreamType::DTS_HD_AUDIO => "DTS-HD High-Res Audio",
+            StreamType::DTS_HD_SECONDARY_AUDIO => "DTS Express",
+            StreamType::DTS_HD_MASTER_AUDIO => "DTS-HD Master Audio",
+            StreamType::PRESENTATION_GRAPHICS => "Presentation Graphics",
+            StreamType::INTERACTIVE_GRAPHICS => "Interactive Graphics",
+            StreamType::SUBTITLE => "Subtitle",
+        }
+    }
+}
+
+impl Display for VideoFormat {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            VideoFormat::Unknown => "",
+            VideoFormat::VIDEOFORMAT_480i => "480i",
+            VideoFormat::VIDEOFORMAT_576i => "576i",
+            VideoFormat::VIDEOFORMAT_480p => "480p",
+            VideoFormat::VIDEOFORMAT_1080i => "1080i",
+            VideoFormat::VIDEOFORMAT_720p => "720p",
+            VideoFormat::VIDEOFORMAT_1080p => "1080p",
+            VideoFormat::VIDEOFORMAT_576p => "576p",
+            VideoFormat::VIDEOFORMAT_2160p => "2160p",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for FrameRate {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            FrameRate::Unknown => "",
+            FrameRate::FRAMERATE_23_976 => "23.976 fps",
+            FrameRate::FRAMERATE_24 => "24 fps",
+            FrameRate::FRAMERATE_25 => "25 fps",
+            FrameRate::FRAMERATE_29_97 => "29.97 fps",
+            FrameRate::FRAMERATE_50 => "50 fps",
+            FrameRate::FRAMERATE_59_94 => "59.94 fps",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for AspectRatio {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            AspectRatio::Unknown => "",
+            AspectRatio::ASPECT_4_3 => "4:3",
+            AspectRatio::ASPECT_16_9 => "16:9",
+            AspectRatio::ASPECT_2_21 => "2.21:1",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for ChannelLayout {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            ChannelLayout::Unknown => "",
+            ChannelLayout::CHANNELLAYOUT_MONO => "Mono",
+            ChannelLayout::CHANNELLAYOUT_STEREO => "Stereo",
+            ChannelLayout::CHANNELLAYOUT_MULTI => "Multi-channel",
+            ChannelLayout::CHANNELLAYOUT_COMBO => "Stereo + Multi-channel",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for SampleRate {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            SampleRate::Unknown => "",
+            SampleRate::SAMPLERATE_48 => "48 kHz",
+            SampleRate::SAMPLERATE_96 => "96 kHz",
+            SampleRate::SAMPLERATE_192 => "192 kHz",
+            SampleRate::SAMPLERATE_48_192 => "48/192 kHz",
+            SampleRate::SAMPLERATE_48_96 => "48/96 kHz",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Text encoding of a text subtitle stream's dialogs.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterCode {
+    Unknown = 0x00,
+    CHARACTERCODE_UTF8 = 0x01,
+    CHARACTERCODE_UTF16BE = 0x02,
+    CHARACTERCODE_SHIFT_JIS = 0x03,
+    /// KS C 5601-1987, as EUC-KR.
+    CHARACTERCODE_KSC_5601 = 0x04,
+    CHARACTERCODE_GB18030 = 0x05,
+    /// GB 2312, as EUC-CN.
+    CHARACTERCODE_GB2312 = 0x06,
+    CHARACTERCODE_BIG5 = 0x07,
+}
+impl From<u8> for CharacterCode {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => CharacterCode::CHARACTERCODE_UTF8,
+            0x02 => CharacterCode::CHARACTERCODE_UTF16BE,
+            0x03 => CharacterCode::CHARACTERCODE_SHIFT_JIS,
+            0x04 => CharacterCode::CHARACTERCODE_KSC_5601,
+            0x05 => CharacterCode::CHARACTERCODE_GB18030,
+            0x06 => CharacterCode::CHARACTERCODE_GB2312,
+            0x07 => CharacterCode::CHARACTERCODE_BIG5,
+            _ => {
+                error!("Unknown value for CharacterCode", "{}", value);
+                CharacterCode::Unknown
+            }
+        }
+    }
+}
+
+impl Display for CharacterCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            CharacterCode::Unknown => "",
+            CharacterCode::CHARACTERCODE_UTF8 => "UTF-8",
+            CharacterCode::CHARACTERCODE_UTF16BE => "UTF-16BE",
+            CharacterCode::CHARACTERCODE_SHIFT_JIS => "Shift-JIS",
+            CharacterCode::CHARACTERCODE_KSC_5601 => "KSC 5601",
+            CharacterCode::CHARACTERCODE_GB18030 => "GB18030",
+            CharacterCode::CHARACTERCODE_GB2312 => "GB2312",
+            CharacterCode::CHARACTERCODE_BIG5 => "Big5",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 impl_default!(StreamType);
 impl_default!(VideoFormat);
 impl_default!(FrameRate);
 impl_default!(ChannelLayout);
 impl_default!(SampleRate);
 impl_default!(AspectRatio);
+impl_default!(CharacterCode);
@@ -1,30 +1,25 @@
-use std::fmt::Debug;
+use core::fmt::Debug;
 
-use super::FromBinary;
-
-#[repr(C, packed)]
-#[derive(Default)]
-pub struct StreamsHeader {
-    pub stream_info_len: u16,
-    _pad_0: [u8; 0x2],
-    pub stream_count_video: u8,
-    pub stream_count_audio: u8,
-    pub stream_count_pg: u8,
-    pub stream_count_ig: u8,
-    pub stream_count_secondary_audio: u8,
-    pub stream_count_secondary_video: u8,
-    pub stream_count_pip: u8,
-    _pad_1: [u8; 0x5],
-}
-
-impl FromBinary for StreamsHeader {
-    fn sanitize(&mut self) {
-        self.stream_info_len = self.stream_info_len.swap_bytes();
+binary_struct! {
+    #[derive(Default, Clone)]
+    pub struct StreamsHeader {
+        pub stream_info_len: u16,
+        _pad_0: [u8; 0x2],
+        pub stream_count_video: u8,
+        pub stream_count_audio: u8,
+        pub stream_count_pg: u8,
+        pub stream_count_ig: u8,
+        pub stream_count_secondary_audio: u8,
+        pub stream_count_secondary_video: u8,
+        pub stream_count_pip: u8,
+        /// Dolby Vision enhancement layer streams on UHD discs.
+        pub stream_count_dolby_vision: u8,
+        _pad_1: [u8; 0x4],
     }
 }
 
 impl Debug for StreamsHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:#?}", FriendlyStreamsHeader::new(self))
     }
 }
@@ -46,6 +41,7 @@ struct FriendlyStreamsHeader {
     stream_count_secondary_audio: u8,
     stream_count_secondary_video: u8,
     stream_count_pip: u8,
+    stream_count_dolby_vision: u8,
 }
 
 impl FriendlyStreamsHeader {
@@ -59,6 +55,7 @@ impl FriendlyStreamsHeader {
             stream_count_secondary_audio: header.stream_count_secondary_audio,
             stream_count_secondary_video: header.stream_count_secondary_video,
             stream_count_pip: header.stream_count_pip,
+            stream_count_dolby_vision: header.stream_count_dolby_vision,
         }
     }
 }
@@ -0,0 +1,781 @@
+use core::fmt::Write;
+use std::io::Result;
+
+use encoding_rs::{Encoding, BIG5, EUC_KR, GB18030, GBK, SHIFT_JIS, UTF_16BE, UTF_8};
+
+use super::stream_type::CharacterCode;
+use crate::playlist_view::{ByteReader, ParseResult};
+
+const SEGMENT_DIALOG_STYLE: u8 = 0x81;
+const SEGMENT_DIALOG_PRESENTATION: u8 = 0x82;
+
+/// Marks an inline entry in a dialog region's data.
+const ESCAPE: u8 = 0x1B;
+const INLINE_TEXT: u8 = 0x01;
+const INLINE_FONT_ID: u8 = 0x02;
+const INLINE_FONT_STYLE: u8 = 0x03;
+const INLINE_FONT_SIZE: u8 = 0x04;
+const INLINE_FONT_COLOR: u8 = 0x05;
+const INLINE_LINE_BREAK: u8 = 0x0A;
+const INLINE_RESET_STYLE: u8 = 0x0B;
+
+/// Text subtitle timestamps tick at 90 kHz.
+const TEXTST_CLOCK: f64 = 90_000.0;
+
+/// Text subtitles are authored for a 1920x1080 frame.
+const FRAME_WIDTH: i32 = 1920;
+const FRAME_HEIGHT: i32 = 1080;
+
+impl CharacterCode {
+    fn encoding(&self) -> &'static Encoding {
+        match self {
+            CharacterCode::Unknown | CharacterCode::CHARACTERCODE_UTF8 => UTF_8,
+            CharacterCode::CHARACTERCODE_UTF16BE => UTF_16BE,
+            CharacterCode::CHARACTERCODE_SHIFT_JIS => SHIFT_JIS,
+            CharacterCode::CHARACTERCODE_KSC_5601 => EUC_KR,
+            CharacterCode::CHARACTERCODE_GB18030 => GB18030,
+            // GBK is a superset of EUC-CN.
+            CharacterCode::CHARACTERCODE_GB2312 => GBK,
+            CharacterCode::CHARACTERCODE_BIG5 => BIG5,
+        }
+    }
+
+    /// Decodes dialog text, replacing malformed sequences.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        self.encoding()
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    fn read(reader: &mut ByteReader<'_>) -> ParseResult<Self> {
+        Ok(Rect {
+            x: reader.u16()?,
+            y: reader.u16()?,
+            width: reader.u16()?,
+            height: reader.u16()?,
+        })
+    }
+}
+
+/// Font style bits of a region style or an inline style change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FontStyle(pub u8);
+
+impl FontStyle {
+    pub fn bold(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+
+    pub fn italic(&self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    pub fn outline(&self) -> bool {
+        self.0 & 0x04 != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub id: u8,
+    pub y: u8,
+    pub cr: u8,
+    pub cb: u8,
+    /// Opacity, 0 is transparent.
+    pub t: u8,
+}
+
+impl PaletteEntry {
+    /// BT.709 studio range to full range RGB.
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        let y = 1.164 * (self.y as f64 - 16.0);
+        let cr = self.cr as f64 - 128.0;
+        let cb = self.cb as f64 - 128.0;
+        let clamp = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+        (
+            clamp(y + 1.793 * cr),
+            clamp(y - 0.213 * cb - 0.533 * cr),
+            clamp(y + 2.112 * cb),
+        )
+    }
+}
+
+/// Where and how a dialog region is drawn.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionStyle {
+    pub id: u8,
+    pub region: Rect,
+    pub background_color: u8,
+    /// Relative to the region.
+    pub text_box: Rect,
+    pub text_flow: u8,
+    /// 1 left, 2 center, 3 right.
+    pub h_align: u8,
+    /// 1 top, 2 middle, 3 bottom.
+    pub v_align: u8,
+    pub line_space: u8,
+    pub font_id: u8,
+    pub font_style: FontStyle,
+    pub font_size: u8,
+    pub font_color: u8,
+    pub outline_color: u8,
+    /// 1 thin, 2 medium, 3 thick.
+    pub outline_thickness: u8,
+}
+
+/// The dialog style segment that starts a text subtitle stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DialogStyle {
+    /// Whether the player may substitute its own styles.
+    pub player_style: bool,
+    pub region_styles: Vec<RegionStyle>,
+    pub palette: Vec<PaletteEntry>,
+    /// Number of dialog presentation segments that follow.
+    pub dialog_count: u16,
+}
+
+/// A piece of a dialog region's content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    Text(String),
+    FontId(u8),
+    FontStyle {
+        style: FontStyle,
+        outline_color: u8,
+        outline_thickness: u8,
+    },
+    FontSize(u8),
+    FontColor(u8),
+    LineBreak,
+    /// Back to the region style.
+    ResetStyle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogRegion {
+    /// Continues the same region of the previous dialog without a gap.
+    pub continuous: bool,
+    pub forced: bool,
+    pub style_id: u8,
+    pub content: Vec<Inline>,
+}
+
+/// One dialog presentation segment, times in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialog {
+    pub start: f64,
+    pub end: f64,
+    /// Palette entries that replace the dialog style's from here on.
+    pub palette_update: Vec<PaletteEntry>,
+    pub regions: Vec<DialogRegion>,
+}
+
+/// A text subtitle (TextST) stream, from its PES payloads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSubtitle {
+    pub character_code: CharacterCode,
+    pub style: Option<DialogStyle>,
+    pub dialogs: Vec<Dialog>,
+}
+
+impl TextSubtitle {
+    pub fn new(character_code: CharacterCode) -> Self {
+        TextSubtitle {
+            character_code,
+            style: None,
+            dialogs: Vec::new(),
+        }
+    }
+
+    /// Adds the segments of one PES payload.
+    pub fn push_payload(&mut self, payload: &[u8]) -> Result<()> {
+        let mut reader = ByteReader::new(payload);
+        while reader.remaining() >= 3 {
+            let segment_type = reader.u8()?;
+            let len = reader.u16()? as usize;
+            let mut segment = ByteReader::at(payload, reader.position(), len)?;
+            reader.skip(len)?;
+            match segment_type {
+                SEGMENT_DIALOG_STYLE => self.style = Some(read_dialog_style(&mut segment)?),
+                SEGMENT_DIALOG_PRESENTATION => {
+                    let dialog = read_dialog(&mut segment, self.character_code)?;
+                    self.dialogs.push(dialog);
+                }
+                _ => error!("Unknown text subtitle segment", "{:#04X}", segment_type),
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves every dialog by `seconds`, dropping the ones that end up
+    /// entirely before zero.
+    pub fn shift(&mut self, seconds: f64) {
+        self.dialogs.retain_mut(|dialog| {
+            dialog.start = (dialog.start + seconds).max(0.0);
+            dialog.end += seconds;
+            dialog.end > 0.0
+        });
+    }
+
+    fn region_style(&self, id: u8) -> RegionStyle {
+        self.style
+            .as_ref()
+            .and_then(|style| style.region_styles.iter().find(|s| s.id == id))
+            .cloned()
+            .unwrap_or_else(default_region_style)
+    }
+
+    pub fn to_srt(&self) -> String {
+        let mut out = String::new();
+        for (index, dialog) in self.dialogs.iter().enumerate() {
+            let text = dialog
+                .regions
+                .iter()
+                .map(|region| {
+                    let base = self.region_style(region.style_id).font_style;
+                    srt_text(region, base)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let _ = write!(
+                out,
+                "{}\n{} --> {}\n{}\n\n",
+                index + 1,
+                srt_time(dialog.start),
+                srt_time(dialog.end),
+                text
+            );
+        }
+        out
+    }
+
+    pub fn to_ass(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "[Script Info]\nScriptType: v4.00+\nPlayResX: {}\nPlayResY: {}\nWrapStyle: 2\nScaledBorderAndShadow: yes\n\n",
+            FRAME_WIDTH, FRAME_HEIGHT
+        );
+        out.push_str(
+            "[V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, \
+             OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, \
+             Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, \
+             MarginV, Encoding\n",
+        );
+        let default_style = [default_region_style()];
+        let styles = match &self.style {
+            Some(style) if !style.region_styles.is_empty() => &style.region_styles[..],
+            _ => &default_style[..],
+        };
+        let palette = self
+            .style
+            .as_ref()
+            .map(|style| &style.palette[..])
+            .unwrap_or_default();
+        for style in styles {
+            let text_left = style.region.x as i32 + style.text_box.x as i32;
+            let text_top = style.region.y as i32 + style.text_box.y as i32;
+            let margin_v = if style.v_align == 1 {
+                text_top
+            } else {
+                FRAME_HEIGHT - text_top - style.text_box.height as i32
+            };
+            let _ = writeln!(
+                out,
+                "Style: Region{},Arial,{},{},&H000000FF,{},{},{},{},0,0,100,100,0,0,1,{},0,{},{},{},{},1",
+                style.id,
+                style.font_size,
+                ass_color(palette, style.font_color),
+                ass_color(palette, style.outline_color),
+                ass_color(palette, style.background_color),
+                if style.font_style.bold() { -1 } else { 0 },
+                if style.font_style.italic() { -1 } else { 0 },
+                if style.font_style.outline() {
+                    style.outline_thickness
+                } else {
+                    0
+                },
+                ass_alignment(style),
+                text_left.max(0),
+                (FRAME_WIDTH - text_left - style.text_box.width as i32).max(0),
+                margin_v.max(0),
+            );
+        }
+        out.push_str(
+            "\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, \
+             Effect, Text\n",
+        );
+        for dialog in &self.dialogs {
+            for region in &dialog.regions {
+                let _ = writeln!(
+                    out,
+                    "Dialogue: 0,{},{},Region{},,0,0,0,,{}",
+                    ass_time(dialog.start),
+                    ass_time(dialog.end),
+                    region.style_id,
+                    ass_text(region, palette)
+                );
+            }
+        }
+        out
+    }
+}
+
+fn default_region_style() -> RegionStyle {
+    RegionStyle {
+        h_align: 2,
+        v_align: 3,
+        font_size: 48,
+        ..Default::default()
+    }
+}
+
+fn read_palette(reader: &mut ByteReader<'_>) -> ParseResult<Vec<PaletteEntry>> {
+    let len = reader.u16()? as usize;
+    let mut palette = Vec::with_capacity(len / 5);
+    for _ in 0..len / 5 {
+        palette.push(PaletteEntry {
+            id: reader.u8()?,
+            y: reader.u8()?,
+            cr: reader.u8()?,
+            cb: reader.u8()?,
+            t: reader.u8()?,
+        });
+    }
+    reader.skip(len % 5)?;
+    Ok(palette)
+}
+
+fn read_dialog_style(reader: &mut ByteReader<'_>) -> ParseResult<DialogStyle> {
+    let flags = reader.u16()?;
+    let region_style_count = reader.u8()?;
+    let user_style_count = reader.u8()?;
+    let mut region_styles = Vec::with_capacity(region_style_count as usize);
+    for _ in 0..region_style_count {
+        let id = reader.u8()?;
+        let region = Rect::read(reader)?;
+        let background_color = reader.u8()?;
+        reader.skip(1)?;
+        region_styles.push(RegionStyle {
+            id,
+            region,
+            background_color,
+            text_box: Rect::read(reader)?,
+            text_flow: reader.u8()?,
+            h_align: reader.u8()?,
+            v_align: reader.u8()?,
+            line_space: reader.u8()?,
+            font_id: reader.u8()?,
+            font_style: FontStyle(reader.u8()?),
+            font_size: reader.u8()?,
+            font_color: reader.u8()?,
+            outline_color: reader.u8()?,
+            outline_thickness: reader.u8()?,
+        });
+    }
+    // User styles only adjust the region styles at the viewer's request.
+    reader.skip(user_style_count as usize * 15)?;
+    let palette = read_palette(reader)?;
+    Ok(DialogStyle {
+        player_style: flags & 0x8000 != 0,
+        region_styles,
+        palette,
+        dialog_count: reader.u16()?,
+    })
+}
+
+/// A 33-bit timestamp in 5 bytes, after 7 reserved bits.
+fn read_pts(reader: &mut ByteReader<'_>) -> ParseResult<f64> {
+    let high = (reader.u8()? & 0x01) as u64;
+    let pts = high << 32 | reader.u32()? as u64;
+    Ok(pts as f64 / TEXTST_CLOCK)
+}
+
+fn read_dialog(reader: &mut ByteReader<'_>, code: CharacterCode) -> ParseResult<Dialog> {
+    let start = read_pts(reader)?;
+    let end = read_pts(reader)?;
+    let palette_update = if reader.u8()? & 0x80 != 0 {
+        read_palette(reader)?
+    } else {
+        Vec::new()
+    };
+    let region_count = reader.u8()?;
+    let mut regions = Vec::with_capacity(region_count as usize);
+    for _ in 0..region_count {
+        let flags = reader.u8()?;
+        let style_id = reader.u8()?;
+        let len = reader.u16()? as usize;
+        let data = reader.bytes(len)?;
+        regions.push(DialogRegion {
+            continuous: flags & 0x80 != 0,
+            forced: flags & 0x40 != 0,
+            style_id,
+            content: read_inline(data, code),
+        });
+    }
+    Ok(Dialog {
+        start,
+        end,
+        palette_update,
+        regions,
+    })
+}
+
+/// Splits region data into inline entries, skipping bytes outside an
+/// escape and stopping at a truncated one.
+fn read_inline(data: &[u8], code: CharacterCode) -> Vec<Inline> {
+    let mut content = Vec::new();
+    let mut at = 0;
+    while at + 3 <= data.len() {
+        if data[at] != ESCAPE {
+            at += 1;
+            continue;
+        }
+        let kind = data[at + 1];
+        let len = data[at + 2] as usize;
+        let Some(value) = data.get(at + 3..at + 3 + len) else {
+            break;
+        };
+        at += 3 + len;
+        let first = value.first().copied().unwrap_or_default();
+        content.push(match kind {
+            INLINE_TEXT => Inline::Text(code.decode(value)),
+            INLINE_FONT_ID => Inline::FontId(first),
+            INLINE_FONT_STYLE => Inline::FontStyle {
+                style: FontStyle(first),
+                outline_color: value.get(1).copied().unwrap_or_default(),
+                outline_thickness: value.get(2).copied().unwrap_or_default(),
+            },
+            INLINE_FONT_SIZE => Inline::FontSize(first),
+            INLINE_FONT_COLOR => Inline::FontColor(first),
+            INLINE_LINE_BREAK => Inline::LineBreak,
+            INLINE_RESET_STYLE => Inline::ResetStyle,
+            _ => continue,
+        });
+    }
+    content
+}
+
+fn srt_time(seconds: f64) -> String {
+    let ms = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+fn ass_time(seconds: f64) -> String {
+    let cs = (seconds * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+/// Bold and italic as SRT tags, reopened on every style change so they
+/// always nest.
+fn srt_text(region: &DialogRegion, base: FontStyle) -> String {
+    fn open(out: &mut String, style: FontStyle) {
+        if style.bold() {
+            out.push_str("<b>");
+        }
+        if style.italic() {
+            out.push_str("<i>");
+        }
+    }
+    fn close(out: &mut String, style: FontStyle) {
+        if style.italic() {
+            out.push_str("</i>");
+        }
+        if style.bold() {
+            out.push_str("</b>");
+        }
+    }
+
+    let mut out = String::new();
+    let mut current = base;
+    open(&mut out, current);
+    for inline in &region.content {
+        let next = match inline {
+            Inline::Text(text) => {
+                out.push_str(text);
+                continue;
+            }
+            Inline::LineBreak => {
+                out.push('\n');
+                continue;
+            }
+            Inline::FontStyle { style, .. } => *style,
+            Inline::ResetStyle => base,
+            _ => continue,
+        };
+        if (next.bold(), next.italic()) != (current.bold(), current.italic()) {
+            close(&mut out, current);
+            open(&mut out, next);
+            current = next;
+        }
+    }
+    close(&mut out, current);
+    out
+}
+
+fn ass_text(region: &DialogRegion, palette: &[PaletteEntry]) -> String {
+    let mut out = String::new();
+    for inline in &region.content {
+        match inline {
+            // Braces would start an override block.
+            Inline::Text(text) => out.push_str(&text.replace('{', "(").replace('}', ")")),
+            Inline::LineBreak => out.push_str("\\N"),
+            Inline::FontStyle { style, .. } => {
+                let _ = write!(
+                    out,
+                    "{{\\b{}\\i{}}}",
+                    style.bold() as u8,
+                    style.italic() as u8
+                );
+            }
+            Inline::FontSize(size) => {
+                let _ = write!(out, "{{\\fs{}}}", size);
+            }
+            Inline::FontColor(color) => {
+                // Drop the alpha, `\c` only takes `&HBBGGRR&`.
+                let color = ass_color(palette, *color);
+                let _ = write!(out, "{{\\c&H{}&}}", &color[4..]);
+            }
+            Inline::ResetStyle => out.push_str("{\\r}"),
+            Inline::FontId(_) => {}
+        }
+    }
+    out
+}
+
+/// A palette entry as `&HAABBGGRR`, opaque white when it's missing.
+fn ass_color(palette: &[PaletteEntry], id: u8) -> String {
+    let Some(entry) = palette.iter().find(|entry| entry.id == id) else {
+        return "&H00FFFFFF".into();
+    };
+    let (r, g, b) = entry.rgb();
+    format!("&H{:02X}{:02X}{:02X}{:02X}", 255 - entry.t, b, g, r)
+}
+
+/// Numpad position from the region style's alignment.
+fn ass_alignment(style: &RegionStyle) -> u8 {
+    let column = match style.h_align {
+        1 => 1,
+        3 => 3,
+        _ => 2,
+    };
+    let row = match style.v_align {
+        1 => 6,
+        2 => 3,
+        _ => 0,
+    };
+    row + column
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A segment of `segment_type` holding `body`.
+    fn segment(segment_type: u8, body: &[u8]) -> Vec<u8> {
+        let mut segment = vec![segment_type];
+        segment.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        segment.extend_from_slice(body);
+        segment
+    }
+
+    fn rect(x: u16, y: u16, width: u16, height: u16) -> Vec<u8> {
+        [x, y, width, height]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect()
+    }
+
+    /// A dialog style with region style 0 along the bottom of the frame and
+    /// a palette of opaque white (0) and black (1).
+    pub fn dialog_style(dialog_count: u16) -> Vec<u8> {
+        let mut body = vec![0x80, 0, 1, 1, 0];
+        body.extend(rect(0, 800, 1920, 280));
+        body.extend_from_slice(&[1, 0]);
+        body.extend(rect(160, 40, 1600, 200));
+        body.extend_from_slice(&[1, 2, 3, 10, 0, 0, 52, 0, 1, 2]);
+        body.extend_from_slice(&[0; 15]);
+        body.extend_from_slice(&10u16.to_be_bytes());
+        body.extend_from_slice(&[0, 235, 128, 128, 255, 1, 16, 128, 128, 255]);
+        body.extend_from_slice(&dialog_count.to_be_bytes());
+        segment(SEGMENT_DIALOG_STYLE, &body)
+    }
+
+    /// A dialog from `start` to `end` (90 kHz) with `(flags, inline data)`
+    /// regions in region style 0.
+    pub fn dialog(start: u64, end: u64, regions: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for pts in [start, end] {
+            body.push((pts >> 32) as u8 & 1);
+            body.extend_from_slice(&(pts as u32).to_be_bytes());
+        }
+        body.push(0);
+        body.push(regions.len() as u8);
+        for (flags, data) in regions {
+            body.extend_from_slice(&[*flags, 0]);
+            body.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            body.extend_from_slice(data);
+        }
+        segment(SEGMENT_DIALOG_PRESENTATION, &body)
+    }
+
+    pub fn inline(kind: u8, value: &[u8]) -> Vec<u8> {
+        let mut inline = vec![ESCAPE, kind, value.len() as u8];
+        inline.extend_from_slice(value);
+        inline
+    }
+
+    fn sample() -> TextSubtitle {
+        let first = [
+            inline(INLINE_TEXT, b"Hello"),
+            inline(INLINE_LINE_BREAK, &[]),
+            inline(INLINE_FONT_STYLE, &[0x03, 1, 2]),
+            inline(INLINE_TEXT, b"world"),
+            inline(INLINE_RESET_STYLE, &[]),
+        ]
+        .concat();
+        let second = [
+            inline(INLINE_FONT_COLOR, &[1]),
+            inline(INLINE_TEXT, b"a {note}"),
+        ]
+        .concat();
+        let mut payload = dialog_style(2);
+        payload.extend(dialog(90_000, 270_000, &[(0x40, &first)]));
+        let mut subtitle = TextSubtitle::new(CharacterCode::CHARACTERCODE_UTF8);
+        subtitle.push_payload(&payload).unwrap();
+        subtitle
+            .push_payload(&dialog(450_000, 540_000, &[(0x80, &second)]))
+            .unwrap();
+        subtitle
+    }
+
+    #[test]
+    fn decodes_segments() {
+        let subtitle = sample();
+        let style = subtitle.style.as_ref().unwrap();
+        assert!(style.player_style);
+        assert_eq!(style.dialog_count, 2);
+        let region = &style.region_styles[0];
+        assert_eq!(
+            region.region,
+            Rect {
+                x: 0,
+                y: 800,
+                width: 1920,
+                height: 280
+            }
+        );
+        assert_eq!(
+            (region.h_align, region.v_align, region.font_size),
+            (2, 3, 52)
+        );
+        assert_eq!(style.palette.len(), 2);
+        assert_eq!(style.palette[0].rgb(), (255, 255, 255));
+        assert_eq!(style.palette[1].rgb(), (0, 0, 0));
+
+        let dialog = &subtitle.dialogs[0];
+        assert_eq!((dialog.start, dialog.end), (1.0, 3.0));
+        let region = &dialog.regions[0];
+        assert!(region.forced && !region.continuous);
+        assert_eq!(
+            region.content,
+            [
+                Inline::Text("Hello".into()),
+                Inline::LineBreak,
+                Inline::FontStyle {
+                    style: FontStyle(0x03),
+                    outline_color: 1,
+                    outline_thickness: 2
+                },
+                Inline::Text("world".into()),
+                Inline::ResetStyle,
+            ]
+        );
+        assert!(subtitle.dialogs[1].regions[0].continuous);
+    }
+
+    #[test]
+    fn writes_srt() {
+        assert_eq!(
+            sample().to_srt(),
+            "1\n00:00:01,000 --> 00:00:03,000\nHello\n<b><i>world</i></b>\n\n\
+             2\n00:00:05,000 --> 00:00:06,000\na {note}\n\n"
+        );
+    }
+
+    #[test]
+    fn writes_ass() {
+        let ass = sample().to_ass();
+        assert!(ass.contains(
+            "Style: Region0,Arial,52,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,\
+             100,100,0,0,1,0,0,2,160,160,40,1\n"
+        ));
+        assert!(ass.contains(
+            "Dialogue: 0,0:00:01.00,0:00:03.00,Region0,,0,0,0,,Hello\\N{\\b1\\i1}world{\\r}\n"
+        ));
+        assert!(ass.contains(
+            "Dialogue: 0,0:00:05.00,0:00:06.00,Region0,,0,0,0,,{\\c&H000000&}a (note)\n"
+        ));
+    }
+
+    #[test]
+    fn shifts_dialogs() {
+        let mut subtitle = sample();
+        subtitle.shift(-4.0);
+        assert_eq!(subtitle.dialogs.len(), 1);
+        assert_eq!(
+            (subtitle.dialogs[0].start, subtitle.dialogs[0].end),
+            (1.0, 2.0)
+        );
+        subtitle.shift(-1.5);
+        assert_eq!(
+            (subtitle.dialogs[0].start, subtitle.dialogs[0].end),
+            (0.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn decodes_character_codes() {
+        assert_eq!(CharacterCode::from(2).decode(&[0, b'h', 0, b'i']), "hi");
+        assert_eq!(CharacterCode::from(3).decode(&[0x82, 0xA0]), "\u{3042}");
+        assert_eq!(CharacterCode::from(1).decode(&[b'a', 0xFF]), "a\u{FFFD}");
+        assert_eq!(CharacterCode::from(9), CharacterCode::Unknown);
+        assert_eq!(CharacterCode::CHARACTERCODE_BIG5.to_string(), "Big5");
+    }
+
+    #[test]
+    fn rejects_truncated_segments() {
+        let mut subtitle = TextSubtitle::new(CharacterCode::CHARACTERCODE_UTF8);
+        let style = dialog_style(0);
+        assert!(subtitle.push_payload(&style[..style.len() - 4]).is_err());
+
+        // Unknown segments are skipped, a truncated escape ends the region.
+        let mut payload = segment(0x83, &[1, 2, 3]);
+        payload.extend(dialog(0, 90_000, &[(0, &[ESCAPE, INLINE_TEXT, 10, b'a'])]));
+        subtitle.push_payload(&payload).unwrap();
+        assert!(subtitle.dialogs[0].regions[0].content.is_empty());
+    }
+}
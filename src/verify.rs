@@ -0,0 +1,510 @@
+//! Checks that a disc's playlists, clip information and stream files agree
+//! with each other, to find missing or damaged files in a disc backup.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{BufReader, Read, Result, Seek, SeekFrom},
+};
+
+use crate::{
+    disc,
+    lint::{self, LintReport, Severity},
+    playlist_view::{PlaylistView, StreamEntry},
+    ts::{
+        clpi::{ClipInfo, StcSequence},
+        packet::{SOURCE_PACKET_SIZE, SYNC_BYTE},
+        stream_type::StreamType,
+    },
+    vfs::{self, DiscFs},
+};
+
+/// What was found for one clip, read once however many playlists use it.
+#[derive(Debug, Default)]
+struct ClipFiles {
+    has_clip_info: bool,
+    has_stream: bool,
+    /// `None` when missing or damaged.
+    info: Option<ClipInfo>,
+}
+
+struct Verifier<'a> {
+    fs: &'a dyn DiscFs,
+    clips: BTreeMap<String, ClipFiles>,
+    /// Findings about a clip's own files, by file name.
+    files: BTreeMap<String, LintReport>,
+}
+
+/// Verifies every playlist of the disc in `fs` against the clips it plays.
+/// Returns findings by playlist, then the damaged clip files by file name,
+/// skipping files without findings.
+pub fn verify(fs: &dyn DiscFs) -> Result<Vec<(String, LintReport)>> {
+    let mut verifier = Verifier {
+        fs,
+        clips: BTreeMap::new(),
+        files: BTreeMap::new(),
+    };
+    let mut reports = Vec::new();
+    for file in disc::playlist_files(fs)? {
+        let mut report = LintReport::default();
+        let data = fs.read(&vfs::join("BDMV/PLAYLIST", &file));
+        match data.as_deref().map(PlaylistView::parse) {
+            Ok(Ok(view)) => verifier.playlist(&view, &mut report),
+            Ok(Err(error)) => report.push(
+                Severity::Error,
+                "damaged_file",
+                "",
+                Some(error.offset),
+                error.to_string(),
+            ),
+            Err(error) => report.push(Severity::Error, "damaged_file", "", None, error.to_string()),
+        }
+        if !report.is_empty() {
+            reports.push((file.to_uppercase(), report));
+        }
+    }
+    reports.extend(verifier.files);
+    Ok(reports)
+}
+
+impl Verifier<'_> {
+    fn playlist(&mut self, view: &PlaylistView, report: &mut LintReport) {
+        for (i, item) in view.play_items.iter().enumerate() {
+            let prefix = format!("play_item[{}]", i);
+            let locations = lint::stream_locations(&prefix, item);
+            // The STC id follows the connection condition.
+            let stc_id = item.raw.get(13).copied().unwrap_or_default();
+            let clips =
+                std::iter::once(item.clip_name).chain(item.angles.iter().map(|a| a.clip_name));
+            for (angle, clip_name) in clips.enumerate() {
+                let location = match angle {
+                    0 => prefix.clone(),
+                    angle => format!("{}.angle[{}]", prefix, angle),
+                };
+                let Some(info) = self.clip(clip_name, &location, item.offset, report) else {
+                    continue;
+                };
+                let stc = info
+                    .atc_sequences
+                    .iter()
+                    .flat_map(|atc| &atc.stc_sequences)
+                    .nth(stc_id as usize);
+                match stc {
+                    Some(stc) => check_range(
+                        stc,
+                        item.header.in_time as u32 & 0x7FFF_FFFF,
+                        item.header.out_time as u32 & 0x7FFF_FFFF,
+                        &location,
+                        item.offset,
+                        report,
+                    ),
+                    None => report.push(
+                        Severity::Error,
+                        "stc_id",
+                        &location,
+                        Some(item.offset),
+                        format!("{} has no STC sequence {}", clip_name, stc_id),
+                    ),
+                }
+                // Streams of the play item's own clip, sub path ones are
+                // checked with their sub play item.
+                for (entry, location) in item.streams.iter().zip(&locations) {
+                    if matches!(entry.entry_type, 1 | 3) {
+                        check_pid(info, clip_name, entry, location, report);
+                    }
+                }
+            }
+        }
+
+        for (i, sub_path) in view.sub_paths.iter().enumerate() {
+            for (j, sub_item) in sub_path.items.iter().enumerate() {
+                let location = format!("sub_path[{}].item[{}]", i, j);
+                let Some(info) = self.clip(sub_item.clip_name, &location, sub_item.offset, report)
+                else {
+                    continue;
+                };
+                // Sub play items don't say which STC sequence, any will do.
+                let stcs = info
+                    .atc_sequences
+                    .iter()
+                    .flat_map(|atc| &atc.stc_sequences)
+                    .collect::<Vec<_>>();
+                let inside = |stc: &&StcSequence| {
+                    stc.presentation_start_time <= sub_item.time_in
+                        && sub_item.time_out <= stc.presentation_end_time
+                };
+                if let Some(stc) = stcs.first().filter(|_| !stcs.iter().any(inside)) {
+                    check_range(
+                        stc,
+                        sub_item.time_in,
+                        sub_item.time_out,
+                        &location,
+                        sub_item.offset,
+                        report,
+                    );
+                }
+
+                let mut checked = BTreeSet::new();
+                for (k, item) in view.play_items.iter().enumerate() {
+                    let prefix = format!("play_item[{}]", k);
+                    let locations = lint::stream_locations(&prefix, item);
+                    for (entry, location) in item.streams.iter().zip(&locations) {
+                        let in_sub_item = matches!(entry.entry_type, 2 | 4)
+                            && entry.subpath_id == Some(i as u8)
+                            && entry.subclip_id.unwrap_or(0) as usize == j;
+                        if in_sub_item && checked.insert(entry.pid) {
+                            check_pid(info, sub_item.clip_name, entry, location, report);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The clip information of `name`, reporting its missing files against
+    /// the playlist and, the first time, damage against the files.
+    fn clip(
+        &mut self,
+        name: &str,
+        location: &str,
+        offset: usize,
+        report: &mut LintReport,
+    ) -> Option<&ClipInfo> {
+        if !self.clips.contains_key(name) {
+            let clip = self.read_clip(name);
+            self.clips.insert(name.to_owned(), clip);
+        }
+        let clip = &self.clips[name];
+        let missing = [(clip.has_clip_info, "CLPI"), (clip.has_stream, "M2TS")];
+        for (_, extension) in missing.iter().filter(|(exists, _)| !exists) {
+            report.push(
+                Severity::Error,
+                "missing_file",
+                location,
+                Some(offset),
+                format!("{}.{} is missing", name, extension),
+            );
+        }
+        clip.info.as_ref()
+    }
+
+    fn read_clip(&mut self, name: &str) -> ClipFiles {
+        let clip_info_name = format!("{}.CLPI", name);
+        let clip_info_path = vfs::join("BDMV/CLIPINF", &clip_info_name);
+        let stream_name = format!("{}.M2TS", name);
+        let stream_path = vfs::join("BDMV/STREAM", &stream_name);
+
+        let mut clip = ClipFiles {
+            has_clip_info: self.fs.exists(&clip_info_path),
+            has_stream: self.fs.exists(&stream_path),
+            info: None,
+        };
+        if clip.has_clip_info {
+            let info = self
+                .fs
+                .open(&clip_info_path)
+                .and_then(|file| ClipInfo::parse(&mut BufReader::new(file)));
+            match info {
+                Ok(info) => clip.info = Some(info),
+                Err(error) => self.files.entry(clip_info_name).or_default().push(
+                    Severity::Error,
+                    "damaged_file",
+                    "",
+                    None,
+                    error.to_string(),
+                ),
+            }
+        }
+        if clip.has_stream {
+            let mut report = LintReport::default();
+            if let Err(error) =
+                self.check_stream_file(&stream_path, clip.info.as_ref(), &mut report)
+            {
+                report.push(Severity::Error, "damaged_file", "", None, error.to_string());
+            }
+            if !report.is_empty() {
+                self.files.insert(stream_name, report);
+            }
+        }
+        clip
+    }
+
+    /// The stream file holds whole source packets, as many as its clip
+    /// information says, and the first and last start with a sync byte.
+    fn check_stream_file(
+        &self,
+        path: &str,
+        info: Option<&ClipInfo>,
+        report: &mut LintReport,
+    ) -> Result<()> {
+        let len = self.fs.metadata(path)?.len;
+        let packet_size = SOURCE_PACKET_SIZE as u64;
+        let packets = len / packet_size;
+        if len % packet_size != 0 {
+            report.push(
+                Severity::Error,
+                "damaged_file",
+                "",
+                Some(len as usize),
+                format!("ends {} bytes into a source packet", len % packet_size),
+            );
+        }
+        if let Some(info) = info {
+            let expected = info.source_packet_count as u64;
+            if packets < expected {
+                report.push(
+                    Severity::Error,
+                    "damaged_file",
+                    "",
+                    Some(len as usize),
+                    format!(
+                        "truncated, {} of the {} source packets in the clip information",
+                        packets, expected
+                    ),
+                );
+            } else if packets > expected {
+                report.push(
+                    Severity::Warning,
+                    "packet_count",
+                    "",
+                    None,
+                    format!(
+                        "{} source packets, the clip information says {}",
+                        packets, expected
+                    ),
+                );
+            }
+        }
+        if packets == 0 {
+            return Ok(());
+        }
+
+        let mut file = self.fs.open(path)?;
+        let mut packet = [0u8; SOURCE_PACKET_SIZE];
+        let last = (packets - 1) * packet_size;
+        for offset in [0, last] {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut packet)?;
+            // After the 4 byte arrival time stamp.
+            if packet[4] != SYNC_BYTE {
+                report.push(
+                    Severity::Error,
+                    "damaged_file",
+                    "",
+                    Some(offset as usize),
+                    format!("no sync byte in source packet {}", offset / packet_size),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// In and out times, 45 kHz, within the STC sequence's presentation.
+fn check_range(
+    stc: &StcSequence,
+    time_in: u32,
+    time_out: u32,
+    location: &str,
+    offset: usize,
+    report: &mut LintReport,
+) {
+    if time_in < stc.presentation_start_time || time_out > stc.presentation_end_time {
+        let seconds = |time: u32| time as f64 / 45_000.0;
+        report.push(
+            Severity::Error,
+            "item_range",
+            location,
+            Some(offset),
+            format!(
+                "{:.3}s to {:.3}s is outside the clip's {:.3}s to {:.3}s",
+                seconds(time_in),
+                seconds(time_out),
+                seconds(stc.presentation_start_time),
+                seconds(stc.presentation_end_time)
+            ),
+        );
+    }
+}
+
+/// The STN entry's PID is in the clip's program with the same coding type.
+fn check_pid(
+    info: &ClipInfo,
+    clip_name: &str,
+    entry: &StreamEntry,
+    location: &str,
+    report: &mut LintReport,
+) {
+    let stream = info
+        .programs
+        .iter()
+        .flat_map(|program| &program.streams)
+        .find(|stream| stream.pid == entry.pid);
+    let Some(stream) = stream else {
+        report.push(
+            Severity::Error,
+            "missing_pid",
+            location,
+            Some(entry.offset),
+            format!("PID {:#06x} isn't in {}", entry.pid, clip_name),
+        );
+        return;
+    };
+    let coding_type = entry.attributes.first().copied().unwrap_or_default();
+    if stream.coding_type != coding_type {
+        report.push(
+            Severity::Error,
+            "coding_mismatch",
+            location,
+            Some(entry.offset),
+            format!(
+                "PID {:#06x} is {} but {} has {}",
+                entry.pid,
+                entry.stream_type().codec_name(),
+                clip_name,
+                StreamType::from(stream.coding_type).codec_name()
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{
+            clpi, mpls, mpls_with_sub_paths, source_packet, PlayItem, SubPlayItem, MPLS_00800,
+        },
+        vfs::MemoryFs,
+    };
+
+    const VIDEO: &[u8] = &[0x1B, 0x61, 0x30];
+    const AUDIO: &[u8] = &[0x81, 0x61, b'e', b'n', b'g'];
+
+    fn verify_files(files: &[(&str, &[u8])]) -> Vec<(String, LintReport)> {
+        let mut fs = MemoryFs::new();
+        for (path, data) in files {
+            fs.insert(path, *data);
+        }
+        verify(&fs).unwrap()
+    }
+
+    /// Severity, check and location of the findings about `name`.
+    fn findings<'a>(
+        reports: &'a [(String, LintReport)],
+        name: &str,
+    ) -> Vec<(Severity, &'a str, &'a str)> {
+        reports
+            .iter()
+            .filter(|(n, _)| n == name)
+            .flat_map(|(_, report)| &report.findings)
+            .map(|f| (f.severity, f.check, f.location.as_str()))
+            .collect()
+    }
+
+    fn packets(count: usize) -> Vec<u8> {
+        (0..count)
+            .flat_map(|_| source_packet(0x1011, false, None, &[0; 184]))
+            .collect()
+    }
+
+    #[test]
+    fn passes_matching_files() {
+        let playlist = mpls(
+            &[PlayItem {
+                clip: "00001",
+                time_in: 0,
+                time_out: 450_000,
+                streams: &[(0, 0x1011, VIDEO), (1, 0x1100, AUDIO)],
+            }],
+            &[(0, 0)],
+        );
+        let clip_info = clpi(3, (0, 450_000), &[(0x1011, 0x1B), (0x1100, 0x81)], &[]);
+        let stream = packets(3);
+        let reports = verify_files(&[
+            ("BDMV/PLAYLIST/00001.mpls", &playlist),
+            ("BDMV/CLIPINF/00001.clpi", &clip_info),
+            ("BDMV/STREAM/00001.m2ts", &stream),
+        ]);
+        assert!(reports.is_empty(), "{:?}", reports);
+    }
+
+    #[test]
+    fn reports_missing_and_damaged_playlists() {
+        let truncated = &MPLS_00800[..MPLS_00800.len() / 2];
+        let reports = verify_files(&[
+            ("BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+            ("BDMV/PLAYLIST/00801.mpls", truncated),
+        ]);
+        let names = reports
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["00800.MPLS", "00801.MPLS"]);
+        let missing = (Severity::Error, "missing_file", "play_item[0]");
+        let sub_path_missing = (Severity::Error, "missing_file", "sub_path[0].item[0]");
+        assert_eq!(
+            findings(&reports, "00800.MPLS"),
+            [missing, missing, sub_path_missing, sub_path_missing]
+        );
+        assert_eq!(
+            findings(&reports, "00801.MPLS"),
+            [(Severity::Error, "damaged_file", "")]
+        );
+        assert!(reports[1].1.findings[0].offset.is_some());
+    }
+
+    #[test]
+    fn checks_playlists_against_clips() {
+        let playlist = mpls_with_sub_paths(
+            &[PlayItem {
+                clip: "00001",
+                time_in: 0,
+                time_out: 45_000 * 20,
+                streams: &[
+                    (0, 0x1011, VIDEO),
+                    (1, 0x1100, AUDIO),
+                    (2, 0x1200, &[0x90, b'e', b'n', b'g']),
+                    (2, 0x1800, &[0x92, 0x01, b'e', b'n', b'g']),
+                ],
+            }],
+            &[SubPlayItem {
+                clip: "00002",
+                time_in: 0,
+                time_out: 45_000 * 20,
+                sync_play_item_id: 0,
+                sync_start_pts: 0,
+            }],
+            &[],
+        );
+        let clip_info = clpi(4, (0, 45_000 * 10), &[(0x1011, 0x1B), (0x1100, 0x83)], &[]);
+        // Half a packet short of 3, the last one without its sync byte.
+        let mut stream = packets(3);
+        stream[2 * 192 + 4] = 0;
+        stream.extend_from_slice(&[0; 96]);
+        let reports = verify_files(&[
+            ("BDMV/PLAYLIST/00001.mpls", &playlist),
+            ("BDMV/CLIPINF/00001.clpi", &clip_info),
+            ("BDMV/STREAM/00001.m2ts", &stream),
+            ("BDMV/CLIPINF/00002.clpi", b"HDMV0200"),
+        ]);
+        assert_eq!(
+            findings(&reports, "00001.MPLS"),
+            [
+                (Severity::Error, "item_range", "play_item[0]"),
+                (
+                    Severity::Error,
+                    "coding_mismatch",
+                    "play_item[0].stn.audio[0]"
+                ),
+                (Severity::Error, "missing_pid", "play_item[0].stn.pg[0]"),
+                (Severity::Error, "missing_file", "sub_path[0].item[0]"),
+            ]
+        );
+        let damaged = (Severity::Error, "damaged_file", "");
+        assert_eq!(
+            findings(&reports, "00001.M2TS"),
+            [damaged, damaged, damaged]
+        );
+        assert_eq!(findings(&reports, "00002.CLPI"), [damaged]);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    sync::Arc,
+};
+
+use super::{components, not_found, DirEntry, DiscFs, Extent, ExtentReader, ImageSource, ReadSeek};
+
+const SECTOR_SIZE: u64 = 2048;
+const DESCRIPTOR_SECTOR: u64 = 16;
+const FLAG_DIRECTORY: u8 = 0x02;
+
+/// A directory record: where an entry's data is and how long it is.
+#[derive(Debug, Clone)]
+struct Record {
+    name: String,
+    is_dir: bool,
+    extent: u64,
+    len: u64,
+}
+
+impl Record {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let name_len = *data.get(32)? as usize;
+        let name = data.get(33..33 + name_len)?;
+        let name = String::from_utf8_lossy(name);
+        let name = name.split(';').next().unwrap_or_default();
+        Some(Record {
+            name: name.strip_suffix('.').unwrap_or(name).to_owned(),
+            is_dir: data[25] & FLAG_DIRECTORY != 0,
+            extent: u64::from(u32::from_le_bytes(data[2..6].try_into().ok()?)),
+            len: u64::from(u32::from_le_bytes(data[10..14].try_into().ok()?)),
+        })
+    }
+}
+
+/// Read-only ISO 9660 file system, for images without a UDF volume.
+#[derive(Debug)]
+pub struct Iso9660Fs {
+    source: Arc<dyn ImageSource>,
+    root: Record,
+    label: String,
+}
+
+impl Iso9660Fs {
+    pub fn new(source: Arc<dyn ImageSource>) -> Result<Self> {
+        let mut image = source.open()?;
+        let mut descriptor = vec![0; SECTOR_SIZE as usize];
+        image.seek(SeekFrom::Start(DESCRIPTOR_SECTOR * SECTOR_SIZE))?;
+        image.read_exact(&mut descriptor)?;
+        if descriptor[0] != 1 || &descriptor[1..6] != b"CD001" {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "ISO 9660: no primary volume descriptor",
+            ));
+        }
+        let label = String::from_utf8_lossy(&descriptor[40..72])
+            .trim_end()
+            .to_owned();
+        let root = Record::parse(&descriptor[156..190])
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "ISO 9660: bad root directory"))?;
+        Ok(Iso9660Fs {
+            source,
+            root,
+            label,
+        })
+    }
+
+    fn reader(&self, record: &Record) -> Result<ExtentReader<Box<dyn ReadSeek>>> {
+        let extent = Extent {
+            offset: Some(record.extent * SECTOR_SIZE),
+            len: record.len,
+        };
+        Ok(ExtentReader::new(
+            self.source.open()?,
+            vec![extent],
+            record.len,
+        ))
+    }
+
+    fn records(&self, dir: &Record) -> Result<Vec<Record>> {
+        let mut data = Vec::new();
+        self.reader(dir)?.read_to_end(&mut data)?;
+
+        let mut records = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let len = data[pos] as usize;
+            if len == 0 {
+                // Records don't cross sectors, the rest of this one is padding.
+                pos = (pos / SECTOR_SIZE as usize + 1) * SECTOR_SIZE as usize;
+                continue;
+            }
+            let Some(record) = data.get(pos..pos + len).and_then(Record::parse) else {
+                break;
+            };
+            // Skip the `.` and `..` entries, named 0x00 and 0x01.
+            if !matches!(record.name.as_bytes(), [0] | [1]) {
+                records.push(record);
+            }
+            pos += len;
+        }
+        Ok(records)
+    }
+
+    fn lookup(&self, path: &str) -> Result<Record> {
+        let mut record = self.root.clone();
+        for component in components(path) {
+            if !record.is_dir {
+                return Err(not_found(path));
+            }
+            record = self
+                .records(&record)?
+                .into_iter()
+                .find(|r| r.name.eq_ignore_ascii_case(component))
+                .ok_or_else(|| not_found(path))?;
+        }
+        Ok(record)
+    }
+}
+
+impl DiscFs for Iso9660Fs {
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let dir = self.lookup(path)?;
+        if !dir.is_dir {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a directory", path),
+            ));
+        }
+        let mut entries = self
+            .records(&dir)?
+            .into_iter()
+            .map(|record| DirEntry {
+                name: record.name,
+                is_dir: record.is_dir,
+                len: if record.is_dir { 0 } else { record.len },
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn ReadSeek>> {
+        let record = self.lookup(path)?;
+        if record.is_dir {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is a directory", path),
+            ));
+        }
+        Ok(Box::new(self.reader(&record)?))
+    }
+
+    fn label(&self) -> Option<String> {
+        Some(self.label.clone()).filter(|label| !label.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::open_image;
+
+    fn record(name: &[u8], extent: u32, len: u32, is_dir: bool) -> Vec<u8> {
+        let mut record = vec![0; 33];
+        record[2..6].copy_from_slice(&extent.to_le_bytes());
+        record[6..10].copy_from_slice(&extent.to_be_bytes());
+        record[10..14].copy_from_slice(&len.to_le_bytes());
+        record[14..18].copy_from_slice(&len.to_be_bytes());
+        record[25] = if is_dir { FLAG_DIRECTORY } else { 0 };
+        record[32] = name.len() as u8;
+        record.extend_from_slice(name);
+        record.resize((record.len() + 1) & !1, 0);
+        record[0] = record.len() as u8;
+        record
+    }
+
+    /// An image with `BDMV/INDEX.BDMV;1` and an empty `CERTIFICATE`
+    /// folder.
+    fn image() -> Vec<u8> {
+        let mut image = vec![0; 22 * SECTOR_SIZE as usize];
+        let mut sector = |n: usize, data: &[u8]| {
+            image[n * 2048..n * 2048 + data.len()].copy_from_slice(data);
+        };
+
+        let mut descriptor = vec![1];
+        descriptor.extend_from_slice(b"CD001");
+        descriptor.resize(40, 0);
+        descriptor.extend_from_slice(format!("{:<32}", "SAMPLE").as_bytes());
+        descriptor.resize(156, 0);
+        descriptor.extend(record(&[0], 18, 2048, true));
+        sector(16, &descriptor);
+
+        let root = [
+            record(&[0], 18, 2048, true),
+            record(&[1], 18, 2048, true),
+            record(b"BDMV", 19, 2048, true),
+            record(b"CERTIFICATE", 21, 2048, true),
+        ]
+        .concat();
+        sector(18, &root);
+        let bdmv = [
+            record(&[0], 19, 2048, true),
+            record(&[1], 18, 2048, true),
+            record(b"INDEX.BDMV;1", 20, 4, false),
+        ]
+        .concat();
+        sector(19, &bdmv);
+        sector(20, b"INDX");
+        sector(
+            21,
+            &[record(&[0], 21, 2048, true), record(&[1], 18, 2048, true)].concat(),
+        );
+        image
+    }
+
+    fn open(image: Vec<u8>) -> Result<Iso9660Fs> {
+        Iso9660Fs::new(Arc::new(Arc::<[u8]>::from(image)))
+    }
+
+    #[test]
+    fn reads_files() {
+        let fs = open(image()).unwrap();
+        assert_eq!(fs.label().as_deref(), Some("SAMPLE"));
+        let names = fs
+            .read_dir("")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["BDMV", "CERTIFICATE"]);
+        assert!(fs.read_dir("CERTIFICATE").unwrap().is_empty());
+        assert_eq!(
+            fs.metadata("bdmv/index.bdmv").unwrap(),
+            DirEntry {
+                name: "INDEX.BDMV".to_owned(),
+                is_dir: false,
+                len: 4
+            }
+        );
+        assert_eq!(fs.read("BDMV/index.bdmv").unwrap(), b"INDX");
+        assert_eq!(
+            fs.open("BDMV/MovieObject.bdmv").err().unwrap().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            fs.open("BDMV/INDEX.BDMV/x").err().unwrap().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn opened_as_fallback_to_udf() {
+        let fs = open_image(Arc::new(Arc::<[u8]>::from(image()))).unwrap();
+        assert_eq!(fs.read("BDMV/INDEX.BDMV").unwrap(), b"INDX");
+    }
+
+    #[test]
+    fn rejects_malformed_images() {
+        let mut image = image();
+        image[16 * 2048 + 1] = b'X';
+        assert_eq!(open(image).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        let mut image = self::image();
+        image[16 * 2048 + 156 + 32] = 200;
+        assert_eq!(open(image).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        let error = open(vec![0; 1000]).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        // A name running past its record ends the listing.
+        let mut image = self::image();
+        image[19 * 2048 + 68 + 32] = 40;
+        let fs = open(image).unwrap();
+        assert!(fs.read_dir("BDMV").unwrap().is_empty());
+    }
+}
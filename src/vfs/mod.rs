@@ -0,0 +1,414 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs::{self, File},
+    io::{Cursor, Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+pub mod iso9660;
+pub mod udf;
+
+pub trait ReadSeek: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub len: u64,
+}
+
+/// Read-only access to the files of a disc, whether it is a folder or an
+/// image. Paths are relative to the disc root, `/` separated, and looked up
+/// ignoring ASCII case like the players do.
+pub trait DiscFs: Debug + Send + Sync {
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>>;
+
+    fn open(&self, path: &str) -> Result<Box<dyn ReadSeek>>;
+
+    /// Volume label, if the file system records one.
+    fn label(&self) -> Option<String> {
+        None
+    }
+
+    fn metadata(&self, path: &str) -> Result<DirEntry> {
+        let (parent, name) = split_parent(path);
+        if name.is_empty() {
+            return Ok(DirEntry {
+                name: String::new(),
+                is_dir: true,
+                len: 0,
+            });
+        }
+        self.read_dir(parent)?
+            .into_iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.open(path)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Where an image is read from. Each file opened on the image gets its
+/// own reader.
+pub trait ImageSource: Debug + Send + Sync {
+    fn open(&self) -> Result<Box<dyn ReadSeek>>;
+}
+
+impl ImageSource for PathBuf {
+    fn open(&self) -> Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(File::open(self)?))
+    }
+}
+
+impl ImageSource for Arc<[u8]> {
+    fn open(&self) -> Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(Cursor::new(self.clone())))
+    }
+}
+
+/// Opens a disc image, as UDF when it has a UDF volume and as ISO 9660
+/// otherwise.
+pub fn open_image(source: Arc<dyn ImageSource>) -> Result<Arc<dyn DiscFs>> {
+    match udf::UdfFs::new(source.clone()) {
+        Ok(fs) => Ok(Arc::new(fs)),
+        Err(udf_error) => match iso9660::Iso9660Fs::new(source) {
+            Ok(fs) => Ok(Arc::new(fs)),
+            Err(_) => Err(udf_error),
+        },
+    }
+}
+
+/// Total size of the files under `path`.
+pub fn dir_size(fs: &dyn DiscFs, path: &str) -> u64 {
+    fs.read_dir(path)
+        .map(|entries| {
+            entries
+                .into_iter()
+                .map(|entry| match entry.is_dir {
+                    true => dir_size(fs, &join(path, &entry.name)),
+                    false => entry.len,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+pub fn join(path: &str, name: &str) -> String {
+    match path.trim_matches('/') {
+        "" => name.to_owned(),
+        path => format!("{}/{}", path, name),
+    }
+}
+
+/// Splits a path into its components, ignoring empty ones.
+pub fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|c| !c.is_empty() && *c != ".")
+}
+
+fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_matches('/');
+    match path.rfind('/') {
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => ("", path),
+    }
+}
+
+pub(crate) fn not_found(path: &str) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("{} not found on the disc", path),
+    )
+}
+
+/// A disc copied to a local folder.
+#[derive(Debug, Clone)]
+pub struct DirFs {
+    root: PathBuf,
+}
+
+impl DirFs {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirFs { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Maps a disc path to the local path, matching each component without
+    /// regard to case.
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let mut resolved = self.root.clone();
+        for component in components(path) {
+            let exact = resolved.join(component);
+            if exact.exists() {
+                resolved = exact;
+                continue;
+            }
+            resolved = fs::read_dir(&resolved)?
+                .filter_map(|entry| entry.ok())
+                .find(|entry| {
+                    entry
+                        .file_name()
+                        .to_string_lossy()
+                        .eq_ignore_ascii_case(component)
+                })
+                .map(|entry| entry.path())
+                .ok_or_else(|| not_found(path))?;
+        }
+        Ok(resolved)
+    }
+}
+
+impl DiscFs for DirFs {
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let mut entries = fs::read_dir(self.resolve(path)?)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok()?;
+                Some(DirEntry {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    is_dir: meta.is_dir(),
+                    len: if meta.is_dir() { 0 } else { meta.len() },
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(File::open(self.resolve(path)?)?))
+    }
+
+    fn metadata(&self, path: &str) -> Result<DirEntry> {
+        let resolved = self.resolve(path)?;
+        let meta = fs::metadata(&resolved)?;
+        Ok(DirEntry {
+            name: resolved
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            is_dir: meta.is_dir(),
+            len: if meta.is_dir() { 0 } else { meta.len() },
+        })
+    }
+}
+
+/// Disc files held in memory, like those of a folder dropped on a web page.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    files: BTreeMap<String, Arc<[u8]>>,
+    label: Option<String>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The folder name the files were dropped from, used as the label.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Adds a file. Folders leading up to `BDMV` or `AACS` are dropped, so
+    /// `Movie/BDMV/index.bdmv` lands at `BDMV/index.bdmv`.
+    pub fn insert(&mut self, path: &str, data: impl Into<Arc<[u8]>>) {
+        let parts = components(path).collect::<Vec<_>>();
+        let root = parts
+            .iter()
+            .position(|c| {
+                ["BDMV", "AACS", "CERTIFICATE"]
+                    .iter()
+                    .any(|r| c.eq_ignore_ascii_case(r))
+            })
+            .unwrap_or(0);
+        self.files.insert(parts[root..].join("/"), data.into());
+    }
+
+    fn find(&self, path: &str) -> Option<(&String, &Arc<[u8]>)> {
+        let path = components(path).collect::<Vec<_>>().join("/");
+        self.files
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&path))
+    }
+}
+
+impl DiscFs for MemoryFs {
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let dir = components(path).collect::<Vec<_>>();
+        let mut entries = BTreeMap::<String, DirEntry>::new();
+        for (name, data) in self.files.iter() {
+            let parts = name.split('/').collect::<Vec<_>>();
+            let inside = parts.len() > dir.len()
+                && parts
+                    .iter()
+                    .zip(dir.iter())
+                    .all(|(a, b)| a.eq_ignore_ascii_case(b));
+            if !inside {
+                continue;
+            }
+            let is_dir = parts.len() > dir.len() + 1;
+            let child = parts[dir.len()];
+            entries
+                .entry(child.to_uppercase())
+                .or_insert_with(|| DirEntry {
+                    name: child.to_owned(),
+                    is_dir,
+                    len: if is_dir { 0 } else { data.len() as u64 },
+                });
+        }
+        if entries.is_empty() && !dir.is_empty() {
+            return Err(not_found(path));
+        }
+        Ok(entries.into_values().collect())
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn ReadSeek>> {
+        let (_, data) = self.find(path).ok_or_else(|| not_found(path))?;
+        Ok(Box::new(Cursor::new(data.clone())))
+    }
+
+    fn label(&self) -> Option<String> {
+        self.label.clone()
+    }
+}
+
+/// A run of file data in an image, `None` for sparse (unrecorded) data.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Extent {
+    pub offset: Option<u64>,
+    pub len: u64,
+}
+
+/// Reads a file stored as a list of extents of an image.
+pub(crate) struct ExtentReader<R> {
+    image: R,
+    extents: Vec<Extent>,
+    len: u64,
+    position: u64,
+}
+
+impl<R: Read + Seek> ExtentReader<R> {
+    pub fn new(image: R, extents: Vec<Extent>, len: u64) -> Self {
+        ExtentReader {
+            image,
+            extents,
+            len,
+            position: 0,
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for ExtentReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.position >= self.len {
+            return Ok(0);
+        }
+        let mut start = 0;
+        for extent in self.extents.iter() {
+            if self.position < start + extent.len {
+                let within = self.position - start;
+                let wanted = (extent.len - within)
+                    .min(self.len - self.position)
+                    .min(buf.len() as u64) as usize;
+                let read = match extent.offset {
+                    Some(offset) => {
+                        self.image.seek(SeekFrom::Start(offset + within))?;
+                        self.image.read(&mut buf[..wanted])?
+                    }
+                    None => {
+                        buf[..wanted].fill(0);
+                        wanted
+                    }
+                };
+                self.position += read as u64;
+                return Ok(read);
+            }
+            start += extent.len;
+        }
+        Ok(0)
+    }
+}
+
+impl<R: Read + Seek> Seek for ExtentReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position =
+            position.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "seek before the start"))?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_fs_keeps_paths_from_the_disc_root() {
+        let mut fs = MemoryFs::new().with_label("Movie");
+        fs.insert("Movie/BDMV/PLAYLIST/00800.mpls", &b"MPLS"[..]);
+        fs.insert("Movie/AACS/Unit_Key_RO.inf", &b"key"[..]);
+        fs.insert("BDMV/index.bdmv", &b"INDX"[..]);
+
+        let names = |path| {
+            fs.read_dir(path)
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.name, e.is_dir))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(""),
+            [("AACS".to_owned(), true), ("BDMV".to_owned(), true)]
+        );
+        assert_eq!(
+            names("bdmv"),
+            [
+                ("index.bdmv".to_owned(), false),
+                ("PLAYLIST".to_owned(), true)
+            ]
+        );
+
+        let mut data = Vec::new();
+        fs.open("bdmv/playlist/00800.MPLS")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"MPLS");
+        assert_eq!(fs.metadata("AACS/Unit_Key_RO.inf").unwrap().len, 3);
+        assert_eq!(fs.label().as_deref(), Some("Movie"));
+    }
+
+    #[test]
+    fn memory_fs_reports_missing_files() {
+        let mut fs = MemoryFs::new();
+        fs.insert("BDMV/index.bdmv", &b"INDX"[..]);
+        assert_eq!(
+            fs.open("BDMV/MovieObject.bdmv").err().unwrap().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            fs.read_dir("BDMV/PLAYLIST").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(fs.label(), None);
+    }
+}
@@ -0,0 +1,735 @@
+use std::{
+    io::{Cursor, Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    sync::Arc,
+};
+
+use super::{components, not_found, DirEntry, DiscFs, Extent, ExtentReader, ImageSource, ReadSeek};
+
+const SECTOR_SIZE: u64 = 2048;
+const ANCHOR_SECTOR: u64 = 256;
+const MIN_BLOCK_SIZE: u64 = 512;
+const MAX_BLOCK_SIZE: u64 = 65536;
+/// Offset of the partition maps in a logical volume descriptor.
+const PARTITION_MAPS_START: usize = 440;
+
+// Descriptor tag identifiers (ECMA-167).
+const TAG_ANCHOR: u16 = 2;
+const TAG_PARTITION: u16 = 5;
+const TAG_LOGICAL_VOLUME: u16 = 6;
+const TAG_TERMINATING: u16 = 8;
+const TAG_FILE_SET: u16 = 256;
+const TAG_FILE_IDENTIFIER: u16 = 257;
+const TAG_ALLOCATION_EXTENT: u16 = 258;
+const TAG_FILE_ENTRY: u16 = 261;
+const TAG_EXTENDED_FILE_ENTRY: u16 = 266;
+
+const FILE_TYPE_DIRECTORY: u8 = 4;
+const FID_DELETED: u8 = 0x04;
+const FID_PARENT: u8 = 0x08;
+
+const EXTENT_LENGTH_MASK: u32 = 0x3FFF_FFFF;
+const EXTENT_RECORDED: u32 = 0;
+const EXTENT_CONTINUATION: u32 = 3;
+
+fn field<const N: usize>(data: &[u8], at: usize) -> Result<[u8; N]> {
+    at.checked_add(N)
+        .and_then(|end| data.get(at..end)?.try_into().ok())
+        .ok_or_else(|| invalid("truncated descriptor"))
+}
+
+fn byte(data: &[u8], at: usize) -> Result<u8> {
+    Ok(field::<1>(data, at)?[0])
+}
+
+fn le16(data: &[u8], at: usize) -> Result<u16> {
+    field(data, at).map(u16::from_le_bytes)
+}
+
+fn le32(data: &[u8], at: usize) -> Result<u32> {
+    field(data, at).map(u32::from_le_bytes)
+}
+
+fn le64(data: &[u8], at: usize) -> Result<u64> {
+    field(data, at).map(u64::from_le_bytes)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("UDF: {}", message))
+}
+
+/// Decodes an OSTA compressed Unicode string (CS0).
+fn decode_cs0(data: &[u8]) -> String {
+    match data.split_first() {
+        Some((8, rest)) => rest.iter().map(|&b| char::from(b)).collect(),
+        Some((16, rest)) => {
+            let units = rest
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::new(),
+    }
+}
+
+/// A fixed length `dstring`, whose last byte holds the used length.
+fn decode_dstring(data: &[u8]) -> String {
+    let Some((&len, text)) = data.split_last() else {
+        return String::new();
+    };
+    decode_cs0(&text[..(len as usize).min(text.len())])
+}
+
+/// Long allocation descriptor: an extent within a given partition.
+#[derive(Debug, Clone, Copy)]
+struct LongAd {
+    len: u32,
+    block: u32,
+    partition: u16,
+}
+
+impl LongAd {
+    fn parse(data: &[u8]) -> Result<Self> {
+        Ok(LongAd {
+            len: le32(data, 0)?,
+            block: le32(data, 4)?,
+            partition: le16(data, 8)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PartitionMap {
+    /// Type 1 map onto a partition descriptor.
+    Physical { start: u64 },
+    /// UDF 2.50 metadata partition: blocks live inside the metadata file,
+    /// itself stored in a physical partition.
+    Metadata { extents: Vec<Extent> },
+}
+
+/// A file or directory, read from its (extended) file entry.
+#[derive(Debug, Clone)]
+struct Node {
+    is_dir: bool,
+    len: u64,
+    data: NodeData,
+}
+
+#[derive(Debug, Clone)]
+enum NodeData {
+    Embedded(Vec<u8>),
+    Extents(Vec<Extent>),
+}
+
+/// Read-only UDF file system (up to 2.50, as used by Blu-ray) on an image.
+#[derive(Debug)]
+pub struct UdfFs {
+    source: Arc<dyn ImageSource>,
+    block_size: u64,
+    maps: Vec<PartitionMap>,
+    root: LongAd,
+    label: String,
+}
+
+impl UdfFs {
+    pub fn new(source: Arc<dyn ImageSource>) -> Result<Self> {
+        let image = &mut source.open()?;
+        let anchor = read_at(image, ANCHOR_SECTOR * SECTOR_SIZE, SECTOR_SIZE as usize)?;
+        if le16(&anchor, 0)? != TAG_ANCHOR {
+            return Err(invalid("no anchor volume descriptor"));
+        }
+        let sequence_len = u64::from(le32(&anchor, 16)?);
+        let sequence_start = u64::from(le32(&anchor, 20)?);
+
+        let mut partitions = Vec::new();
+        let mut logical_volume = None;
+        for sector in 0..sequence_len / SECTOR_SIZE {
+            let descriptor = read_at(
+                image,
+                (sequence_start + sector) * SECTOR_SIZE,
+                SECTOR_SIZE as usize,
+            )?;
+            match le16(&descriptor, 0)? {
+                TAG_PARTITION => partitions.push((
+                    le16(&descriptor, 22)?,
+                    u64::from(le32(&descriptor, 188)?) * SECTOR_SIZE,
+                )),
+                TAG_LOGICAL_VOLUME => logical_volume = Some(descriptor),
+                TAG_TERMINATING => break,
+                _ => {}
+            }
+        }
+        let lvd = logical_volume.ok_or_else(|| invalid("no logical volume descriptor"))?;
+        let block_size = u64::from(le32(&lvd, 212)?);
+        if !block_size.is_power_of_two() || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size)
+        {
+            return Err(invalid("unsupported logical block size"));
+        }
+        let label = decode_dstring(&lvd[84..212]);
+        let file_set = LongAd::parse(&lvd[248..264])?;
+
+        let physical_start = |number: u16| {
+            partitions
+                .iter()
+                .find(|(n, _)| *n == number)
+                .map(|(_, start)| *start)
+                .ok_or_else(|| invalid("partition map refers to a missing partition"))
+        };
+
+        let map_count = le32(&lvd, 268)? as usize;
+        let mut pos = PARTITION_MAPS_START;
+        // Every map takes at least the 6 bytes of a type 1 map.
+        let mut maps = Vec::with_capacity(map_count.min((lvd.len() - pos) / 6));
+        let mut pending_metadata = Vec::new();
+        for _ in 0..map_count {
+            let (kind, len) = (byte(&lvd, pos)?, byte(&lvd, pos + 1)? as usize);
+            let map = lvd
+                .get(pos..pos + len)
+                .filter(|map| !map.is_empty())
+                .ok_or_else(|| invalid("truncated partition map"))?;
+            match kind {
+                1 => maps.push(PartitionMap::Physical {
+                    start: physical_start(le16(map, 4)?)?,
+                }),
+                2 if map
+                    .get(5..28)
+                    .is_some_and(|id| id.starts_with(b"*UDF Metadata Partition")) =>
+                {
+                    let start = physical_start(le16(map, 38)?)?;
+                    pending_metadata.push((maps.len(), start, le32(map, 40)?));
+                    maps.push(PartitionMap::Physical { start });
+                }
+                2 => {
+                    // Virtual and sparable partitions read like physical
+                    // ones on pressed discs.
+                    maps.push(PartitionMap::Physical {
+                        start: physical_start(le16(map, 38)?)?,
+                    });
+                }
+                _ => return Err(invalid("unknown partition map type")),
+            }
+            pos += len;
+        }
+
+        let mut fs = UdfFs {
+            source: source.clone(),
+            block_size,
+            maps,
+            root: file_set,
+            label,
+        };
+        for (index, start, file_location) in pending_metadata {
+            let entry = read_at(
+                image,
+                start + u64::from(file_location) * block_size,
+                block_size as usize,
+            )?;
+            let node = fs.parse_entry(image, &entry, index as u16, Some(start))?;
+            let NodeData::Extents(extents) = node.data else {
+                return Err(invalid("embedded metadata file"));
+            };
+            fs.maps[index] = PartitionMap::Metadata { extents };
+        }
+
+        let file_set = fs.read_block(image, fs.root)?;
+        if le16(&file_set, 0)? != TAG_FILE_SET {
+            return Err(invalid("no file set descriptor"));
+        }
+        fs.root = LongAd::parse(&file_set[400..416])?;
+        Ok(fs)
+    }
+
+    /// Byte offset in the image of a logical block of a partition.
+    fn block_offset(&self, partition: u16, block: u32) -> Result<u64> {
+        let offset = u64::from(block) * self.block_size;
+        match self.maps.get(partition as usize) {
+            Some(PartitionMap::Physical { start }) => Ok(start + offset),
+            Some(PartitionMap::Metadata { extents }) => {
+                let mut base = 0;
+                for extent in extents {
+                    if offset < base + extent.len {
+                        return extent
+                            .offset
+                            .map(|start| start + offset - base)
+                            .ok_or_else(|| invalid("unrecorded metadata block"));
+                    }
+                    base += extent.len;
+                }
+                Err(invalid("block outside the metadata partition"))
+            }
+            None => Err(invalid("unknown partition reference")),
+        }
+    }
+
+    fn read_block(&self, image: &mut Box<dyn ReadSeek>, ad: LongAd) -> Result<Vec<u8>> {
+        let offset = self.block_offset(ad.partition, ad.block)?;
+        read_at(image, offset, self.block_size as usize)
+    }
+
+    fn read_node(&self, image: &mut Box<dyn ReadSeek>, icb: LongAd) -> Result<Node> {
+        let entry = self.read_block(image, icb)?;
+        self.parse_entry(image, &entry, icb.partition, None)
+    }
+
+    /// Parses a file entry. Short allocation descriptors are relative to
+    /// `partition`, or to the physical partition at `physical` while the
+    /// metadata partition itself is being located.
+    fn parse_entry(
+        &self,
+        image: &mut Box<dyn ReadSeek>,
+        entry: &[u8],
+        partition: u16,
+        physical: Option<u64>,
+    ) -> Result<Node> {
+        let (ea_len_at, ad_start) = match le16(entry, 0)? {
+            TAG_FILE_ENTRY => (168, 176),
+            TAG_EXTENDED_FILE_ENTRY => (208, 216),
+            _ => return Err(invalid("expected a file entry")),
+        };
+        let is_dir = byte(entry, 27)? == FILE_TYPE_DIRECTORY;
+        let flags = le16(entry, 34)?;
+        let len = le64(entry, 56)?;
+        let ea_len = le32(entry, ea_len_at)? as usize;
+        let ad_len = le32(entry, ea_len_at + 4)? as usize;
+        let descriptors = ea_len
+            .checked_add(ad_start)
+            .and_then(|start| entry.get(start..start.checked_add(ad_len)?))
+            .ok_or_else(|| invalid("allocation descriptors past the entry"))?;
+
+        let mut extents = Vec::new();
+        let mut descriptors = descriptors.to_vec();
+        loop {
+            let mut continuation = None;
+            match flags & 0x7 {
+                0 => {
+                    for ad in descriptors.chunks_exact(8) {
+                        let raw = le32(ad, 0)?;
+                        let block = le32(ad, 4)?;
+                        let offset = match physical {
+                            Some(start) => start + u64::from(block) * self.block_size,
+                            None => self.block_offset(partition, block)?,
+                        };
+                        if !self.push_extent(&mut extents, raw, offset, &mut continuation) {
+                            break;
+                        }
+                    }
+                }
+                1 => {
+                    for ad in descriptors.chunks_exact(16) {
+                        let ad = LongAd::parse(ad)?;
+                        let offset = self.block_offset(ad.partition, ad.block)?;
+                        if !self.push_extent(&mut extents, ad.len, offset, &mut continuation) {
+                            break;
+                        }
+                    }
+                }
+                3 => {
+                    return Ok(Node {
+                        is_dir,
+                        len,
+                        data: NodeData::Embedded(
+                            descriptors[..(len as usize).min(ad_len)].to_vec(),
+                        ),
+                    })
+                }
+                _ => return Err(invalid("unsupported allocation descriptor type")),
+            }
+            let Some((offset, len)) = continuation else {
+                break;
+            };
+            // More descriptors follow an allocation extent descriptor
+            // header, within one block.
+            let extent = read_at(image, offset, len.min(self.block_size as usize))?;
+            if extent.len() < 24 || le16(&extent, 0)? != TAG_ALLOCATION_EXTENT {
+                return Err(invalid("expected an allocation extent descriptor"));
+            }
+            let ad_len = (le32(&extent, 20)? as usize).min(extent.len() - 24);
+            descriptors = extent[24..24 + ad_len].to_vec();
+        }
+
+        Ok(Node {
+            is_dir,
+            len,
+            data: NodeData::Extents(extents),
+        })
+    }
+
+    /// Adds the extent of one allocation descriptor. Returns `false` at the
+    /// end of the descriptor list.
+    fn push_extent(
+        &self,
+        extents: &mut Vec<Extent>,
+        raw_len: u32,
+        offset: u64,
+        continuation: &mut Option<(u64, usize)>,
+    ) -> bool {
+        let len = u64::from(raw_len & EXTENT_LENGTH_MASK);
+        if len == 0 {
+            return false;
+        }
+        match raw_len >> 30 {
+            EXTENT_CONTINUATION => {
+                *continuation = Some((offset, len as usize));
+                false
+            }
+            EXTENT_RECORDED => {
+                extents.push(Extent {
+                    offset: Some(offset),
+                    len,
+                });
+                true
+            }
+            _ => {
+                extents.push(Extent { offset: None, len });
+                true
+            }
+        }
+    }
+
+    fn node_reader(&self, node: &Node) -> Result<Box<dyn ReadSeek>> {
+        Ok(match &node.data {
+            NodeData::Embedded(data) => Box::new(Cursor::new(data.clone())),
+            NodeData::Extents(extents) => Box::new(ExtentReader::new(
+                self.source.open()?,
+                extents.clone(),
+                node.len,
+            )),
+        })
+    }
+
+    /// File identifiers of a directory: the name and the ICB of each entry.
+    fn read_fids(&self, dir: &Node) -> Result<Vec<(String, LongAd)>> {
+        let mut data = Vec::new();
+        self.node_reader(dir)?.read_to_end(&mut data)?;
+
+        let mut fids = Vec::new();
+        let mut pos = 0;
+        while pos + 38 <= data.len() {
+            let fid = &data[pos..];
+            if le16(fid, 0)? != TAG_FILE_IDENTIFIER {
+                break;
+            }
+            let characteristics = fid[18];
+            let name_len = fid[19] as usize;
+            let icb = LongAd::parse(&fid[20..36])?;
+            let iu_len = le16(fid, 36)? as usize;
+            let name_start = 38 + iu_len;
+            let name = fid
+                .get(name_start..name_start + name_len)
+                .map(decode_cs0)
+                .unwrap_or_default();
+            pos += (name_start + name_len + 3) & !3;
+
+            if characteristics & (FID_PARENT | FID_DELETED) == 0 {
+                fids.push((name, icb));
+            }
+        }
+        Ok(fids)
+    }
+
+    fn lookup(&self, image: &mut Box<dyn ReadSeek>, path: &str) -> Result<Node> {
+        let mut node = self.read_node(image, self.root)?;
+        for component in components(path) {
+            if !node.is_dir {
+                return Err(not_found(path));
+            }
+            let icb = self
+                .read_fids(&node)?
+                .into_iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(component))
+                .map(|(_, icb)| icb)
+                .ok_or_else(|| not_found(path))?;
+            node = self.read_node(image, icb)?;
+        }
+        Ok(node)
+    }
+}
+
+impl DiscFs for UdfFs {
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>> {
+        let image = &mut self.source.open()?;
+        let dir = self.lookup(image, path)?;
+        if !dir.is_dir {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a directory", path),
+            ));
+        }
+        let mut entries = Vec::new();
+        for (name, icb) in self.read_fids(&dir)? {
+            let node = self.read_node(image, icb)?;
+            entries.push(DirEntry {
+                name,
+                is_dir: node.is_dir,
+                len: if node.is_dir { 0 } else { node.len },
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn ReadSeek>> {
+        let node = self.lookup(&mut self.source.open()?, path)?;
+        if node.is_dir {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is a directory", path),
+            ));
+        }
+        self.node_reader(&node)
+    }
+
+    fn label(&self) -> Option<String> {
+        Some(self.label.clone()).filter(|label| !label.is_empty())
+    }
+}
+
+fn read_at(image: &mut Box<dyn ReadSeek>, offset: u64, len: usize) -> Result<Vec<u8>> {
+    let mut buffer = vec![0; len];
+    image.seek(SeekFrom::Start(offset))?;
+    image.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARTITION_START: usize = 300;
+
+    /// A UDF image with 2048 byte blocks: `BDMV/index.bdmv` over two
+    /// extents and `BDMV/MovieObject.bdmv` embedded in an extended file
+    /// entry. `patch` edits the image before it is opened.
+    fn image(patch: impl FnOnce(&mut Vec<u8>)) -> Result<UdfFs> {
+        let mut image = vec![0; (PARTITION_START + 10) * SECTOR_SIZE as usize];
+        let sector = |image: &mut Vec<u8>, n: usize, data: &[u8]| {
+            image[n * 2048..n * 2048 + data.len()].copy_from_slice(data);
+        };
+        let block = |n: usize| PARTITION_START + n;
+
+        let mut anchor = descriptor(TAG_ANCHOR);
+        anchor[16..20].copy_from_slice(&(3 * 2048u32).to_le_bytes());
+        anchor[20..24].copy_from_slice(&32u32.to_le_bytes());
+        sector(&mut image, ANCHOR_SECTOR as usize, &anchor);
+
+        let mut partition = descriptor(TAG_PARTITION);
+        partition[188..192].copy_from_slice(&(PARTITION_START as u32).to_le_bytes());
+        sector(&mut image, 32, &partition);
+
+        let mut lvd = descriptor(TAG_LOGICAL_VOLUME);
+        lvd[84] = 8;
+        lvd[85..91].copy_from_slice(b"SAMPLE");
+        lvd[211] = 7;
+        lvd[212..216].copy_from_slice(&2048u32.to_le_bytes());
+        lvd[248..264].copy_from_slice(&long_ad(2048, 0));
+        lvd[268..272].copy_from_slice(&1u32.to_le_bytes());
+        lvd[440..446].copy_from_slice(&[1, 6, 1, 0, 0, 0]);
+        sector(&mut image, 33, &lvd);
+        sector(&mut image, 34, &descriptor(TAG_TERMINATING));
+
+        let mut file_set = descriptor(TAG_FILE_SET);
+        file_set[400..416].copy_from_slice(&long_ad(2048, 1));
+        sector(&mut image, block(0), &file_set);
+
+        let root_fids = [fid(b"", 0, FID_PARENT), fid(b"BDMV", 3, 0)].concat();
+        sector(
+            &mut image,
+            block(1),
+            &file_entry(true, &root_fids, &[(root_fids.len(), 2)]),
+        );
+        sector(&mut image, block(2), &root_fids);
+
+        let bdmv_fids = [
+            fid(b"", 1, FID_PARENT),
+            fid(b"index.bdmv", 5, 0),
+            fid(b"old.bdmv", 5, FID_DELETED),
+            fid(b"MovieObject.bdmv", 7, 0),
+        ]
+        .concat();
+        sector(
+            &mut image,
+            block(3),
+            &file_entry(true, &bdmv_fids, &[(bdmv_fids.len(), 4)]),
+        );
+        sector(&mut image, block(4), &bdmv_fids);
+
+        sector(
+            &mut image,
+            block(5),
+            &file_entry(false, &[], &[(2048, 6), (100, 9)]),
+        );
+        sector(&mut image, block(6), &[b'a'; 2048]);
+        sector(&mut image, block(9), &[b'b'; 100]);
+
+        let mut embedded = descriptor(TAG_EXTENDED_FILE_ENTRY);
+        embedded[34] = 3;
+        embedded[56..64].copy_from_slice(&5u64.to_le_bytes());
+        embedded[212..216].copy_from_slice(&5u32.to_le_bytes());
+        embedded[216..221].copy_from_slice(b"MOBJ0");
+        sector(&mut image, block(7), &embedded);
+
+        patch(&mut image);
+        UdfFs::new(Arc::new(Arc::<[u8]>::from(image)))
+    }
+
+    fn descriptor(tag: u16) -> Vec<u8> {
+        let mut data = vec![0; 2048];
+        data[..2].copy_from_slice(&tag.to_le_bytes());
+        data
+    }
+
+    fn long_ad(len: u32, block: u32) -> [u8; 16] {
+        let mut ad = [0; 16];
+        ad[..4].copy_from_slice(&len.to_le_bytes());
+        ad[4..8].copy_from_slice(&block.to_le_bytes());
+        ad
+    }
+
+    fn fid(name: &[u8], icb: u32, characteristics: u8) -> Vec<u8> {
+        let mut fid = descriptor(TAG_FILE_IDENTIFIER);
+        fid.truncate(38);
+        fid[18] = characteristics;
+        fid[20..36].copy_from_slice(&long_ad(2048, icb));
+        if !name.is_empty() {
+            fid[19] = name.len() as u8 + 1;
+            fid.push(8);
+            fid.extend_from_slice(name);
+        }
+        fid.resize((fid.len() + 3) & !3, 0);
+        fid
+    }
+
+    /// A file entry with short allocation descriptors (length, block).
+    fn file_entry(is_dir: bool, data: &[u8], extents: &[(usize, u32)]) -> Vec<u8> {
+        let mut entry = descriptor(TAG_FILE_ENTRY);
+        entry[27] = if is_dir { FILE_TYPE_DIRECTORY } else { 5 };
+        let len = match is_dir {
+            true => data.len() as u64,
+            false => extents.iter().map(|(len, _)| *len as u64).sum(),
+        };
+        entry[56..64].copy_from_slice(&len.to_le_bytes());
+        entry[172..176].copy_from_slice(&(8 * extents.len() as u32).to_le_bytes());
+        for (i, (len, block)) in extents.iter().enumerate() {
+            let at = 176 + 8 * i;
+            entry[at..at + 4].copy_from_slice(&(*len as u32).to_le_bytes());
+            entry[at + 4..at + 8].copy_from_slice(&block.to_le_bytes());
+        }
+        entry
+    }
+
+    fn lvd_offset(at: usize) -> usize {
+        33 * 2048 + at
+    }
+
+    #[test]
+    fn reads_files() {
+        let fs = image(|_| {}).unwrap();
+        assert_eq!(fs.label().as_deref(), Some("SAMPLE"));
+
+        let root = fs.read_dir("").unwrap();
+        assert_eq!(
+            root,
+            [DirEntry {
+                name: "BDMV".to_owned(),
+                is_dir: true,
+                len: 0
+            }]
+        );
+        let names = fs
+            .read_dir("bdmv")
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.name, entry.len))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                ("MovieObject.bdmv".to_owned(), 5),
+                ("index.bdmv".to_owned(), 2148)
+            ]
+        );
+
+        let index = fs.read("BDMV/INDEX.BDMV").unwrap();
+        assert_eq!(index.len(), 2148);
+        assert!(index[..2048].iter().all(|&b| b == b'a'));
+        assert!(index[2048..].iter().all(|&b| b == b'b'));
+        assert_eq!(fs.read("BDMV/MovieObject.bdmv").unwrap(), b"MOBJ0");
+
+        let missing = fs.open("BDMV/old.bdmv").err().unwrap();
+        assert_eq!(missing.kind(), ErrorKind::NotFound);
+        assert_eq!(
+            fs.open("BDMV").err().unwrap().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn rejects_bad_block_sizes() {
+        for size in [0u32, 256, 3000, 1 << 20] {
+            let error = image(|image| {
+                let at = lvd_offset(212);
+                image[at..at + 4].copy_from_slice(&size.to_le_bytes());
+            })
+            .err()
+            .unwrap();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{}", size);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_partition_maps() {
+        // More maps than the descriptor holds.
+        let error = image(|image| {
+            let at = lvd_offset(268);
+            image[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        // A zero length map would never advance.
+        let error = image(|image| image[lvd_offset(441)] = 0).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        // A type 2 map too short to name its partition.
+        let error = image(|image| {
+            let at = lvd_offset(268);
+            image[at..at + 4].copy_from_slice(&2u32.to_le_bytes());
+            image[lvd_offset(446)..lvd_offset(448)].copy_from_slice(&[2, 20]);
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        let error = image(|image| image[ANCHOR_SECTOR as usize * 2048] = 0)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        // Allocation descriptors running past the entry.
+        let fs = image(|image| {
+            let at = (PARTITION_START + 5) * 2048 + 172;
+            image[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        })
+        .unwrap();
+        let error = fs.open("BDMV/index.bdmv").err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        // An entry that is not a file entry.
+        let fs = image(|image| image[(PARTITION_START + 7) * 2048] = 0).unwrap();
+        let error = fs.open("BDMV/MovieObject.bdmv").err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn field_readers_check_bounds() {
+        assert_eq!(le16(&[1, 2], 0).unwrap(), 0x0201);
+        assert_eq!(le64(&[1, 0, 0, 0, 0, 0, 0, 1], 0).unwrap(), 1 | 1 << 56);
+        assert!(le32(&[0; 4], 1).is_err());
+        assert!(byte(&[], 0).is_err());
+        assert!(le16(&[0; 4], usize::MAX).is_err());
+    }
+}
@@ -1,22 +1,332 @@
-use std::io::BufReader;
+use std::sync::Arc;
 
-use mpls_tool::parser::Parser;
-use wasm_bindgen::prelude::wasm_bindgen;
+use js_sys::{Array, Error, Reflect, Uint8Array};
+use mpls_tool::{
+    disc::Disc,
+    model::{DiscSummary, PlaylistModel},
+    parser::Parser,
+    playlist_view::{ParseError, ParseErrorKind},
+    vfs::MemoryFs,
+};
+use serde::Serialize;
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+export interface Playlist {
+    name: string;
+    /** Version string of the file, like `MPLS0300`. */
+    version: string;
+    playback_type: number;
+    playback_count: number;
+    /** Seconds. */
+    length: number;
+    angle_count: number;
+    flags: PlaylistFlags;
+    play_items: PlayItem[];
+    streams: Stream[];
+    chapters: Chapter[];
+}
+
+export interface PlaylistFlags {
+    random_access: boolean;
+    audio_mix_app: boolean;
+    lossless_may_bypass_mixer: boolean;
+    mvc_base_view_r: boolean;
+    sdr_conversion_notification: boolean;
+}
+
+export interface PlayItem {
+    clip: string;
+    /** Seconds on the clip's timeline. */
+    time_in: number;
+    time_out: number;
+    /** Seconds from the start of the playlist. */
+    relative_time_in: number;
+    length: number;
+    /** Clips of the other angles, from angle 1. */
+    angles: string[];
+    pids: number[];
+}
+
+export interface Stream {
+    pid: number;
+    kind: "video" | "audio" | "graphics" | "text";
+    codec: string;
+    /** ISO 639-2 code as stored on the disc. */
+    language: string | null;
+    /** BCP 47 tag, `fr` for `fre` and `fra`. */
+    language_tag: string | null;
+    language_name: string | null;
+    description: string;
+    bit_rate: number | null;
+}
+
+export interface Chapter {
+    number: number;
+    /** Seconds from the start of the playlist. */
+    time_in: number;
+    length: number;
+}
+
+/** A file of a dropped folder, `path` as in `webkitRelativePath`. */
+export interface DiscFile {
+    path: string;
+    data: Uint8Array;
+}
+
+export interface DiscSummary {
+    name: string;
+    label: string;
+    title: string | null;
+    /** Bytes of the files given. */
+    size: number;
+    protection: string;
+    extras: string[];
+    main_feature: string | null;
+    index: Index | null;
+    /** Best main feature candidates first. */
+    playlists: PlaylistSummary[];
+}
+
+export interface Index {
+    version: string;
+    first_playback: Title;
+    top_menu: Title;
+    titles: Title[];
+}
+
+export interface Title {
+    kind: "hdmv" | "bdj" | "unknown";
+    /** Movie object number, or `.bdjo` name. */
+    object: string;
+    accessible: boolean;
+}
+
+export interface PlaylistSummary {
+    name: string;
+    /** Seconds. */
+    length: number;
+    item_count: number;
+    angle_count: number;
+    chapter_count: number;
+    video_format: string | null;
+    audio_languages: string[];
+    subtitle_languages: string[];
+    score: number;
+    flags: ("main_feature" | "short" | "repeated_clips" | "obfuscation_group" | "likely_decoy")[];
+    reasons: string[];
+    duplicate_of: string | null;
+    clips: ClipSummary[];
+}
+
+export interface ClipSummary {
+    name: string;
+    /** Seconds. */
+    length: number;
+    /** Bytes, from the stream file or its clip information. */
+    size: number | null;
+    /** Bits per second. */
+    recording_rate: number | null;
+}
+
+/** Thrown by `analyze_mpls` for data that isn't a valid playlist. */
+export interface MplsError extends Error {
+    kind: "truncated" | "bad_magic" | "bad_offset" | "bad_text";
+    /** Byte offset in the file where parsing failed. */
+    offset: number;
+}
+"#;
 
 #[wasm_bindgen]
-pub fn analyze_mpls(data: Vec<u8>) -> bool {
-    let reader = BufReader::new(data.as_slice());
-    run(reader).is_ok()
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Playlist")]
+    pub type JsPlaylist;
+
+    #[wasm_bindgen(typescript_type = "DiscFile[]")]
+    pub type JsDiscFiles;
+
+    #[wasm_bindgen(typescript_type = "DiscSummary")]
+    pub type JsDiscSummary;
 }
 
-fn run(mut reader: BufReader<&[u8]>) -> std::io::Result<()> {
-    let mut parser = Parser::default();
-    let reader = &mut reader;
-    parser.read_header(reader)?;
-    parser.read_playlist_header(reader)?;
-    parser.read_play_item_entries(reader)?;
-    parser.read_chapters_header(reader)?;
-    parser.read_chapters(reader)?;
+/// Parses an `.mpls` file, throwing an `MplsError` when it isn't valid.
+#[wasm_bindgen]
+pub fn analyze_mpls(data: &[u8], name: Option<String>) -> Result<JsPlaylist, JsValue> {
+    let parser = Parser::from_bytes(data).map_err(parse_error)?;
+    let model = PlaylistModel::new(name.as_deref().unwrap_or_default(), &parser);
+    Ok(to_js(&model)?.unchecked_into())
+}
+
+/// Analyzes the files of a dropped disc folder. Only `BDMV/PLAYLIST` is
+/// required; `index.bdmv`, `CLIPINF` and `META` add to the summary, and the
+/// large stream files may be left out.
+#[wasm_bindgen]
+pub fn analyze_disc(files: JsDiscFiles, label: Option<String>) -> Result<JsDiscSummary, JsValue> {
+    let files: Array = files.unchecked_into();
+    let mut fs = MemoryFs::new();
+    for file in files.iter() {
+        let path = Reflect::get(&file, &"path".into())?
+            .as_string()
+            .ok_or_else(|| Error::new("DiscFile.path must be a string"))?;
+        let data: Uint8Array = Reflect::get(&file, &"data".into())?
+            .dyn_into()
+            .map_err(|_| Error::new("DiscFile.data must be a Uint8Array"))?;
+        fs.insert(&path, data.to_vec());
+    }
+    let summary =
+        summarize(fs, label.unwrap_or_default()).map_err(|e| Error::new(&e.to_string()))?;
+    Ok(to_js(&summary)?.unchecked_into())
+}
+
+/// Summary of the dropped files, labelled `name` unless it is empty.
+fn summarize(fs: MemoryFs, name: String) -> std::io::Result<DiscSummary> {
+    let fs = if name.is_empty() {
+        fs
+    } else {
+        fs.with_label(&name)
+    };
+    let disc = Disc::from_fs(Arc::new(fs), name)?;
+    Ok(DiscSummary::new(&disc))
+}
+
+/// Whether `data` parses as a playlist.
+#[wasm_bindgen]
+pub fn is_mpls(data: &[u8]) -> bool {
+    Parser::from_bytes(data).is_ok()
+}
+
+/// `None` as `null` and maps as plain objects, like `JSON.parse` would give.
+fn to_js(value: &impl Serialize) -> Result<JsValue, JsValue> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(JsValue::from)
+}
+
+fn parse_error(error: ParseError) -> JsValue {
+    let js = Error::new(&error.to_string());
+    js.set_name("MplsError");
+    Reflect::set(&js, &"kind".into(), &error_kind(error.kind).into()).ok();
+    Reflect::set(&js, &"offset".into(), &(error.offset as f64).into()).ok();
+    js.into()
+}
+
+/// `MplsError.kind` of a parse error.
+fn error_kind(kind: ParseErrorKind) -> &'static str {
+    match kind {
+        ParseErrorKind::Truncated => "truncated",
+        ParseErrorKind::BadMagic => "bad_magic",
+        ParseErrorKind::BadOffset => "bad_offset",
+        ParseErrorKind::BadText => "bad_text",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MPLS_00800: &[u8] = include_bytes!("../../00800.mpls");
+    const MPLS_00801: &[u8] = include_bytes!("../../00801.mpls");
+
+    #[test]
+    fn checks_playlists() {
+        assert!(is_mpls(MPLS_00800));
+        assert!(!is_mpls(&MPLS_00800[..MPLS_00800.len() / 2]));
+        assert!(!is_mpls(b"MOBJ0200"));
+    }
+
+    #[test]
+    fn names_error_kinds() {
+        let kind = |data: &[u8]| error_kind(Parser::from_bytes(data).err().unwrap().kind);
+        let mut other = MPLS_00800.to_vec();
+        other[..4].copy_from_slice(b"MOBJ");
+        assert_eq!(kind(&other), "bad_magic");
+        assert_eq!(kind(&MPLS_00800[..4]), "truncated");
+        assert_eq!(kind(&MPLS_00800[..MPLS_00800.len() / 2]), "bad_offset");
+    }
+
+    #[test]
+    fn summarizes_dropped_folders() {
+        let mut fs = MemoryFs::new();
+        fs.insert("Movie/BDMV/PLAYLIST/00800.mpls", MPLS_00800);
+        fs.insert("Movie/BDMV/PLAYLIST/00801.mpls", MPLS_00801);
+        let summary = summarize(fs, "Movie".to_owned()).unwrap();
+        assert_eq!(
+            (summary.name.as_str(), summary.label.as_str()),
+            ("Movie", "Movie")
+        );
+        assert_eq!(summary.playlists.len(), 2);
+        assert!(summary.main_feature.is_some());
+        assert!(summary.index.is_none());
+
+        let mut fs = MemoryFs::new();
+        fs.insert("Movie/BDMV/index.bdmv", &b"INDX0200"[..]);
+        assert!(summarize(fs, String::new()).is_err());
+    }
+
+    // The JS values only exist on wasm32; run with `wasm-pack test --node wasm`.
+    #[cfg(target_arch = "wasm32")]
+    mod js {
+        use wasm_bindgen_test::wasm_bindgen_test;
+
+        use super::*;
+
+        fn get(value: &JsValue, key: &str) -> JsValue {
+            Reflect::get(value, &key.into()).unwrap()
+        }
+
+        #[wasm_bindgen_test]
+        fn returns_playlist_objects() {
+            let playlist: JsValue = analyze_mpls(MPLS_00800, Some("00800.MPLS".into()))
+                .unwrap()
+                .into();
+            assert_eq!(get(&playlist, "name").as_string().unwrap(), "00800.MPLS");
+            assert_eq!(get(&playlist, "version").as_string().unwrap(), "MPLS0300");
+            let streams: Array = get(&playlist, "streams").dyn_into().unwrap();
+            assert_eq!(streams.length(), 30);
+            let audio = streams.get(1);
+            assert_eq!(get(&audio, "language").as_string().unwrap(), "eng");
+            assert!(get(&audio, "bit_rate").is_null());
+        }
+
+        #[wasm_bindgen_test]
+        fn throws_mpls_errors() {
+            let error = analyze_mpls(&MPLS_00800[..MPLS_00800.len() / 2], None)
+                .err()
+                .unwrap();
+            assert_eq!(get(&error, "name").as_string().unwrap(), "MplsError");
+            assert_eq!(get(&error, "kind").as_string().unwrap(), "bad_offset");
+            assert!(get(&error, "offset").as_f64().unwrap() > 0.0);
+        }
+
+        fn disc_file(path: &str, data: &[u8]) -> JsValue {
+            let file = js_sys::Object::new();
+            Reflect::set(&file, &"path".into(), &path.into()).unwrap();
+            Reflect::set(&file, &"data".into(), &Uint8Array::from(data)).unwrap();
+            file.into()
+        }
+
+        #[wasm_bindgen_test]
+        fn returns_disc_summaries() {
+            let files = Array::of2(
+                &disc_file("Movie/BDMV/PLAYLIST/00800.mpls", MPLS_00800),
+                &disc_file("Movie/BDMV/PLAYLIST/00801.mpls", MPLS_00801),
+            );
+            let summary: JsValue = analyze_disc(files.unchecked_into(), Some("Movie".into()))
+                .unwrap()
+                .into();
+            assert_eq!(get(&summary, "label").as_string().unwrap(), "Movie");
+            assert!(get(&summary, "index").is_null());
+            let playlists: Array = get(&summary, "playlists").dyn_into().unwrap();
+            assert_eq!(playlists.length(), 2);
+        }
 
-    Ok(())
+        #[wasm_bindgen_test]
+        fn rejects_files_without_data() {
+            let file = disc_file("BDMV/PLAYLIST/00800.mpls", MPLS_00800);
+            Reflect::set(&file, &"data".into(), &"not bytes".into()).unwrap();
+            let files = Array::of1(&file);
+            assert!(analyze_disc(files.unchecked_into(), None).is_err());
+        }
+    }
 }